@@ -0,0 +1,200 @@
+//! A machine-readable record of what Samus does, written as JSON lines for scripts.
+//!
+//! Each line is one [`ActivityEvent`] with a `seq` number and a `type` tag, e.g.
+//! `{"seq":0,"type":"message","role":"user","content":"hi"}`.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, Write};
+use std::sync::Arc;
+
+use crate::config::LogLevel;
+use crate::logging;
+use crate::mcp::client::Role;
+
+/// One entry in the activity stream. Fields are only ever added, never renamed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ActivityEvent {
+    /// Chat text from the user or a reply from the model
+    Message { role: Role, content: String },
+    /// A slash command, shell command or search was started
+    ToolCall { tool: String, input: String },
+    /// What a tool call produced
+    ToolResult {
+        tool: String,
+        output: String,
+        success: bool,
+    },
+    /// A model request failed
+    Error { message: String },
+}
+
+#[derive(Serialize)]
+struct Record<'a> {
+    seq: u64,
+    #[serde(flatten)]
+    event: &'a ActivityEvent,
+}
+
+struct Sink {
+    writer: Box<dyn Write + Send>,
+    next_seq: u64,
+    failed: bool,
+}
+
+/// Where activity events are written; clones share the same output and sequence
+#[derive(Clone)]
+pub struct ActivityLog {
+    sink: Arc<Mutex<Sink>>,
+}
+
+impl std::fmt::Debug for ActivityLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ActivityLog")
+            .field("next_seq", &self.sink.lock().next_seq)
+            .finish()
+    }
+}
+
+impl ActivityLog {
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            sink: Arc::new(Mutex::new(Sink {
+                writer: Box::new(writer),
+                next_seq: 0,
+                failed: false,
+            })),
+        }
+    }
+
+    /// Open `target`: `fd:N` for an inherited file descriptor (Unix only), otherwise
+    /// a file path, which is truncated
+    pub fn open(target: &str) -> io::Result<Self> {
+        if let Some(fd) = target.strip_prefix("fd:") {
+            let fd: i32 = fd.parse().map_err(|_| {
+                io::Error::new(io::ErrorKind::InvalidInput, format!("invalid descriptor '{}'", fd))
+            })?;
+            return Self::from_fd(fd);
+        }
+        Ok(Self::new(File::create(target)?))
+    }
+
+    #[cfg(unix)]
+    fn from_fd(fd: i32) -> io::Result<Self> {
+        use std::os::fd::FromRawFd;
+
+        // Refuse descriptors that aren't open rather than writing to whatever reuses them
+        if unsafe { libc::fcntl(fd, libc::F_GETFD) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: the descriptor is open and was handed to us for this purpose
+        Ok(Self::new(unsafe { File::from_raw_fd(fd) }))
+    }
+
+    #[cfg(not(unix))]
+    fn from_fd(_fd: i32) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "file descriptors are only supported on Unix",
+        ))
+    }
+
+    /// Write `event` as one line. A failed write is logged once and later events are
+    /// dropped, so a closed pipe never interrupts the app.
+    pub fn emit(&self, event: ActivityEvent) {
+        let mut sink = self.sink.lock();
+        if sink.failed {
+            return;
+        }
+
+        let record = Record {
+            seq: sink.next_seq,
+            event: &event,
+        };
+        sink.next_seq += 1;
+
+        let result = serde_json::to_vec(&record)
+            .map_err(io::Error::from)
+            .and_then(|mut line| {
+                line.push(b'\n');
+                sink.writer.write_all(&line)?;
+                sink.writer.flush()
+            });
+        if let Err(e) = result {
+            sink.failed = true;
+            logging::log(
+                LogLevel::Warn,
+                "activity",
+                format!("Stopped writing activity events: {}", e),
+            );
+        }
+    }
+}
+
+/// Capturing output in tests
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+
+    /// Writer whose contents stay readable after it's handed to a log
+    #[derive(Clone, Default)]
+    pub struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl SharedBuffer {
+        pub fn text(&self) -> String {
+            String::from_utf8(self.0.lock().clone()).unwrap()
+        }
+    }
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::mock::SharedBuffer;
+
+    #[test]
+    fn test_events_are_numbered_json_lines() {
+        let buffer = SharedBuffer::default();
+        let log = ActivityLog::new(buffer.clone());
+        log.emit(ActivityEvent::Message {
+            role: Role::User,
+            content: "hi\nthere".to_string(),
+        });
+        log.clone().emit(ActivityEvent::Error {
+            message: "boom".to_string(),
+        });
+
+        assert_eq!(
+            buffer.text(),
+            "{\"seq\":0,\"type\":\"message\",\"role\":\"user\",\"content\":\"hi\\nthere\"}\n\
+             {\"seq\":1,\"type\":\"error\",\"message\":\"boom\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_open_rejects_bad_targets() {
+        assert!(ActivityLog::open("fd:nope").is_err());
+        assert!(ActivityLog::open("/nonexistent-dir/events.jsonl").is_err());
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("events.jsonl");
+        let log = ActivityLog::open(path.to_str().unwrap()).unwrap();
+        log.emit(ActivityEvent::Error {
+            message: "x".to_string(),
+        });
+        assert!(std::fs::read_to_string(&path).unwrap().ends_with("\"message\":\"x\"}\n"));
+    }
+}
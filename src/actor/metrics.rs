@@ -1,36 +1,61 @@
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use histogram::Histogram;
-use metrics::{Counter, Gauge};
-use parking_lot::RwLock;
-use crate::actor::ActorPath;
+use parking_lot::{Mutex, RwLock};
+use crate::actor::{ActorError, ActorPath};
 use std::time::{Duration, Instant};
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct ActorMetrics {
     pub messages_processed: u64,
     pub messages_failed: u64,
     pub processing_time: Duration,
     pub mailbox_size: usize,
     pub last_processed: Option<Instant>,
+    pub restarts: u64,
+    /// Failures the actor was resumed after, keeping its state
+    pub resumes: u64,
+    /// The most recent error the actor was resumed after
+    pub last_resumed_error: Option<String>,
+    /// Messages that took longer than the slow-handler threshold
+    pub slow_messages: u64,
+    pub slowest_message: Duration,
 }
 
 pub struct MetricsCollector {
     actor_stats: Arc<RwLock<HashMap<ActorPath, ActorMetrics>>>,
-    message_latency: Histogram,
-    memory_usage: Gauge,
-    error_rates: Counter,
-    dead_letters: Counter,
+    message_latency: Mutex<Histogram>,
+    // Totals across all actors, mirrored to the `metrics` recorder as they change
+    errors: AtomicU64,
+    dead_letters: AtomicU64,
+    restarts: AtomicU64,
+    resumes: AtomicU64,
+    slow_messages: AtomicU64,
+    // Counters named by the actors themselves, e.g. cache hits
+    named_counters: RwLock<HashMap<String, u64>>,
+}
+
+// 2^7 buckets per power of two, up to 2^20us (about a second)
+const LATENCY_GROUPING_POWER: u8 = 7;
+const LATENCY_MAX_VALUE_POWER: u8 = 20;
+
+fn latency_histogram() -> Histogram {
+    Histogram::new(LATENCY_GROUPING_POWER, LATENCY_MAX_VALUE_POWER)
+        .expect("valid histogram bounds")
 }
 
 impl MetricsCollector {
     pub fn new() -> Self {
         Self {
             actor_stats: Arc::new(RwLock::new(HashMap::new())),
-            message_latency: Histogram::new_with_bounds(1, 1_000_000, 3).unwrap(), // 1us to 1s
-            memory_usage: metrics::gauge!("actor_system_memory_usage"),
-            error_rates: metrics::counter!("actor_system_errors"),
-            dead_letters: metrics::counter!("actor_system_dead_letters"),
+            message_latency: Mutex::new(latency_histogram()),
+            errors: AtomicU64::new(0),
+            dead_letters: AtomicU64::new(0),
+            restarts: AtomicU64::new(0),
+            resumes: AtomicU64::new(0),
+            slow_messages: AtomicU64::new(0),
+            named_counters: RwLock::new(HashMap::new()),
         }
     }
 
@@ -40,15 +65,44 @@ impl MetricsCollector {
         metrics.messages_processed += 1;
         metrics.processing_time += duration;
         metrics.last_processed = Some(Instant::now());
-        
-        self.message_latency.record(duration.as_micros() as u64);
+
+        // Anything slower than the top bucket is clamped into it
+        let micros = (duration.as_micros() as u64).min((1 << LATENCY_MAX_VALUE_POWER) - 1);
+        let _ = self.message_latency.lock().increment(micros);
     }
 
     pub fn record_message_failed(&self, actor: &ActorPath) {
         let mut stats = self.actor_stats.write();
         let metrics = stats.entry(actor.clone()).or_default();
         metrics.messages_failed += 1;
-        self.error_rates.increment(1);
+        self.errors.fetch_add(1, Ordering::Relaxed);
+        metrics::increment_counter!("actor_system_errors");
+    }
+
+    pub fn record_restart(&self, actor: &ActorPath) {
+        let mut stats = self.actor_stats.write();
+        let metrics = stats.entry(actor.clone()).or_default();
+        metrics.restarts += 1;
+        self.restarts.fetch_add(1, Ordering::Relaxed);
+        metrics::increment_counter!("actor_system_restarts");
+    }
+
+    pub fn record_resume(&self, actor: &ActorPath, error: &ActorError) {
+        let mut stats = self.actor_stats.write();
+        let metrics = stats.entry(actor.clone()).or_default();
+        metrics.resumes += 1;
+        metrics.last_resumed_error = Some(error.to_string());
+        self.resumes.fetch_add(1, Ordering::Relaxed);
+        metrics::increment_counter!("actor_system_resumes");
+    }
+
+    pub fn record_slow_message(&self, actor: &ActorPath, duration: Duration) {
+        let mut stats = self.actor_stats.write();
+        let metrics = stats.entry(actor.clone()).or_default();
+        metrics.slow_messages += 1;
+        metrics.slowest_message = metrics.slowest_message.max(duration);
+        self.slow_messages.fetch_add(1, Ordering::Relaxed);
+        metrics::increment_counter!("actor_system_slow_messages");
     }
 
     pub fn update_mailbox_size(&self, actor: &ActorPath, size: usize) {
@@ -58,44 +112,83 @@ impl MetricsCollector {
     }
 
     pub fn record_dead_letter(&self) {
-        self.dead_letters.increment(1);
+        self.dead_letters.fetch_add(1, Ordering::Relaxed);
+        metrics::increment_counter!("actor_system_dead_letters");
     }
 
     pub fn update_memory_usage(&self, bytes: i64) {
-        self.memory_usage.set(bytes as f64);
+        metrics::gauge!("actor_system_memory_usage", bytes as f64);
+    }
+
+    /// Bump the counter `name`, e.g. `file_cache_hits`
+    pub fn increment_counter(&self, name: &str) {
+        *self.named_counters.write().entry(name.to_string()).or_default() += 1;
+        metrics::increment_counter!(name.to_string());
+    }
+
+    /// Current value of the counter `name`
+    pub fn counter(&self, name: &str) -> u64 {
+        self.named_counters.read().get(name).copied().unwrap_or(0)
+    }
+
+    /// Record how long an operation called `name` took
+    pub fn record_duration(&self, name: &str, duration: Duration) {
+        metrics::histogram!(name.to_string(), duration.as_secs_f64());
     }
 
     pub fn get_actor_metrics(&self, actor: &ActorPath) -> Option<ActorMetrics> {
         self.actor_stats.read().get(actor).cloned()
     }
 
+    /// Message latencies in microseconds at the 50th to 99th percentiles, each the
+    /// lower bound of the bucket it fell in; zero until a message has been handled
     pub fn get_latency_percentiles(&self) -> Vec<(f64, u64)> {
-        vec![
-            (50.0, self.message_latency.value_at_percentile(50.0)),
-            (75.0, self.message_latency.value_at_percentile(75.0)),
-            (90.0, self.message_latency.value_at_percentile(90.0)),
-            (95.0, self.message_latency.value_at_percentile(95.0)),
-            (99.0, self.message_latency.value_at_percentile(99.0)),
-        ]
+        let latency = self.message_latency.lock();
+        [50.0, 75.0, 90.0, 95.0, 99.0]
+            .into_iter()
+            .map(|percentile| {
+                let value = latency
+                    .percentile(percentile)
+                    .map_or(0, |bucket| bucket.start());
+                (percentile, value)
+            })
+            .collect()
     }
 
     pub fn reset_histogram(&self) {
-        self.message_latency.clear();
+        *self.message_latency.lock() = latency_histogram();
     }
 
     pub fn error_count(&self) -> u64 {
-        self.error_rates.get() as u64
+        self.errors.load(Ordering::Relaxed)
     }
 
     pub fn dead_letter_count(&self) -> u64 {
-        self.dead_letters.get() as u64
+        self.dead_letters.load(Ordering::Relaxed)
+    }
+
+    pub fn restart_count(&self) -> u64 {
+        self.restarts.load(Ordering::Relaxed)
+    }
+
+    pub fn resume_count(&self) -> u64 {
+        self.resumes.load(Ordering::Relaxed)
+    }
+
+    pub fn slow_message_count(&self) -> u64 {
+        self.slow_messages.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for MetricsCollector {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::thread;
 
     #[test]
     fn test_metrics_collection() {
@@ -1,14 +1,16 @@
-use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::{mpsc, oneshot};
+use tokio::runtime::Handle;
+use tokio::sync::mpsc;
 use async_trait::async_trait;
 use thiserror::Error;
 
 mod system;
 mod metrics;
+mod timer;
 
 pub use system::ActorSystem;
 pub use metrics::MetricsCollector;
+pub use timer::TimerHandle;
 
 #[derive(Error, Debug)]
 pub enum ActorError {
@@ -24,11 +26,51 @@ pub enum ActorError {
 #[derive(Clone, Debug, Hash, Eq, PartialEq)]
 pub struct ActorPath(String);
 
+impl ActorPath {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self(path.into())
+    }
+
+    /// Path of a direct child named `name` under this path
+    pub fn child(&self, name: &str) -> Self {
+        Self(format!("{}/{}", self.0.trim_end_matches('/'), name))
+    }
+
+    /// Parent path, or `None` for a root-level path such as `/system`
+    pub fn parent(&self) -> Option<Self> {
+        let trimmed = self.0.trim_end_matches('/');
+        match trimmed.rfind('/') {
+            Some(0) | None => None,
+            Some(idx) => Some(Self(trimmed[..idx].to_string())),
+        }
+    }
+
+    /// Whether this path sits directly beneath `parent`
+    pub fn is_child_of(&self, parent: &ActorPath) -> bool {
+        self.parent().as_ref() == Some(parent)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
 /// Reference to an actor that can receive messages
-#[derive(Clone)]
 pub struct ActorRef<T: Send + 'static> {
     tx: mpsc::Sender<T>,
     path: ActorPath,
+    runtime: Option<Handle>,
+}
+
+// Not derived: that would require the message type itself to be `Clone`
+impl<T: Send + 'static> Clone for ActorRef<T> {
+    fn clone(&self) -> Self {
+        Self {
+            tx: self.tx.clone(),
+            path: self.path.clone(),
+            runtime: self.runtime.clone(),
+        }
+    }
 }
 
 /// Configuration for actor behavior
@@ -37,6 +79,10 @@ pub struct ActorConfig {
     pub mailbox_size: usize,
     pub supervision_strategy: SupervisionStrategy,
     pub shutdown_timeout: Duration,
+    pub restart_policy: RestartPolicy,
+    /// A message taking longer than this to handle is logged as a warning and counted
+    /// in the actor's metrics; `None` never warns
+    pub slow_message_threshold: Option<Duration>,
 }
 
 impl Default for ActorConfig {
@@ -45,10 +91,44 @@ impl Default for ActorConfig {
             mailbox_size: 1000,
             supervision_strategy: SupervisionStrategy::Restart,
             shutdown_timeout: Duration::from_secs(5),
+            restart_policy: RestartPolicy::default(),
+            slow_message_threshold: Some(Duration::from_secs(1)),
+        }
+    }
+}
+
+/// Limits applied by `SupervisionStrategy::Restart`. An actor that needs more than
+/// `max_restarts` restarts within `within` is escalated to `Stop`.
+#[derive(Clone, Debug)]
+pub struct RestartPolicy {
+    pub max_restarts: u32,
+    pub within: Duration,
+    /// Delay before the first restart; doubled for each further restart in the window
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            max_restarts: 5,
+            within: Duration::from_secs(60),
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
         }
     }
 }
 
+impl RestartPolicy {
+    /// Backoff before the `attempt`-th restart (1-based) in the current window
+    pub fn backoff_for(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
 #[derive(Clone, Copy)]
 pub enum SupervisionStrategy {
     Stop,
@@ -73,20 +153,81 @@ pub trait Actor: Send + Sync {
     async fn post_stop(&mut self) -> Result<(), ActorError> {
         Ok(())
     }
+
+    /// Called under `SupervisionStrategy::Resume` after `handle` fails, before the next
+    /// message. The actor keeps whatever state the failure left, so this is the place to
+    /// repair it; returning an error stops the actor instead.
+    async fn on_error(&mut self, _error: &ActorError) -> Result<(), ActorError> {
+        Ok(())
+    }
 }
 
 impl<T: Send + 'static> ActorRef<T> {
     pub fn new(tx: mpsc::Sender<T>, path: ActorPath) -> Self {
-        Self { tx, path }
+        Self { tx, path, runtime: None }
+    }
+
+    /// Attach the runtime that timers for this actor should be scheduled on
+    pub fn with_runtime(mut self, runtime: Handle) -> Self {
+        self.runtime = Some(runtime);
+        self
     }
 
     pub async fn send(&self, msg: T) -> Result<(), ActorError> {
         self.tx.send(msg).await.map_err(|_| ActorError::MailboxFull)
     }
 
+    /// Deliver `msg` only if the mailbox has room right now. On failure the message is
+    /// handed back with `MailboxFull`, or `ActorStopped` if nothing will ever receive it.
+    pub fn try_send(&self, msg: T) -> Result<(), (ActorError, T)> {
+        self.tx.try_send(msg).map_err(|e| match e {
+            mpsc::error::TrySendError::Full(msg) => (ActorError::MailboxFull, msg),
+            mpsc::error::TrySendError::Closed(msg) => (ActorError::ActorStopped, msg),
+        })
+    }
+
     pub fn path(&self) -> &ActorPath {
         &self.path
     }
+
+    /// Deliver `msg` once after `delay`
+    pub fn send_later(&self, msg: T, delay: Duration) -> TimerHandle {
+        let tx = self.tx.clone();
+        self.spawn_timer(async move {
+            tokio::time::sleep(delay).await;
+            // The actor may have stopped in the meantime; nothing to deliver to then
+            let _ = tx.send(msg).await;
+        })
+    }
+
+    /// Deliver a message built by `make_msg` every `period` until cancelled or the actor stops
+    pub fn send_interval<F>(&self, mut make_msg: F, period: Duration) -> TimerHandle
+    where
+        F: FnMut() -> T + Send + 'static,
+    {
+        let tx = self.tx.clone();
+        self.spawn_timer(async move {
+            let mut interval =
+                tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            loop {
+                interval.tick().await;
+                if tx.send(make_msg()).await.is_err() {
+                    break;
+                }
+            }
+        })
+    }
+
+    fn spawn_timer<F>(&self, task: F) -> TimerHandle
+    where
+        F: std::future::Future<Output = ()> + Send + 'static,
+    {
+        let handle = match &self.runtime {
+            Some(runtime) => runtime.spawn(task),
+            None => tokio::spawn(task),
+        };
+        TimerHandle::new(handle)
+    }
 }
 
 /// Message types for the system supervisor
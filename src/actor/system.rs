@@ -1,19 +1,17 @@
 use std::sync::Arc;
 use tokio::runtime::Runtime;
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
-use object_pool::{Pool, Reusable};
-use futures::future::BoxFuture;
 use parking_lot::RwLock;
-use std::collections::HashMap;
-use std::time::Instant;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
 
 use crate::actor::{
     Actor, ActorConfig, ActorError, ActorPath, ActorRef,
-    DeadLetter, DeadLetterOffice, MetricsCollector, SupervisorMsg,
+    DeadLetterOffice, MetricsCollector, RestartPolicy, SupervisorMsg,
 };
-
-type MessagePool<T> = Pool<Box<T>>;
+use crate::config::LogLevel;
+use crate::logging;
 
 pub struct ActorSystem {
     runtime: Arc<Runtime>,
@@ -21,7 +19,6 @@ pub struct ActorSystem {
     config: ActorConfig,
     metrics: Arc<MetricsCollector>,
     dead_letters: Arc<DeadLetterOffice>,
-    message_pools: Arc<RwLock<HashMap<std::any::TypeId, Box<dyn std::any::Any + Send + Sync>>>>,
     handles: Arc<RwLock<HashMap<ActorPath, JoinHandle<()>>>>,
 }
 
@@ -29,7 +26,7 @@ impl ActorSystem {
     pub fn new(config: ActorConfig) -> Arc<Self> {
         let runtime = Arc::new(Runtime::new().expect("Failed to create Tokio runtime"));
         let metrics = Arc::new(MetricsCollector::new());
-        let (dead_letters, dead_letter_rx) = DeadLetterOffice::new(1000);
+        let (dead_letters, mut dead_letter_rx) = DeadLetterOffice::new(1000);
         let dead_letters = Arc::new(dead_letters);
         
         // Create supervisor channel
@@ -42,7 +39,6 @@ impl ActorSystem {
             config,
             metrics: metrics.clone(),
             dead_letters: dead_letters.clone(),
-            message_pools: Arc::new(RwLock::new(HashMap::new())),
             handles: Arc::new(RwLock::new(HashMap::new())),
         });
 
@@ -52,15 +48,18 @@ impl ActorSystem {
             while let Some(msg) = sup_rx.recv().await {
                 match msg {
                     SupervisorMsg::ActorStarted(path) => {
-                        println!("Actor started: {}", path.0);
+                        logging::log(LogLevel::Debug, "actor", format!("Actor started: {}", path.0));
                     }
                     SupervisorMsg::ActorStopped(path) => {
-                        println!("Actor stopped: {}", path.0);
+                        logging::log(LogLevel::Debug, "actor", format!("Actor stopped: {}", path.0));
                         system_clone.handles.write().remove(&path);
                     }
                     SupervisorMsg::ActorFailed(path, error) => {
-                        println!("Actor failed: {} - Error: {}", path.0, error);
-                        // Implement supervision strategy here
+                        logging::log(
+                            LogLevel::Warn,
+                            "actor",
+                            format!("Actor failed: {} - Error: {}", path.0, error),
+                        );
                     }
                 }
             }
@@ -70,7 +69,11 @@ impl ActorSystem {
         runtime.spawn(async move {
             while let Some(dead_letter) = dead_letter_rx.recv().await {
                 metrics.record_dead_letter();
-                println!("Dead letter for {}: {:?}", dead_letter.recipient.0, dead_letter.error);
+                logging::log(
+                    LogLevel::Debug,
+                    "actor",
+                    format!("Dead letter for {}: {:?}", dead_letter.recipient.0, dead_letter.error),
+                );
             }
         });
 
@@ -82,29 +85,13 @@ impl ActorSystem {
         actor: A,
         path: ActorPath,
     ) -> Result<ActorRef<A::Message>, ActorError> {
-        let (tx, rx) = mpsc::channel(self.config.mailbox_size);
-        let actor_ref = ActorRef::new(tx, path.clone());
+        let (tx, mut rx) = mpsc::channel(self.config.mailbox_size);
+        let actor_ref = ActorRef::new(tx, path.clone()).with_runtime(self.runtime.handle().clone());
 
         let mut actor = actor;
+        let key = path.clone();
         let system = self.clone();
         let metrics = self.metrics.clone();
-        let dead_letters = self.dead_letters.clone();
-        
-        // Get or create message pool
-        let type_id = std::any::TypeId::of::<A::Message>();
-        let pool = {
-            let mut pools = self.message_pools.write();
-            pools.entry(type_id)
-                .or_insert_with(|| {
-                    Box::new(Pool::<Box<A::Message>>::new(
-                        self.config.mailbox_size * 2,
-                        || Box::new(unsafe { std::mem::zeroed() })
-                    ))
-                })
-                .downcast_ref::<MessagePool<A::Message>>()
-                .expect("Invalid message pool type")
-                .clone()
-        };
 
         let handle = self.runtime.spawn(async move {
             if let Err(e) = actor.pre_start().await {
@@ -117,18 +104,37 @@ impl ActorSystem {
 
             system.supervisor.send(SupervisorMsg::ActorStarted(path.clone())).await.ok();
 
+            let mut restarts = RestartTracker::new(system.config.restart_policy.clone());
+
             while let Some(msg) = rx.recv().await {
                 let start = Instant::now();
-                metrics.update_mailbox_size(&path, rx.capacity().unwrap_or(0));
+                metrics.update_mailbox_size(&path, rx.max_capacity() - rx.capacity());
 
-                match actor.handle(msg).await {
+                let result = actor.handle(msg).await;
+                let duration = start.elapsed();
+                let threshold = system.config.slow_message_threshold;
+                if let Some(threshold) = threshold.filter(|threshold| duration > *threshold) {
+                    // A slow handler holds up everything queued behind it
+                    metrics.record_slow_message(&path, duration);
+                    logging::log(
+                        LogLevel::Warn,
+                        logging::SLOW_HANDLER_TARGET,
+                        format!(
+                            "{} took {:?} to handle a message (threshold {:?})",
+                            path.as_str(),
+                            duration,
+                            threshold
+                        ),
+                    );
+                }
+
+                match result {
                     Ok(()) => {
-                        let duration = start.elapsed();
                         metrics.record_message_processed(&path, duration);
                     }
                     Err(e) => {
                         metrics.record_message_failed(&path);
-                        if let Err(e) = handle_actor_error(&system, &path, e, &actor).await {
+                        if handle_actor_error(&system, &path, e, &mut actor, &mut restarts).await.is_err() {
                             break;
                         }
                     }
@@ -145,12 +151,13 @@ impl ActorSystem {
             system.supervisor.send(SupervisorMsg::ActorStopped(path.clone())).await.ok();
         });
 
-        self.handles.write().insert(path.clone(), handle);
+        self.handles.write().insert(key, handle);
         Ok(actor_ref)
     }
 
     pub async fn stop(&self, path: &ActorPath) -> Result<(), ActorError> {
-        if let Some(handle) = self.handles.write().remove(path) {
+        let handle = self.handles.write().remove(path);
+        if let Some(handle) = handle {
             handle.abort();
             self.supervisor.send(SupervisorMsg::ActorStopped(path.clone())).await.ok();
         }
@@ -160,25 +167,97 @@ impl ActorSystem {
     pub fn metrics(&self) -> &Arc<MetricsCollector> {
         &self.metrics
     }
+
+    pub fn dead_letters(&self) -> &Arc<DeadLetterOffice> {
+        &self.dead_letters
+    }
+
+    /// Whether an actor is registered at `path` and its task is still running
+    pub fn lookup(&self, path: &ActorPath) -> bool {
+        self.handles
+            .read()
+            .get(path)
+            .is_some_and(|handle| !handle.is_finished())
+    }
+
+    /// Direct descendants of `path`, sorted for stable output
+    pub fn children(&self, path: &ActorPath) -> Vec<ActorPath> {
+        let mut children: Vec<ActorPath> = self
+            .handles
+            .read()
+            .keys()
+            .filter(|candidate| candidate.is_child_of(path))
+            .cloned()
+            .collect();
+        children.sort_by(|a, b| a.0.cmp(&b.0));
+        children
+    }
+}
+
+/// Restart history for a single actor, used to enforce its `RestartPolicy`
+struct RestartTracker {
+    policy: RestartPolicy,
+    recent: VecDeque<Instant>,
+}
+
+impl RestartTracker {
+    fn new(policy: RestartPolicy) -> Self {
+        Self {
+            policy,
+            recent: VecDeque::new(),
+        }
+    }
+
+    /// Register a restart attempt, returning the backoff to wait before restarting,
+    /// or `None` if the policy's limit within the window has been exhausted
+    fn next_restart(&mut self) -> Option<Duration> {
+        let now = Instant::now();
+        while let Some(oldest) = self.recent.front() {
+            if now.duration_since(*oldest) > self.policy.within {
+                self.recent.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        if self.recent.len() >= self.policy.max_restarts as usize {
+            return None;
+        }
+
+        self.recent.push_back(now);
+        Some(self.policy.backoff_for(self.recent.len() as u32))
+    }
 }
 
 async fn handle_actor_error<A: Actor>(
     system: &Arc<ActorSystem>,
     path: &ActorPath,
     error: ActorError,
-    actor: &A,
+    actor: &mut A,
+    restarts: &mut RestartTracker,
 ) -> Result<(), ActorError> {
     match system.config.supervision_strategy {
-        crate::actor::SupervisionStrategy::Stop => {
-            system.stop(path).await?;
-            Err(error)
-        }
+        // An Err tells the run loop to exit, which runs `post_stop` and reports the stop
+        crate::actor::SupervisionStrategy::Stop => Err(error),
         crate::actor::SupervisionStrategy::Restart => {
+            let Some(backoff) = restarts.next_restart() else {
+                // Crash-looping: escalate to Stop rather than restarting forever
+                return Err(error);
+            };
+
+            system.metrics.record_restart(path);
+            tokio::time::sleep(backoff).await;
+
             actor.post_stop().await?;
             actor.pre_start().await?;
             Ok(())
         }
-        crate::actor::SupervisionStrategy::Resume => Ok(()),
+        crate::actor::SupervisionStrategy::Resume => {
+            system.metrics.record_resume(path, &error);
+            // If it couldn't recover, don't feed it more messages
+            actor.on_error(&error).await?;
+            Ok(())
+        }
     }
 }
 
@@ -217,6 +296,7 @@ mod tests {
             mailbox_size: 10,
             supervision_strategy: crate::actor::SupervisionStrategy::Stop,
             shutdown_timeout: Duration::from_secs(1),
+            ..ActorConfig::default()
         };
 
         let system = ActorSystem::new(config);
@@ -239,4 +319,260 @@ mod tests {
         let metrics = system.metrics().get_actor_metrics(&ActorPath("/test".to_string())).unwrap();
         assert_eq!(metrics.messages_failed, 1);
     }
+
+    /// Fails every message and reports when `post_stop` runs
+    struct StoppingActor {
+        stopped: mpsc::UnboundedSender<()>,
+    }
+
+    #[async_trait::async_trait]
+    impl Actor for StoppingActor {
+        type Message = String;
+
+        async fn handle(&mut self, _msg: Self::Message) -> Result<(), ActorError> {
+            Err(ActorError::Internal("Failed".to_string()))
+        }
+
+        async fn post_stop(&mut self) -> Result<(), ActorError> {
+            let _ = self.stopped.send(());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stopped_actor_runs_post_stop() {
+        let config = ActorConfig {
+            supervision_strategy: crate::actor::SupervisionStrategy::Stop,
+            ..ActorConfig::default()
+        };
+        let system = ActorSystem::new(config);
+        let (tx, mut stopped) = mpsc::unbounded_channel();
+        let path = ActorPath::new("/test/stopping");
+        let actor_ref = system.spawn(StoppingActor { stopped: tx }, path.clone()).unwrap();
+
+        actor_ref.send("fail".to_string()).await.unwrap();
+
+        let post_stop = tokio::time::timeout(Duration::from_secs(1), stopped.recv()).await.unwrap();
+        assert_eq!(post_stop, Some(()));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!system.lookup(&path));
+        assert!(system.children(&ActorPath::new("/test")).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_restart_limit_escalates_to_stop() {
+        let config = ActorConfig {
+            supervision_strategy: crate::actor::SupervisionStrategy::Restart,
+            restart_policy: RestartPolicy {
+                max_restarts: 3,
+                within: Duration::from_secs(60),
+                initial_backoff: Duration::from_millis(1),
+                max_backoff: Duration::from_millis(4),
+            },
+            ..ActorConfig::default()
+        };
+
+        let system = ActorSystem::new(config);
+        let path = ActorPath::new("/test/crash-loop");
+        let actor_ref = system.spawn(TestActor::new(), path.clone()).unwrap();
+
+        for _ in 0..6 {
+            // Later sends fail once the actor has been stopped
+            actor_ref.send("fail".to_string()).await.ok();
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let metrics = system.metrics().get_actor_metrics(&path).unwrap();
+        assert_eq!(metrics.restarts, 3);
+        assert_eq!(metrics.messages_failed, 4);
+        assert!(!system.lookup(&path));
+    }
+
+    #[test]
+    fn test_restart_backoff_is_exponential_and_capped() {
+        let policy = RestartPolicy {
+            max_restarts: 10,
+            within: Duration::from_secs(60),
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(500),
+        };
+
+        assert_eq!(policy.backoff_for(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff_for(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff_for(3), Duration::from_millis(400));
+        assert_eq!(policy.backoff_for(4), Duration::from_millis(500));
+    }
+
+    /// Counts messages and, when resumed after a failure, records that it healed
+    struct HealingActor {
+        handled: mpsc::UnboundedSender<String>,
+        healed: mpsc::UnboundedSender<String>,
+    }
+
+    #[async_trait::async_trait]
+    impl Actor for HealingActor {
+        type Message = String;
+
+        async fn handle(&mut self, msg: Self::Message) -> Result<(), ActorError> {
+            if msg == "fail" {
+                return Err(ActorError::Internal("Failed".to_string()));
+            }
+            let _ = self.handled.send(msg);
+            Ok(())
+        }
+
+        async fn on_error(&mut self, error: &ActorError) -> Result<(), ActorError> {
+            let _ = self.healed.send(error.to_string());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resumed_actor_keeps_processing_and_records_the_error() {
+        let config = ActorConfig {
+            supervision_strategy: crate::actor::SupervisionStrategy::Resume,
+            ..ActorConfig::default()
+        };
+        let system = ActorSystem::new(config);
+        let (handled_tx, mut handled) = mpsc::unbounded_channel();
+        let (healed_tx, mut healed) = mpsc::unbounded_channel();
+        let path = ActorPath::new("/test/resume");
+        let actor_ref = system
+            .spawn(HealingActor { handled: handled_tx, healed: healed_tx }, path.clone())
+            .unwrap();
+
+        actor_ref.send("fail".to_string()).await.unwrap();
+        actor_ref.send("after".to_string()).await.unwrap();
+
+        let msg = tokio::time::timeout(Duration::from_secs(1), handled.recv()).await.unwrap();
+        assert_eq!(msg.as_deref(), Some("after"));
+        assert_eq!(healed.try_recv().ok().as_deref(), Some("internal error: Failed"));
+
+        let metrics = system.metrics().get_actor_metrics(&path).unwrap();
+        assert_eq!(metrics.messages_failed, 1);
+        assert_eq!(metrics.resumes, 1);
+        assert_eq!(metrics.last_resumed_error.as_deref(), Some("internal error: Failed"));
+        assert_eq!(metrics.restarts, 0);
+        assert!(system.lookup(&path));
+    }
+
+    /// Takes `delay` over each message
+    struct SlowActor {
+        delay: Duration,
+    }
+
+    #[async_trait::async_trait]
+    impl Actor for SlowActor {
+        type Message = String;
+
+        async fn handle(&mut self, _msg: Self::Message) -> Result<(), ActorError> {
+            tokio::time::sleep(self.delay).await;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slow_handler_is_warned_about() {
+        let config = ActorConfig {
+            slow_message_threshold: Some(Duration::from_millis(20)),
+            ..ActorConfig::default()
+        };
+        let system = ActorSystem::new(config);
+        let path = ActorPath::new("/test/slow");
+        let actor_ref = system
+            .spawn(SlowActor { delay: Duration::from_millis(50) }, path.clone())
+            .unwrap();
+
+        actor_ref.send("work".to_string()).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(150)).await;
+
+        let metrics = system.metrics().get_actor_metrics(&path).unwrap();
+        assert_eq!(metrics.messages_processed, 1);
+        assert_eq!(metrics.slow_messages, 1);
+        assert!(metrics.slowest_message >= Duration::from_millis(50));
+        assert!(logging::recent(100).iter().any(|record| {
+            record.target == logging::SLOW_HANDLER_TARGET && record.message.starts_with("/test/slow took")
+        }));
+    }
+
+    /// Forwards every message it receives so tests can observe delivery
+    struct ForwardingActor {
+        received: mpsc::UnboundedSender<String>,
+    }
+
+    #[async_trait::async_trait]
+    impl Actor for ForwardingActor {
+        type Message = String;
+
+        async fn handle(&mut self, msg: Self::Message) -> Result<(), ActorError> {
+            let _ = self.received.send(msg);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_later_delivers_after_delay() {
+        let system = ActorSystem::new(ActorConfig::default());
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let actor_ref = system
+            .spawn(ForwardingActor { received: tx }, ActorPath::new("/test/delayed"))
+            .unwrap();
+
+        let start = std::time::Instant::now();
+        actor_ref.send_later("tick".to_string(), Duration::from_millis(50));
+
+        let msg = tokio::time::timeout(Duration::from_secs(1), rx.recv()).await.unwrap();
+        assert_eq!(msg.as_deref(), Some("tick"));
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn test_send_interval_repeats_until_cancelled() {
+        let system = ActorSystem::new(ActorConfig::default());
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let actor_ref = system
+            .spawn(ForwardingActor { received: tx }, ActorPath::new("/test/interval"))
+            .unwrap();
+
+        let timer = actor_ref.send_interval(|| "ping".to_string(), Duration::from_millis(10));
+        for _ in 0..3 {
+            let msg = tokio::time::timeout(Duration::from_secs(1), rx.recv()).await.unwrap();
+            assert_eq!(msg.as_deref(), Some("ping"));
+        }
+
+        timer.cancel();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        while rx.try_recv().is_ok() {}
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(rx.try_recv().is_err());
+        assert!(timer.is_finished());
+    }
+
+    #[tokio::test]
+    async fn test_actor_hierarchy_lookup() {
+        let system = ActorSystem::new(ActorConfig::default());
+        let parent = ActorPath::new("/system/workers");
+
+        system.spawn(TestActor::new(), parent.child("b")).unwrap();
+        system.spawn(TestActor::new(), parent.child("a")).unwrap();
+        system.spawn(TestActor::new(), parent.child("a").child("nested")).unwrap();
+        system.spawn(TestActor::new(), ActorPath::new("/system/other")).unwrap();
+
+        assert!(system.lookup(&parent.child("a")));
+        assert!(!system.lookup(&parent.child("missing")));
+
+        assert_eq!(
+            system.children(&parent),
+            vec![parent.child("a"), parent.child("b")]
+        );
+        assert_eq!(
+            system.children(&parent.child("a")),
+            vec![parent.child("a").child("nested")]
+        );
+
+        system.stop(&parent.child("b")).await.unwrap();
+        assert!(!system.lookup(&parent.child("b")));
+        assert_eq!(system.children(&parent), vec![parent.child("a")]);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,22 @@
+use tokio::task::JoinHandle;
+
+/// Handle to a scheduled message; cancelling stops any pending or future deliveries
+pub struct TimerHandle {
+    handle: JoinHandle<()>,
+}
+
+impl TimerHandle {
+    pub(crate) fn new(handle: JoinHandle<()>) -> Self {
+        Self { handle }
+    }
+
+    /// Cancel the timer. Messages already delivered are unaffected.
+    pub fn cancel(&self) {
+        self.handle.abort();
+    }
+
+    /// Whether the timer has fired for the last time or been cancelled
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+}
@@ -1,4 +1,5 @@
 pub mod actor_bench;
+pub mod diff_bench;
 pub mod event_store_bench;
 pub mod file_ops_bench;
 
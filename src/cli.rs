@@ -0,0 +1,228 @@
+//! Command-line arguments: the TUI by default, or a headless subcommand
+
+use clap::{Arg, ArgAction, ArgMatches, Command, value_parser};
+use std::ffi::OsString;
+use std::sync::atomic::AtomicBool;
+
+use crate::config::Config;
+use crate::fs::WorkspacePaths;
+use crate::services::ripgrep::RipgrepError;
+use crate::ui::grep::{DEFAULT_GREP_MAX_RESULTS, run_grep};
+
+/// Parsed command line
+#[derive(Debug, Clone, PartialEq)]
+pub struct CliArgs {
+    pub command: CliCommand,
+    /// Where to write activity as JSON lines: a file path or `fd:N`
+    pub events: Option<String>,
+}
+
+/// What samus was asked to do
+#[derive(Debug, Clone, PartialEq)]
+pub enum CliCommand {
+    /// Start the TUI. A question here is answered without it when input is piped in
+    Tui { question: Option<String> },
+    /// Answer one prompt, with any piped input as context, and exit
+    Ask { question: String },
+    /// Search the current directory with ripgrep and print the results
+    Search {
+        pattern: String,
+        glob: Option<String>,
+        max_results: usize,
+    },
+}
+
+/// The argument parser, also used for `--help`
+pub fn command() -> Command {
+    Command::new("samus")
+        .about("Terminal interface for AI-assisted coding")
+        .version(env!("CARGO_PKG_VERSION"))
+        .args_conflicts_with_subcommands(true)
+        .arg(
+            Arg::new("events")
+                .long("events")
+                .value_name("FILE|fd:N")
+                .help("Write messages and tool calls as JSON lines to a file or descriptor")
+                .global(true)
+                .action(ArgAction::Set),
+        )
+        .arg(
+            Arg::new("question")
+                .help("Question about input piped to stdin, answered without the TUI")
+                .num_args(1..)
+                .trailing_var_arg(true),
+        )
+        .subcommand(
+            Command::new("ask")
+                .about("Answer a single prompt and exit")
+                .arg(
+                    Arg::new("question")
+                        .help("Prompt to send; piped input is included as context")
+                        .required(true)
+                        .num_args(1..)
+                        .trailing_var_arg(true),
+                ),
+        )
+        .subcommand(
+            Command::new("search")
+                .about("Search the current directory with ripgrep")
+                .arg(Arg::new("pattern").help("Regular expression").required(true))
+                .arg(Arg::new("glob").help("Only search files matching this glob"))
+                .arg(
+                    Arg::new("max")
+                        .long("max")
+                        .value_name("N")
+                        .help(format!(
+                            "Stop after this many matches [default: {}]",
+                            DEFAULT_GREP_MAX_RESULTS
+                        ))
+                        .value_parser(value_parser!(usize))
+                        .action(ArgAction::Set),
+                ),
+        )
+}
+
+/// Parse `args`, including the program name first as in `std::env::args_os()`
+pub fn parse_args<I, T>(args: I) -> Result<CliArgs, clap::Error>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let matches = command().try_get_matches_from(args)?;
+    // Global arguments end up on whichever subcommand was used
+    let events = matches
+        .subcommand()
+        .map_or(&matches, |(_, sub)| sub)
+        .get_one::<String>("events")
+        .cloned();
+    let command = match matches.subcommand() {
+        Some(("ask", ask)) => CliCommand::Ask {
+            question: joined(ask, "question").unwrap_or_default(),
+        },
+        Some(("search", search)) => CliCommand::Search {
+            pattern: search.get_one::<String>("pattern").cloned().unwrap_or_default(),
+            glob: search.get_one::<String>("glob").cloned(),
+            max_results: search
+                .get_one::<usize>("max")
+                .copied()
+                .unwrap_or(DEFAULT_GREP_MAX_RESULTS),
+        },
+        _ => CliCommand::Tui {
+            question: joined(&matches, "question"),
+        },
+    };
+    Ok(CliArgs { command, events })
+}
+
+/// Words of a multi-value argument joined with spaces
+fn joined(matches: &ArgMatches, id: &str) -> Option<String> {
+    let words: Vec<&str> = matches
+        .get_many::<String>(id)?
+        .map(String::as_str)
+        .collect();
+    Some(words.join(" "))
+}
+
+/// Run `samus search` under the current directory, returning the text to print
+pub fn run_search(
+    config: &Config,
+    pattern: &str,
+    glob: Option<String>,
+    max_results: usize,
+) -> Result<String, RipgrepError> {
+    let paths = WorkspacePaths::from_current_dir();
+    let outcome = run_grep(
+        paths.root(),
+        pattern,
+        glob,
+        max_results,
+        &config.ripgrep.binary,
+        &AtomicBool::new(false),
+    )?;
+    Ok(outcome.render(&paths))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::client::ChatTurn;
+    use crate::oneshot::mock::MockCompleter;
+    use crate::oneshot::{one_shot_turns, run_one_shot};
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(
+            parse_args(["samus"]).unwrap().command,
+            CliCommand::Tui { question: None }
+        );
+        assert_eq!(
+            parse_args(["samus", "summarize", "this"]).unwrap().command,
+            CliCommand::Tui {
+                question: Some("summarize this".to_string())
+            }
+        );
+        assert_eq!(
+            parse_args(["samus", "ask", "what", "does", "--this", "do?"]).unwrap().command,
+            CliCommand::Ask {
+                question: "what does --this do?".to_string()
+            }
+        );
+        assert_eq!(
+            parse_args(["samus", "search", "fn main", "*.rs", "--max", "5"]).unwrap().command,
+            CliCommand::Search {
+                pattern: "fn main".to_string(),
+                glob: Some("*.rs".to_string()),
+                max_results: 5,
+            }
+        );
+        assert_eq!(
+            parse_args(["samus", "search", "TODO"]).unwrap().command,
+            CliCommand::Search {
+                pattern: "TODO".to_string(),
+                glob: None,
+                max_results: DEFAULT_GREP_MAX_RESULTS,
+            }
+        );
+
+        assert!(parse_args(["samus", "ask"]).is_err());
+        assert!(parse_args(["samus", "--events"]).is_err());
+        assert!(parse_args(["samus", "search"]).is_err());
+        assert!(parse_args(["samus", "search", "x", "--max", "lots"]).is_err());
+    }
+
+    #[test]
+    fn test_events_flag_applies_to_every_command() {
+        let args = parse_args(["samus", "--events", "fd:3"]).unwrap();
+        assert_eq!(args.events.as_deref(), Some("fd:3"));
+        assert_eq!(args.command, CliCommand::Tui { question: None });
+
+        let args = parse_args(["samus", "ask", "--events", "out.jsonl", "hi"]).unwrap();
+        assert_eq!(args.events.as_deref(), Some("out.jsonl"));
+        assert_eq!(
+            args.command,
+            CliCommand::Ask {
+                question: "hi".to_string()
+            }
+        );
+
+        assert_eq!(parse_args(["samus", "search", "x"]).unwrap().events, None);
+    }
+
+    #[test]
+    fn test_ask_prints_the_answer() {
+        let CliCommand::Ask { question } =
+            parse_args(["samus", "ask", "why", "Rust?"]).unwrap().command
+        else {
+            panic!("expected the ask subcommand");
+        };
+        let client = MockCompleter::replying("Because it's fast and safe.");
+        let mut stdout = Vec::new();
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(run_one_shot(&client, &one_shot_turns(None, &question), &mut stdout))
+            .unwrap();
+
+        assert_eq!(String::from_utf8(stdout).unwrap(), "Because it's fast and safe.\n");
+        assert_eq!(client.seen(), vec![ChatTurn::user("why Rust?")]);
+    }
+}
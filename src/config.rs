@@ -1,8 +1,73 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use thiserror::Error;
 
 use crate::error::TaskError;
+use crate::secret::Secret;
+use crate::services::ripgrep::RipgrepBinaryPreference;
+use crate::ui::app::MainViewType;
+use crate::ui::diff::DiffViewMode;
+use crate::ui::prompt_template::PromptTemplate;
+
+/// A problem found by `Config::validate`
+#[derive(Error, Debug, PartialEq)]
+pub enum ConfigError {
+    #[error("{0} must not be empty")]
+    MissingField(String),
+    #[error("server '{server}' has an invalid URL '{url}': {reason}")]
+    InvalidUrl {
+        server: String,
+        url: String,
+        reason: String,
+    },
+    #[error("server '{0}' has an empty API key")]
+    EmptyApiKey(String),
+    #[error("server id '{0}' is used more than once")]
+    DuplicateServer(String),
+    #[error("{field} must be between 1 and {max}, got {value}")]
+    OutOfRange {
+        field: &'static str,
+        value: usize,
+        max: usize,
+    },
+    #[error("ui.default_view '{0}' is not one of: {names}", names = MainViewType::NAMES.join(", "))]
+    UnknownView(String),
+    #[error("context.file_template must contain {{content}}")]
+    MissingFileContent,
+}
+
+/// Upper bound for the in-memory history limits; anything larger is almost certainly a typo
+const MAX_HISTORY_LIMIT: usize = 1_000_000;
+
+/// Most backup versions that can be kept per file
+const MAX_BACKUP_VERSIONS: usize = 10_000;
+
+/// How often the UI polls for input and background results unless configured
+pub const DEFAULT_TICK_RATE_MS: u64 = 100;
+
+/// Slowest allowed tick; beyond this the spinner and streamed output look stuck
+const MAX_TICK_RATE_MS: u64 = 10_000;
+
+/// Longest configurable `!command` timeout: a day
+const MAX_COMMAND_TIMEOUT_SECS: u64 = 24 * 60 * 60;
+
+/// Rows of search results or symbols shown before "load more", unless configured
+pub const DEFAULT_RESULT_PAGE_SIZE: usize = 200;
+
+/// Largest allowed result page
+const MAX_RESULT_PAGE_SIZE: usize = 100_000;
+
+/// Fuzzy matches scoring lower than this are dropped, unless configured
+pub const DEFAULT_FUZZY_MIN_SCORE: i64 = 50;
+
+/// Fuzzy matches kept, best first, unless configured
+pub const DEFAULT_FUZZY_MAX_RESULTS: usize = 100;
+
+/// How a file's contents are wrapped when sent as context, unless configured
+pub const DEFAULT_FILE_TEMPLATE: &str = "{path}:\n```{lang}\n{content}\n```";
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Config {
@@ -10,6 +75,25 @@ pub struct Config {
     pub log_level: LogLevel,
     pub mcp_servers: Vec<McpServerConfig>,
     pub terminal: TerminalConfig,
+    #[serde(default)]
+    pub history: HistoryConfig,
+    #[serde(default)]
+    pub ui: UiConfig,
+    /// Extra slash-command prompt templates; these replace built-ins of the same name
+    #[serde(default)]
+    pub prompt_templates: Vec<PromptTemplate>,
+    #[serde(default)]
+    pub ripgrep: RipgrepConfig,
+    /// Model id per kind of request, e.g. `{"review": "anthropic/claude-3-opus"}`.
+    /// `chat` sets the starting model; other tasks fall back to the current one.
+    #[serde(default)]
+    pub task_models: HashMap<LlmTask, String>,
+    #[serde(default)]
+    pub backups: BackupConfig,
+    #[serde(default)]
+    pub auto_apply: AutoApplyConfig,
+    #[serde(default)]
+    pub context: ContextConfig,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -26,14 +110,339 @@ pub struct McpServerConfig {
     pub id: String,
     pub name: String,
     pub url: String,
-    pub api_key: Option<String>,
+    pub api_key: Option<Secret<String>>,
     pub enabled: bool,
+    /// Extra headers sent with every request, e.g. OpenRouter's attribution headers
+    #[serde(default = "McpServerConfig::default_headers")]
+    pub headers: HashMap<String, String>,
+    /// Client-side cap on outgoing requests; `None` leaves rate limiting to the server
+    #[serde(default)]
+    pub requests_per_minute: Option<u32>,
+    /// Approximate token limit for a conversation; the oldest turns are dropped to fit
+    #[serde(default)]
+    pub context_token_budget: Option<usize>,
+}
+
+impl McpServerConfig {
+    /// Attribution headers OpenRouter uses to credit requests to this app
+    pub fn default_headers() -> HashMap<String, String> {
+        HashMap::from([
+            (
+                "HTTP-Referer".to_string(),
+                "https://github.com/ochsec/Samus".to_string(),
+            ),
+            ("X-Title".to_string(), "Samus".to_string()),
+        ])
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.id.trim().is_empty() {
+            return Err(ConfigError::MissingField("mcp_servers.id".to_string()));
+        }
+        if self.url.trim().is_empty() {
+            return Err(ConfigError::MissingField(format!("mcp_servers.{}.url", self.id)));
+        }
+
+        let invalid_url = |reason: String| ConfigError::InvalidUrl {
+            server: self.id.clone(),
+            url: self.url.clone(),
+            reason,
+        };
+        let url = reqwest::Url::parse(&self.url).map_err(|e| invalid_url(e.to_string()))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(invalid_url(format!("unsupported scheme '{}'", url.scheme())));
+        }
+
+        if self
+            .api_key
+            .as_ref()
+            .is_some_and(|api_key| api_key.expose().trim().is_empty())
+        {
+            return Err(ConfigError::EmptyApiKey(self.id.clone()));
+        }
+
+        if self.requests_per_minute == Some(0) {
+            return Err(ConfigError::OutOfRange {
+                field: "mcp_servers.requests_per_minute",
+                value: 0,
+                max: u32::MAX as usize,
+            });
+        }
+
+        if self.context_token_budget == Some(0) {
+            return Err(ConfigError::OutOfRange {
+                field: "mcp_servers.context_token_budget",
+                value: 0,
+                max: usize::MAX,
+            });
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TerminalConfig {
     pub default_shell: Option<String>,
     pub history_limit: usize,
+    /// Where open terminals' titles and working directories are saved between sessions;
+    /// `None` disables it
+    #[serde(default = "TerminalConfig::default_state_file")]
+    pub state_file: Option<PathBuf>,
+    /// What a terminal scrolled back through its output does when new output arrives
+    #[serde(default)]
+    pub scroll_behavior: ScrollBehavior,
+    /// Seconds a `!command` may run before it's killed, unless it starts with its own
+    /// timeout like `!600s make`; defaults to 300
+    #[serde(default)]
+    pub command_timeout_secs: Option<u64>,
+}
+
+impl TerminalConfig {
+    fn default_state_file() -> Option<PathBuf> {
+        std::env::var_os("HOME")
+            .map(|home| PathBuf::from(home).join(".samus").join("terminals.json"))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HistoryConfig {
+    /// Maximum number of chat messages kept in memory
+    #[serde(default = "HistoryConfig::default_chat_limit")]
+    pub chat_limit: usize,
+    /// Maximum number of submitted inputs kept for Up/Down recall
+    #[serde(default = "HistoryConfig::default_command_limit")]
+    pub command_limit: usize,
+    /// Where command history is persisted between sessions; `None` disables persistence
+    #[serde(default = "HistoryConfig::default_command_history_file")]
+    pub command_history_file: Option<PathBuf>,
+    /// Where terminal command usage counts are persisted, to rank suggestions; `None` disables it
+    #[serde(default = "HistoryConfig::default_command_frequency_file")]
+    pub command_frequency_file: Option<PathBuf>,
+    /// Whether `/new` also clears the visible chat, not just the model's context
+    #[serde(default = "HistoryConfig::default_clear_chat_on_new")]
+    pub clear_chat_on_new: bool,
+    /// Where the files `/recent` switches between are persisted; `None` keeps them for
+    /// the session only
+    #[serde(default = "HistoryConfig::default_recent_files_file")]
+    pub recent_files_file: Option<PathBuf>,
+    /// Most files `/recent` remembers
+    #[serde(default = "HistoryConfig::default_recent_files_limit")]
+    pub recent_files_limit: usize,
+}
+
+impl HistoryConfig {
+    fn default_chat_limit() -> usize {
+        100
+    }
+
+    fn default_command_limit() -> usize {
+        50
+    }
+
+    fn default_command_history_file() -> Option<PathBuf> {
+        std::env::var_os("HOME")
+            .map(|home| PathBuf::from(home).join(".samus").join("command_history.json"))
+    }
+
+    fn default_command_frequency_file() -> Option<PathBuf> {
+        std::env::var_os("HOME")
+            .map(|home| PathBuf::from(home).join(".samus").join("command_frequency.json"))
+    }
+
+    fn default_clear_chat_on_new() -> bool {
+        true
+    }
+
+    fn default_recent_files_file() -> Option<PathBuf> {
+        std::env::var_os("HOME")
+            .map(|home| PathBuf::from(home).join(".samus").join("recent_files.json"))
+    }
+
+    fn default_recent_files_limit() -> usize {
+        20
+    }
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        HistoryConfig {
+            chat_limit: Self::default_chat_limit(),
+            command_limit: Self::default_command_limit(),
+            command_history_file: Self::default_command_history_file(),
+            command_frequency_file: Self::default_command_frequency_file(),
+            clear_chat_on_new: Self::default_clear_chat_on_new(),
+            recent_files_file: Self::default_recent_files_file(),
+            recent_files_limit: Self::default_recent_files_limit(),
+        }
+    }
+}
+
+/// Kind of request sent to the model, for picking a model per task
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum LlmTask {
+    Chat,
+    Explain,
+    Review,
+    /// Rewriting code, as `/refactor` does
+    ApplyEdit,
+}
+
+impl LlmTask {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LlmTask::Chat => "chat",
+            LlmTask::Explain => "explain",
+            LlmTask::Review => "review",
+            LlmTask::ApplyEdit => "apply-edit",
+        }
+    }
+
+    /// Task a prompt template performs; custom templates count as chat
+    pub fn for_template(name: &str) -> Self {
+        match name {
+            "explain" => LlmTask::Explain,
+            "review" => LlmTask::Review,
+            "refactor" => LlmTask::ApplyEdit,
+            _ => LlmTask::Chat,
+        }
+    }
+}
+
+/// How a terminal view reacts to new output while scrolled back
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ScrollBehavior {
+    /// Keep the scroll position and count the new lines below it
+    #[default]
+    StayPut,
+    /// Jump to the bottom
+    Follow,
+}
+
+/// Animation shown while a request or command is running
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SpinnerStyle {
+    #[default]
+    Braille,
+    Ascii,
+    /// Show only the elapsed time
+    Off,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct RipgrepConfig {
+    /// Which ripgrep `/grep` runs: "system_first" (default), "bundled_first",
+    /// or `{"explicit": "/path/to/rg"}`
+    pub binary: RipgrepBinaryPreference,
+    /// How `/grep` orders its results; every weight defaults to a sensible value
+    pub ranking: crate::services::ripgrep::ranking::RankingWeights,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct UiConfig {
+    pub spinner: SpinnerStyle,
+    /// View shown at startup, e.g. "code_outline"; defaults to the shell output
+    pub default_view: Option<String>,
+    /// Slash command run once the app is ready, e.g. "/outline src/main.rs"
+    pub startup_command: Option<String>,
+    /// How diffs are first shown: "inline" (default), "side_by_side" or "unified"
+    pub diff_view_mode: DiffViewMode,
+    /// Milliseconds between UI ticks; defaults to 100
+    pub tick_rate_ms: Option<u64>,
+    /// Rows the Search and Code Outline views render before Space loads more;
+    /// defaults to 200
+    pub result_page_size: Option<usize>,
+    /// Lowest score a fuzzy match needs to be listed; defaults to 50
+    pub fuzzy_min_score: Option<i64>,
+    /// Most fuzzy matches listed, best first; defaults to 100
+    pub fuzzy_max_results: Option<usize>,
+}
+
+/// Where `apply_diff` keeps copies of the files it changes, restored by `/undo`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct BackupConfig {
+    /// Directory mirroring the workspace layout, relative to the workspace root unless
+    /// absolute. Without one, a single `<file>.bak` is kept next to each file.
+    pub dir: Option<PathBuf>,
+    /// Versions kept per file in `dir`; `None` keeps them all
+    pub keep_versions: Option<usize>,
+    /// Versions in `dir` older than this are deleted, except a file's newest
+    pub max_age_days: Option<u64>,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            keep_versions: Some(10),
+            max_age_days: None,
+        }
+    }
+}
+
+/// Which agent edits are applied without asking first. Anything outside these
+/// limits, or that only deletes lines, always waits for confirmation.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct AutoApplyConfig {
+    /// Off by default: every edit is confirmed
+    pub enabled: bool,
+    /// Most lines an edit may touch, counting the larger side of each block
+    pub max_changed_lines: usize,
+    /// Most search/replace blocks in one edit
+    pub max_blocks: usize,
+}
+
+/// How files are put in front of the model
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct ContextConfig {
+    /// Wrapper for each pinned file's contents. `{path}`, `{lang}` (the fence tag for the
+    /// file's language, e.g. "rust", or empty) and `{content}` are filled in.
+    pub file_template: String,
+}
+
+impl Default for ContextConfig {
+    fn default() -> Self {
+        Self {
+            file_template: DEFAULT_FILE_TEMPLATE.to_string(),
+        }
+    }
+}
+
+impl Default for AutoApplyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_changed_lines: 1,
+            max_blocks: 1,
+        }
+    }
+}
+
+impl UiConfig {
+    /// Interval between UI ticks
+    pub fn tick_rate(&self) -> Duration {
+        Duration::from_millis(self.tick_rate_ms.unwrap_or(DEFAULT_TICK_RATE_MS))
+    }
+
+    /// Rows of results rendered per page
+    pub fn result_page_size(&self) -> usize {
+        self.result_page_size.unwrap_or(DEFAULT_RESULT_PAGE_SIZE)
+    }
+
+    pub fn fuzzy_min_score(&self) -> i64 {
+        self.fuzzy_min_score.unwrap_or(DEFAULT_FUZZY_MIN_SCORE)
+    }
+
+    pub fn fuzzy_max_results(&self) -> usize {
+        self.fuzzy_max_results.unwrap_or(DEFAULT_FUZZY_MAX_RESULTS)
+    }
 }
 
 impl Default for Config {
@@ -45,7 +454,18 @@ impl Default for Config {
             terminal: TerminalConfig {
                 default_shell: None,
                 history_limit: 1000,
+                state_file: TerminalConfig::default_state_file(),
+                scroll_behavior: ScrollBehavior::default(),
+                command_timeout_secs: None,
             },
+            history: HistoryConfig::default(),
+            ui: UiConfig::default(),
+            prompt_templates: Vec::new(),
+            ripgrep: RipgrepConfig::default(),
+            task_models: HashMap::new(),
+            backups: BackupConfig::default(),
+            auto_apply: AutoApplyConfig::default(),
+            context: ContextConfig::default(),
         }
     }
 }
@@ -55,11 +475,128 @@ impl Config {
         Self::default()
     }
     
+    /// Check the configuration for missing or nonsensical values before the app starts
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.app_name.trim().is_empty() {
+            return Err(ConfigError::MissingField("app_name".to_string()));
+        }
+
+        let mut seen = Vec::new();
+        for server in &self.mcp_servers {
+            server.validate()?;
+            if seen.contains(&&server.id) {
+                return Err(ConfigError::DuplicateServer(server.id.clone()));
+            }
+            seen.push(&server.id);
+        }
+
+        let limits = [
+            ("terminal.history_limit", self.terminal.history_limit),
+            ("history.chat_limit", self.history.chat_limit),
+            ("history.command_limit", self.history.command_limit),
+            ("history.recent_files_limit", self.history.recent_files_limit),
+        ];
+        for (field, value) in limits {
+            if value == 0 || value > MAX_HISTORY_LIMIT {
+                return Err(ConfigError::OutOfRange {
+                    field,
+                    value,
+                    max: MAX_HISTORY_LIMIT,
+                });
+            }
+        }
+
+        if !self.context.file_template.contains("{content}") {
+            return Err(ConfigError::MissingFileContent);
+        }
+
+        for (task, model) in &self.task_models {
+            if model.trim().is_empty() {
+                return Err(ConfigError::MissingField(format!("task_models.{}", task.name())));
+            }
+        }
+
+        if let Some(tick_rate) = self
+            .ui
+            .tick_rate_ms
+            .filter(|&tick_rate| tick_rate == 0 || tick_rate > MAX_TICK_RATE_MS)
+        {
+            return Err(ConfigError::OutOfRange {
+                field: "ui.tick_rate_ms",
+                value: tick_rate as usize,
+                max: MAX_TICK_RATE_MS as usize,
+            });
+        }
+
+        if let Some(timeout) = self
+            .terminal
+            .command_timeout_secs
+            .filter(|&timeout| timeout == 0 || timeout > MAX_COMMAND_TIMEOUT_SECS)
+        {
+            return Err(ConfigError::OutOfRange {
+                field: "terminal.command_timeout_secs",
+                value: timeout as usize,
+                max: MAX_COMMAND_TIMEOUT_SECS as usize,
+            });
+        }
+
+        if let Some(page_size) = self
+            .ui
+            .result_page_size
+            .filter(|&page_size| page_size == 0 || page_size > MAX_RESULT_PAGE_SIZE)
+        {
+            return Err(ConfigError::OutOfRange {
+                field: "ui.result_page_size",
+                value: page_size,
+                max: MAX_RESULT_PAGE_SIZE,
+            });
+        }
+
+        if let Some(max_results) = self
+            .ui
+            .fuzzy_max_results
+            .filter(|&max_results| max_results == 0 || max_results > MAX_RESULT_PAGE_SIZE)
+        {
+            return Err(ConfigError::OutOfRange {
+                field: "ui.fuzzy_max_results",
+                value: max_results,
+                max: MAX_RESULT_PAGE_SIZE,
+            });
+        }
+
+        if let Some(keep) = self
+            .backups
+            .keep_versions
+            .filter(|&keep| keep == 0 || keep > MAX_BACKUP_VERSIONS)
+        {
+            return Err(ConfigError::OutOfRange {
+                field: "backups.keep_versions",
+                value: keep,
+                max: MAX_BACKUP_VERSIONS,
+            });
+        }
+
+        if let Some(view) = self
+            .ui
+            .default_view
+            .as_ref()
+            .filter(|view| MainViewType::from_name(view).is_none())
+        {
+            return Err(ConfigError::UnknownView(view.clone()));
+        }
+
+        Ok(())
+    }
+
     pub fn get_usize(&self, key: &str) -> Option<usize> {
         match key {
             "terminal.history_limit" => Some(self.terminal.history_limit),
+            "history.chat_limit" => Some(self.history.chat_limit),
+            "history.command_limit" => Some(self.history.command_limit),
+            "history.recent_files_limit" => Some(self.history.recent_files_limit),
             "tree_sitter.max_file_size" => Some(5 * 1024 * 1024), // 5MB default
             "tree_sitter.max_parsers_per_lang" => Some(4),        // 4 parsers per language default
+            "tree_sitter.max_concurrent_parses" => Some(4),       // 4 parses in flight default
             _ => None,
         }
     }
@@ -94,4 +631,189 @@ impl Config {
 
         fs::write(path, content).map_err(|e| TaskError::IoError(e))
     }
+
+    /// Defaults that read and write nothing under `$HOME`, so tests don't depend on, or
+    /// change, the files of whoever runs them
+    #[cfg(test)]
+    pub fn without_persistence() -> Self {
+        let mut config = Config::default();
+        config.history.command_history_file = None;
+        config.history.command_frequency_file = None;
+        config.history.recent_files_file = None;
+        config.terminal.state_file = None;
+        config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server(id: &str, url: &str, api_key: Option<&str>) -> McpServerConfig {
+        McpServerConfig {
+            id: id.to_string(),
+            name: id.to_string(),
+            url: url.to_string(),
+            api_key: api_key.map(Secret::new),
+            enabled: true,
+            headers: McpServerConfig::default_headers(),
+            requests_per_minute: None,
+            context_token_budget: None,
+        }
+    }
+
+    fn valid_config() -> Config {
+        let mut config = Config::default();
+        config.mcp_servers.push(server(
+            "openrouter",
+            "https://openrouter.ai/api/v1/chat/completions",
+            Some("sk-or-v1-test0123456789abcdef"),
+        ));
+        config
+    }
+
+    #[test]
+    fn test_valid_config() {
+        assert_eq!(Config::default().validate(), Ok(()));
+        assert_eq!(valid_config().validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_invalid_server_url() {
+        let mut config = valid_config();
+        config.mcp_servers[0].url = "not a url".to_string();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::InvalidUrl { .. })
+        ));
+
+        config.mcp_servers[0].url = "ftp://openrouter.ai/api".to_string();
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("unsupported scheme 'ftp'"));
+
+        config.mcp_servers[0].url = " ".to_string();
+        assert_eq!(
+            config.validate(),
+            Err(ConfigError::MissingField("mcp_servers.openrouter.url".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_missing_fields_and_empty_key() {
+        let mut config = valid_config();
+        config.mcp_servers[0].api_key = Some(Secret::new(""));
+        assert_eq!(
+            config.validate(),
+            Err(ConfigError::EmptyApiKey("openrouter".to_string()))
+        );
+
+        let mut config = valid_config();
+        config.app_name.clear();
+        assert_eq!(
+            config.validate(),
+            Err(ConfigError::MissingField("app_name".to_string()))
+        );
+
+        let mut config = valid_config();
+        config
+            .mcp_servers
+            .push(server("openrouter", "http://localhost:8080", None));
+        assert_eq!(
+            config.validate(),
+            Err(ConfigError::DuplicateServer("openrouter".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_task_models() {
+        let models: HashMap<LlmTask, String> =
+            serde_json::from_str(r#"{"review": "big-model", "apply-edit": "edit-model"}"#).unwrap();
+        assert_eq!(models[&LlmTask::Review], "big-model");
+        assert_eq!(models[&LlmTask::ApplyEdit], "edit-model");
+
+        let mut config = valid_config();
+        config.task_models = models;
+        assert_eq!(config.validate(), Ok(()));
+
+        config.task_models.insert(LlmTask::Explain, " ".to_string());
+        assert_eq!(
+            config.validate(),
+            Err(ConfigError::MissingField("task_models.explain".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_partial_history_section_uses_defaults() {
+        let history: HistoryConfig = serde_json::from_str(r#"{"chat_limit": 5}"#).unwrap();
+        let defaults = HistoryConfig::default();
+        assert_eq!(history.chat_limit, 5);
+        assert_eq!(history.command_limit, defaults.command_limit);
+        assert_eq!(history.command_history_file, defaults.command_history_file);
+        assert_eq!(history.recent_files_limit, defaults.recent_files_limit);
+
+        let history: HistoryConfig =
+            serde_json::from_str(r#"{"command_history_file": null}"#).unwrap();
+        assert_eq!(history.chat_limit, defaults.chat_limit);
+        assert_eq!(history.command_history_file, None);
+    }
+
+    #[test]
+    fn test_default_view_name() {
+        let mut config = valid_config();
+        config.ui.default_view = Some("code_outline".to_string());
+        assert_eq!(config.validate(), Ok(()));
+
+        config.ui.default_view = Some("sidebar".to_string());
+        assert_eq!(
+            config.validate(),
+            Err(ConfigError::UnknownView("sidebar".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_tick_rate() {
+        let mut config = valid_config();
+        assert_eq!(config.ui.tick_rate(), Duration::from_millis(DEFAULT_TICK_RATE_MS));
+
+        config.ui.tick_rate_ms = Some(16);
+        assert_eq!(config.validate(), Ok(()));
+        assert_eq!(config.ui.tick_rate(), Duration::from_millis(16));
+
+        config.ui.tick_rate_ms = Some(0);
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::OutOfRange { field: "ui.tick_rate_ms", value: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn test_limits_out_of_range() {
+        let mut config = valid_config();
+        config.history.chat_limit = 0;
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::OutOfRange { field: "history.chat_limit", value: 0, .. })
+        ));
+
+        let mut config = valid_config();
+        config.terminal.history_limit = usize::MAX;
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::OutOfRange { field: "terminal.history_limit", .. })
+        ));
+
+        let mut config = valid_config();
+        config.mcp_servers[0].requests_per_minute = Some(0);
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::OutOfRange { field: "mcp_servers.requests_per_minute", .. })
+        ));
+
+        let mut config = valid_config();
+        config.backups.keep_versions = Some(0);
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::OutOfRange { field: "backups.keep_versions", value: 0, .. })
+        ));
+    }
 }
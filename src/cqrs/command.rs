@@ -7,7 +7,7 @@ use std::fmt;
 pub trait Aggregate: Send + Sync {
     type Error: Error;
     
-    fn apply_event(&mut self, event: Event) -> Result<(), Self::Error>;
+    fn apply_event(&mut self, event: &dyn Event) -> Result<(), Self::Error>;
     fn current_version(&self) -> u32;
 }
 
@@ -37,7 +37,7 @@ impl Error for ValidationError {}
 
 // Event trait with versioning support
 #[async_trait]
-pub trait Event: Send + Sync + Clone {
+pub trait Event: EventClone + Send + Sync {
     fn version(&self) -> u32;
     fn aggregate_id(&self) -> &str;
     fn event_type(&self) -> &str;
@@ -50,6 +50,23 @@ pub trait Event: Send + Sync + Clone {
     async fn deserialize(bytes: &[u8]) -> Result<Self, Box<dyn Error>> where Self: Sized;
 }
 
+// Lets boxed events be cloned; implemented for every `Clone` event
+pub trait EventClone {
+    fn clone_box(&self) -> Box<dyn Event>;
+}
+
+impl<T: Event + Clone + 'static> EventClone for T {
+    fn clone_box(&self) -> Box<dyn Event> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn Event> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
 // Command handler trait
 #[async_trait]
 pub trait CommandHandler<C: Command>: Send + Sync {
@@ -3,6 +3,7 @@ use futures::stream::{self, StreamExt};
 use parking_lot::RwLock;
 use std::collections::HashMap;
 use std::error::Error;
+use std::ops::Range;
 use std::sync::Arc;
 use tokio::sync::Semaphore;
 
@@ -12,6 +13,24 @@ use super::snapshot::{Snapshot, SnapshotStrategy, HybridSnapshotStrategy, Snapsh
 const MAX_CONCURRENT_OPERATIONS: usize = 32;
 const DEFAULT_BATCH_SIZE: usize = 100;
 
+// Limits for the append path of `InMemoryEventStore`
+#[derive(Debug, Clone, Copy)]
+pub struct EventStoreConfig {
+    // Events whose metadata is prepared at once
+    pub max_concurrent_operations: usize,
+    // Events appended to a stream per chunk
+    pub batch_size: usize,
+}
+
+impl Default for EventStoreConfig {
+    fn default() -> Self {
+        EventStoreConfig {
+            max_concurrent_operations: MAX_CONCURRENT_OPERATIONS,
+            batch_size: DEFAULT_BATCH_SIZE,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum EventStoreError {
     ConcurrencyError(String),
@@ -70,16 +89,43 @@ pub trait EventStore: Send + Sync {
     ) -> Result<(), EventStoreError>;
 }
 
+// Events of one stream still held in memory. Those covered by a snapshot may have been
+// compacted away; `pruned` of them, so the event at index `i` has version `pruned + i`.
+#[derive(Clone, Default)]
+struct EventStream {
+    pruned: usize,
+    events: Vec<(Box<dyn Event>, EventMetadata)>,
+}
+
+impl EventStream {
+    // Events ever appended, pruned or not
+    fn len(&self) -> usize {
+        self.pruned + self.events.len()
+    }
+}
+
 // Optimized in-memory event store implementation
 pub struct InMemoryEventStore {
-    events: Arc<RwLock<HashMap<String, Vec<(Box<dyn Event>, EventMetadata)>>>>,
+    events: Arc<RwLock<HashMap<String, EventStream>>>,
     snapshots: Arc<RwLock<HashMap<String, Snapshot>>>,
     snapshot_strategy: Box<dyn SnapshotStrategy>,
     semaphore: Arc<Semaphore>,
+    config: EventStoreConfig,
+    metrics: Arc<RwLock<EventStoreMetrics>>,
 }
 
 impl InMemoryEventStore {
     pub fn new() -> Self {
+        Self::with_config(EventStoreConfig::default())
+    }
+
+    pub fn with_config(config: EventStoreConfig) -> Self {
+        // Zero would never hand out a permit, or never make progress chunking
+        let config = EventStoreConfig {
+            max_concurrent_operations: config.max_concurrent_operations.max(1),
+            batch_size: config.batch_size.max(1),
+        };
+
         // Use hybrid snapshot strategy by default
         let snapshot_strategy = Box::new(HybridSnapshotStrategy::new(100, 3600));
         
@@ -87,34 +133,117 @@ impl InMemoryEventStore {
             events: Arc::new(RwLock::new(HashMap::new())),
             snapshots: Arc::new(RwLock::new(HashMap::new())),
             snapshot_strategy,
-            semaphore: Arc::new(Semaphore::new(MAX_CONCURRENT_OPERATIONS)),
+            semaphore: Arc::new(Semaphore::new(config.max_concurrent_operations)),
+            config,
+            metrics: Arc::new(RwLock::new(EventStoreMetrics::new())),
         }
     }
 
+    pub fn config(&self) -> EventStoreConfig {
+        self.config
+    }
+
+    // Counters so far, including the size of every batch appended
+    pub fn metrics(&self) -> EventStoreMetrics {
+        self.metrics.read().clone()
+    }
+
+    // Drop the events of `stream_id` that its current snapshot already covers, leaving
+    // the snapshot as the baseline to replay from. Versions, and the offsets
+    // `read_events` takes, are unchanged. Returns how many events were dropped.
+    pub fn compact(&self, stream_id: &str) -> Result<usize, EventStoreError> {
+        let snapshot_version = self
+            .snapshots
+            .read()
+            .get(stream_id)
+            .map(|snapshot| snapshot.version as usize)
+            .ok_or(EventStoreError::SnapshotError(SnapshotError::NotFound))?;
+
+        let mut events_lock = self.events.write();
+        let stream = events_lock.get_mut(stream_id)
+            .ok_or_else(|| EventStoreError::StorageError(
+                format!("Stream {} not found", stream_id)
+            ))?;
+
+        let covered = snapshot_version
+            .min(stream.len())
+            .saturating_sub(stream.pruned);
+        stream.events.drain(..covered);
+        stream.pruned += covered;
+        Ok(covered)
+    }
+
+    // Events of `stream_id` still held, oldest first, for which `predicate` holds. For
+    // projections that only care about some of a stream.
+    pub fn read_events_filtered<P>(
+        &self,
+        stream_id: &str,
+        predicate: P,
+    ) -> Result<Vec<Box<dyn Event>>, EventStoreError>
+    where
+        P: Fn(&dyn Event, &EventMetadata) -> bool,
+    {
+        let events_lock = self.events.read();
+        let stream = events_lock.get(stream_id)
+            .ok_or_else(|| EventStoreError::StorageError(
+                format!("Stream {} not found", stream_id)
+            ))?;
+
+        Ok(stream.events
+            .iter()
+            .filter(|(event, metadata)| predicate(event.as_ref(), metadata))
+            .map(|(event, _)| event.clone())
+            .collect())
+    }
+
+    // Events of `stream_id` whose `event_type()` is `event_type`
+    pub fn read_events_of_type(
+        &self,
+        stream_id: &str,
+        event_type: &str,
+    ) -> Result<Vec<Box<dyn Event>>, EventStoreError> {
+        self.read_events_filtered(stream_id, |event, _| event.event_type() == event_type)
+    }
+
+    // Events of `stream_id` recorded with schema version `schema_version`
+    pub fn read_events_with_schema_version(
+        &self,
+        stream_id: &str,
+        schema_version: u32,
+    ) -> Result<Vec<Box<dyn Event>>, EventStoreError> {
+        self.read_events_filtered(stream_id, |_, metadata| {
+            metadata.schema_version == schema_version
+        })
+    }
+
+    // Events of `stream_id` appended within `timestamps`, in Unix seconds
+    pub fn read_events_between(
+        &self,
+        stream_id: &str,
+        timestamps: Range<u64>,
+    ) -> Result<Vec<Box<dyn Event>>, EventStoreError> {
+        self.read_events_filtered(stream_id, |_, metadata| {
+            timestamps.contains(&metadata.timestamp)
+        })
+    }
+
+    // Version of the first event still held for `stream_id`; earlier ones were compacted
+    pub fn first_retained_version(&self, stream_id: &str) -> Option<usize> {
+        self.events.read().get(stream_id).map(|stream| stream.pruned)
+    }
+
     async fn process_events_batch(
         &self,
         events: Vec<Box<dyn Event>>,
     ) -> Result<Vec<(Box<dyn Event>, EventMetadata)>, EventStoreError> {
         // Process events in parallel with controlled concurrency
-        let results = stream::iter(events)
-            .map(|event| {
-                let permit = self.semaphore.clone().acquire_owned();
-                async move {
-                    let _permit = permit.await;
-                    
-                    let metadata = EventMetadata {
-                        version: event.version(),
-                        schema_version: event.schema_version(),
-                        timestamp: std::time::SystemTime::now()
-                            .duration_since(std::time::UNIX_EPOCH)
-                            .unwrap()
-                            .as_secs(),
-                    };
-                    
-                    Ok((event, metadata))
-                }
-            })
-            .buffer_unordered(MAX_CONCURRENT_OPERATIONS)
+        let mut pending = Vec::with_capacity(events.len());
+        for event in events {
+            pending.push(prepare_event(self.semaphore.clone(), event));
+        }
+        let results = stream::iter(pending)
+            // Ordered, so events land in the stream in the order they were given
+            .buffered(self.config.max_concurrent_operations)
             .collect::<Vec<_>>()
             .await;
 
@@ -128,6 +257,25 @@ impl InMemoryEventStore {
     }
 }
 
+// Stamp `event` with its metadata once a permit is free
+async fn prepare_event(
+    semaphore: Arc<Semaphore>,
+    event: Box<dyn Event>,
+) -> Result<(Box<dyn Event>, EventMetadata), EventStoreError> {
+    let _permit = semaphore.acquire_owned().await;
+
+    let metadata = EventMetadata {
+        version: event.version(),
+        schema_version: event.schema_version(),
+        timestamp: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs(),
+    };
+
+    Ok((event, metadata))
+}
+
 #[async_trait]
 impl EventStore for InMemoryEventStore {
     async fn append_events(
@@ -139,11 +287,11 @@ impl EventStore for InMemoryEventStore {
         let processed_events = self.process_events_batch(events).await?;
         
         let mut events_lock = self.events.write();
-        let stream_events = events_lock.entry(stream_id.to_string())
-            .or_insert_with(Vec::new);
+        let stream = events_lock.entry(stream_id.to_string())
+            .or_default();
 
         // Optimistic concurrency check
-        let current_version = stream_events.len() as i64 - 1;
+        let current_version = stream.len() as i64 - 1;
         if expected_version >= 0 && current_version != expected_version {
             return Err(EventStoreError::ConcurrencyError(
                 format!("Expected version {}, but current version is {}", 
@@ -152,26 +300,30 @@ impl EventStore for InMemoryEventStore {
         }
 
         // Append events in batches
-        for chunk in processed_events.chunks(DEFAULT_BATCH_SIZE) {
-            stream_events.extend(chunk.to_vec());
+        for chunk in processed_events.chunks(self.config.batch_size) {
+            stream.events.extend(chunk.to_vec());
+            self.metrics.write().record_batch(chunk.len());
+            metrics::histogram!("event_store_batch_size", chunk.len() as f64);
         }
 
-        // Check if snapshot should be created
-        if self.snapshot_strategy.should_snapshot(stream_events.len() as u32) {
+        // Check if snapshot should be created, going by the stream's version
+        // so that compaction doesn't reset the count
+        if self.snapshot_strategy.should_snapshot(stream.len() as u32) {
             // Create snapshot asynchronously
             let stream_id = stream_id.to_string();
-            let events = stream_events.clone();
+            let last = stream.events.last().map(|(event, _)| event.clone());
+            let version = stream.len() as u32;
             let snapshots = self.snapshots.clone();
             
             tokio::spawn(async move {
-                let snapshot_data = events.last()
-                    .map(|(event, _)| event.serialize().await.ok())
-                    .flatten()
-                    .unwrap_or_default();
+                let snapshot_data = match last {
+                    Some(event) => event.serialize().await.unwrap_or_default(),
+                    None => Vec::new(),
+                };
 
                 let snapshot = Snapshot::new(
                     stream_id.clone(),
-                    events.len() as u32,
+                    version,
                     snapshot_data,
                 );
 
@@ -190,15 +342,20 @@ impl EventStore for InMemoryEventStore {
     ) -> Result<Vec<Box<dyn Event>>, EventStoreError> {
         let events_lock = self.events.read();
         
-        let stream_events = events_lock.get(stream_id)
+        let stream = events_lock.get(stream_id)
             .ok_or_else(|| EventStoreError::StorageError(
                 format!("Stream {} not found", stream_id)
             ))?;
 
-        let start_idx = start.max(0) as usize;
-        let end_idx = (start + count).min(stream_events.len() as i64) as usize;
+        // Offsets are versions; compacted events are simply no longer returned
+        let pruned = stream.pruned as i64;
+        let start_idx = (start.max(pruned) - pruned) as usize;
+        let end_idx = ((start + count).min(stream.len() as i64) - pruned).max(0) as usize;
+        if start_idx >= end_idx {
+            return Ok(Vec::new());
+        }
 
-        Ok(stream_events[start_idx..end_idx]
+        Ok(stream.events[start_idx..end_idx]
             .iter()
             .map(|(event, _)| event.clone())
             .collect())
@@ -221,21 +378,227 @@ impl EventStore for InMemoryEventStore {
     }
 }
 
+impl Default for InMemoryEventStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 // Metrics for monitoring event store performance
+#[derive(Debug, Clone, Default)]
 pub struct EventStoreMetrics {
     pub events_processed: u64,
     pub snapshots_created: u64,
     pub average_event_processing_time_ms: f64,
     pub average_batch_size: f64,
+    // Size of each batch appended, oldest first
+    pub batch_sizes: Vec<usize>,
 }
 
 impl EventStoreMetrics {
     pub fn new() -> Self {
-        EventStoreMetrics {
-            events_processed: 0,
-            snapshots_created: 0,
-            average_event_processing_time_ms: 0.0,
-            average_batch_size: 0.0,
+        Self::default()
+    }
+
+    fn record_batch(&mut self, size: usize) {
+        self.batch_sizes.push(size);
+        self.events_processed += size as u64;
+        self.average_batch_size = self.events_processed as f64 / self.batch_sizes.len() as f64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone)]
+    struct Counted {
+        aggregate_id: String,
+        amount: i64,
+    }
+
+    #[async_trait]
+    impl Event for Counted {
+        fn version(&self) -> u32 {
+            1
+        }
+
+        fn aggregate_id(&self) -> &str {
+            &self.aggregate_id
+        }
+
+        fn event_type(&self) -> &str {
+            "counted"
+        }
+
+        fn schema_version(&self) -> u32 {
+            1
+        }
+
+        async fn serialize(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+            Ok(self.amount.to_le_bytes().to_vec())
+        }
+
+        async fn deserialize(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+            let amount = i64::from_le_bytes(bytes.try_into()?);
+            Ok(Counted { aggregate_id: "counter".to_string(), amount })
+        }
+    }
+
+    // A second kind of event in the same stream, on a newer schema
+    #[derive(Clone)]
+    struct Renamed {
+        name: String,
+    }
+
+    #[async_trait]
+    impl Event for Renamed {
+        fn version(&self) -> u32 {
+            1
+        }
+
+        fn aggregate_id(&self) -> &str {
+            "counter"
+        }
+
+        fn event_type(&self) -> &str {
+            "renamed"
+        }
+
+        fn schema_version(&self) -> u32 {
+            2
+        }
+
+        async fn serialize(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+            Ok(self.name.as_bytes().to_vec())
+        }
+
+        async fn deserialize(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+            Ok(Renamed { name: String::from_utf8(bytes.to_vec())? })
         }
     }
-}
\ No newline at end of file
+
+    fn counted(amounts: impl IntoIterator<Item = i64>) -> Vec<Box<dyn Event>> {
+        amounts
+            .into_iter()
+            .map(|amount| {
+                Box::new(Counted { aggregate_id: "counter".to_string(), amount }) as Box<dyn Event>
+            })
+            .collect()
+    }
+
+    async fn amounts(events: Vec<Box<dyn Event>>) -> Vec<i64> {
+        let mut amounts = Vec::new();
+        for event in events {
+            let bytes = event.serialize().await.unwrap();
+            amounts.push(i64::from_le_bytes(bytes.try_into().unwrap()));
+        }
+        amounts
+    }
+
+    #[tokio::test]
+    async fn test_large_append_under_small_limits() {
+        let store = InMemoryEventStore::with_config(EventStoreConfig {
+            max_concurrent_operations: 2,
+            batch_size: 64,
+        });
+
+        store.append_events("counter", counted(0..250), -1).await.unwrap();
+
+        let events = store.read_events("counter", 0, 250).await.unwrap();
+        assert_eq!(amounts(events).await, (0..250).collect::<Vec<_>>());
+
+        let metrics = store.metrics();
+        assert_eq!(metrics.batch_sizes, vec![64, 64, 64, 58]);
+        assert_eq!(metrics.events_processed, 250);
+        assert_eq!(metrics.average_batch_size, 62.5);
+    }
+
+    #[tokio::test]
+    async fn test_replay_after_compaction() {
+        let store = InMemoryEventStore::new();
+        store.append_events("counter", counted(1..=10), -1).await.unwrap();
+
+        // State after the first six events, as an aggregate would snapshot it
+        let total: i64 = (1..=6).sum();
+        let snapshot = Snapshot::new("counter".to_string(), 6, total.to_le_bytes().to_vec());
+        store.create_snapshot("counter", snapshot).await.unwrap();
+
+        assert_eq!(store.compact("counter").unwrap(), 6);
+        assert_eq!(store.first_retained_version("counter"), Some(6));
+        // Already compacted up to the snapshot
+        assert_eq!(store.compact("counter").unwrap(), 0);
+
+        // Replay: the snapshot, then everything after it
+        let snapshot = store.read_snapshot("counter").await.unwrap().unwrap();
+        let mut state = i64::from_le_bytes(snapshot.data.as_slice().try_into().unwrap());
+        let rest = store.read_events("counter", snapshot.version as i64, 100).await.unwrap();
+        assert_eq!(amounts(rest).await, vec![7, 8, 9, 10]);
+        for amount in amounts(store.read_events("counter", 6, 100).await.unwrap()).await {
+            state += amount;
+        }
+        assert_eq!(state, (1..=10).sum::<i64>());
+
+        // Offsets into the compacted part find nothing, and versions carry on
+        assert!(store.read_events("counter", 0, 6).await.unwrap().is_empty());
+        assert_eq!(amounts(store.read_events("counter", 4, 4).await.unwrap()).await, vec![7, 8]);
+        store.append_events("counter", counted([11]), 9).await.unwrap();
+        assert_eq!(amounts(store.read_events("counter", 10, 1).await.unwrap()).await, vec![11]);
+    }
+
+    // The version of the stream's snapshot, once the background task has written it
+    async fn snapshot_version(store: &InMemoryEventStore, at_least: u32) -> Option<u32> {
+        for _ in 0..100 {
+            match store.read_snapshot("counter").await.unwrap() {
+                Some(snapshot) if snapshot.version >= at_least => return Some(snapshot.version),
+                _ => tokio::time::sleep(std::time::Duration::from_millis(5)).await,
+            }
+        }
+        None
+    }
+
+    #[tokio::test]
+    async fn test_snapshots_follow_the_version_past_compaction() {
+        let store = InMemoryEventStore::new();
+        store.append_events("counter", counted(0..99), -1).await.unwrap();
+        assert!(store.read_snapshot("counter").await.unwrap().is_none());
+
+        // The default strategy snapshots once the stream reaches 100 events
+        store.append_events("counter", counted([99]), 98).await.unwrap();
+        assert_eq!(snapshot_version(&store, 100).await, Some(100));
+        assert_eq!(store.compact("counter").unwrap(), 100);
+
+        // Only one event is held now, but the stream is at version 101
+        store.append_events("counter", counted([100]), 99).await.unwrap();
+        assert_eq!(snapshot_version(&store, 101).await, Some(101));
+    }
+
+    #[tokio::test]
+    async fn test_filtering_mixed_events() {
+        let store = InMemoryEventStore::new();
+        let mut events = counted([1, 2]);
+        events.push(Box::new(Renamed { name: "total".to_string() }));
+        events.extend(counted([3]));
+        events.push(Box::new(Renamed { name: "sum".to_string() }));
+        store.append_events("counter", events, -1).await.unwrap();
+
+        let counts = store.read_events_of_type("counter", "counted").unwrap();
+        assert_eq!(amounts(counts).await, vec![1, 2, 3]);
+
+        let renames = store.read_events_with_schema_version("counter", 2).unwrap();
+        let mut names = Vec::new();
+        for event in renames {
+            names.push(String::from_utf8(event.serialize().await.unwrap()).unwrap());
+        }
+        assert_eq!(names, ["total", "sum"]);
+
+        // Everything was appended just now
+        assert_eq!(store.read_events_between("counter", 0..u64::MAX).unwrap().len(), 5);
+        assert!(store.read_events_between("counter", 0..1).unwrap().is_empty());
+        assert!(store.read_events_filtered("counter", |_, _| false).unwrap().is_empty());
+        assert!(matches!(
+            store.read_events_filtered("missing", |_, _| true),
+            Err(EventStoreError::StorageError(_))
+        ));
+    }
+}
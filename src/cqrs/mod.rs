@@ -1,10 +1,12 @@
 pub mod command;
 pub mod event_store;
 pub mod snapshot;
+pub mod tool_calls;
 
 pub use command::{Command, CommandHandler, Event};
 pub use event_store::EventStore;
 pub use snapshot::Snapshot;
+pub use tool_calls::{RecordingTaskManager, ToolCallEvent};
 
 // Re-export key types
 pub use command::ValidationError;
\ No newline at end of file
@@ -0,0 +1,296 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::error::Error;
+use std::sync::Arc;
+use std::time::Instant;
+
+use super::command::Event;
+use super::event_store::{EventStore, EventStoreError};
+use crate::config::LogLevel;
+use crate::error::TaskError;
+use crate::logging;
+use crate::secret::{self, MASK};
+use crate::task::{Task, TaskManagerTrait, TaskResult};
+
+// Parameters whose values are always masked, whatever they look like
+const SENSITIVE_PARAMS: &[&str] = &[
+    "api_key",
+    "apikey",
+    "token",
+    "password",
+    "secret",
+    "authorization",
+];
+
+// Longest result summary kept in an event
+const MAX_SUMMARY_CHARS: usize = 120;
+
+// One tool invocation by the agent, as recorded in the session's stream
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCallEvent {
+    pub session_id: String,
+    pub tool: String,
+    // Parameters with secrets masked
+    pub params: Value,
+    pub summary: String,
+    pub duration_ms: u64,
+    pub success: bool,
+}
+
+impl ToolCallEvent {
+    // Record of running `task`, which took `duration_ms` and ended with `result`
+    pub fn new(
+        session_id: &str,
+        task: &Task,
+        result: &Result<TaskResult, TaskError>,
+        duration_ms: u64,
+    ) -> Self {
+        ToolCallEvent {
+            session_id: session_id.to_string(),
+            tool: task.name.clone(),
+            params: redact_params(&task.params),
+            summary: summarize(result),
+            duration_ms,
+            success: result.is_ok(),
+        }
+    }
+
+    // One line of the `/history` timeline
+    pub fn render(&self) -> String {
+        format!(
+            "{} {} -> {} ({} ms): {}",
+            self.tool,
+            self.params,
+            if self.success { "ok" } else { "failed" },
+            self.duration_ms,
+            self.summary
+        )
+    }
+}
+
+#[async_trait]
+impl Event for ToolCallEvent {
+    fn version(&self) -> u32 {
+        1
+    }
+
+    fn aggregate_id(&self) -> &str {
+        &self.session_id
+    }
+
+    fn event_type(&self) -> &str {
+        "tool_called"
+    }
+
+    fn schema_version(&self) -> u32 {
+        1
+    }
+
+    async fn serialize(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    async fn deserialize(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
+
+// Stream holding the tool calls of one session
+pub fn stream_id(session_id: &str) -> String {
+    format!("tool_calls-{}", session_id)
+}
+
+// `params` with sensitive keys masked and secrets scrubbed from every string
+pub fn redact_params(params: &Value) -> Value {
+    match params {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| {
+                    let sensitive = SENSITIVE_PARAMS
+                        .iter()
+                        .any(|name| key.to_lowercase().contains(name));
+                    let value = if sensitive {
+                        Value::String(MASK.to_string())
+                    } else {
+                        redact_params(value)
+                    };
+                    (key.clone(), value)
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(redact_params).collect()),
+        Value::String(text) => Value::String(secret::redact(text)),
+        other => other.clone(),
+    }
+}
+
+// A short, redacted description of what a tool returned
+fn summarize(result: &Result<TaskResult, TaskError>) -> String {
+    let summary = match result {
+        Ok(TaskResult::Text(text)) => text.lines().next().unwrap_or("").to_string(),
+        Ok(TaskResult::Json(value)) => value.to_string(),
+        Ok(TaskResult::Binary(bytes)) => format!("{} bytes", bytes.len()),
+        Err(e) => e.to_string(),
+    };
+    let summary = secret::redact(&summary);
+    match summary.char_indices().nth(MAX_SUMMARY_CHARS) {
+        Some((end, _)) => format!("{}…", &summary[..end]),
+        None => summary,
+    }
+}
+
+// Runs tasks through `inner` and appends a `ToolCallEvent` for each to the session's
+// stream. A failed append is logged but doesn't fail the task.
+pub struct RecordingTaskManager<M: TaskManagerTrait> {
+    inner: M,
+    store: Arc<dyn EventStore>,
+    session_id: String,
+}
+
+impl<M: TaskManagerTrait> RecordingTaskManager<M> {
+    pub fn new(inner: M, store: Arc<dyn EventStore>, session_id: impl Into<String>) -> Self {
+        RecordingTaskManager {
+            inner,
+            store,
+            session_id: session_id.into(),
+        }
+    }
+
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+}
+
+#[async_trait]
+impl<M: TaskManagerTrait> TaskManagerTrait for RecordingTaskManager<M> {
+    async fn execute_task(&self, task: Task) -> Result<TaskResult, TaskError> {
+        let started = Instant::now();
+        let recorded = task.clone();
+        let result = self.inner.execute_task(task).await;
+
+        let duration_ms = started.elapsed().as_millis() as u64;
+        let event = ToolCallEvent::new(&self.session_id, &recorded, &result, duration_ms);
+        if let Err(e) = self
+            .store
+            .append_events(&stream_id(&self.session_id), vec![Box::new(event)], -1)
+            .await
+        {
+            logging::log(
+                LogLevel::Warn,
+                "tool_calls",
+                format!("Failed to record {} call: {}", recorded.name, e),
+            );
+        }
+        result
+    }
+}
+
+// Every tool call recorded for `session_id`, oldest first
+pub async fn replay(
+    store: &dyn EventStore,
+    session_id: &str,
+) -> Result<Vec<ToolCallEvent>, EventStoreError> {
+    let events = match store.read_events(&stream_id(session_id), 0, i64::MAX).await {
+        Ok(events) => events,
+        // Nothing recorded yet
+        Err(EventStoreError::StorageError(_)) => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut calls = Vec::with_capacity(events.len());
+    for event in events
+        .iter()
+        .filter(|event| event.event_type() == "tool_called")
+    {
+        let bytes = event
+            .serialize()
+            .await
+            .map_err(|e| EventStoreError::SerializationError(e.to_string()))?;
+        let call = <ToolCallEvent as Event>::deserialize(&bytes)
+            .await
+            .map_err(|e| EventStoreError::SerializationError(e.to_string()))?;
+        calls.push(call);
+    }
+    Ok(calls)
+}
+
+// What `/history` shows: the session's tool calls as a numbered timeline
+pub async fn timeline(store: &dyn EventStore, session_id: &str) -> Result<String, EventStoreError> {
+    let calls = replay(store, session_id).await?;
+    if calls.is_empty() {
+        return Ok("No tool calls recorded in this session".to_string());
+    }
+
+    let mut lines = vec![format!("Tool calls ({}):", calls.len())];
+    for (i, call) in calls.iter().enumerate() {
+        lines.push(format!("  {}. {}", i + 1, call.render()));
+    }
+    Ok(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cqrs::event_store::InMemoryEventStore;
+    use serde_json::json;
+
+    // Echoes the `text` param back, and fails tasks named "fail"
+    struct Echo;
+
+    #[async_trait]
+    impl TaskManagerTrait for Echo {
+        async fn execute_task(&self, task: Task) -> Result<TaskResult, TaskError> {
+            if task.name == "fail" {
+                return Err(TaskError::HandlerNotFound(task.name));
+            }
+            let text = task.params["text"].as_str().unwrap_or_default().to_string();
+            Ok(TaskResult::Text(text))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_executed_tasks_replay_as_a_timeline() {
+        let store: Arc<dyn EventStore> = Arc::new(InMemoryEventStore::new());
+        let manager = RecordingTaskManager::new(Echo, store.clone(), "session-1");
+
+        let result = manager
+            .execute_task(Task::new(
+                "echo",
+                json!({"text": "hello\nworld", "api_key": "sk-live"}),
+            ))
+            .await;
+        assert!(matches!(result, Ok(TaskResult::Text(_))));
+        assert!(
+            manager
+                .execute_task(Task::new("fail", json!({})))
+                .await
+                .is_err()
+        );
+
+        let calls = replay(store.as_ref(), "session-1").await.unwrap();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].tool, "echo");
+        assert_eq!(calls[0].params["api_key"], MASK);
+        assert_eq!(calls[0].summary, "hello");
+        assert!(calls[0].success);
+        assert!(!calls[1].success);
+
+        let history = timeline(store.as_ref(), "session-1").await.unwrap();
+        let lines: Vec<&str> = history.lines().collect();
+        assert_eq!(lines[0], "Tool calls (2):");
+        assert!(lines[1].starts_with("  1. echo "), "{}", lines[1]);
+        assert!(lines[1].contains("-> ok") && !lines[1].contains("sk-live"));
+        assert!(
+            lines[2].starts_with("  2. fail {} -> failed"),
+            "{}",
+            lines[2]
+        );
+
+        // Other sessions have their own streams
+        assert_eq!(
+            timeline(store.as_ref(), "session-2").await.unwrap(),
+            "No tool calls recorded in this session"
+        );
+    }
+}
@@ -1,5 +1,13 @@
 use thiserror::Error;
 
+use crate::actor::ActorError;
+use crate::cqrs::event_store::EventStoreError;
+use crate::integrations::IntegrationError;
+use crate::services::ripgrep::RipgrepError;
+use crate::services::tree_sitter::TreeSitterError;
+use crate::tools::{ApplyDiffError, CodeSearchError, FileError, TreeParserError};
+use crate::ui::diff::DiffError;
+
 #[derive(Debug, Error)]
 pub enum TaskError {
     #[error("Task execution failed: {0}")]
@@ -34,6 +42,31 @@ pub enum TaskError {
     
     #[error("Task manager not initialized")]
     NotInitialized,
+
+    #[error("Path is outside the workspace: {0}")]
+    PathOutsideWorkspace(std::path::PathBuf),
+
+    #[error(
+        "The model returned an empty response{}",
+        .finish_reason.as_deref().map(describe_finish_reason).unwrap_or_default()
+    )]
+    EmptyResponse { finish_reason: Option<String> },
+
+    #[error(
+        "Rate limit reached: too many requests queued, try again in {}s",
+        .retry_after.as_secs().max(1)
+    )]
+    RateLimited { retry_after: std::time::Duration },
+}
+
+/// Explain an LLM finish reason in words, for the empty-response notice
+fn describe_finish_reason(reason: &str) -> String {
+    match reason {
+        "length" => " (stopped at the max token limit)".to_string(),
+        "content_filter" => " (content was filtered)".to_string(),
+        "stop" => String::new(),
+        other => format!(" (finish reason: {})", other),
+    }
 }
 
 // Note: The #[derive(Error)] above already implements Display and Error trait
@@ -44,3 +77,180 @@ impl From<serde_json::Error> for TaskError {
         TaskError::SerializationError(err.to_string())
     }
 }
+
+/// Any error the app can surface, keeping the original error so the UI can match on
+/// where it came from and word the message for its category
+#[derive(Debug, Error)]
+pub enum SamusError {
+    #[error(transparent)]
+    Task(#[from] TaskError),
+
+    #[error(transparent)]
+    ReadFile(#[from] FileError),
+
+    #[error(transparent)]
+    ApplyDiff(#[from] ApplyDiffError),
+
+    #[error(transparent)]
+    Diff(#[from] DiffError),
+
+    #[error(transparent)]
+    TreeSitter(#[from] TreeSitterError),
+
+    #[error(transparent)]
+    TreeParser(#[from] TreeParserError),
+
+    #[error(transparent)]
+    CodeSearch(#[from] CodeSearchError),
+
+    #[error(transparent)]
+    Ripgrep(#[from] RipgrepError),
+
+    #[error(transparent)]
+    Integration(#[from] IntegrationError),
+
+    #[error("Event store error: {0}")]
+    EventStore(#[from] EventStoreError),
+
+    #[error("Actor error: {0}")]
+    Actor(#[from] ActorError),
+}
+
+/// Broad kinds of failure, for choosing how to present an error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCategory {
+    Cancelled,
+    Configuration,
+    FileSystem,
+    Parsing,
+    Search,
+    Model,
+    Editor,
+    Internal,
+}
+
+impl ErrorCategory {
+    /// Short heading shown before the error message
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Cancelled => "Cancelled",
+            Self::Configuration => "Configuration problem",
+            Self::FileSystem => "File error",
+            Self::Parsing => "Parse error",
+            Self::Search => "Search error",
+            Self::Model => "Model error",
+            Self::Editor => "Editor integration error",
+            Self::Internal => "Internal error",
+        }
+    }
+}
+
+impl SamusError {
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            Self::Task(err) => match err {
+                TaskError::Cancelled => ErrorCategory::Cancelled,
+                TaskError::InvalidConfiguration(_)
+                | TaskError::InvalidParameter(_)
+                | TaskError::NotInitialized => ErrorCategory::Configuration,
+                TaskError::IoError(_)
+                | TaskError::FileSystem(_)
+                | TaskError::PathOutsideWorkspace(_) => ErrorCategory::FileSystem,
+                TaskError::EmptyResponse { .. } | TaskError::RateLimited { .. } => {
+                    ErrorCategory::Model
+                }
+                _ => ErrorCategory::Internal,
+            },
+            Self::ReadFile(_) | Self::ApplyDiff(_) | Self::Diff(_) => ErrorCategory::FileSystem,
+            Self::TreeSitter(_) | Self::TreeParser(_) => ErrorCategory::Parsing,
+            Self::CodeSearch(_) | Self::Ripgrep(_) => ErrorCategory::Search,
+            Self::Integration(_) => ErrorCategory::Editor,
+            Self::EventStore(_) | Self::Actor(_) => ErrorCategory::Internal,
+        }
+    }
+
+    /// Message for display, headed by the error's category
+    pub fn user_message(&self) -> String {
+        format!("{}: {}", self.category().label(), self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_task_errors_convert_by_kind() {
+        let cases = [
+            (TaskError::Cancelled, ErrorCategory::Cancelled),
+            (TaskError::NotInitialized, ErrorCategory::Configuration),
+            (TaskError::FileSystem("gone".to_string()), ErrorCategory::FileSystem),
+            (TaskError::EmptyResponse { finish_reason: None }, ErrorCategory::Model),
+            (TaskError::Tool("broken".to_string()), ErrorCategory::Internal),
+        ];
+        for (err, category) in cases {
+            let err = SamusError::from(err);
+            assert!(matches!(err, SamusError::Task(_)));
+            assert_eq!(err.category(), category);
+        }
+
+        let io = std::io::Error::new(std::io::ErrorKind::NotFound, "missing");
+        assert_eq!(SamusError::from(TaskError::from(io)).category(), ErrorCategory::FileSystem);
+    }
+
+    #[test]
+    fn test_file_and_diff_errors_convert() {
+        let err = SamusError::from(FileError::NotFound(PathBuf::from("a.txt")));
+        assert!(matches!(err, SamusError::ReadFile(FileError::NotFound(_))));
+        assert_eq!(err.user_message(), "File error: File not found: a.txt");
+
+        let err = SamusError::from(ApplyDiffError::ContentMismatch);
+        assert!(matches!(err, SamusError::ApplyDiff(ApplyDiffError::ContentMismatch)));
+        assert_eq!(err.category(), ErrorCategory::FileSystem);
+
+        let err = SamusError::from(DiffError::DiffGenerationError);
+        assert!(matches!(err, SamusError::Diff(_)));
+        assert_eq!(err.to_string(), "Failed to generate diff");
+    }
+
+    #[test]
+    fn test_parse_and_search_errors_convert() {
+        let err = SamusError::from(TreeSitterError::UnsupportedLanguage("cobol".to_string()));
+        assert!(matches!(err, SamusError::TreeSitter(_)));
+        assert_eq!(err.user_message(), "Parse error: Language not supported: cobol");
+
+        let err = SamusError::from(TreeParserError::FileSizeExceeded);
+        assert!(matches!(err, SamusError::TreeParser(_)));
+        assert_eq!(err.category(), ErrorCategory::Parsing);
+
+        let err = SamusError::from(CodeSearchError::QueryError("bad".to_string()));
+        assert!(matches!(err, SamusError::CodeSearch(_)));
+        assert_eq!(err.category(), ErrorCategory::Search);
+
+        let err = SamusError::from(RipgrepError::BinaryNotFound);
+        assert!(matches!(err, SamusError::Ripgrep(RipgrepError::BinaryNotFound)));
+        assert_eq!(err.user_message(), "Search error: Ripgrep binary not found");
+    }
+
+    #[test]
+    fn test_integration_errors_convert() {
+        let err = SamusError::from(IntegrationError::WorkspaceNotFound);
+        assert!(matches!(err, SamusError::Integration(_)));
+        assert_eq!(err.user_message(), "Editor integration error: Workspace not found");
+    }
+
+    #[test]
+    fn test_runtime_errors_convert() {
+        let err = SamusError::from(ActorError::MailboxFull);
+        assert!(matches!(err, SamusError::Actor(ActorError::MailboxFull)));
+        assert_eq!(err.user_message(), "Internal error: Actor error: mailbox full");
+
+        let err = SamusError::from(EventStoreError::StorageError("disk full".to_string()));
+        assert!(matches!(err, SamusError::EventStore(EventStoreError::StorageError(_))));
+        assert_eq!(
+            err.user_message(),
+            "Internal error: Event store error: Storage error: disk full"
+        );
+    }
+}
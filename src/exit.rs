@@ -0,0 +1,137 @@
+//! How the process exits: its status codes, and putting the terminal back however the
+//! TUI session ends
+
+use ratatui::{Terminal, backend::Backend};
+use std::io;
+use std::ops::{Deref, DerefMut};
+use std::process::ExitCode;
+
+/// The user quit, or a headless command finished
+pub const EXIT_SUCCESS: u8 = 0;
+
+/// A fatal error: bad configuration, failed I/O, or a command that failed
+pub const EXIT_FAILURE: u8 = 1;
+
+/// How a TUI session ended
+#[derive(Debug)]
+pub enum SessionEnd {
+    /// The user quit
+    Quit,
+    /// The event loop stopped on an error
+    Failed(io::Error),
+}
+
+impl SessionEnd {
+    pub fn code(&self) -> u8 {
+        match self {
+            SessionEnd::Quit => EXIT_SUCCESS,
+            SessionEnd::Failed(_) => EXIT_FAILURE,
+        }
+    }
+
+    pub fn exit_code(&self) -> ExitCode {
+        ExitCode::from(self.code())
+    }
+}
+
+/// Restores a terminal put into raw mode / the alternate screen
+pub type RestoreFn<B> = Box<dyn FnOnce(&mut Terminal<B>)>;
+
+/// Owns the terminal while the TUI runs and restores it when dropped, so cleanup happens
+/// on an error or a panic unwinding through `main` just as it does on quit
+pub struct TerminalGuard<B: Backend> {
+    terminal: Terminal<B>,
+    restore: Option<RestoreFn<B>>,
+}
+
+impl<B: Backend> TerminalGuard<B> {
+    pub fn new(terminal: Terminal<B>, restore: RestoreFn<B>) -> Self {
+        Self {
+            terminal,
+            restore: Some(restore),
+        }
+    }
+}
+
+impl<B: Backend> Deref for TerminalGuard<B> {
+    type Target = Terminal<B>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.terminal
+    }
+}
+
+impl<B: Backend> DerefMut for TerminalGuard<B> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.terminal
+    }
+}
+
+impl<B: Backend> Drop for TerminalGuard<B> {
+    fn drop(&mut self) {
+        if let Some(restore) = self.restore.take() {
+            restore(&mut self.terminal);
+        }
+    }
+}
+
+/// Run the event loop on the guarded terminal, restoring the terminal before returning
+/// so an error can be printed to the normal screen
+pub fn run_session<B, F>(mut guard: TerminalGuard<B>, run: F) -> SessionEnd
+where
+    B: Backend,
+    F: FnOnce(&mut Terminal<B>) -> io::Result<()>,
+{
+    let result = run(&mut guard);
+    drop(guard);
+    match result {
+        Ok(()) => SessionEnd::Quit,
+        Err(e) => SessionEnd::Failed(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::backend::TestBackend;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    fn guarded() -> (TerminalGuard<TestBackend>, Arc<AtomicBool>) {
+        let restored = Arc::new(AtomicBool::new(false));
+        let flag = restored.clone();
+        let terminal = Terminal::new(TestBackend::new(20, 5)).unwrap();
+        let guard = TerminalGuard::new(
+            terminal,
+            Box::new(move |_| flag.store(true, Ordering::SeqCst)),
+        );
+        (guard, restored)
+    }
+
+    #[test]
+    fn test_error_exits_non_zero_after_restoring_the_terminal() {
+        let (guard, restored) = guarded();
+        let end = run_session(guard, |_| {
+            assert!(!restored.load(Ordering::SeqCst));
+            Err(io::Error::other("event stream closed"))
+        });
+        assert!(matches!(end, SessionEnd::Failed(_)));
+        assert_ne!(end.code(), EXIT_SUCCESS);
+        assert!(restored.load(Ordering::SeqCst));
+
+        let (guard, restored) = guarded();
+        let end = run_session(guard, |_| Ok(()));
+        assert_eq!(end.code(), EXIT_SUCCESS);
+        assert!(restored.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_panic_still_restores_the_terminal() {
+        let (guard, restored) = guarded();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            run_session(guard, |_| panic!("draw failed"))
+        }));
+        assert!(result.is_err());
+        assert!(restored.load(Ordering::SeqCst));
+    }
+}
@@ -1,4 +1,8 @@
 pub mod operations;
+pub mod samusignore;
 pub mod watcher;
+pub mod workspace;
 
 pub use operations::FileSystemOperations;
+pub use samusignore::SamusIgnore;
+pub use workspace::{WorkspacePaths, safe_join};
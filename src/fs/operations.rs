@@ -1,3 +1,4 @@
+use super::samusignore::SamusIgnore;
 use crate::error::TaskError;
 use async_trait::async_trait;
 use std::fs::{self, File};
@@ -9,7 +10,7 @@ const MAX_RETRIES: u32 = 3;
 const RETRY_DELAY: Duration = Duration::from_millis(100);
 
 /// Normalizes a path to use platform-specific separators and resolves relative components
-fn normalize_path(path: &Path) -> PathBuf {
+pub(super) fn normalize_path(path: &Path) -> PathBuf {
     let mut normalized = PathBuf::new();
     for component in path.components() {
         match component {
@@ -89,12 +90,52 @@ pub trait FileSystem: Send + Sync {
     async fn file_metadata(&self, path: &str) -> Result<fs::Metadata, TaskError>;
 }
 
+/// Directory entries `list_files_filtered` callers skip by default
+pub const DEFAULT_IGNORE_PATTERNS: &[&str] = &[".git", "target", "node_modules"];
+
 /// Concrete implementation of FileSystem.
-pub struct LocalFileSystem;
+pub struct LocalFileSystem {
+    /// Workspace exclusions applied by `list_files_filtered`
+    samusignore: Option<SamusIgnore>,
+}
 
 impl LocalFileSystem {
     pub fn new() -> Self {
-        LocalFileSystem
+        LocalFileSystem { samusignore: None }
+    }
+
+    /// Also skip paths excluded by the workspace's `.samusignore` when listing
+    pub fn with_samusignore(mut self, samusignore: SamusIgnore) -> Self {
+        self.samusignore = Some(samusignore);
+        self
+    }
+
+    /// List files and directories under `dir`, descending at most `max_depth` levels.
+    /// Entries whose name matches one of `ignore_patterns`, or that `.samusignore`
+    /// excludes, are skipped along with everything beneath them. Patterns are exact
+    /// names or `*.ext`/`prefix*` wildcards. Symlinked directories aren't descended into.
+    pub fn list_files_filtered(
+        &self,
+        dir: &Path,
+        max_depth: Option<usize>,
+        ignore_patterns: &[String],
+    ) -> Result<Vec<PathBuf>, TaskError> {
+        let mut normalized = normalize_path(dir);
+        if normalized.as_os_str().is_empty() {
+            // "." normalizes away entirely
+            normalized.push(".");
+        }
+        let mut entries = Vec::new();
+        visit_dirs_filtered(
+            &normalized,
+            1,
+            max_depth.unwrap_or(usize::MAX),
+            ignore_patterns,
+            self.samusignore.as_ref(),
+            &mut entries,
+        )?;
+        entries.sort();
+        Ok(entries)
     }
 }
 
@@ -338,6 +379,56 @@ fn visit_dirs(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), TaskError> {
     Ok(())
 }
 
+// Helper function to recursively visit directories, honouring depth and ignore patterns
+fn visit_dirs_filtered(
+    dir: &Path,
+    depth: usize,
+    max_depth: usize,
+    ignore_patterns: &[String],
+    samusignore: Option<&SamusIgnore>,
+    entries: &mut Vec<PathBuf>,
+) -> Result<(), TaskError> {
+    if depth > max_depth || !dir.is_dir() {
+        return Ok(());
+    }
+
+    for entry in fs::read_dir(dir).map_err(TaskError::from)? {
+        let entry = entry.map_err(TaskError::from)?;
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if ignore_patterns
+            .iter()
+            .any(|pattern| matches_ignore_pattern(&name, pattern))
+        {
+            continue;
+        }
+
+        let is_dir = path.is_dir();
+        if samusignore.is_some_and(|samusignore| samusignore.is_ignored(&path, is_dir)) {
+            continue;
+        }
+
+        entries.push(path.clone());
+        // Symlinked directories are listed but not followed, so a link cycle can't
+        // recurse forever
+        let is_symlink = entry.file_type().map_err(TaskError::from)?.is_symlink();
+        if is_dir && !is_symlink {
+            visit_dirs_filtered(&path, depth + 1, max_depth, ignore_patterns, samusignore, entries)?;
+        }
+    }
+    Ok(())
+}
+
+fn matches_ignore_pattern(name: &str, pattern: &str) -> bool {
+    if let Some(suffix) = pattern.strip_prefix('*') {
+        name.ends_with(suffix)
+    } else if let Some(prefix) = pattern.strip_suffix('*') {
+        name.starts_with(prefix)
+    } else {
+        name == pattern
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -427,4 +518,77 @@ mod tests {
             _ => panic!("Expected IoError"),
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    async fn test_list_files_filtered() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join("src/nested")).unwrap();
+        fs::create_dir_all(root.join("target/debug")).unwrap();
+        fs::write(root.join("src/main.rs"), "").unwrap();
+        fs::write(root.join("src/nested/deep.rs"), "").unwrap();
+        fs::write(root.join("notes.log"), "").unwrap();
+        fs::write(root.join("target/debug/app"), "").unwrap();
+
+        let local = LocalFileSystem::new();
+        let ignore = vec!["target".to_string(), "*.log".to_string()];
+
+        let all = local.list_files_filtered(root, None, &ignore).unwrap();
+        let relative: Vec<_> = all.iter().map(|p| p.strip_prefix(root).unwrap()).collect();
+        assert_eq!(
+            relative,
+            vec![
+                Path::new("src"),
+                Path::new("src/main.rs"),
+                Path::new("src/nested"),
+                Path::new("src/nested/deep.rs"),
+            ]
+        );
+
+        let shallow = local.list_files_filtered(root, Some(2), &ignore).unwrap();
+        assert!(shallow.contains(&root.join("src/nested")));
+        assert!(!shallow.contains(&root.join("src/nested/deep.rs")));
+    }
+
+    #[test]
+    async fn test_list_files_filtered_honours_samusignore() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join("vendor/lib")).unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("vendor/lib/dep.rs"), "").unwrap();
+        fs::write(root.join("src/main.rs"), "").unwrap();
+        fs::write(root.join("src/schema.generated.rs"), "").unwrap();
+        fs::write(root.join(".samusignore"), "vendor/\n*.generated.rs\n").unwrap();
+
+        let local = LocalFileSystem::new().with_samusignore(SamusIgnore::load(root));
+        let all = local.list_files_filtered(root, None, &[]).unwrap();
+        let relative: Vec<_> = all.iter().map(|p| p.strip_prefix(root).unwrap()).collect();
+        assert_eq!(
+            relative,
+            vec![Path::new(".samusignore"), Path::new("src"), Path::new("src/main.rs")]
+        );
+
+        // Without it, everything is listed
+        let unfiltered = LocalFileSystem::new().list_files_filtered(root, None, &[]).unwrap();
+        assert!(unfiltered.contains(&root.join("vendor/lib/dep.rs")));
+        assert!(unfiltered.contains(&root.join("src/schema.generated.rs")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    async fn test_list_files_filtered_does_not_follow_symlink_cycles() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/main.rs"), "").unwrap();
+        std::os::unix::fs::symlink(root, root.join("src/loop")).unwrap();
+
+        let all = LocalFileSystem::new().list_files_filtered(root, None, &[]).unwrap();
+        let relative: Vec<_> = all.iter().map(|p| p.strip_prefix(root).unwrap()).collect();
+        assert_eq!(
+            relative,
+            vec![Path::new("src"), Path::new("src/loop"), Path::new("src/main.rs")]
+        );
+    }
+}
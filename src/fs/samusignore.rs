@@ -0,0 +1,134 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::path::{Path, PathBuf};
+
+use crate::config::LogLevel;
+use crate::logging;
+
+/// Name of the workspace file listing paths Samus skips, in gitignore syntax
+pub const SAMUSIGNORE_FILE: &str = ".samusignore";
+
+/// Samus-specific exclusions read from `.samusignore` at the workspace root.
+///
+/// Unlike `.gitignore` these only affect Samus: the file tree, directory outlines
+/// and the symbol index skip matching paths, while git still sees them.
+#[derive(Debug, Clone)]
+pub struct SamusIgnore {
+    root: PathBuf,
+    matcher: Gitignore,
+}
+
+impl SamusIgnore {
+    /// Patterns from `root/.samusignore`; empty if there's no such file.
+    /// Lines that fail to parse are logged and skipped.
+    pub fn load(root: impl AsRef<Path>) -> Self {
+        let root = root.as_ref();
+        let file = root.join(SAMUSIGNORE_FILE);
+        if !file.is_file() {
+            return Self::empty(root);
+        }
+
+        let mut builder = GitignoreBuilder::new(root);
+        if let Some(e) = builder.add(&file) {
+            logging::log(
+                LogLevel::Warn,
+                "samusignore",
+                format!("Skipping invalid lines in {}: {}", file.display(), e),
+            );
+        }
+        match builder.build() {
+            Ok(matcher) => Self {
+                root: root.to_path_buf(),
+                matcher,
+            },
+            Err(e) => {
+                logging::log(
+                    LogLevel::Warn,
+                    "samusignore",
+                    format!("Ignoring {}: {}", file.display(), e),
+                );
+                Self::empty(root)
+            }
+        }
+    }
+
+    /// Exclusions that match nothing
+    pub fn empty(root: impl AsRef<Path>) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+            matcher: Gitignore::empty(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.matcher.is_empty()
+    }
+
+    /// Whether `path`, or a directory above it, is excluded. Relative paths are taken
+    /// from the current directory; paths outside the workspace are never excluded.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+
+        let absolute;
+        let path = if path.is_absolute() {
+            path
+        } else {
+            let Ok(cwd) = std::env::current_dir() else {
+                return false;
+            };
+            absolute = cwd.join(path);
+            &absolute
+        };
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        if relative.as_os_str().is_empty() {
+            return false;
+        }
+
+        self.matcher
+            .matched_path_or_any_parents(relative, is_dir)
+            .is_ignore()
+    }
+}
+
+impl Default for SamusIgnore {
+    fn default() -> Self {
+        Self::empty("")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_patterns_match_under_root_only() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let root = temp.path();
+        std::fs::write(
+            root.join(SAMUSIGNORE_FILE),
+            "# generated code\nvendor/\n*.min.js\n!keep.min.js\n",
+        )
+        .unwrap();
+
+        let ignore = SamusIgnore::load(root);
+        assert!(ignore.is_ignored(&root.join("vendor"), true));
+        assert!(ignore.is_ignored(&root.join("vendor/lib/a.rs"), false));
+        assert!(ignore.is_ignored(&root.join("web/app.min.js"), false));
+        assert!(!ignore.is_ignored(&root.join("web/keep.min.js"), false));
+        assert!(!ignore.is_ignored(&root.join("src/main.rs"), false));
+        // A file named like the directory pattern isn't a directory
+        assert!(!ignore.is_ignored(&root.join("vendor"), false));
+        assert!(!ignore.is_ignored(Path::new("/elsewhere/vendor/a.rs"), false));
+    }
+
+    #[test]
+    fn test_missing_file_ignores_nothing() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let ignore = SamusIgnore::load(temp.path());
+        assert!(ignore.is_empty());
+        assert!(!ignore.is_ignored(&temp.path().join("anything"), false));
+    }
+}
@@ -0,0 +1,207 @@
+use std::path::{Path, PathBuf};
+
+use super::operations::normalize_path;
+use crate::error::TaskError;
+
+/// Join `user_path` onto `root`, refusing anything that ends up outside `root`.
+///
+/// Symlinks are resolved for the part of the path that exists, so a link pointing out
+/// of the workspace is caught too. The rest (e.g. a file about to be written) is
+/// normalized lexically. Returns the absolute path.
+pub fn safe_join(root: impl AsRef<Path>, user_path: impl AsRef<Path>) -> Result<PathBuf, TaskError> {
+    let root = root.as_ref().canonicalize()?;
+    let user_path = user_path.as_ref();
+    let joined = normalize_path(&root.join(user_path));
+
+    // Walk up to the deepest ancestor that exists (or is a symlink, even a dangling one)
+    let mut existing = joined.as_path();
+    let mut missing = Vec::new();
+    while std::fs::symlink_metadata(existing).is_err() {
+        let (Some(name), Some(parent)) = (existing.file_name(), existing.parent()) else {
+            break;
+        };
+        missing.push(name);
+        existing = parent;
+    }
+
+    let mut resolved = existing.canonicalize()?;
+    resolved.extend(missing.into_iter().rev());
+
+    if resolved.starts_with(&root) {
+        Ok(resolved)
+    } else {
+        Err(TaskError::PathOutsideWorkspace(user_path.to_path_buf()))
+    }
+}
+
+/// Resolves paths against the workspace root and displays them relative to it.
+///
+/// Paths are kept absolute for IO; only what's shown to the user is shortened, so the
+/// same file looks the same whether it was typed as `./src/main.rs`, `src/main.rs` or
+/// `/home/me/project/src/main.rs`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkspacePaths {
+    root: PathBuf,
+}
+
+impl WorkspacePaths {
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        let root = root.as_ref();
+        let root = if root.is_absolute() {
+            root.to_path_buf()
+        } else {
+            std::env::current_dir()
+                .map(|cwd| cwd.join(root))
+                .unwrap_or_else(|_| root.to_path_buf())
+        };
+        Self {
+            root: root.canonicalize().unwrap_or_else(|_| normalize_path(&root)),
+        }
+    }
+
+    /// Workspace rooted at the current directory
+    pub fn from_current_dir() -> Self {
+        Self::new(".")
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Absolute path for `path`, which may be relative to the workspace root.
+    /// Existing paths are canonicalized; others are normalized lexically.
+    pub fn resolve(&self, path: impl AsRef<Path>) -> PathBuf {
+        let path = path.as_ref();
+        let joined = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            self.root.join(path)
+        };
+        joined.canonicalize().unwrap_or_else(|_| normalize_path(&joined))
+    }
+
+    /// Absolute path for `path` that's guaranteed to be inside the workspace; see `safe_join`
+    pub fn join_checked(&self, path: impl AsRef<Path>) -> Result<PathBuf, TaskError> {
+        safe_join(&self.root, path)
+    }
+
+    /// `path` relative to the workspace root, or `None` if it lies outside it
+    pub fn relative(&self, path: impl AsRef<Path>) -> Option<PathBuf> {
+        self.resolve(path)
+            .strip_prefix(&self.root)
+            .ok()
+            .map(Path::to_path_buf)
+    }
+
+    /// Compact form for display: relative inside the workspace, absolute outside it
+    pub fn display(&self, path: impl AsRef<Path>) -> String {
+        match self.relative(&path) {
+            Some(relative) if relative.as_os_str().is_empty() => ".".to_string(),
+            Some(relative) => relative.display().to_string(),
+            None => self.resolve(path).display().to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn workspace() -> (TempDir, WorkspacePaths) {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir_all(temp.path().join("src")).unwrap();
+        fs::write(temp.path().join("src/main.rs"), "").unwrap();
+        let paths = WorkspacePaths::new(temp.path());
+        (temp, paths)
+    }
+
+    #[test]
+    fn test_absolute_path_under_root() {
+        let (_temp, paths) = workspace();
+        let absolute = paths.root().join("src/main.rs");
+
+        assert_eq!(paths.display(&absolute), "src/main.rs");
+        assert_eq!(paths.relative(&absolute), Some(PathBuf::from("src/main.rs")));
+        assert_eq!(paths.display(paths.root()), ".");
+    }
+
+    #[test]
+    fn test_relative_forms_resolve_to_the_same_file() {
+        let (_temp, paths) = workspace();
+        let expected = paths.root().join("src/main.rs");
+
+        for typed in ["src/main.rs", "./src/main.rs", "src/../src/main.rs"] {
+            assert_eq!(paths.resolve(typed), expected, "{}", typed);
+            assert_eq!(paths.display(typed), "src/main.rs", "{}", typed);
+        }
+
+        // Paths that don't exist yet are still resolved, just without canonicalizing
+        assert_eq!(paths.resolve("src/new.rs"), paths.root().join("src/new.rs"));
+        assert_eq!(paths.display("src/new.rs"), "src/new.rs");
+    }
+
+    #[test]
+    fn test_absolute_path_outside_root() {
+        let (_temp, paths) = workspace();
+        let other = TempDir::new().unwrap();
+        let outside = other.path().canonicalize().unwrap().join("notes.txt");
+
+        assert_eq!(paths.relative(&outside), None);
+        assert_eq!(paths.display(&outside), outside.display().to_string());
+        assert!(paths.display("../sibling.txt").starts_with('/'));
+    }
+
+    #[test]
+    fn test_safe_join_accepts_subpaths() {
+        let (_temp, paths) = workspace();
+        let root = paths.root();
+
+        assert_eq!(safe_join(root, "src/main.rs").unwrap(), root.join("src/main.rs"));
+        assert_eq!(safe_join(root, "./src/../src/main.rs").unwrap(), root.join("src/main.rs"));
+        assert_eq!(safe_join(root, root.join("src")).unwrap(), root.join("src"));
+
+        // Files that don't exist yet, e.g. about to be written
+        assert_eq!(safe_join(root, "src/new/mod.rs").unwrap(), root.join("src/new/mod.rs"));
+        assert_eq!(paths.join_checked("notes.txt").unwrap(), root.join("notes.txt"));
+    }
+
+    #[test]
+    fn test_safe_join_rejects_traversal() {
+        let (_temp, paths) = workspace();
+        let root = paths.root();
+
+        for escape in ["..", "../sibling.txt", "src/../../x", "/etc/passwd"] {
+            assert!(
+                matches!(safe_join(root, escape), Err(TaskError::PathOutsideWorkspace(_))),
+                "{}",
+                escape
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_safe_join_rejects_symlink_escapes() {
+        let (_temp, paths) = workspace();
+        let root = paths.root();
+        let outside = TempDir::new().unwrap();
+        fs::write(outside.path().join("secret.txt"), "secret").unwrap();
+
+        std::os::unix::fs::symlink(outside.path(), root.join("link")).unwrap();
+        std::os::unix::fs::symlink(outside.path().join("secret.txt"), root.join("secret")).unwrap();
+
+        for escape in ["link/secret.txt", "link/new.txt", "secret"] {
+            assert!(
+                matches!(safe_join(root, escape), Err(TaskError::PathOutsideWorkspace(_))),
+                "{}",
+                escape
+            );
+        }
+
+        // Links that stay inside the workspace are fine
+        std::os::unix::fs::symlink(root.join("src"), root.join("src_link")).unwrap();
+        assert_eq!(safe_join(root, "src_link/main.rs").unwrap(), root.join("src/main.rs"));
+    }
+}
@@ -0,0 +1,22 @@
+pub mod activity;
+pub mod actor;
+pub mod cli;
+pub mod config;
+pub mod context;
+pub mod cqrs;
+pub mod error;
+pub mod exit;
+pub mod fs;
+pub mod integrations;
+pub mod logging;
+pub mod mcp;
+pub mod oneshot;
+pub mod perf;
+pub mod resource;
+pub mod secret;
+pub mod services;
+pub mod shell;
+pub mod simple_client;
+pub mod task;
+pub mod tools;
+pub mod ui;
@@ -1,45 +1,117 @@
-mod config;
-mod context;
-mod error;
-mod fs;
-mod integrations;
-mod mcp;
-mod perf;
-mod resource;
-mod services;
-mod shell;
-mod simple_client;
-mod task;
-mod tools;
-mod ui;
-
 use crossterm::{
-    event::{DisableMouseCapture, EnableMouseCapture},
+    event::{DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use dotenv::dotenv;
-use std::{error::Error, io};
-
-use crate::config::McpServerConfig;
-use crate::services::tree_sitter::initialize_service;
-use crate::task::{TaskRegistry, TaskManager};
-use crate::task::tree_sitter_task::TreeSitterTaskHandler;
-use crate::task::shell_task::ShellTaskHandler;
-use crate::ui::app::App;
-use crate::ui::tui::render_ui;
-
-/// Application entry point
-fn main() -> Result<(), Box<dyn Error>> {
+use std::io;
+use std::process::ExitCode;
+
+use samus::{activity, cli, config, fs, oneshot, secret};
+use samus::config::McpServerConfig;
+use samus::cqrs::{EventStore, RecordingTaskManager};
+use samus::cqrs::event_store::InMemoryEventStore;
+use samus::exit::{EXIT_FAILURE, EXIT_SUCCESS, SessionEnd, TerminalGuard, run_session};
+use samus::services::tree_sitter::initialize_service;
+use samus::task::{TaskRegistry, TaskManager};
+use samus::task::tree_sitter_task::TreeSitterTaskHandler;
+use samus::task::shell_task::ShellTaskHandler;
+use samus::ui::app::App;
+use samus::ui::tui::draw_if_needed;
+
+/// Application entry point. Exits non-zero when anything fatal goes wrong, including
+/// an error that ends the TUI, and zero when the user quits.
+fn main() -> ExitCode {
     // Load .env file
     dotenv().ok();
 
     // We'll create tokio runtimes as needed for async operations
 
-    println!("Starting Samus with Ratatui interface...");
+    // Prints help, or the parse error, and exits when the arguments are bad
+    let cli::CliArgs { command, events } =
+        cli::parse_args(std::env::args_os()).unwrap_or_else(|e| e.exit());
+
+    // Activity for scripts, only when asked for with --events
+    let activity = match events.as_deref().map(activity::ActivityLog::open) {
+        Some(Ok(log)) => Some(log),
+        Some(Err(e)) => {
+            eprintln!("Failed to open --events target: {}", e);
+            return ExitCode::from(EXIT_FAILURE);
+        }
+        None => None,
+    };
+
+    // Read anything piped in (`cat file | samus "summarize this"`) before the
+    // terminal is touched; the TUI reads keys from the tty instead
+    let piped = if matches!(command, cli::CliCommand::Search { .. }) {
+        None
+    } else {
+        match oneshot::read_piped_stdin() {
+            Ok(piped) => piped,
+            Err(e) => {
+                eprintln!("Failed to read stdin: {}", e);
+                return ExitCode::from(EXIT_FAILURE);
+            }
+        }
+    };
 
     // Initialize config
-    let app_config = config::Config::new();
+    let mut app_config = config::Config::new();
+
+    // Configure OpenRouter if API key is available
+    if let Ok(api_key) = std::env::var("OPEN_ROUTER_API_KEY") {
+        app_config.mcp_servers.push(McpServerConfig {
+            id: "openrouter".to_string(),
+            name: "OpenRouter".to_string(),
+            url: "https://openrouter.ai/api/v1/chat/completions".to_string(),
+            api_key: Some(secret::Secret::new(api_key)),
+            enabled: true,
+            headers: McpServerConfig::default_headers(),
+            requests_per_minute: std::env::var("SAMUS_REQUESTS_PER_MINUTE")
+                .ok()
+                .and_then(|rpm| rpm.parse().ok()),
+            context_token_budget: std::env::var("SAMUS_CONTEXT_TOKENS")
+                .ok()
+                .and_then(|tokens| tokens.parse().ok()),
+        });
+    }
+
+    // Refuse to start with a broken configuration rather than failing confusingly later
+    if let Err(e) = app_config.validate() {
+        eprintln!("Invalid configuration: {}", e);
+        return ExitCode::from(EXIT_FAILURE);
+    }
+
+    // Headless commands, and questions about piped input, print to stdout and exit
+    // without starting the TUI
+    let question = match command {
+        cli::CliCommand::Ask { question } => Some(question),
+        cli::CliCommand::Tui { question } => question.filter(|_| piped.is_some()),
+        cli::CliCommand::Search {
+            pattern,
+            glob,
+            max_results,
+        } => match cli::run_search(&app_config, &pattern, glob, max_results) {
+            Ok(output) => {
+                println!("{}", output);
+                return ExitCode::from(EXIT_SUCCESS);
+            }
+            Err(e) => {
+                eprintln!("Search failed: {}", e);
+                return ExitCode::from(EXIT_FAILURE);
+            }
+        },
+    };
+    if let Some(question) = question {
+        let turns = oneshot::one_shot_turns(piped.as_deref(), &question);
+        if let Err(e) = oneshot::run(&app_config, &turns, activity.as_ref()) {
+            eprintln!("Error: {}", e);
+            return ExitCode::from(EXIT_FAILURE);
+        }
+        return ExitCode::from(EXIT_SUCCESS);
+    }
+
+    println!("Starting Samus with Ratatui interface...");
     
     // Initialize TreeSitter service
     let tree_sitter_service = initialize_service(&app_config);
@@ -60,87 +132,111 @@ fn main() -> Result<(), Box<dyn Error>> {
     
     // Create Arc for registry and task manager
     let task_registry = std::sync::Arc::new(task_registry);
-    let task_manager = std::sync::Arc::new(TaskManager::new(fs_impl, task_registry.clone()));
-    
-    // Setup terminal with better error handling
-    enable_raw_mode().or_else(|err| {
-        eprintln!("Failed to enable raw mode: {}", err);
-        Err(err)
-    })?;
-    
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen).or_else(|err| {
-        eprintln!("Failed to enter alternate screen: {}", err);
-        let _ = disable_raw_mode(); // Try to clean up
-        Err(err)
-    })?;
-    
-    execute!(stdout, EnableMouseCapture).or_else(|err| {
-        eprintln!("Failed to enable mouse capture: {}", err);
-        let _ = disable_raw_mode(); // Try to clean up
-        let _ = execute!(stdout, LeaveAlternateScreen);
-        Err(err)
-    })?;
+    // Every tool call is recorded in this session's stream, for /history
+    let event_store: std::sync::Arc<dyn EventStore> = std::sync::Arc::new(InMemoryEventStore::new());
+    let session_id = uuid::Uuid::new_v4().to_string();
+    let task_manager = std::sync::Arc::new(RecordingTaskManager::new(
+        TaskManager::new(fs_impl, task_registry.clone()),
+        event_store.clone(),
+        session_id.clone(),
+    ));
     
-    let backend = ratatui::backend::CrosstermBackend::new(stdout);
-    let mut terminal = ratatui::Terminal::new(backend).or_else(|err| {
-        eprintln!("Failed to create terminal: {}", err);
-        let _ = disable_raw_mode(); // Try to clean up
-        let _ = execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture);
-        Err(err)
-    })?;
+    // Restored by the guard however the session ends
+    let terminal = match setup_terminal() {
+        Ok(terminal) => TerminalGuard::new(terminal, Box::new(restore_terminal)),
+        Err(e) => {
+            eprintln!("Failed to set up the terminal: {}", e);
+            return ExitCode::from(EXIT_FAILURE);
+        }
+    };
 
     // Create app state
-    let mut app = App::new();
+    let mut app = App::with_config(&app_config);
     
     // Set task manager
-    app.set_task_manager(task_manager.clone());
+    app.set_task_manager(task_manager);
+    app.set_tool_call_log(event_store, session_id);
+    app.activity = activity;
     
     // Initialize TreeSitter with default values
     app.init_tree_sitter(10_000_000, 5); // 10MB max file size, 5 parsers per language
 
-    // Configure OpenRouter if API key is available
-    if let Ok(api_key) = std::env::var("OPEN_ROUTER_API_KEY") {
-        // Create config
-        let config = McpServerConfig {
-            id: "openrouter".to_string(),
-            name: "OpenRouter".to_string(),
-            url: "https://openrouter.ai/api/v1/chat/completions".to_string(),
-            api_key: Some(api_key),
-            enabled: true,
-        };
+    // Initialize the LLM client from the first enabled server
+    if let Some(config) = app_config.mcp_servers.iter().find(|s| s.enabled).cloned()
+        && let Err(e) = app.init_llm_client(config)
+    {
+        eprintln!("Error configuring OpenRouter client: {}", e);
+    }
 
-        // Initialize client
-        if let Err(e) = app.init_llm_client(config) {
-            eprintln!("Error configuring OpenRouter client: {}", e);
-        }
+    if let Some(piped) = piped {
+        app.load_piped_input(piped);
     }
 
+    app.run_startup_command();
+
     // Main event loop
-    let res = run_app(&mut terminal, &mut app);
+    let end = run_session(terminal, |terminal| {
+        let res = run_app(terminal, &mut app);
+        app.save_terminals();
+        res
+    });
+
+    if let SessionEnd::Failed(err) = &end {
+        eprintln!("Error: {}", err);
+    }
+    end.exit_code()
+}
+
+type CrosstermTerminal = ratatui::Terminal<ratatui::backend::CrosstermBackend<io::Stdout>>;
+
+/// Raw mode, the alternate screen, mouse capture and bracketed paste, undoing whatever
+/// was already enabled if a step fails
+fn setup_terminal() -> io::Result<CrosstermTerminal> {
+    enable_raw_mode()?;
 
-    // Restore terminal with better error handling
+    let mut stdout = io::stdout();
+    if let Err(e) = execute!(stdout, EnterAlternateScreen, EnableMouseCapture) {
+        let _ = execute!(stdout, LeaveAlternateScreen, DisableMouseCapture);
+        let _ = disable_raw_mode();
+        return Err(e);
+    }
+
+    // Pasted text arrives as one event instead of a key press per character, so
+    // newlines inside it don't submit the input. Terminals without support just
+    // fall back to key events.
+    if let Err(e) = execute!(stdout, EnableBracketedPaste) {
+        eprintln!("Failed to enable bracketed paste: {}", e);
+    }
+
+    ratatui::Terminal::new(ratatui::backend::CrosstermBackend::new(stdout)).inspect_err(|_| {
+        let _ = execute!(
+            io::stdout(),
+            LeaveAlternateScreen,
+            DisableMouseCapture,
+            DisableBracketedPaste
+        );
+        let _ = disable_raw_mode();
+    })
+}
+
+/// Undo `setup_terminal`, reporting but otherwise ignoring failures so every step is tried
+fn restore_terminal(terminal: &mut CrosstermTerminal) {
     if let Err(e) = disable_raw_mode() {
         eprintln!("Error disabling raw mode: {}", e);
     }
-    
+
     if let Err(e) = execute!(
         terminal.backend_mut(),
         LeaveAlternateScreen,
-        DisableMouseCapture
+        DisableMouseCapture,
+        DisableBracketedPaste
     ) {
         eprintln!("Error leaving alternate screen: {}", e);
     }
-    
+
     if let Err(e) = terminal.show_cursor() {
         eprintln!("Error showing cursor: {}", e);
     }
-
-    if let Err(err) = res {
-        println!("{:?}", err);
-    }
-
-    Ok(())
 }
 
 fn run_app<B: ratatui::backend::Backend>(
@@ -148,19 +244,24 @@ fn run_app<B: ratatui::backend::Backend>(
     app: &mut App,
 ) -> io::Result<()> {
     loop {
-        // Draw UI
-        terminal.draw(|f| render_ui(f, app))?;
+        // Draw UI when something changed
+        draw_if_needed(terminal, app)?;
 
         // Handle events
-        if crossterm::event::poll(std::time::Duration::from_millis(100))? {
-            if let crossterm::event::Event::Key(key) = crossterm::event::read()? {
-                // Process key event
-                app.handle_key_event(key);
-                
-                // Check if we should quit
-                if app.should_quit {
-                    return Ok(());
+        if crossterm::event::poll(app.tick_rate)? {
+            match crossterm::event::read()? {
+                crossterm::event::Event::Key(key) => {
+                    // Process key event
+                    app.handle_key_event(key);
+
+                    // Check if we should quit
+                    if app.should_quit {
+                        return Ok(());
+                    }
                 }
+                crossterm::event::Event::Paste(text) => app.handle_paste(&text),
+                crossterm::event::Event::Resize(_, _) => app.request_redraw(),
+                _ => {}
             }
         }
         
@@ -1,9 +1,17 @@
 use reqwest::{Client as HttpClient, header};
+use serde::{Deserialize, Serialize};
 use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
 
+use super::context::{DEFAULT_CONTEXT_TOKEN_BUDGET, trim_to_budget};
+use super::models::{ModelCache, ModelInfo, lookup_context_length};
 use super::protocol::Version;
-use crate::config::McpServerConfig;
+use super::rate_limit::{MAX_QUEUE_WAIT, RateLimiter};
+use crate::config::{LogLevel, McpServerConfig};
 use crate::error::TaskError;
+use crate::logging;
+use crate::secret::redact;
 
 // Define ToolDefinition with the necessary fields
 #[derive(Debug, Clone)]
@@ -14,11 +22,100 @@ pub struct ToolDefinition {
     pub schema: serde_json::Value,
 }
 
+/// Model list used when the server URL isn't an OpenRouter-style completions endpoint
+const OPENROUTER_MODELS_URL: &str = "https://openrouter.ai/api/v1/models";
+
+/// Prompt sent to pick up a response that stopped at the token limit
+pub const CONTINUE_PROMPT: &str = "Continue exactly where you left off.";
+
+/// Who a chat turn came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::System => "system",
+            Role::User => "user",
+            Role::Assistant => "assistant",
+        }
+    }
+}
+
+/// Model used until one is picked with `/model`
+pub const DEFAULT_MODEL: &str = "anthropic/claude-3-haiku";
+
+/// One message of a conversation sent to the model
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatTurn {
+    pub role: Role,
+    pub content: String,
+}
+
+impl ChatTurn {
+    pub fn system(content: impl Into<String>) -> Self {
+        Self {
+            role: Role::System,
+            content: content.into(),
+        }
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self {
+            role: Role::User,
+            content: content.into(),
+        }
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self {
+            role: Role::Assistant,
+            content: content.into(),
+        }
+    }
+}
+
+/// Conversation that asks the model to carry on from a truncated `partial` answer to `prompt`
+pub fn continuation_turns(prompt: &str, partial: &str) -> Vec<ChatTurn> {
+    vec![
+        ChatTurn::user(prompt),
+        ChatTurn::assistant(partial),
+        ChatTurn::user(CONTINUE_PROMPT),
+    ]
+}
+
+/// Text of a completion and why the model stopped
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatCompletion {
+    pub content: String,
+    pub finish_reason: Option<String>,
+}
+
+impl ChatCompletion {
+    /// Whether the model stopped because it ran out of tokens
+    pub fn is_truncated(&self) -> bool {
+        self.finish_reason.as_deref() == Some("length")
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct OpenRouterClient {
     http_client: HttpClient,
     config: McpServerConfig,
     model: String,
+    extra_headers: header::HeaderMap,
+    debug: bool,
+    /// Shared by clones, so requests from every copy count against one budget
+    rate_limiter: Option<RateLimiter>,
+    /// Approximate token limit for a conversation; older turns are trimmed to fit
+    context_budget: usize,
+    /// Shared by clones, so the model list is fetched once for all of them
+    model_cache: ModelCache,
 }
 
 impl OpenRouterClient {
@@ -30,75 +127,183 @@ impl OpenRouterClient {
 
         // Create HTTP client with authorization header
         let mut headers = header::HeaderMap::new();
-        let auth_value = format!("Bearer {}", api_key);
-        headers.insert(
-            header::AUTHORIZATION,
-            header::HeaderValue::from_str(&auth_value)
-                .map_err(|e| TaskError::InvalidConfiguration(e.to_string()))?,
-        );
+        let mut auth_value = header::HeaderValue::from_str(&format!("Bearer {}", api_key.expose()))
+            .map_err(|e| TaskError::InvalidConfiguration(redact(&e.to_string())))?;
+        // Keeps the header out of reqwest's Debug output
+        auth_value.set_sensitive(true);
+        headers.insert(header::AUTHORIZATION, auth_value);
 
         let http_client = HttpClient::builder()
             .default_headers(headers)
             .build()
-            .map_err(|e| TaskError::InvalidConfiguration(e.to_string()))?;
+            .map_err(|e| TaskError::InvalidConfiguration(redact(&e.to_string())))?;
 
-        Ok(Self {
+        let headers = config.headers.clone();
+        let rate_limiter = config.requests_per_minute.map(RateLimiter::new);
+        let context_budget = config
+            .context_token_budget
+            .unwrap_or(DEFAULT_CONTEXT_TOKEN_BUDGET);
+        let mut client = Self {
             http_client,
             config,
             model,
-        })
+            extra_headers: header::HeaderMap::new(),
+            debug: false,
+            rate_limiter,
+            context_budget,
+            model_cache: ModelCache::default(),
+        };
+        client.set_headers(headers)?;
+
+        Ok(client)
+    }
+
+    /// Log full request bodies and raw responses (redacted) for debugging. Off by default.
+    pub fn set_debug(&mut self, debug: bool) {
+        self.debug = debug;
+    }
+
+    /// Cap outgoing requests per minute, queueing any excess; `None` removes the cap
+    pub fn set_rate_limit(&mut self, requests_per_minute: Option<u32>) {
+        self.config.requests_per_minute = requests_per_minute;
+        self.rate_limiter = requests_per_minute.map(RateLimiter::new);
+    }
+
+    /// Approximate token limit for a conversation; `None` restores the default
+    pub fn set_context_budget(&mut self, tokens: Option<usize>) {
+        self.config.context_token_budget = tokens;
+        self.context_budget = tokens.unwrap_or(DEFAULT_CONTEXT_TOKEN_BUDGET);
+    }
+
+    pub fn context_budget(&self) -> usize {
+        self.context_budget
+    }
+
+    /// Replace the extra headers sent with every request.
+    /// The authorization header is managed separately and can't be overridden here.
+    pub fn set_headers(&mut self, headers: HashMap<String, String>) -> Result<(), TaskError> {
+        let mut extra_headers = header::HeaderMap::new();
+        for (name, value) in headers {
+            let header_name = header::HeaderName::from_bytes(name.as_bytes()).map_err(|_| {
+                TaskError::InvalidConfiguration(format!("Invalid header name: {:?}", name))
+            })?;
+            if header_name == header::AUTHORIZATION {
+                return Err(TaskError::InvalidConfiguration(
+                    "Authorization header is set from the API key".to_string(),
+                ));
+            }
+            let header_value = header::HeaderValue::from_str(&value).map_err(|_| {
+                TaskError::InvalidConfiguration(format!("Invalid value for header {}", name))
+            })?;
+            extra_headers.insert(header_name, header_value);
+        }
+
+        self.extra_headers = extra_headers;
+        Ok(())
     }
 
     pub async fn chat(&self, prompt: String) -> Result<String, TaskError> {
+        self.complete(&[ChatTurn::user(prompt)])
+            .await
+            .map(|completion| completion.content)
+    }
+
+    /// Request payload for OpenRouter
+    fn build_payload(&self, turns: &[ChatTurn]) -> serde_json::Value {
+        let messages: Vec<serde_json::Value> = turns
+            .iter()
+            .map(|turn| json!({ "role": turn.role.as_str(), "content": turn.content }))
+            .collect();
+
+        json!({
+            "model": self.model.clone(),
+            "messages": messages,
+        })
+    }
+
+    /// Send a whole conversation and return the reply along with its finish reason
+    pub async fn complete(&self, turns: &[ChatTurn]) -> Result<ChatCompletion, TaskError> {
+        self.complete_keeping(turns, 1).await
+    }
+
+    /// Like `complete`, but the last `keep_last` turns are never trimmed to fit the
+    /// context budget, e.g. a truncated answer together with the prompt it answers
+    pub async fn complete_keeping(
+        &self,
+        turns: &[ChatTurn],
+        keep_last: usize,
+    ) -> Result<ChatCompletion, TaskError> {
         // Check if prompt is empty
-        if prompt.trim().is_empty() {
+        if turns.last().is_none_or(|turn| turn.content.trim().is_empty()) {
             return Err(TaskError::ExecutionFailed(
                 "Input must have at least 1 token".to_string(),
             ));
         }
 
-        // Prepare request payload for OpenRouter
-        let payload = json!({
-            "model": self.model.clone(),
-            "messages": [
-                {
-                    "role": "user",
-                    "content": prompt,
-                }
-            ]
-        });
+        // Queue behind earlier requests rather than tripping the provider's limit
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.acquire(MAX_QUEUE_WAIT).await?;
+        }
+
+        // Drop the oldest history rather than have the provider reject the request
+        let trimmed = trim_to_budget(turns, self.context_budget, keep_last);
+        if trimmed.dropped > 0 {
+            logging::log(
+                LogLevel::Warn,
+                "llm",
+                format!(
+                    "conversation exceeds the {} token context budget; dropped {} oldest turn(s), ~{} tokens remain",
+                    self.context_budget, trimmed.dropped, trimmed.estimated_tokens
+                ),
+            );
+        }
+
+        let payload = self.build_payload(&trimmed.turns);
+
+        if self.debug {
+            logging::debug(
+                "llm",
+                format!("request to {}: {}", self.config.url, payload),
+            );
+        }
 
         // Send request to OpenRouter
         let response = self
             .http_client
             .post(&self.config.url)
+            .headers(self.extra_headers.clone())
             .json(&payload)
             .send()
             .await
-            .map_err(|e| TaskError::ExecutionFailed(format!("Failed to send request: {}", e)))?;
+            .map_err(|e| execution_error("Failed to send request", e))?;
 
         // Handle response
         let status = response.status();
         if !status.is_success() {
-            let error_text = response.text().await.map_err(|e| {
-                TaskError::ExecutionFailed(format!("Failed to read error response: {}", e))
-            })?;
-            return Err(TaskError::ExecutionFailed(format!(
-                "OpenRouter request failed with status {}: {}",
-                status, error_text
-            )));
+            let error_text = response
+                .text()
+                .await
+                .map_err(|e| execution_error("Failed to read error response", e))?;
+            return Err(execution_error(
+                &format!("OpenRouter request failed with status {}", status),
+                error_text,
+            ));
         }
 
         // Get the response body as text first
-        let response_body = response.text().await.map_err(|e| {
-            TaskError::ExecutionFailed(format!("Failed to read response body: {}", e))
-        })?;
+        let response_body = response
+            .text()
+            .await
+            .map_err(|e| execution_error("Failed to read response body", e))?;
+
+        if self.debug {
+            logging::debug("llm", format!("response ({}): {}", status, response_body));
+        }
 
         // Parse the JSON
         let response_json: serde_json::Value =
-            serde_json::from_str(&response_body).map_err(|e| {
-                TaskError::ExecutionFailed(format!("Failed to parse response JSON: {}", e))
-            })?;
+            serde_json::from_str(&response_body)
+                .map_err(|e| execution_error("Failed to parse response JSON", e))?;
 
         // Extract assistant message - more flexible approach to handle different provider formats
         let content = if let Some(choices) = response_json.get("choices") {
@@ -107,6 +312,9 @@ impl OpenRouterClient {
                     if let Some(content) = message.get("content") {
                         if let Some(text) = content.as_str() {
                             text.to_string()
+                        } else if content.is_null() {
+                            // Filtered responses come back with null content
+                            String::new()
                         } else {
                             return Err(TaskError::ExecutionFailed(
                                 "Content is not a string".to_string(),
@@ -121,10 +329,10 @@ impl OpenRouterClient {
                         {
                             content.to_string()
                         } else {
-                            return Err(TaskError::ExecutionFailed(format!(
-                                "Could not find content in response: {:?}",
-                                first_choice
-                            )));
+                            return Err(execution_error(
+                                "Could not find content in response",
+                                format!("{:?}", first_choice),
+                            ));
                         }
                     }
                 } else {
@@ -136,10 +344,10 @@ impl OpenRouterClient {
                     {
                         content.to_string()
                     } else {
-                        return Err(TaskError::ExecutionFailed(format!(
-                            "Response missing message field: {:?}",
-                            first_choice
-                        )));
+                        return Err(execution_error(
+                            "Response missing message field",
+                            format!("{:?}", first_choice),
+                        ));
                     }
                 }
             } else {
@@ -148,13 +356,83 @@ impl OpenRouterClient {
                 ));
             }
         } else {
-            return Err(TaskError::ExecutionFailed(format!(
-                "Response missing choices field: {:?}",
-                response_json
-            )));
+            return Err(execution_error(
+                "Response missing choices field",
+                format!("{:?}", response_json),
+            ));
         };
 
-        Ok(content)
+        let finish_reason = response_json
+            .pointer("/choices/0/finish_reason")
+            .and_then(|r| r.as_str())
+            .map(|r| r.to_string());
+
+        if content.trim().is_empty() {
+            return Err(TaskError::EmptyResponse { finish_reason });
+        }
+
+        Ok(ChatCompletion {
+            content,
+            finish_reason,
+        })
+    }
+
+    /// Model list endpoint next to the configured chat completions URL
+    fn models_url(&self) -> String {
+        match self.config.url.strip_suffix("/chat/completions") {
+            Some(base) => format!("{}/models", base),
+            None => OPENROUTER_MODELS_URL.to_string(),
+        }
+    }
+
+    /// Fetch the model list, bypassing the cache
+    async fn fetch_models(&self) -> Result<Vec<ModelInfo>, TaskError> {
+        let response = self
+            .http_client
+            .get(self.models_url())
+            .headers(self.extra_headers.clone())
+            .send()
+            .await
+            .map_err(|e| execution_error("Failed to fetch models", e))?;
+
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| execution_error("Failed to read model list", e))?;
+        if !status.is_success() {
+            return Err(execution_error(
+                &format!("Model list request failed with status {}", status),
+                body,
+            ));
+        }
+        ModelInfo::parse_list(&body)
+    }
+
+    /// Available models, from the cache unless it has expired
+    pub async fn models(&self) -> Result<Arc<Vec<ModelInfo>>, TaskError> {
+        self.model_cache.get_or_fetch(|| self.fetch_models()).await
+    }
+
+    /// Fetch the model list again now
+    pub async fn refresh_models(&self) -> Result<Arc<Vec<ModelInfo>>, TaskError> {
+        self.model_cache.refresh(|| self.fetch_models()).await
+    }
+
+    /// Context window of `model`, from the model list if it can be had, otherwise from
+    /// the built-in table
+    pub async fn context_length(&self, model: &str) -> Option<usize> {
+        match self.models().await {
+            Ok(models) => lookup_context_length(&models, model),
+            Err(_) => None,
+        }
+        .or_else(|| super::context::model_context_length(model))
+    }
+
+    /// Context window of `model` without touching the network; see
+    /// `ModelCache::cached_context_length`
+    pub fn cached_context_length(&self, model: &str) -> Option<usize> {
+        self.model_cache.cached_context_length(model)
     }
 
     pub fn get_model(&self) -> &str {
@@ -165,3 +443,271 @@ impl OpenRouterClient {
         self.model = model;
     }
 }
+
+/// Build an execution error, scrubbing secrets from anything the server echoed back
+fn execution_error(context: &str, detail: impl std::fmt::Display) -> TaskError {
+    TaskError::ExecutionFailed(redact(&format!("{}: {}", context, detail)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secret::Secret;
+
+    const KEY: &str = "sk-or-v1-test0123456789abcdef";
+
+    fn config() -> McpServerConfig {
+        McpServerConfig {
+            id: "openrouter".to_string(),
+            name: "OpenRouter".to_string(),
+            url: "https://openrouter.ai/api/v1/chat/completions".to_string(),
+            api_key: Some(Secret::new(KEY)),
+            enabled: true,
+            headers: McpServerConfig::default_headers(),
+            requests_per_minute: None,
+            context_token_budget: None,
+        }
+    }
+
+    /// Serve a single canned chat completion and hand back the raw request it received
+    async fn mock_server() -> (String, tokio::task::JoinHandle<String>) {
+        mock_server_with(r#"{"choices":[{"message":{"content":"hello"}}]}"#).await
+    }
+
+    async fn mock_server_with(body: &'static str) -> (String, tokio::task::JoinHandle<String>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/chat/completions", listener.local_addr().unwrap());
+
+        let handle = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            loop {
+                let n = stream.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request);
+                if let Some(header_end) = text.find("\r\n\r\n") {
+                    let content_length = text[..header_end]
+                        .lines()
+                        .find_map(|line| {
+                            let (name, value) = line.split_once(':')?;
+                            name.eq_ignore_ascii_case("content-length")
+                                .then(|| value.trim().parse::<usize>().ok())?
+                        })
+                        .unwrap_or(0);
+                    if request.len() >= header_end + 4 + content_length {
+                        break;
+                    }
+                }
+                if n == 0 {
+                    break;
+                }
+            }
+
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8_lossy(&request).into_owned()
+        });
+
+        (url, handle)
+    }
+
+    #[tokio::test]
+    async fn test_configured_headers_sent_with_request() {
+        let (url, server) = mock_server().await;
+        let mut config = config();
+        config.url = url;
+
+        let mut client = OpenRouterClient::new(config, "test-model".to_string()).unwrap();
+        let mut headers = McpServerConfig::default_headers();
+        headers.insert("X-Custom".to_string(), "custom-value".to_string());
+        client.set_headers(headers).unwrap();
+
+        let reply = client.chat("ping".to_string()).await.unwrap();
+        assert_eq!(reply, "hello");
+
+        let request = server.await.unwrap().to_lowercase();
+        assert!(request.contains("http-referer: https://github.com/ochsec/samus"));
+        assert!(request.contains("x-title: samus"));
+        assert!(request.contains("x-custom: custom-value"));
+        assert!(request.contains(&format!("authorization: bearer {}", KEY.to_lowercase())));
+    }
+
+    #[tokio::test]
+    async fn test_debug_logging_captures_request_without_key() {
+        let model = "debug-logging-test-model";
+        let logged = || {
+            logging::recent(usize::MAX)
+                .into_iter()
+                .filter(|r| r.target == "llm" && r.message.contains(model))
+                .collect::<Vec<_>>()
+        };
+
+        // Off by default
+        let (url, server) = mock_server().await;
+        let mut config = config();
+        config.url = url;
+        let client = OpenRouterClient::new(config.clone(), model.to_string()).unwrap();
+        client.chat("quiet".to_string()).await.unwrap();
+        server.await.unwrap();
+        assert!(logged().is_empty());
+
+        let (url, server) = mock_server().await;
+        config.url = url;
+        let mut client = OpenRouterClient::new(config, model.to_string()).unwrap();
+        client.set_debug(true);
+        client.chat("loud".to_string()).await.unwrap();
+        server.await.unwrap();
+
+        let records = logged();
+        assert!(!records.is_empty());
+        assert!(records.iter().any(|r| r.message.contains("loud")));
+        assert!(records.iter().all(|r| !r.message.contains(KEY)));
+        assert!(
+            logging::recent(usize::MAX)
+                .iter()
+                .any(|r| r.target == "llm" && r.message.contains("response (200 OK)"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_empty_response_reports_finish_reason() {
+        let (url, server) =
+            mock_server_with(r#"{"choices":[{"message":{"content":"  \n"},"finish_reason":"length"}]}"#)
+                .await;
+        let mut config = config();
+        config.url = url;
+        let client = OpenRouterClient::new(config.clone(), "test-model".to_string()).unwrap();
+
+        let error = client.chat("hi".to_string()).await.unwrap_err();
+        server.await.unwrap();
+        assert!(matches!(
+            &error,
+            TaskError::EmptyResponse { finish_reason: Some(reason) } if reason == "length"
+        ));
+        assert_eq!(
+            error.to_string(),
+            "The model returned an empty response (stopped at the max token limit)"
+        );
+
+        let (url, server) =
+            mock_server_with(r#"{"choices":[{"message":{"content":null},"finish_reason":"content_filter"}]}"#)
+                .await;
+        config.url = url;
+        let client = OpenRouterClient::new(config, "test-model".to_string()).unwrap();
+        let error = client.chat("hi".to_string()).await.unwrap_err();
+        server.await.unwrap();
+        assert_eq!(
+            error.to_string(),
+            "The model returned an empty response (content was filtered)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_length_finish_reason_marks_truncation() {
+        let (url, server) = mock_server_with(
+            r#"{"choices":[{"message":{"content":"The first half"},"finish_reason":"length"}]}"#,
+        )
+        .await;
+        let mut config = config();
+        config.url = url;
+        let client = OpenRouterClient::new(config, "test-model".to_string()).unwrap();
+
+        let completion = client.complete(&[ChatTurn::user("Explain")]).await.unwrap();
+        server.await.unwrap();
+        assert_eq!(completion.content, "The first half");
+        assert!(completion.is_truncated());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_context_length_lookups_fetch_models_once() {
+        // The mock server answers a single request, so a second fetch would fail
+        let (url, server) = mock_server_with(
+            r#"{"data":[{"id":"acme/new-model","name":"New","context_length":65536}]}"#,
+        )
+        .await;
+        let mut config = config();
+        config.url = url;
+        let client = OpenRouterClient::new(config, "test-model".to_string()).unwrap();
+        let other = client.clone();
+
+        let (a, b) = tokio::join!(
+            client.context_length("acme/new-model"),
+            other.context_length("acme/new-model")
+        );
+        assert_eq!((a, b), (Some(65536), Some(65536)));
+        assert!(server.await.unwrap().starts_with("GET /models "));
+
+        // Later lookups are answered from the cache
+        assert_eq!(client.context_length("acme/new-model").await, Some(65536));
+        assert_eq!(client.cached_context_length("acme/new-model"), Some(65536));
+        assert_eq!(client.context_length("anthropic/claude-3-opus").await, Some(200_000));
+    }
+
+    #[test]
+    fn test_continuation_payload() {
+        let client = OpenRouterClient::new(config(), "test-model".to_string()).unwrap();
+        let payload = client.build_payload(&continuation_turns("Explain", "The first half"));
+
+        assert_eq!(
+            payload,
+            json!({
+                "model": "test-model",
+                "messages": [
+                    { "role": "user", "content": "Explain" },
+                    { "role": "assistant", "content": "The first half" },
+                    { "role": "user", "content": CONTINUE_PROMPT },
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_set_headers_rejects_invalid_entries() {
+        let mut client = OpenRouterClient::new(config(), "test-model".to_string()).unwrap();
+
+        let bad_name = HashMap::from([("Bad Header".to_string(), "value".to_string())]);
+        assert!(matches!(
+            client.set_headers(bad_name),
+            Err(TaskError::InvalidConfiguration(_))
+        ));
+
+        let bad_value = HashMap::from([("X-Title".to_string(), "line\nbreak".to_string())]);
+        assert!(client.set_headers(bad_value).is_err());
+
+        let auth = HashMap::from([("Authorization".to_string(), "Bearer other".to_string())]);
+        assert!(client.set_headers(auth).is_err());
+
+        // A failed update leaves the previous headers in place
+        assert_eq!(client.extra_headers.len(), 2);
+    }
+
+    #[test]
+    fn test_api_key_not_in_debug_output() {
+        let config = config();
+        assert!(!format!("{:?}", config).contains(KEY));
+
+        let client = OpenRouterClient::new(config, "anthropic/claude-3-haiku".to_string()).unwrap();
+        assert!(!format!("{:?}", client).contains(KEY));
+    }
+
+    #[test]
+    fn test_api_key_not_in_formatted_error() {
+        let _client = OpenRouterClient::new(config(), "anthropic/claude-3-haiku".to_string()).unwrap();
+
+        // Servers sometimes echo the offending credential back in the error body
+        let error = execution_error(
+            "OpenRouter request failed with status 401",
+            format!("{{\"error\": \"invalid key {}\", \"header\": \"Bearer {}\"}}", KEY, KEY),
+        );
+
+        assert!(!error.to_string().contains(KEY));
+        assert!(!format!("{:?}", error).contains(KEY));
+    }
+}
@@ -0,0 +1,207 @@
+//! Keeping conversations within the model's context window
+
+use super::client::{ChatTurn, Role};
+
+/// Token budget used when a server doesn't configure one
+pub const DEFAULT_CONTEXT_TOKEN_BUDGET: usize = 32_000;
+
+/// Rough characters-per-token ratio for English text and code
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Tokens each message costs on top of its content (role markers and separators)
+const TOKENS_PER_TURN: usize = 4;
+
+/// Context window sizes of models we know about, matched by id prefix
+const KNOWN_CONTEXT_LENGTHS: &[(&str, usize)] = &[
+    ("anthropic/claude-3", 200_000),
+    ("anthropic/claude-2", 100_000),
+    ("openai/gpt-4o", 128_000),
+    ("openai/gpt-4-turbo", 128_000),
+    ("openai/gpt-4", 8_192),
+    ("openai/gpt-3.5-turbo", 16_385),
+    ("google/gemini-pro-1.5", 1_000_000),
+    ("meta-llama/llama-3", 8_192),
+];
+
+/// Context window of `model` in tokens, if it's one we know about
+pub fn model_context_length(model: &str) -> Option<usize> {
+    KNOWN_CONTEXT_LENGTHS
+        .iter()
+        .find(|(prefix, _)| model.starts_with(prefix))
+        .map(|(_, length)| *length)
+}
+
+/// Approximate number of tokens in `text`
+pub fn estimate_text_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN)
+}
+
+/// Approximate number of tokens `turn` takes up in a request
+pub fn estimate_turn_tokens(turn: &ChatTurn) -> usize {
+    estimate_text_tokens(&turn.content) + TOKENS_PER_TURN
+}
+
+/// Approximate number of tokens a whole conversation takes up
+pub fn estimate_tokens(turns: &[ChatTurn]) -> usize {
+    turns.iter().map(estimate_turn_tokens).sum()
+}
+
+/// A conversation cut down to fit a token budget
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrimmedHistory {
+    pub turns: Vec<ChatTurn>,
+    /// How many turns were dropped from the start of the conversation
+    pub dropped: usize,
+    /// Estimated size of the kept turns
+    pub estimated_tokens: usize,
+}
+
+/// Drop the oldest turns until the conversation fits in `budget` tokens.
+///
+/// System turns are always kept, as are the last `keep_last` turns (at least one),
+/// so the result can still exceed the budget when those alone are too large. Turns
+/// are otherwise kept newest-first, and their original order is preserved.
+pub fn trim_to_budget(turns: &[ChatTurn], budget: usize, keep_last: usize) -> TrimmedHistory {
+    let total = estimate_tokens(turns);
+    if total <= budget {
+        return TrimmedHistory {
+            turns: turns.to_vec(),
+            dropped: 0,
+            estimated_tokens: total,
+        };
+    }
+
+    let first_kept = turns.len().saturating_sub(keep_last.max(1));
+    let mut keep = vec![false; turns.len()];
+    let mut used = 0;
+    for (i, turn) in turns.iter().enumerate() {
+        if turn.role == Role::System || i >= first_kept {
+            keep[i] = true;
+            used += estimate_turn_tokens(turn);
+        }
+    }
+
+    // Fill what's left of the budget with the most recent turns, stopping at the
+    // first one that doesn't fit so the kept history has no gaps
+    for (i, turn) in turns.iter().enumerate().rev() {
+        if keep[i] {
+            continue;
+        }
+        let cost = estimate_turn_tokens(turn);
+        if used + cost > budget {
+            break;
+        }
+        keep[i] = true;
+        used += cost;
+    }
+
+    let kept: Vec<ChatTurn> = turns
+        .iter()
+        .zip(&keep)
+        .filter(|(_, keep)| **keep)
+        .map(|(turn, _)| turn.clone())
+        .collect();
+
+    TrimmedHistory {
+        dropped: turns.len() - kept.len(),
+        turns: kept,
+        estimated_tokens: used,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_turn_tokens(&ChatTurn::user("")), TOKENS_PER_TURN);
+        assert_eq!(estimate_turn_tokens(&ChatTurn::user("abcd")), 1 + TOKENS_PER_TURN);
+        assert_eq!(estimate_turn_tokens(&ChatTurn::user("abcde")), 2 + TOKENS_PER_TURN);
+        assert_eq!(
+            estimate_tokens(&[ChatTurn::user("abcd"), ChatTurn::assistant("abcd")]),
+            2 * (1 + TOKENS_PER_TURN)
+        );
+    }
+
+    #[test]
+    fn test_text_estimate_is_close_to_real_tokenizers() {
+        // 10 tokens with the Claude and GPT-4 tokenizers
+        let estimate = estimate_text_tokens("The quick brown fox jumps over the lazy dog.");
+        assert!((9..=12).contains(&estimate), "estimate was {}", estimate);
+    }
+
+    #[test]
+    fn test_known_model_context_lengths() {
+        assert_eq!(model_context_length("anthropic/claude-3-haiku"), Some(200_000));
+        assert_eq!(model_context_length("openai/gpt-4o-mini"), Some(128_000));
+        assert_eq!(model_context_length("openai/gpt-4"), Some(8_192));
+        assert_eq!(model_context_length("someone/new-model"), None);
+    }
+
+    #[test]
+    fn test_history_within_budget_is_untouched() {
+        let turns = vec![
+            ChatTurn::system("be brief"),
+            ChatTurn::user("hi"),
+            ChatTurn::assistant("hello"),
+        ];
+        let trimmed = trim_to_budget(&turns, 1_000, 1);
+
+        assert_eq!(trimmed.turns, turns);
+        assert_eq!(trimmed.dropped, 0);
+    }
+
+    #[test]
+    fn test_history_over_budget_is_trimmed_keeping_system_message() {
+        let filler = "x".repeat(400);
+        let mut turns = vec![ChatTurn::system("You are a coding assistant.")];
+        for i in 0..10 {
+            turns.push(ChatTurn::user(format!("question {} {}", i, filler)));
+            turns.push(ChatTurn::assistant(format!("answer {} {}", i, filler)));
+        }
+        turns.push(ChatTurn::user("latest question"));
+
+        let budget = 500;
+        assert!(estimate_tokens(&turns) > budget);
+        let trimmed = trim_to_budget(&turns, budget, 1);
+
+        assert!(trimmed.dropped > 0);
+        assert!(trimmed.estimated_tokens <= budget);
+        assert_eq!(trimmed.estimated_tokens, estimate_tokens(&trimmed.turns));
+        assert_eq!(trimmed.turns.len() + trimmed.dropped, turns.len());
+
+        // The system prompt survives at the front, and the newest turns are kept in order
+        assert_eq!(trimmed.turns[0], turns[0]);
+        let kept_tail = &trimmed.turns[1..];
+        assert_eq!(kept_tail, &turns[turns.len() - kept_tail.len()..]);
+        assert_eq!(trimmed.turns.last().unwrap().content, "latest question");
+    }
+
+    #[test]
+    fn test_latest_turn_is_kept_even_if_too_large() {
+        let turns = vec![
+            ChatTurn::user("earlier"),
+            ChatTurn::assistant("reply"),
+            ChatTurn::user("y".repeat(1_000)),
+        ];
+        let trimmed = trim_to_budget(&turns, 10, 1);
+
+        assert_eq!(trimmed.turns, vec![turns[2].clone()]);
+        assert_eq!(trimmed.dropped, 2);
+    }
+
+    #[test]
+    fn test_last_turns_are_kept_together() {
+        let filler = "z".repeat(400);
+        let mut turns = vec![ChatTurn::user(format!("old {}", filler))];
+        turns.extend(crate::mcp::client::continuation_turns(
+            &format!("prompt {}", filler),
+            &format!("partial {}", filler),
+        ));
+        let trimmed = trim_to_budget(&turns, 50, 3);
+
+        assert_eq!(trimmed.turns, turns[1..]);
+        assert_eq!(trimmed.dropped, 1);
+    }
+}
@@ -1,5 +1,8 @@
 pub mod client;
+pub mod context;
+pub mod models;
 pub mod protocol;
+pub mod rate_limit;
 pub mod server_manager;
 pub mod task_executor;
 
@@ -0,0 +1,216 @@
+//! OpenRouter's model list, cached so lookups like a model's context length don't
+//! each cost a request
+
+use serde::Deserialize;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+use super::context::model_context_length;
+use crate::config::LogLevel;
+use crate::error::TaskError;
+use crate::logging;
+
+/// How long a fetched model list is used before it's fetched again
+pub const MODEL_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// After a failed refresh, how long the stale list is used before trying again
+const FAILED_REFRESH_RETRY: Duration = Duration::from_secs(60);
+
+/// One entry of OpenRouter's `/models` list
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ModelInfo {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    /// Context window in tokens, when the provider reports it
+    #[serde(default)]
+    pub context_length: Option<usize>,
+}
+
+impl ModelInfo {
+    /// Parse the body of a `/models` response: `{"data": [...]}`
+    pub fn parse_list(body: &str) -> Result<Vec<ModelInfo>, TaskError> {
+        #[derive(Deserialize)]
+        struct ModelList {
+            data: Vec<ModelInfo>,
+        }
+
+        serde_json::from_str::<ModelList>(body)
+            .map(|list| list.data)
+            .map_err(|e| TaskError::ExecutionFailed(format!("Failed to parse model list: {}", e)))
+    }
+}
+
+#[derive(Debug)]
+struct CachedModels {
+    models: Arc<Vec<ModelInfo>>,
+    expires_at: Instant,
+}
+
+/// Model list shared by every clone, fetched at most once per TTL.
+///
+/// Whoever finds the list missing or expired fetches it while holding the write lock,
+/// so concurrent lookups wait for that one request instead of making their own. If a
+/// refresh fails the previous list is kept and used for a while before retrying.
+#[derive(Debug, Clone)]
+pub struct ModelCache {
+    cached: Arc<RwLock<Option<CachedModels>>>,
+    ttl: Duration,
+}
+
+impl Default for ModelCache {
+    fn default() -> Self {
+        Self::new(MODEL_CACHE_TTL)
+    }
+}
+
+impl ModelCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            cached: Arc::default(),
+            ttl,
+        }
+    }
+
+    /// The model list, calling `fetch` only if it's missing or expired
+    pub async fn get_or_fetch<F, Fut>(&self, fetch: F) -> Result<Arc<Vec<ModelInfo>>, TaskError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Vec<ModelInfo>, TaskError>>,
+    {
+        let fresh = |entry: &&CachedModels| Instant::now() < entry.expires_at;
+        if let Some(cached) = self.cached.read().await.as_ref().filter(fresh) {
+            return Ok(cached.models.clone());
+        }
+
+        let mut cached = self.cached.write().await;
+        // Someone else may have fetched it while this caller waited for the lock
+        if let Some(current) = cached.as_ref().filter(fresh) {
+            return Ok(current.models.clone());
+        }
+        self.fetch_into(&mut cached, fetch).await
+    }
+
+    /// Fetch the list now, whether or not the cached one has expired
+    pub async fn refresh<F, Fut>(&self, fetch: F) -> Result<Arc<Vec<ModelInfo>>, TaskError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Vec<ModelInfo>, TaskError>>,
+    {
+        let mut cached = self.cached.write().await;
+        self.fetch_into(&mut cached, fetch).await
+    }
+
+    async fn fetch_into<F, Fut>(
+        &self,
+        cached: &mut Option<CachedModels>,
+        fetch: F,
+    ) -> Result<Arc<Vec<ModelInfo>>, TaskError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Vec<ModelInfo>, TaskError>>,
+    {
+        match fetch().await {
+            Ok(models) => {
+                let models = Arc::new(models);
+                *cached = Some(CachedModels {
+                    models: models.clone(),
+                    expires_at: Instant::now() + self.ttl,
+                });
+                Ok(models)
+            }
+            Err(e) => match cached.as_mut() {
+                Some(stale) => {
+                    logging::log(
+                        LogLevel::Warn,
+                        "llm",
+                        format!("Keeping the cached model list; refreshing it failed: {}", e),
+                    );
+                    stale.expires_at = Instant::now() + FAILED_REFRESH_RETRY.min(self.ttl);
+                    Ok(stale.models.clone())
+                }
+                None => Err(e),
+            },
+        }
+    }
+
+    /// Context length of `model` from whatever list is cached, even an expired one,
+    /// falling back to the built-in table. Never waits on the network, so it's safe to
+    /// call while drawing.
+    pub fn cached_context_length(&self, model: &str) -> Option<usize> {
+        let from_cache = self.cached.try_read().ok().and_then(|cached| {
+            let cached = cached.as_ref()?;
+            lookup_context_length(&cached.models, model)
+        });
+        from_cache.or_else(|| model_context_length(model))
+    }
+}
+
+/// Context length `models` reports for `model`
+pub fn lookup_context_length(models: &[ModelInfo], model: &str) -> Option<usize> {
+    models
+        .iter()
+        .find(|info| info.id == model)
+        .and_then(|info| info.context_length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn models() -> Vec<ModelInfo> {
+        ModelInfo::parse_list(
+            r#"{"data": [
+                {"id": "anthropic/claude-3-haiku", "name": "Claude 3 Haiku", "context_length": 200000},
+                {"id": "acme/new-model", "name": "New", "context_length": 65536}
+            ]}"#,
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_lookups_fetch_once() {
+        let cache = ModelCache::default();
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let fetch = || async {
+            fetches.fetch_add(1, Ordering::SeqCst);
+            // Long enough for the other lookup to find the cache empty too
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok(models())
+        };
+
+        let (a, b) = tokio::join!(cache.get_or_fetch(fetch), cache.get_or_fetch(fetch));
+        assert_eq!(lookup_context_length(&a.unwrap(), "acme/new-model"), Some(65536));
+        assert_eq!(lookup_context_length(&b.unwrap(), "acme/new-model"), Some(65536));
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+        // Until it expires, or is refreshed explicitly
+        cache.get_or_fetch(fetch).await.unwrap();
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+        cache.refresh(fetch).await.unwrap();
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_failed_refresh_keeps_stale_models() {
+        let cache = ModelCache::new(Duration::ZERO);
+        let failing = || async { Err(TaskError::ExecutionFailed("offline".to_string())) };
+        assert!(cache.get_or_fetch(failing).await.is_err());
+
+        cache.get_or_fetch(|| async { Ok(models()) }).await.unwrap();
+        // Expired straight away, but the failed refresh falls back to it
+        let stale = cache.get_or_fetch(failing).await.unwrap();
+        assert_eq!(stale.len(), 2);
+        assert_eq!(cache.cached_context_length("acme/new-model"), Some(65536));
+    }
+
+    #[test]
+    fn test_cached_context_length_falls_back_to_known_models() {
+        let cache = ModelCache::default();
+        assert_eq!(cache.cached_context_length("anthropic/claude-3-opus"), Some(200_000));
+        assert_eq!(cache.cached_context_length("acme/new-model"), None);
+    }
+}
@@ -1,6 +1,14 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use thiserror::Error;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::{Mutex, oneshot};
+
+use crate::config::LogLevel;
+use crate::logging;
 
 /// Protocol version information
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -191,9 +199,335 @@ pub trait McpProtocol {
     fn get_state(&self) -> ServerState;
 }
 
+const JSONRPC_VERSION: &str = "2.0";
+const CONTENT_LENGTH_HEADER: &str = "content-length:";
+
+/// How messages are delimited on the wire
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Framing {
+    /// One JSON document per line (MCP stdio transport)
+    Newline,
+    /// LSP-style `Content-Length: N` header, blank line, then N bytes of JSON
+    ContentLength,
+}
+
+/// Transport-level failures while reading, writing or correlating messages
+#[derive(Debug, Error)]
+pub enum JsonRpcTransportError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Invalid frame header: {0}")]
+    InvalidHeader(String),
+
+    #[error("Connection closed before a response arrived")]
+    Closed,
+
+    #[error("{0}")]
+    Rpc(JsonRpcError),
+}
+
+/// The `error` object of a JSON-RPC response
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct JsonRpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+}
+
+impl JsonRpcError {
+    pub const PARSE_ERROR: i64 = -32700;
+    pub const INVALID_REQUEST: i64 = -32600;
+    pub const METHOD_NOT_FOUND: i64 = -32601;
+    pub const INVALID_PARAMS: i64 = -32602;
+    pub const INTERNAL_ERROR: i64 = -32603;
+}
+
+impl fmt::Display for JsonRpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "JSON-RPC error {}: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for JsonRpcError {}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct JsonRpcRequest {
+    pub jsonrpc: String,
+    pub id: u64,
+    pub method: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params: Option<serde_json::Value>,
+}
+
+impl JsonRpcRequest {
+    pub fn new(id: u64, method: &str, params: Option<serde_json::Value>) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id,
+            method: method.to_string(),
+            params,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct JsonRpcResponse {
+    pub jsonrpc: String,
+    /// `None` when the server couldn't tell which request failed, e.g. on a parse error
+    pub id: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<JsonRpcError>,
+}
+
+impl JsonRpcResponse {
+    pub fn success(id: u64, result: serde_json::Value) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id: Some(id),
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    pub fn error(id: u64, error: JsonRpcError) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id: Some(id),
+            result: None,
+            error: Some(error),
+        }
+    }
+
+    /// Collapse into the call's outcome; a response with neither field is treated as `null`
+    pub fn into_result(self) -> Result<serde_json::Value, JsonRpcError> {
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(self.result.unwrap_or(serde_json::Value::Null)),
+        }
+    }
+}
+
+/// Write a single message using `framing`
+pub async fn write_message<W, T>(
+    writer: &mut W,
+    message: &T,
+    framing: Framing,
+) -> Result<(), JsonRpcTransportError>
+where
+    W: AsyncWrite + Unpin,
+    T: Serialize,
+{
+    let body = serde_json::to_vec(message)?;
+
+    match framing {
+        Framing::Newline => {
+            writer.write_all(&body).await?;
+            writer.write_all(b"\n").await?;
+        }
+        Framing::ContentLength => {
+            let header = format!("Content-Length: {}\r\n\r\n", body.len());
+            writer.write_all(header.as_bytes()).await?;
+            writer.write_all(&body).await?;
+        }
+    }
+
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Read the next message, accepting either framing. Returns `None` at end of stream.
+pub async fn read_message<R>(reader: &mut R) -> Result<Option<serde_json::Value>, JsonRpcTransportError>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).await? == 0 {
+            return Ok(None);
+        }
+
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            // Tolerate stray blank lines between messages
+            continue;
+        }
+
+        if !trimmed.to_ascii_lowercase().starts_with(CONTENT_LENGTH_HEADER) {
+            return Ok(Some(serde_json::from_str(trimmed)?));
+        }
+
+        let length: usize = trimmed[CONTENT_LENGTH_HEADER.len()..]
+            .trim()
+            .parse()
+            .map_err(|_| JsonRpcTransportError::InvalidHeader(trimmed.to_string()))?;
+
+        // Skip any further headers (e.g. Content-Type) up to the blank separator line
+        loop {
+            line.clear();
+            if reader.read_line(&mut line).await? == 0 {
+                return Err(JsonRpcTransportError::InvalidHeader(
+                    "stream ended inside headers".to_string(),
+                ));
+            }
+            if line.trim().is_empty() {
+                break;
+            }
+        }
+
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).await?;
+        return Ok(Some(serde_json::from_slice(&body)?));
+    }
+}
+
+/// Requests awaiting a response, keyed by request id
+#[derive(Default)]
+pub struct PendingRequests {
+    next_id: AtomicU64,
+    waiting: parking_lot::Mutex<HashMap<u64, oneshot::Sender<JsonRpcResponse>>>,
+    uncorrelated: parking_lot::Mutex<Vec<JsonRpcError>>,
+}
+
+impl PendingRequests {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allocate a fresh id and a receiver for its response
+    pub fn register(&self) -> (u64, oneshot::Receiver<JsonRpcResponse>) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let (tx, rx) = oneshot::channel();
+        self.waiting.lock().insert(id, tx);
+        (id, rx)
+    }
+
+    /// Hand a response to whoever is waiting on its id.
+    /// Returns `false` for responses nobody asked for. Errors without an id can't be
+    /// matched to a caller, so they're logged and kept for `take_uncorrelated_errors`.
+    pub fn resolve(&self, response: JsonRpcResponse) -> bool {
+        let Some(id) = response.id else {
+            if let Some(error) = response.error {
+                logging::log(
+                    LogLevel::Warn,
+                    "mcp",
+                    format!("Server reported an error for an unknown request: {}", error),
+                );
+                self.uncorrelated.lock().push(error);
+            }
+            return false;
+        };
+        match self.waiting.lock().remove(&id) {
+            Some(tx) => tx.send(response).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Errors the server sent with a `null` id since the last call
+    pub fn take_uncorrelated_errors(&self) -> Vec<JsonRpcError> {
+        std::mem::take(&mut *self.uncorrelated.lock())
+    }
+
+    /// Fail every waiting request with `Closed`, e.g. once the connection is gone
+    pub fn close(&self) {
+        self.waiting.lock().clear();
+    }
+
+    /// Forget a request, e.g. when writing it failed
+    pub fn cancel(&self, id: u64) {
+        self.waiting.lock().remove(&id);
+    }
+
+    pub fn len(&self) -> usize {
+        self.waiting.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Client half of a JSON-RPC connection. Requests may be issued concurrently; responses
+/// are matched back to their callers by id as `dispatch_responses` reads them.
+pub struct JsonRpcClient<W> {
+    writer: Mutex<W>,
+    pending: Arc<PendingRequests>,
+    framing: Framing,
+}
+
+impl<W: AsyncWrite + Unpin> JsonRpcClient<W> {
+    pub fn new(writer: W, framing: Framing) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+            pending: Arc::new(PendingRequests::new()),
+            framing,
+        }
+    }
+
+    pub fn pending(&self) -> Arc<PendingRequests> {
+        self.pending.clone()
+    }
+
+    /// Send a request and wait for its matching response
+    pub async fn call(
+        &self,
+        method: &str,
+        params: Option<serde_json::Value>,
+    ) -> Result<serde_json::Value, JsonRpcTransportError> {
+        let (id, rx) = self.pending.register();
+        let request = JsonRpcRequest::new(id, method, params);
+
+        {
+            let mut writer = self.writer.lock().await;
+            if let Err(e) = write_message(&mut *writer, &request, self.framing).await {
+                self.pending.cancel(id);
+                return Err(e);
+            }
+        }
+
+        let response = rx.await.map_err(|_| JsonRpcTransportError::Closed)?;
+        response.into_result().map_err(JsonRpcTransportError::Rpc)
+    }
+}
+
+/// Read responses until the stream ends, routing each to its pending request.
+/// Messages that aren't responses (e.g. server notifications) are skipped. Once the
+/// stream ends or can't be read, requests still waiting fail with `Closed`.
+pub async fn dispatch_responses<R>(
+    reader: &mut R,
+    pending: &PendingRequests,
+) -> Result<(), JsonRpcTransportError>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let result = loop {
+        match read_message(reader).await {
+            Ok(Some(message)) => {
+                if let Ok(response) = serde_json::from_value::<JsonRpcResponse>(message) {
+                    pending.resolve(response);
+                }
+            }
+            Ok(None) => break Ok(()),
+            Err(e) => break Err(e),
+        }
+    };
+    pending.close();
+    result
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use serde_json::json;
+    use tokio::io::{BufReader, duplex};
 
     #[test]
     fn test_version_compatibility() {
@@ -255,4 +589,187 @@ mod tests {
         assert_eq!(state.status, deserialized.status);
         assert_eq!(state.tools.len(), deserialized.tools.len());
     }
+
+    #[tokio::test]
+    async fn test_newline_framing_roundtrip() {
+        let mut buffer = Vec::new();
+        let request = JsonRpcRequest::new(1, "tools/list", None);
+        write_message(&mut buffer, &request, Framing::Newline).await.unwrap();
+
+        assert!(buffer.ends_with(b"\n"));
+
+        let mut reader = BufReader::new(buffer.as_slice());
+        let message = read_message(&mut reader).await.unwrap().unwrap();
+        assert_eq!(serde_json::from_value::<JsonRpcRequest>(message).unwrap(), request);
+        assert!(read_message(&mut reader).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_content_length_framing_roundtrip() {
+        let mut buffer = Vec::new();
+        // Multi-byte content makes sure the length is counted in bytes, not chars
+        let request = JsonRpcRequest::new(7, "echo", Some(json!({ "text": "héllo\nwörld" })));
+        write_message(&mut buffer, &request, Framing::ContentLength).await.unwrap();
+
+        let text = String::from_utf8(buffer.clone()).unwrap();
+        assert!(text.starts_with("Content-Length: "));
+
+        let mut reader = BufReader::new(buffer.as_slice());
+        let message = read_message(&mut reader).await.unwrap().unwrap();
+        assert_eq!(serde_json::from_value::<JsonRpcRequest>(message).unwrap(), request);
+    }
+
+    #[tokio::test]
+    async fn test_mixed_framing_stream() {
+        let mut buffer = Vec::new();
+        write_message(&mut buffer, &json!({ "a": 1 }), Framing::ContentLength).await.unwrap();
+        write_message(&mut buffer, &json!({ "b": 2 }), Framing::Newline).await.unwrap();
+        buffer.extend_from_slice(b"Content-Length: 8\r\nContent-Type: application/json\r\n\r\n{\"c\": 3}");
+
+        let mut reader = BufReader::new(buffer.as_slice());
+        assert_eq!(read_message(&mut reader).await.unwrap(), Some(json!({ "a": 1 })));
+        assert_eq!(read_message(&mut reader).await.unwrap(), Some(json!({ "b": 2 })));
+        assert_eq!(read_message(&mut reader).await.unwrap(), Some(json!({ "c": 3 })));
+        assert_eq!(read_message(&mut reader).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_content_length_header() {
+        let mut reader = BufReader::new(&b"Content-Length: lots\r\n\r\n{}"[..]);
+        let result = read_message(&mut reader).await;
+        assert!(matches!(result, Err(JsonRpcTransportError::InvalidHeader(_))));
+    }
+
+    #[test]
+    fn test_error_object_parsing() {
+        let raw = json!({
+            "jsonrpc": "2.0",
+            "id": 3,
+            "error": { "code": -32601, "message": "Method not found", "data": { "method": "nope" } }
+        });
+
+        let response: JsonRpcResponse = serde_json::from_value(raw).unwrap();
+        let error = response.into_result().unwrap_err();
+        assert_eq!(error.code, JsonRpcError::METHOD_NOT_FOUND);
+        assert_eq!(error.message, "Method not found");
+        assert_eq!(error.data, Some(json!({ "method": "nope" })));
+        assert_eq!(error.to_string(), "JSON-RPC error -32601: Method not found");
+    }
+
+    #[test]
+    fn test_unknown_response_is_not_resolved() {
+        let pending = PendingRequests::new();
+        let (id, _rx) = pending.register();
+
+        assert!(!pending.resolve(JsonRpcResponse::success(id + 100, json!(null))));
+        assert_eq!(pending.len(), 1);
+    }
+
+    #[test]
+    fn test_error_without_id_is_kept() {
+        let pending = PendingRequests::new();
+        let (_id, _rx) = pending.register();
+
+        let raw = json!({
+            "jsonrpc": "2.0",
+            "id": null,
+            "error": { "code": -32700, "message": "Parse error" }
+        });
+        let response: JsonRpcResponse = serde_json::from_value(raw).unwrap();
+        assert!(!pending.resolve(response));
+
+        let errors = pending.take_uncorrelated_errors();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, JsonRpcError::PARSE_ERROR);
+        assert!(pending.take_uncorrelated_errors().is_empty());
+        assert_eq!(pending.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_calls_fail_when_the_connection_closes() {
+        let (client_end, server_end) = duplex(4096);
+        let (client_read, client_write) = tokio::io::split(client_end);
+
+        let client = JsonRpcClient::new(client_write, Framing::Newline);
+        let pending = client.pending();
+        let dispatcher = tokio::spawn(async move {
+            let mut reader = BufReader::new(client_read);
+            dispatch_responses(&mut reader, &pending).await
+        });
+
+        // The server reads the request, then goes away without answering
+        let server = tokio::spawn(async move {
+            let mut reader = BufReader::new(server_end);
+            read_message(&mut reader).await.unwrap().unwrap();
+        });
+
+        let result = client.call("tools/list", None).await;
+        assert!(matches!(result, Err(JsonRpcTransportError::Closed)));
+        assert!(client.pending().is_empty());
+
+        server.await.unwrap();
+        dispatcher.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_interleaved_requests_match_responses() {
+        let (client_end, server_end) = duplex(4096);
+        let (client_read, client_write) = tokio::io::split(client_end);
+        let (server_read, mut server_write) = tokio::io::split(server_end);
+
+        // Server collects three requests, then answers them in reverse order
+        let server = tokio::spawn(async move {
+            let mut reader = BufReader::new(server_read);
+            let mut requests = Vec::new();
+            while requests.len() < 3 {
+                let message = read_message(&mut reader).await.unwrap().unwrap();
+                requests.push(serde_json::from_value::<JsonRpcRequest>(message).unwrap());
+            }
+
+            for request in requests.into_iter().rev() {
+                let response = if request.method == "fail" {
+                    JsonRpcResponse::error(
+                        request.id,
+                        JsonRpcError {
+                            code: JsonRpcError::INVALID_PARAMS,
+                            message: "bad params".to_string(),
+                            data: None,
+                        },
+                    )
+                } else {
+                    JsonRpcResponse::success(request.id, json!({ "echo": request.params }))
+                };
+                write_message(&mut server_write, &response, Framing::ContentLength)
+                    .await
+                    .unwrap();
+            }
+        });
+
+        let client = JsonRpcClient::new(client_write, Framing::ContentLength);
+        let pending = client.pending();
+        let dispatcher = tokio::spawn(async move {
+            let mut reader = BufReader::new(client_read);
+            dispatch_responses(&mut reader, &pending).await
+        });
+
+        let (first, second, third) = tokio::join!(
+            client.call("echo", Some(json!("first"))),
+            client.call("echo", Some(json!("second"))),
+            client.call("fail", None),
+        );
+
+        assert_eq!(first.unwrap(), json!({ "echo": "first" }));
+        assert_eq!(second.unwrap(), json!({ "echo": "second" }));
+        match third {
+            Err(JsonRpcTransportError::Rpc(error)) => {
+                assert_eq!(error.code, JsonRpcError::INVALID_PARAMS)
+            }
+            other => panic!("Expected an RPC error, got {:?}", other),
+        }
+        assert!(client.pending().is_empty());
+
+        server.await.unwrap();
+        drop(client);
+        dispatcher.abort();
+    }
 }
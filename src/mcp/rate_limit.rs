@@ -0,0 +1,124 @@
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::error::TaskError;
+
+/// Longest a request will wait in the queue before it's rejected instead
+pub const MAX_QUEUE_WAIT: Duration = Duration::from_secs(60);
+
+#[derive(Debug)]
+struct Bucket {
+    /// Tokens currently available; negative while requests are queued
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// Client-side token bucket for outgoing requests.
+///
+/// Each request takes one token and tokens refill at the configured rate, so bursts up
+/// to `burst` go out immediately and anything beyond that is queued until a token is
+/// due. Clones share the same bucket, so every copy of a client draws from one budget.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    bucket: Arc<Mutex<Bucket>>,
+    /// Tokens added per second
+    rate: f64,
+    burst: f64,
+}
+
+impl RateLimiter {
+    /// Allow `requests_per_minute`, with bursts of up to that many requests
+    pub fn new(requests_per_minute: u32) -> Self {
+        Self::with_burst(requests_per_minute, requests_per_minute)
+    }
+
+    pub fn with_burst(requests_per_minute: u32, burst: u32) -> Self {
+        let burst = burst.max(1) as f64;
+        Self {
+            bucket: Arc::new(Mutex::new(Bucket {
+                tokens: burst,
+                last_refill: Instant::now(),
+            })),
+            rate: requests_per_minute.max(1) as f64 / 60.0,
+            burst,
+        }
+    }
+
+    /// Take a token, returning how long the caller must wait before sending.
+    /// Fails without taking a token if that wait would exceed `max_wait`.
+    pub fn reserve(&self, max_wait: Duration) -> Result<Duration, TaskError> {
+        let mut bucket = self.bucket.lock();
+
+        let now = Instant::now();
+        let refilled = now.duration_since(bucket.last_refill).as_secs_f64() * self.rate;
+        bucket.tokens = (bucket.tokens + refilled).min(self.burst);
+        bucket.last_refill = now;
+
+        let wait = if bucket.tokens >= 1.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate)
+        };
+        if wait > max_wait {
+            return Err(TaskError::RateLimited { retry_after: wait });
+        }
+
+        bucket.tokens -= 1.0;
+        Ok(wait)
+    }
+
+    /// Wait for a token, queueing behind earlier requests
+    pub async fn acquire(&self, max_wait: Duration) -> Result<(), TaskError> {
+        let wait = self.reserve(max_wait)?;
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_burst_then_queue() {
+        // 600/min is one token every 100ms
+        let limiter = RateLimiter::with_burst(600, 2);
+        assert_eq!(limiter.reserve(MAX_QUEUE_WAIT).unwrap(), Duration::ZERO);
+        assert_eq!(limiter.reserve(MAX_QUEUE_WAIT).unwrap(), Duration::ZERO);
+
+        // Each further request queues behind the previous one
+        let third = limiter.reserve(MAX_QUEUE_WAIT).unwrap();
+        let fourth = limiter.reserve(MAX_QUEUE_WAIT).unwrap();
+        assert!(third > Duration::from_millis(90), "{:?}", third);
+        assert!(fourth > third + Duration::from_millis(90), "{:?}", fourth);
+    }
+
+    #[test]
+    fn test_rejects_when_queue_too_long() {
+        let limiter = RateLimiter::with_burst(60, 1);
+        limiter.reserve(MAX_QUEUE_WAIT).unwrap();
+
+        let err = limiter.reserve(Duration::from_millis(100)).unwrap_err();
+        assert!(matches!(err, TaskError::RateLimited { .. }));
+
+        // The rejected request didn't use up a token
+        let wait = limiter.reserve(MAX_QUEUE_WAIT).unwrap();
+        assert!(wait <= Duration::from_secs(1), "{:?}", wait);
+    }
+
+    #[tokio::test]
+    async fn test_excess_requests_are_delayed() {
+        let limiter = RateLimiter::with_burst(600, 1);
+        let start = Instant::now();
+
+        limiter.acquire(MAX_QUEUE_WAIT).await.unwrap();
+        assert!(start.elapsed() < Duration::from_millis(50));
+
+        // Clones share the bucket, so this one has to wait for a refill
+        limiter.clone().acquire(MAX_QUEUE_WAIT).await.unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(90), "{:?}", start.elapsed());
+    }
+}
@@ -0,0 +1,203 @@
+//! Answering a single prompt without the TUI, e.g. `cat notes.md | samus "summarize this"`
+
+use std::future::Future;
+use std::io::{self, IsTerminal, Read, Write};
+
+use crate::activity::{ActivityEvent, ActivityLog};
+use crate::config::{Config, LlmTask};
+use crate::error::TaskError;
+use crate::mcp::client::{ChatCompletion, ChatTurn, DEFAULT_MODEL, OpenRouterClient, Role};
+
+/// Anything that can answer a conversation; lets the one-shot path run without a network
+pub trait Completer {
+    fn complete(
+        &self,
+        turns: &[ChatTurn],
+    ) -> impl Future<Output = Result<ChatCompletion, TaskError>> + Send;
+}
+
+impl Completer for OpenRouterClient {
+    fn complete(
+        &self,
+        turns: &[ChatTurn],
+    ) -> impl Future<Output = Result<ChatCompletion, TaskError>> + Send {
+        OpenRouterClient::complete(self, turns)
+    }
+}
+
+/// Everything piped to stdin, or `None` when stdin is an interactive terminal
+pub fn read_piped_stdin() -> io::Result<Option<String>> {
+    let mut stdin = io::stdin();
+    if stdin.is_terminal() {
+        return Ok(None);
+    }
+    let mut content = String::new();
+    stdin.read_to_string(&mut content)?;
+    Ok(Some(content))
+}
+
+/// System turn carrying piped input, so it's sent along with whatever is asked about it
+pub fn piped_context_turn(piped: &str) -> ChatTurn {
+    ChatTurn::system(format!(
+        "The user piped the following input to samus:\n\n{}",
+        piped.trim_end()
+    ))
+}
+
+/// Conversation for a one-shot `question`, with any piped input as context
+pub fn one_shot_turns(piped: Option<&str>, question: &str) -> Vec<ChatTurn> {
+    let mut turns = Vec::with_capacity(2);
+    if let Some(piped) = piped.filter(|p| !p.trim().is_empty()) {
+        turns.push(piped_context_turn(piped));
+    }
+    turns.push(ChatTurn::user(question.trim()));
+    turns
+}
+
+/// Send `turns` and write the answer to `out`, ending with a newline
+pub async fn run_one_shot<C: Completer, W: Write>(
+    client: &C,
+    turns: &[ChatTurn],
+    out: &mut W,
+) -> Result<ChatCompletion, TaskError> {
+    let completion = client.complete(turns).await?;
+
+    out.write_all(completion.content.as_bytes())?;
+    if !completion.content.ends_with('\n') {
+        out.write_all(b"\n")?;
+    }
+    out.flush()?;
+
+    Ok(completion)
+}
+
+/// Answer `turns` with the first enabled server, printing the reply to stdout and
+/// recording the exchange in `activity`
+pub fn run(
+    config: &Config,
+    turns: &[ChatTurn],
+    activity: Option<&ActivityLog>,
+) -> Result<(), TaskError> {
+    let emit = |event| {
+        if let Some(activity) = activity {
+            activity.emit(event);
+        }
+    };
+    for turn in turns {
+        emit(ActivityEvent::Message {
+            role: turn.role,
+            content: turn.content.clone(),
+        });
+    }
+
+    match answer(config, turns) {
+        Ok(completion) => {
+            emit(ActivityEvent::Message {
+                role: Role::Assistant,
+                content: completion.content.clone(),
+            });
+            if completion.is_truncated() {
+                eprintln!("(the response was cut off at the model's length limit)");
+            }
+            Ok(())
+        }
+        Err(e) => {
+            emit(ActivityEvent::Error {
+                message: e.to_string(),
+            });
+            Err(e)
+        }
+    }
+}
+
+fn answer(config: &Config, turns: &[ChatTurn]) -> Result<ChatCompletion, TaskError> {
+    let server = config
+        .mcp_servers
+        .iter()
+        .find(|s| s.enabled)
+        .cloned()
+        .ok_or_else(|| {
+            TaskError::ExecutionFailed(
+                "No LLM server configured; set OPEN_ROUTER_API_KEY".to_string(),
+            )
+        })?;
+    let model = config
+        .task_models
+        .get(&LlmTask::Chat)
+        .map(String::as_str)
+        .unwrap_or(DEFAULT_MODEL);
+    let client = OpenRouterClient::new(server, model.to_string())?;
+
+    let rt = tokio::runtime::Runtime::new()?;
+    rt.block_on(run_one_shot(&client, turns, &mut io::stdout()))
+}
+
+/// Stand-in client for tests of code that talks to the model
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+
+    /// Replies with a fixed answer and records the conversation it was sent
+    pub struct MockCompleter {
+        reply: String,
+        seen: parking_lot::Mutex<Vec<ChatTurn>>,
+    }
+
+    impl MockCompleter {
+        pub fn replying(reply: impl Into<String>) -> Self {
+            Self {
+                reply: reply.into(),
+                seen: parking_lot::Mutex::new(Vec::new()),
+            }
+        }
+
+        /// Turns from the last request
+        pub fn seen(&self) -> Vec<ChatTurn> {
+            self.seen.lock().clone()
+        }
+    }
+
+    impl Completer for MockCompleter {
+        fn complete(
+            &self,
+            turns: &[ChatTurn],
+        ) -> impl Future<Output = Result<ChatCompletion, TaskError>> + Send {
+            *self.seen.lock() = turns.to_vec();
+            let completion = ChatCompletion {
+                content: self.reply.clone(),
+                finish_reason: Some("stop".to_string()),
+            };
+            async move { Ok(completion) }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::mock::MockCompleter;
+
+    #[test]
+    fn test_one_shot_prints_answer_to_stdout() {
+        let client = MockCompleter::replying("Three lines about fruit.");
+        let turns = one_shot_turns(Some("apple\nbanana\ncherry\n"), " summarize this ");
+        let mut stdout = Vec::new();
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(run_one_shot(&client, &turns, &mut stdout)).unwrap();
+
+        assert_eq!(String::from_utf8(stdout).unwrap(), "Three lines about fruit.\n");
+
+        let seen = client.seen();
+        assert_eq!(seen.len(), 2);
+        assert_eq!(seen[0].role, Role::System);
+        assert!(seen[0].content.ends_with("apple\nbanana\ncherry"));
+        assert_eq!(seen[1], ChatTurn::user("summarize this"));
+    }
+
+    #[test]
+    fn test_empty_pipe_adds_no_context() {
+        assert_eq!(one_shot_turns(Some("  \n"), "hi"), vec![ChatTurn::user("hi")]);
+        assert_eq!(one_shot_turns(None, "hi"), vec![ChatTurn::user("hi")]);
+    }
+}
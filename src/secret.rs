@@ -0,0 +1,130 @@
+use lazy_static::lazy_static;
+use parking_lot::RwLock;
+use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+/// Replacement shown wherever a secret would otherwise appear
+pub const MASK: &str = "********";
+
+/// Values shorter than this aren't registered for redaction; masking them would
+/// mangle ordinary text
+const MIN_REDACT_LEN: usize = 8;
+
+lazy_static! {
+    /// Every secret constructed so far, so `redact` can scrub them from arbitrary text
+    static ref KNOWN_SECRETS: RwLock<Vec<String>> = RwLock::new(Vec::new());
+    static ref BEARER_TOKEN: Regex = Regex::new(r"(?i)(bearer\s+)[A-Za-z0-9._~+/=\-]+").unwrap();
+    static ref API_KEY: Regex = Regex::new(r"\bsk-[A-Za-z0-9_\-]{16,}").unwrap();
+}
+
+/// A sensitive value whose `Debug` and `Display` output is masked.
+/// Use `expose` at the single point where the raw value is actually needed.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret<T = String>(T);
+
+impl Secret<String> {
+    pub fn new(value: impl Into<String>) -> Self {
+        let value = value.into();
+        if value.len() >= MIN_REDACT_LEN {
+            let mut known = KNOWN_SECRETS.write();
+            if !known.contains(&value) {
+                known.push(value.clone());
+            }
+        }
+        Self(value)
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl<T> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Secret({})", MASK)
+    }
+}
+
+impl<T> fmt::Display for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(MASK)
+    }
+}
+
+// Secrets round-trip through the config file unmasked; only formatting hides them
+impl<T: Serialize> Serialize for Secret<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret<String> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Secret::new)
+    }
+}
+
+/// Mask every known secret, bearer token and API-key-shaped string in `text`
+pub fn redact(text: &str) -> String {
+    let mut redacted = text.to_string();
+
+    for secret in KNOWN_SECRETS.read().iter() {
+        if redacted.contains(secret.as_str()) {
+            redacted = redacted.replace(secret.as_str(), MASK);
+        }
+    }
+
+    let redacted = BEARER_TOKEN.replace_all(&redacted, format!("${{1}}{}", MASK));
+    API_KEY.replace_all(&redacted, MASK).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_formatting_is_masked() {
+        let secret = Secret::new("super-secret-value");
+
+        assert_eq!(format!("{}", secret), MASK);
+        assert_eq!(format!("{:?}", secret), format!("Secret({})", MASK));
+        assert_eq!(secret.expose(), "super-secret-value");
+    }
+
+    #[test]
+    fn test_redact_known_secret() {
+        let _secret = Secret::new("my-private-token-123");
+
+        let text = "request rejected: token my-private-token-123 is invalid";
+        assert_eq!(redact(text), format!("request rejected: token {} is invalid", MASK));
+    }
+
+    #[test]
+    fn test_redact_patterns() {
+        assert_eq!(
+            redact("Authorization: Bearer abc.def-ghi"),
+            format!("Authorization: Bearer {}", MASK)
+        );
+        assert_eq!(
+            redact("key sk-or-v1-0123456789abcdef0123 was revoked"),
+            format!("key {} was revoked", MASK)
+        );
+        assert_eq!(redact("nothing to hide"), "nothing to hide");
+        // Only whole words starting with sk- look like keys
+        assert_eq!(
+            redact("task-0123456789abcdef0123 finished"),
+            "task-0123456789abcdef0123 finished"
+        );
+    }
+
+    #[test]
+    fn test_secret_serde_roundtrip() {
+        let secret = Secret::new("roundtrip-secret-value");
+        let json = serde_json::to_string(&secret).unwrap();
+        assert_eq!(json, "\"roundtrip-secret-value\"");
+
+        let restored: Secret<String> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, secret);
+    }
+}
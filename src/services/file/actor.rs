@@ -1,22 +1,24 @@
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::mpsc;
-use futures::Stream;
-use crate::actor::{Actor, ActorError, ActorPath};
+use tokio::sync::{mpsc, oneshot};
+use crate::actor::{Actor, ActorError};
 use crate::services::file::buffer::BufferPool;
 use crate::services::file::cache::{FileCache, CacheConfig};
 use super::FileEvent;
 
+/// Where the outcome of a command is sent, if anyone is waiting for it
+pub type Reply<T> = Option<oneshot::Sender<Result<T, std::io::Error>>>;
+
 #[derive(Debug)]
 pub enum FileCommand {
-    Read { path: PathBuf },
-    Write { path: PathBuf, contents: Vec<u8> },
+    Read { path: PathBuf, reply: Reply<Vec<u8>> },
+    Write { path: PathBuf, contents: Vec<u8>, reply: Reply<()> },
     Watch { path: PathBuf },
-    Batch { operations: Vec<FileOperation> },
+    Batch { operations: Vec<BatchOperation> },
 }
 
 #[derive(Debug)]
-pub enum FileOperation {
+pub enum BatchOperation {
     Read(PathBuf),
     Write(PathBuf, Vec<u8>),
 }
@@ -83,7 +85,7 @@ impl FileActor {
         // Write file
         use tokio::io::AsyncWriteExt;
         let mut file = tokio::fs::File::create(&path).await?;
-        file.write_all(buffer.as_slice()).await?;
+        file.write_all(&buffer.as_slice()[..contents.len()]).await?;
         file.sync_all().await?;
 
         // Invalidate cache
@@ -97,16 +99,16 @@ impl FileActor {
         Ok(())
     }
 
-    async fn handle_batch(&self, operations: Vec<FileOperation>) -> Result<Vec<FileResponse>, std::io::Error> {
+    async fn handle_batch(&self, operations: Vec<BatchOperation>) -> Result<Vec<FileResponse>, std::io::Error> {
         let mut responses = Vec::with_capacity(operations.len());
 
         for op in operations {
             match op {
-                FileOperation::Read(path) => {
+                BatchOperation::Read(path) => {
                     let result = self.handle_read(path.clone()).await;
                     responses.push(FileResponse { path, result });
                 }
-                FileOperation::Write(path, contents) => {
+                BatchOperation::Write(path, contents) => {
                     let result = self.handle_write(path.clone(), contents).await
                         .map(|_| Vec::new());
                     responses.push(FileResponse { path, result });
@@ -126,11 +128,21 @@ impl Actor for FileActor {
         let start = std::time::Instant::now();
         
         let result = match msg {
-            FileCommand::Read { path } => {
-                self.handle_read(path).await.map(|_| ())
+            FileCommand::Read { path, reply } => {
+                let result = self.handle_read(path).await;
+                let outcome = result.as_ref().map(|_| ()).map_err(copy_error);
+                if let Some(reply) = reply {
+                    let _ = reply.send(result);
+                }
+                outcome
             }
-            FileCommand::Write { path, contents } => {
-                self.handle_write(path, contents).await
+            FileCommand::Write { path, contents, reply } => {
+                let result = self.handle_write(path, contents).await;
+                let outcome = result.as_ref().map(|_| ()).map_err(copy_error);
+                if let Some(reply) = reply {
+                    let _ = reply.send(result);
+                }
+                outcome
             }
             FileCommand::Watch { path } => {
                 if let Err(e) = self.event_tx.send(FileEvent::Created { path }).await {
@@ -156,15 +168,25 @@ impl Actor for FileActor {
     }
 }
 
+// `io::Error` isn't `Clone`; keep what the caller and the supervisor need to see
+fn copy_error(e: &std::io::Error) -> std::io::Error {
+    std::io::Error::new(e.kind(), e.to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use tokio::sync::mpsc;
-    use std::time::Duration;
+    use crate::services::file::buffer::BufferPoolConfig;
 
     #[tokio::test]
     async fn test_file_actor() {
-        let buffer_pool = BufferPool::new(10, 5, 2);
+        let buffer_pool = BufferPool::new(BufferPoolConfig {
+            small_count: 10,
+            medium_count: 5,
+            large_count: 2,
+            ..BufferPoolConfig::default()
+        })
+        .unwrap();
         let cache_config = CacheConfig::default();
         let metrics = Arc::new(crate::actor::MetricsCollector::new());
         let (event_tx, mut event_rx) = mpsc::channel(100);
@@ -177,6 +199,7 @@ mod tests {
         let write_cmd = FileCommand::Write {
             path: test_path.clone(),
             contents: contents.clone(),
+            reply: None,
         };
         actor.handle(write_cmd).await.unwrap();
 
@@ -186,10 +209,13 @@ mod tests {
         }
 
         // Test read
+        let (reply, read) = oneshot::channel();
         let read_cmd = FileCommand::Read {
             path: test_path.clone(),
+            reply: Some(reply),
         };
         actor.handle(read_cmd).await.unwrap();
+        assert_eq!(read.await.unwrap().unwrap(), contents);
 
         // Clean up
         tokio::fs::remove_file(test_path).await.unwrap();
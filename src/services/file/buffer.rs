@@ -1,11 +1,90 @@
 use std::sync::Arc;
 use object_pool::{Pool, Reusable};
-use parking_lot::Mutex;
+use thiserror::Error;
 
 const DEFAULT_BUFFER_SIZE: usize = 64 * 1024; // 64KB default buffer size
 const MIN_BUFFER_SIZE: usize = 4 * 1024;      // 4KB minimum
 const MAX_BUFFER_SIZE: usize = 1024 * 1024;   // 1MB maximum
 
+#[derive(Error, Debug, PartialEq)]
+pub enum BufferPoolConfigError {
+    #[error("{0} buffer count must be non-zero")]
+    ZeroCount(&'static str),
+    #[error("size thresholds must increase: small {small} < medium {medium} < large {large}")]
+    UnorderedThresholds {
+        small: usize,
+        medium: usize,
+        large: usize,
+    },
+}
+
+/// Sizing for `BufferPool`. Requests up to `small_max_size` draw from the small pool,
+/// up to `medium_max_size` from the medium pool, and anything larger from the large pool.
+#[derive(Debug, Clone)]
+pub struct BufferPoolConfig {
+    pub small_count: usize,
+    pub medium_count: usize,
+    pub large_count: usize,
+    pub small_max_size: usize,
+    pub medium_max_size: usize,
+    pub large_buffer_size: usize,
+}
+
+impl Default for BufferPoolConfig {
+    fn default() -> Self {
+        Self {
+            small_count: 100,
+            medium_count: 50,
+            large_count: 20,
+            small_max_size: MIN_BUFFER_SIZE,
+            medium_max_size: DEFAULT_BUFFER_SIZE,
+            large_buffer_size: MAX_BUFFER_SIZE,
+        }
+    }
+}
+
+impl BufferPoolConfig {
+    pub fn validate(&self) -> Result<(), BufferPoolConfigError> {
+        if self.small_count == 0 {
+            return Err(BufferPoolConfigError::ZeroCount("small"));
+        }
+        if self.medium_count == 0 {
+            return Err(BufferPoolConfigError::ZeroCount("medium"));
+        }
+        if self.large_count == 0 {
+            return Err(BufferPoolConfigError::ZeroCount("large"));
+        }
+        if !(self.small_max_size < self.medium_max_size
+            && self.medium_max_size < self.large_buffer_size)
+        {
+            return Err(BufferPoolConfigError::UnorderedThresholds {
+                small: self.small_max_size,
+                medium: self.medium_max_size,
+                large: self.large_buffer_size,
+            });
+        }
+        Ok(())
+    }
+
+    /// Which pool a request of `size` bytes is served from
+    pub fn class_for(&self, size: usize) -> BufferClass {
+        if size <= self.small_max_size {
+            BufferClass::Small
+        } else if size <= self.medium_max_size {
+            BufferClass::Medium
+        } else {
+            BufferClass::Large
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BufferClass {
+    Small,
+    Medium,
+    Large,
+}
+
 #[derive(Debug)]
 pub struct Buffer {
     data: Vec<u8>,
@@ -35,35 +114,62 @@ impl Buffer {
 }
 
 pub struct BufferPool {
-    small_pool: Pool<Buffer>,  // For small files (<64KB)
-    medium_pool: Pool<Buffer>, // For medium files (64KB-256KB)
-    large_pool: Pool<Buffer>,  // For large files (>256KB)
+    config: BufferPoolConfig,
+    small_pool: Pool<Buffer>,  // For requests up to small_max_size
+    medium_pool: Pool<Buffer>, // For requests up to medium_max_size
+    large_pool: Pool<Buffer>,  // For anything larger
 }
 
 impl BufferPool {
-    pub fn new(small_count: usize, medium_count: usize, large_count: usize) -> Arc<Self> {
-        Arc::new(Self {
-            small_pool: Pool::new(small_count, || Buffer::new(MIN_BUFFER_SIZE)),
-            medium_pool: Pool::new(medium_count, || Buffer::new(DEFAULT_BUFFER_SIZE)),
-            large_pool: Pool::new(large_count, || Buffer::new(MAX_BUFFER_SIZE)),
-        })
-    }
-
-    pub fn acquire(&self, size: usize) -> Reusable<Buffer> {
-        if size <= MIN_BUFFER_SIZE {
-            self.small_pool.try_pull().unwrap_or_else(|| Buffer::new(MIN_BUFFER_SIZE))
-        } else if size <= DEFAULT_BUFFER_SIZE {
-            self.medium_pool.try_pull().unwrap_or_else(|| Buffer::new(DEFAULT_BUFFER_SIZE))
-        } else {
-            self.large_pool.try_pull().unwrap_or_else(|| Buffer::new(MAX_BUFFER_SIZE))
+    pub fn new(config: BufferPoolConfig) -> Result<Arc<Self>, BufferPoolConfigError> {
+        config.validate()?;
+
+        let (small, medium, large) = (
+            config.small_max_size,
+            config.medium_max_size,
+            config.large_buffer_size,
+        );
+
+        Ok(Arc::new(Self {
+            small_pool: Pool::new(config.small_count, || Buffer::new(small)),
+            medium_pool: Pool::new(config.medium_count, || Buffer::new(medium)),
+            large_pool: Pool::new(config.large_count, || Buffer::new(large)),
+            config,
+        }))
+    }
+
+    /// Pull a buffer from the pool matching `size`, allocating a fresh one if it's exhausted
+    pub fn acquire(&self, size: usize) -> Reusable<'_, Buffer> {
+        match self.config.class_for(size) {
+            BufferClass::Small => {
+                let len = self.config.small_max_size;
+                self.small_pool.pull(|| Buffer::new(len))
+            }
+            BufferClass::Medium => {
+                let len = self.config.medium_max_size;
+                self.medium_pool.pull(|| Buffer::new(len))
+            }
+            BufferClass::Large => {
+                // Pooled buffers are `large_buffer_size`, so grow one for oversized requests
+                let len = self.config.large_buffer_size.max(size);
+                let mut buffer = self.large_pool.pull(|| Buffer::new(len));
+                if buffer.as_slice().len() < size {
+                    buffer.resize(size);
+                }
+                buffer
+            }
         }
     }
 
+    pub fn config(&self) -> &BufferPoolConfig {
+        &self.config
+    }
+
     pub fn metrics(&self) -> BufferPoolMetrics {
         BufferPoolMetrics {
-            small_available: self.small_pool.available(),
-            medium_available: self.medium_pool.available(),
-            large_available: self.large_pool.available(),
+            small_available: self.small_pool.len(),
+            medium_available: self.medium_pool.len(),
+            large_available: self.large_pool.len(),
         }
     }
 }
@@ -73,4 +179,72 @@ pub struct BufferPoolMetrics {
     pub small_available: usize,
     pub medium_available: usize,
     pub large_available: usize,
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_rejects_zero_counts() {
+        let config = BufferPoolConfig {
+            medium_count: 0,
+            ..BufferPoolConfig::default()
+        };
+        assert_eq!(config.validate(), Err(BufferPoolConfigError::ZeroCount("medium")));
+    }
+
+    #[test]
+    fn test_config_rejects_unordered_thresholds() {
+        let config = BufferPoolConfig {
+            small_max_size: 64 * 1024,
+            medium_max_size: 4 * 1024,
+            ..BufferPoolConfig::default()
+        };
+        assert!(matches!(
+            config.validate(),
+            Err(BufferPoolConfigError::UnorderedThresholds { .. })
+        ));
+    }
+
+    #[test]
+    fn test_10kb_request_uses_medium_pool() {
+        let pool = BufferPool::new(BufferPoolConfig::default()).unwrap();
+        let before = pool.metrics();
+
+        let buffer = pool.acquire(10 * 1024);
+        let during = pool.metrics();
+
+        assert_eq!(buffer.as_slice().len(), DEFAULT_BUFFER_SIZE);
+        assert_eq!(during.medium_available, before.medium_available - 1);
+        assert_eq!(during.small_available, before.small_available);
+        assert_eq!(during.large_available, before.large_available);
+
+        drop(buffer);
+        assert_eq!(pool.metrics().medium_available, before.medium_available);
+    }
+
+    #[test]
+    fn test_oversized_request_gets_a_big_enough_pooled_buffer() {
+        let pool = BufferPool::new(BufferPoolConfig::default()).unwrap();
+        let before = pool.metrics();
+
+        let size = MAX_BUFFER_SIZE * 2;
+        let mut buffer = pool.acquire(size);
+        assert_eq!(buffer.as_slice().len(), size);
+        assert_eq!(pool.metrics().large_available, before.large_available - 1);
+        buffer.as_mut_slice()[size - 1] = 1;
+
+        drop(buffer);
+        assert_eq!(pool.metrics().large_available, before.large_available);
+    }
+
+    #[test]
+    fn test_class_boundaries() {
+        let config = BufferPoolConfig::default();
+        assert_eq!(config.class_for(4 * 1024), BufferClass::Small);
+        assert_eq!(config.class_for(4 * 1024 + 1), BufferClass::Medium);
+        assert_eq!(config.class_for(64 * 1024), BufferClass::Medium);
+        assert_eq!(config.class_for(64 * 1024 + 1), BufferClass::Large);
+    }
+}
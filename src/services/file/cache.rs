@@ -1,7 +1,7 @@
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::RwLock;
 use lru::LruCache;
 use parking_lot::Mutex;
 
@@ -20,14 +20,14 @@ impl CacheEntry {
 
 pub struct AsyncCache<K, V> {
     cache: Arc<Mutex<LruCache<K, V>>>,
-    max_size: usize,
 }
 
 impl<K: Clone + Eq + std::hash::Hash, V> AsyncCache<K, V> {
     pub fn new(max_size: usize) -> Self {
         Self {
-            cache: Arc::new(Mutex::new(LruCache::new(max_size))),
-            max_size,
+            cache: Arc::new(Mutex::new(LruCache::new(
+                NonZeroUsize::new(max_size).unwrap_or(NonZeroUsize::MIN),
+            ))),
         }
     }
 
@@ -51,6 +51,7 @@ impl<K: Clone + Eq + std::hash::Hash, V> AsyncCache<K, V> {
     }
 }
 
+#[derive(Clone)]
 pub struct CacheConfig {
     pub max_size: usize,
     pub default_ttl: Duration,
@@ -62,15 +63,15 @@ impl Default for CacheConfig {
         Self {
             max_size: 1000,
             default_ttl: Duration::from_secs(300), // 5 minutes
-            eviction_policy: EvictionPolicy::LRU,
+            eviction_policy: EvictionPolicy::Lru,
         }
     }
 }
 
 #[derive(Clone, Copy, Debug)]
 pub enum EvictionPolicy {
-    LRU,
-    FIFO,
+    Lru,
+    Fifo,
     Random,
 }
 
@@ -133,7 +134,7 @@ mod tests {
         let config = CacheConfig {
             max_size: 2,
             default_ttl: Duration::from_millis(100),
-            eviction_policy: EvictionPolicy::LRU,
+            eviction_policy: EvictionPolicy::Lru,
         };
         let cache = FileCache::new(config);
 
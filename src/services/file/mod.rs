@@ -4,14 +4,16 @@ mod cache;
 
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tokio::sync::mpsc;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
 use futures::Stream;
 use futures::stream::BoxStream;
 use async_trait::async_trait;
-use crate::actor::{ActorSystem, ActorPath, ActorConfig};
+use crate::actor::{ActorError, ActorRef, ActorSystem, ActorPath};
+use crate::services::tree_sitter::{IndexChange, SymbolIndex, run_incremental_index};
 
-pub use actor::{FileActor, FileCommand, FileOperation, FileResponse};
-pub use buffer::{Buffer, BufferPool};
+pub use actor::{BatchOperation, FileActor, FileCommand, FileResponse};
+pub use buffer::{Buffer, BufferClass, BufferPool, BufferPoolConfig, BufferPoolConfigError};
 pub use cache::{CacheConfig, FileCache};
 
 #[derive(Debug, Clone)]
@@ -21,11 +23,20 @@ pub enum FileEvent {
     Deleted { path: PathBuf },
 }
 
+impl From<FileEvent> for IndexChange {
+    fn from(event: FileEvent) -> Self {
+        match event {
+            FileEvent::Created { path } | FileEvent::Modified { path } => IndexChange::Changed(path),
+            FileEvent::Deleted { path } => IndexChange::Removed(path),
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, std::io::Error>;
 
 #[async_trait]
 pub trait FileOperation: Send + Sync {
-    type Output;
+    type Output: Send;
     async fn execute(&self, service: &FileOpsImpl) -> Result<Self::Output>;
 }
 
@@ -41,41 +52,67 @@ pub struct FileOpsImpl {
     actor_system: Arc<ActorSystem>,
     actor_ref: crate::actor::ActorRef<FileCommand>,
     buffer_pool: Arc<BufferPool>,
-    cache: Arc<FileCache>,
     metrics: Arc<crate::actor::MetricsCollector>,
+    /// Events from the file actor, until taken by `subscribe_index`
+    events: parking_lot::Mutex<Option<mpsc::Receiver<FileEvent>>>,
+    mailbox_retry: MailboxRetryPolicy,
 }
 
 impl FileOpsImpl {
     pub fn new(actor_system: Arc<ActorSystem>, config: FileOpsConfig) -> Result<Arc<Self>> {
-        let buffer_pool = BufferPool::new(
-            config.small_buffers,
-            config.medium_buffers,
-            config.large_buffers,
-        );
+        let buffer_pool = BufferPool::new(config.buffer_pool)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?;
 
         let metrics = actor_system.metrics().clone();
-        let (event_tx, _) = mpsc::channel(1000);
+        let (event_tx, event_rx) = mpsc::channel(1000);
 
         let file_actor = FileActor::new(
             buffer_pool.clone(),
-            config.cache_config,
+            config.cache_config.clone(),
             metrics.clone(),
             event_tx,
         );
 
         let actor_ref = actor_system
-            .spawn(file_actor, ActorPath("/system/file-ops".to_string()))
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+            .spawn(file_actor, ActorPath::new("/system/file-ops"))
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
 
         Ok(Arc::new(Self {
             actor_system,
             actor_ref,
             buffer_pool,
-            cache: Arc::new(FileCache::new(config.cache_config)),
             metrics,
+            events: parking_lot::Mutex::new(Some(event_rx)),
+            mailbox_retry: config.mailbox_retry,
         }))
     }
 
+    /// Hand `cmd` to the file actor, waiting out a briefly full mailbox
+    async fn send_command(&self, cmd: FileCommand) -> Result<()> {
+        send_with_retry(&self.actor_ref, cmd, &self.mailbox_retry).await
+    }
+
+    /// Keep `index` up to date from the file actor's events, debouncing bursts of changes.
+    ///
+    /// Returns `None` if the events are already subscribed to.
+    pub fn subscribe_index(
+        &self,
+        index: Arc<parking_lot::RwLock<SymbolIndex>>,
+        debounce: std::time::Duration,
+    ) -> Option<tokio::task::JoinHandle<()>> {
+        let mut events = self.events.lock().take()?;
+        let (change_tx, change_rx) = mpsc::channel(1000);
+
+        tokio::spawn(async move {
+            while let Some(event) = events.recv().await {
+                if change_tx.send(IndexChange::from(event)).await.is_err() {
+                    break;
+                }
+            }
+        });
+        Some(tokio::spawn(run_incremental_index(index, change_rx, debounce)))
+    }
+
     pub fn metrics(&self) -> &Arc<crate::actor::MetricsCollector> {
         &self.metrics
     }
@@ -83,37 +120,35 @@ impl FileOpsImpl {
     pub fn buffer_pool(&self) -> &Arc<BufferPool> {
         &self.buffer_pool
     }
+
+    pub fn actor_system(&self) -> &Arc<ActorSystem> {
+        &self.actor_system
+    }
 }
 
 #[async_trait]
 impl FileOps for FileOpsImpl {
     async fn read_file(&self, path: &Path) -> Result<Vec<u8>> {
+        let (reply, result) = oneshot::channel();
         let cmd = FileCommand::Read {
             path: path.to_path_buf(),
+            reply: Some(reply),
         };
 
-        self.actor_ref
-            .send(cmd)
-            .await
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
-
-        // For simplicity, we're assuming the actor has processed the command
-        // In a real implementation, we'd use a response channel
-        Ok(Vec::new())
+        self.send_command(cmd).await?;
+        result.await.map_err(|_| actor_stopped())?
     }
 
     async fn write_file(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        let (reply, result) = oneshot::channel();
         let cmd = FileCommand::Write {
             path: path.to_path_buf(),
             contents: contents.to_vec(),
+            reply: Some(reply),
         };
 
-        self.actor_ref
-            .send(cmd)
-            .await
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
-
-        Ok(())
+        self.send_command(cmd).await?;
+        result.await.map_err(|_| actor_stopped())?
     }
 
     async fn watch_path(&self, path: &Path) -> Result<impl Stream<Item = FileEvent>> {
@@ -121,13 +156,10 @@ impl FileOps for FileOpsImpl {
             path: path.to_path_buf(),
         };
 
-        self.actor_ref
-            .send(cmd)
-            .await
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+        self.send_command(cmd).await?;
 
         // Create a channel for events
-        let (tx, rx) = mpsc::channel(100);
+        let (_tx, rx) = mpsc::channel(100);
         Ok(Box::pin(tokio_stream::wrappers::ReceiverStream::new(rx)) as BoxStream<FileEvent>)
     }
 
@@ -142,20 +174,77 @@ impl FileOps for FileOpsImpl {
     }
 }
 
+#[derive(Default)]
 pub struct FileOpsConfig {
-    pub small_buffers: usize,
-    pub medium_buffers: usize,
-    pub large_buffers: usize,
+    pub buffer_pool: BufferPoolConfig,
     pub cache_config: CacheConfig,
+    pub mailbox_retry: MailboxRetryPolicy,
 }
 
-impl Default for FileOpsConfig {
+/// How long a file operation waits for room in a full actor mailbox before failing
+#[derive(Clone, Debug)]
+pub struct MailboxRetryPolicy {
+    pub max_retries: u32,
+    /// Wait before the first retry; doubled for each further one
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for MailboxRetryPolicy {
     fn default() -> Self {
         Self {
-            small_buffers: 100,
-            medium_buffers: 50,
-            large_buffers: 20,
-            cache_config: CacheConfig::default(),
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(200),
+        }
+    }
+}
+
+impl MailboxRetryPolicy {
+    /// Backoff before the `attempt`-th retry (1-based)
+    pub fn backoff_for(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// The file actor went away without answering
+fn actor_stopped() -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::BrokenPipe, ActorError::ActorStopped.to_string())
+}
+
+/// Deliver `msg`, retrying with backoff while the mailbox is full so a momentary
+/// backlog doesn't fail the operation. Gives up with `WouldBlock` once `policy`'s
+/// retries are spent, or straight away if the actor has stopped.
+async fn send_with_retry<T: Send + 'static>(
+    actor_ref: &ActorRef<T>,
+    mut msg: T,
+    policy: &MailboxRetryPolicy,
+) -> Result<()> {
+    let mut retries = 0;
+    loop {
+        match actor_ref.try_send(msg) {
+            Ok(()) => return Ok(()),
+            Err((ActorError::MailboxFull, returned)) if retries < policy.max_retries => {
+                retries += 1;
+                msg = returned;
+                tokio::time::sleep(policy.backoff_for(retries)).await;
+            }
+            Err((ActorError::MailboxFull, _)) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::WouldBlock,
+                    format!(
+                        "{} mailbox still full after {} retries",
+                        actor_ref.path().as_str(),
+                        retries
+                    ),
+                ));
+            }
+            Err((e, _)) => {
+                return Err(std::io::Error::other(e.to_string()));
+            }
         }
     }
 }
@@ -163,7 +252,7 @@ impl Default for FileOpsConfig {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::time::Duration;
+    use crate::actor::ActorConfig;
     use tempfile::tempdir;
 
     #[tokio::test]
@@ -172,6 +261,7 @@ mod tests {
             mailbox_size: 100,
             supervision_strategy: crate::actor::SupervisionStrategy::Stop,
             shutdown_timeout: Duration::from_secs(1),
+            ..ActorConfig::default()
         };
 
         let actor_system = ActorSystem::new(actor_config);
@@ -189,7 +279,79 @@ mod tests {
         assert_eq!(read_data, test_data);
 
         // Test watch
-        let mut events = file_ops.watch_path(dir.path()).await.unwrap();
+        let _events = file_ops.watch_path(dir.path()).await.unwrap();
         tokio::fs::remove_file(&test_file).await.unwrap();
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_full_mailbox_is_retried_until_it_drains() {
+        let (tx, mut rx) = mpsc::channel::<u32>(1);
+        let actor_ref = ActorRef::new(tx, ActorPath::new("/test/saturated"));
+        let policy = MailboxRetryPolicy {
+            max_retries: 10,
+            initial_backoff: Duration::from_millis(5),
+            max_backoff: Duration::from_millis(20),
+        };
+
+        actor_ref.try_send(1).unwrap();
+        assert!(matches!(actor_ref.try_send(2), Err((ActorError::MailboxFull, 2))));
+
+        // Drains the mailbox a little later, as a busy actor would
+        let drain = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(30)).await;
+            let mut received = Vec::new();
+            while let Some(msg) = rx.recv().await {
+                received.push(msg);
+            }
+            received
+        });
+        send_with_retry(&actor_ref, 2, &policy).await.unwrap();
+        send_with_retry(&actor_ref, 3, &policy).await.unwrap();
+        drop(actor_ref);
+        assert_eq!(drain.await.unwrap(), vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_mailbox_that_never_drains_fails_clearly() {
+        let (tx, _rx) = mpsc::channel::<u32>(1);
+        let actor_ref = ActorRef::new(tx, ActorPath::new("/test/stuck"));
+        let policy = MailboxRetryPolicy {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(2),
+        };
+        actor_ref.try_send(1).unwrap();
+
+        let err = send_with_retry(&actor_ref, 2, &policy).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+        assert_eq!(err.to_string(), "/test/stuck mailbox still full after 3 retries");
+    }
+
+    #[tokio::test]
+    async fn test_write_reindexes_symbols() {
+        let actor_system = ActorSystem::new(ActorConfig::default());
+        let file_ops = FileOpsImpl::new(actor_system.clone(), FileOpsConfig::default()).unwrap();
+
+        let service = Arc::new(crate::services::tree_sitter::TreeSitterService::new(1024 * 1024, 2));
+        let index = Arc::new(parking_lot::RwLock::new(SymbolIndex::new(service)));
+        file_ops
+            .subscribe_index(index.clone(), Duration::from_millis(20))
+            .unwrap();
+        assert!(file_ops.subscribe_index(index.clone(), Duration::from_millis(20)).is_none());
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("lib.py");
+        file_ops.write_file(&path, b"def first():\n    pass\n").await.unwrap();
+        file_ops.write_file(&path, b"def second():\n    pass\n").await.unwrap();
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let index = index.read();
+        let names: Vec<&str> = index
+            .symbols(&path)
+            .unwrap_or_default()
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["second"]);
+    }
+}
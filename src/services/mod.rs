@@ -1,3 +1,4 @@
+pub mod file;
 pub mod ripgrep;
 pub mod tree_sitter;
 
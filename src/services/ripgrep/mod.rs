@@ -1,22 +1,116 @@
+pub mod ranking;
+
+use crate::shell::ansi;
 use std::io::{BufRead, BufReader};
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
+/// Marker appended to lines cut at `max_line_length`
+const ELLIPSIS: &str = "...";
+
 /// Error types specific to Ripgrep operations
 #[derive(Debug, thiserror::Error)]
 pub enum RipgrepError {
     #[error("Ripgrep binary not found")]
     BinaryNotFound,
+    #[error("Configured ripgrep binary not found: {0}")]
+    ConfiguredBinaryNotFound(PathBuf),
     #[error("Failed to execute ripgrep: {0}")]
     ExecutionError(String),
     #[error("Invalid regex pattern: {0}")]
     InvalidPattern(String),
+    #[error("ripgrep {found} doesn't support {feature} (needs {required} or newer)")]
+    UnsupportedFeature {
+        feature: &'static str,
+        required: RipgrepVersion,
+        found: RipgrepVersion,
+    },
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 }
 
+/// A ripgrep release number, e.g. 14.1.0
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RipgrepVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl RipgrepVersion {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self { major, minor, patch }
+    }
+
+    /// Parse the "ripgrep X.Y.Z ..." line printed by `rg --version`
+    pub fn parse(line: &str) -> Option<Self> {
+        let number = line.trim().strip_prefix("ripgrep")?.split_whitespace().next()?;
+        let mut parts = number.split('.').map(|part| part.parse::<u32>().ok());
+        Some(Self::new(parts.next()??, parts.next()??, parts.next().flatten().unwrap_or(0)))
+    }
+}
+
+impl std::fmt::Display for RipgrepVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// First release with `--json` output, which the search parser depends on
+pub const MIN_JSON_VERSION: RipgrepVersion = RipgrepVersion::new(0, 10, 0);
+/// First release with `--multiline`
+pub const MIN_MULTILINE_VERSION: RipgrepVersion = RipgrepVersion::new(0, 10, 0);
+
+/// What the installed ripgrep can do, worked out from `rg --version`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RipgrepCapabilities {
+    /// `None` when the version couldn't be determined; features are then assumed present
+    pub version: Option<RipgrepVersion>,
+    pub json: bool,
+    pub multiline: bool,
+    pub pcre2: bool,
+}
+
+impl RipgrepCapabilities {
+    /// Capabilities described by the full output of `rg --version`
+    pub fn from_version_output(output: &str) -> Self {
+        let version = output.lines().next().and_then(RipgrepVersion::parse);
+        let supports = |min: RipgrepVersion| version.is_none_or(|version| version >= min);
+
+        Self {
+            version,
+            json: supports(MIN_JSON_VERSION),
+            multiline: supports(MIN_MULTILINE_VERSION),
+            pcre2: output.to_lowercase().contains("+pcre2"),
+        }
+    }
+
+    /// Error for a feature this ripgrep is too old to support
+    fn unsupported(&self, feature: &'static str, required: RipgrepVersion) -> RipgrepError {
+        RipgrepError::UnsupportedFeature {
+            feature,
+            required,
+            found: self.version.unwrap_or(RipgrepVersion::new(0, 0, 0)),
+        }
+    }
+}
+
+/// Which ripgrep to use when both a system `rg` and VSCode's bundled copy exist
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RipgrepBinaryPreference {
+    /// `rg` on the PATH, falling back to the bundled copy
+    #[default]
+    SystemFirst,
+    /// VSCode's bundled copy, falling back to `rg` on the PATH
+    BundledFirst,
+    /// Always this binary
+    Explicit(PathBuf),
+}
+
 /// Configuration for search operations
 #[derive(Debug, Clone)]
 pub struct SearchConfig {
@@ -25,6 +119,8 @@ pub struct SearchConfig {
     pub context_lines: usize,
     pub max_results: usize,
     pub max_line_length: usize,
+    /// Let the pattern match across line breaks (`--multiline`)
+    pub multiline: bool,
 }
 
 impl Default for SearchConfig {
@@ -35,6 +131,7 @@ impl Default for SearchConfig {
             context_lines: 2,
             max_results: 300,
             max_line_length: 500,
+            multiline: false,
         }
     }
 }
@@ -45,28 +142,118 @@ pub struct SearchResult {
     pub file_path: PathBuf,
     pub line_number: usize,
     pub line_content: String,
+    /// Byte ranges of each submatch within `line_content`
+    pub match_ranges: Vec<Range<usize>>,
     pub context_before: Vec<String>,
     pub context_after: Vec<String>,
 }
 
+/// A line of interest from ripgrep's `--json` output
+#[derive(Debug, PartialEq)]
+enum RipgrepEvent {
+    Match {
+        path: PathBuf,
+        line_number: usize,
+        text: String,
+        submatches: Vec<Range<usize>>,
+    },
+    Context {
+        path: PathBuf,
+        line_number: usize,
+        text: String,
+    },
+}
+
 /// The main Ripgrep service for high-performance file searching
 pub struct RipgrepService {
     binary_path: PathBuf,
+    /// First line of `rg --version`, if it could be run
+    version: Option<String>,
+    capabilities: RipgrepCapabilities,
     result_count: Arc<AtomicUsize>,
 }
 
 impl RipgrepService {
-    /// Create a new RipgrepService instance
+    /// Create a new RipgrepService instance, preferring the system `rg`
     pub fn new() -> Result<Self, RipgrepError> {
-        let binary_path = Self::detect_binary()?;
-        Ok(Self {
+        Self::with_preference(&RipgrepBinaryPreference::default())
+    }
+
+    /// Create a RipgrepService using the binary chosen by `preference`
+    pub fn with_preference(preference: &RipgrepBinaryPreference) -> Result<Self, RipgrepError> {
+        let binary_path = Self::detect_binary(preference)?;
+        let version_output = Self::version_output(&binary_path).unwrap_or_default();
+
+        let service = Self {
             binary_path,
+            version: version_output.lines().next().map(|line| line.trim().to_string()),
+            capabilities: RipgrepCapabilities::from_version_output(&version_output),
             result_count: Arc::new(AtomicUsize::new(0)),
-        })
+        };
+        crate::logging::log(
+            crate::config::LogLevel::Debug,
+            "ripgrep",
+            format!("Using {}", service.describe()),
+        );
+        Ok(service)
+    }
+
+    /// Path of the ripgrep binary in use
+    pub fn binary_path(&self) -> &Path {
+        &self.binary_path
+    }
+
+    /// Version line reported by the binary, e.g. "ripgrep 14.1.0"
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    /// Features supported by the binary in use
+    pub fn capabilities(&self) -> RipgrepCapabilities {
+        self.capabilities
+    }
+
+    /// The binary and its version, for status messages
+    pub fn describe(&self) -> String {
+        format!(
+            "{} ({})",
+            self.version().unwrap_or("ripgrep, unknown version"),
+            self.binary_path.display()
+        )
+    }
+
+    /// Everything `rg --version` prints, if it runs
+    fn version_output(binary: &Path) -> Option<String> {
+        let output = Command::new(binary).arg("--version").output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        Some(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// Locate the ripgrep binary, checking locations in the order `preference` asks for
+    fn detect_binary(preference: &RipgrepBinaryPreference) -> Result<PathBuf, RipgrepError> {
+        let found = match preference {
+            RipgrepBinaryPreference::Explicit(path) => {
+                return if path.is_file() {
+                    Ok(path.clone())
+                } else {
+                    Err(RipgrepError::ConfiguredBinaryNotFound(path.clone()))
+                };
+            }
+            RipgrepBinaryPreference::SystemFirst => {
+                Self::system_binary().or_else(Self::bundled_binary)
+            }
+            RipgrepBinaryPreference::BundledFirst => {
+                Self::bundled_binary().or_else(Self::system_binary)
+            }
+        };
+
+        found.ok_or(RipgrepError::BinaryNotFound)
     }
 
-    /// Detect the ripgrep binary location using multiple fallback paths
-    fn detect_binary() -> Result<PathBuf, RipgrepError> {
+    /// VSCode's bundled ripgrep, if installed
+    fn bundled_binary() -> Option<PathBuf> {
         // VSCode bundled ripgrep paths
         let vscode_paths = if cfg!(target_os = "windows") {
             vec![
@@ -80,27 +267,25 @@ impl RipgrepService {
             ]
         };
 
-        // Check VSCode paths first
-        for path in vscode_paths {
-            if Path::new(path).exists() {
-                return Ok(PathBuf::from(path));
-            }
-        }
+        vscode_paths
+            .into_iter()
+            .map(PathBuf::from)
+            .find(|path| path.exists())
+    }
 
-        // Try system PATH
-        if let Ok(output) = Command::new("which").arg("rg").output() {
-            if output.status.success() {
-                if let Ok(path) = String::from_utf8(output.stdout) {
-                    let path = path.trim();
-                    return Ok(PathBuf::from(path));
-                }
-            }
+    /// `rg` on the system PATH
+    fn system_binary() -> Option<PathBuf> {
+        let output = Command::new("which").arg("rg").output().ok()?;
+        if !output.status.success() {
+            return None;
         }
-
-        Err(RipgrepError::BinaryNotFound)
+        let path = String::from_utf8(output.stdout).ok()?;
+        let path = path.trim();
+        (!path.is_empty()).then(|| PathBuf::from(path))
     }
 
-    /// Execute a search with the given configuration
+    /// Execute a search with the given configuration.
+    /// The callback receives each result and returns `false` to stop the search early.
     pub fn search<P: AsRef<Path>>(
         &self,
         dir: P,
@@ -112,15 +297,25 @@ impl RipgrepService {
             return Err(RipgrepError::InvalidPattern(e.to_string()));
         }
 
+        // Results are read from --json output, so there's nothing to fall back to without it
+        if !self.capabilities.json {
+            return Err(self.capabilities.unsupported("--json", MIN_JSON_VERSION));
+        }
+        if config.multiline && !self.capabilities.multiline {
+            return Err(self.capabilities.unsupported("--multiline", MIN_MULTILINE_VERSION));
+        }
+
         let mut cmd = Command::new(&self.binary_path);
         cmd.current_dir(dir)
-            .arg("--line-number")
+            .arg("--json")
             .arg("--context")
             .arg(config.context_lines.to_string())
-            .arg("--color")
-            .arg("never")
             .arg("--text"); // Force text mode
 
+        if config.multiline {
+            cmd.arg("--multiline");
+        }
+
         // Add file pattern if specified
         if let Some(pattern) = config.file_pattern {
             cmd.arg("--glob").arg(pattern);
@@ -132,6 +327,7 @@ impl RipgrepService {
         // Configure stdio
         cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
 
+        self.reset_count();
         let mut process = cmd.spawn()?;
         let stdout = process
             .stdout
@@ -141,96 +337,180 @@ impl RipgrepService {
         let mut reader = BufReader::new(stdout);
         let mut line = String::new();
         let mut current_file = None;
-        let mut current_results = Vec::new();
+        let mut current_results: Vec<SearchResult> = Vec::new();
         let mut context_buffer = Vec::new();
-        let mut in_context = false;
+        let mut stopped = false;
+        let mut limit_reached = false;
         let mut callback = callback;
 
         while reader.read_line(&mut line)? > 0 {
-            // Check result limit
+            // Check result limit. Every buffered match was counted, so the current
+            // file's matches still fit and are flushed below.
             if self.result_count.load(Ordering::Relaxed) >= config.max_results {
+                limit_reached = true;
                 break;
             }
 
-            // Process line
-            if line.starts_with("--") {
-                // Context separator
-                in_context = true;
-            } else if let Some((file_path, line_num, content)) = self.parse_result_line(&line) {
-                if current_file.as_ref() != Some(&file_path) {
-                    // New file
-                    self.flush_results(&mut current_results, &mut callback);
-                    current_file = Some(file_path.clone());
-                    current_results.clear();
+            match Self::parse_json_event(&line) {
+                Some(RipgrepEvent::Match {
+                    path,
+                    line_number,
+                    text,
+                    submatches,
+                }) => {
+                    if current_file.as_ref() != Some(&path) {
+                        // New file
+                        if !self.flush_results(&mut current_results, &mut callback) {
+                            stopped = true;
+                            break;
+                        }
+                        current_file = Some(path.clone());
+                        context_buffer.clear();
+                    }
+
+                    let (line_content, match_ranges) =
+                        self.truncate_match(&text, submatches, config.max_line_length);
+
+                    current_results.push(SearchResult {
+                        file_path: path,
+                        line_number,
+                        line_content,
+                        match_ranges,
+                        context_before: std::mem::take(&mut context_buffer),
+                        context_after: Vec::new(),
+                    });
+
+                    self.result_count.fetch_add(1, Ordering::Relaxed);
                 }
-
-                let mut result = SearchResult {
-                    file_path,
-                    line_number: line_num,
-                    line_content: self.truncate_line(&content, config.max_line_length),
-                    context_before: Vec::new(),
-                    context_after: Vec::new(),
-                };
-
-                if in_context {
-                    result.context_before = context_buffer.clone();
-                }
-
-                context_buffer.clear();
-                current_results.push(result);
-
-                self.result_count.fetch_add(1, Ordering::Relaxed);
-            } else {
-                // Context line
-                context_buffer.push(self.truncate_line(&line, config.max_line_length));
-                if context_buffer.len() > config.context_lines {
-                    context_buffer.remove(0);
+                Some(RipgrepEvent::Context {
+                    path,
+                    line_number,
+                    text,
+                }) => {
+                    let text = self.truncate_line(&text, config.max_line_length);
+
+                    // Lines right after a match belong to it; anything else leads into the next match
+                    match current_results.last_mut() {
+                        Some(last)
+                            if last.file_path == path
+                                && line_number > last.line_number
+                                && last.context_after.len() < config.context_lines =>
+                        {
+                            last.context_after.push(text);
+                        }
+                        _ => {
+                            context_buffer.push(text);
+                            if context_buffer.len() > config.context_lines {
+                                context_buffer.remove(0);
+                            }
+                        }
+                    }
                 }
+                None => {}
             }
 
             line.clear();
         }
 
         // Flush any remaining results
-        self.flush_results(&mut current_results, &mut callback);
+        if !stopped && !self.flush_results(&mut current_results, &mut callback) {
+            stopped = true;
+        }
+
+        // Don't leave ripgrep running once the caller has stopped listening
+        if stopped || limit_reached {
+            let _ = process.kill();
+        }
+        let _ = process.wait();
 
         Ok(self.result_count.load(Ordering::Relaxed))
     }
 
-    /// Parse a result line into (file_path, line_number, content)
-    fn parse_result_line(&self, line: &str) -> Option<(PathBuf, usize, String)> {
-        let parts: Vec<&str> = line.splitn(3, ':').collect();
-        if parts.len() != 3 {
-            return None;
+    /// Parse one line of `rg --json` output, ignoring begin/end/summary messages
+    /// and lines that aren't valid UTF-8
+    fn parse_json_event(line: &str) -> Option<RipgrepEvent> {
+        let value: serde_json::Value = serde_json::from_str(line).ok()?;
+        let data = value.get("data")?;
+
+        let path = PathBuf::from(data.get("path")?.get("text")?.as_str()?);
+        let line_number = data.get("line_number")?.as_u64()? as usize;
+        let text = data
+            .get("lines")?
+            .get("text")?
+            .as_str()?
+            .trim_end_matches(['\n', '\r'])
+            .to_string();
+
+        match value.get("type")?.as_str()? {
+            "match" => {
+                let submatches = data
+                    .get("submatches")?
+                    .as_array()?
+                    .iter()
+                    .filter_map(|m| {
+                        let start = m.get("start")?.as_u64()? as usize;
+                        let end = m.get("end")?.as_u64()? as usize;
+                        (start < end && end <= text.len()).then_some(start..end)
+                    })
+                    .collect();
+
+                Some(RipgrepEvent::Match {
+                    path,
+                    line_number,
+                    text,
+                    submatches,
+                })
+            }
+            "context" => Some(RipgrepEvent::Context {
+                path,
+                line_number,
+                text,
+            }),
+            _ => None,
         }
+    }
 
-        let file_path = PathBuf::from(parts[0]);
-        let line_number = parts[1].parse().ok()?;
-        let content = parts[2].trim_end().to_string();
+    /// Truncate a matched line, clipping submatch ranges to the text that's kept
+    fn truncate_match(
+        &self,
+        line: &str,
+        submatches: Vec<Range<usize>>,
+        max_length: usize,
+    ) -> (String, Vec<Range<usize>>) {
+        let content = self.truncate_line(line, max_length);
+        let kept = if content == line {
+            line.len()
+        } else {
+            ansi::visible_prefix_end(line, max_length.saturating_sub(ELLIPSIS.len()))
+        };
 
-        Some((file_path, line_number, content))
+        let ranges = submatches
+            .into_iter()
+            .filter(|range| range.start < kept)
+            .map(|range| range.start..range.end.min(kept))
+            .collect();
+
+        (content, ranges)
     }
 
-    /// Truncate a line to the maximum length
+    /// Truncate a line to the maximum visible length, leaving colour codes intact
     fn truncate_line(&self, line: &str, max_length: usize) -> String {
-        if line.len() <= max_length {
-            line.to_string()
-        } else {
-            format!("{}...", &line[..max_length - 3])
-        }
+        ansi::truncate_visible(line, max_length, ELLIPSIS)
     }
 
-    /// Flush accumulated results through the callback
+    /// Flush accumulated results through the callback.
+    /// Returns `false` once the callback asks to stop the search.
     fn flush_results(
         &self,
         results: &mut Vec<SearchResult>,
         callback: &mut impl FnMut(SearchResult) -> bool,
-    ) {
+    ) -> bool {
         for result in results.drain(..) {
             if !callback(result) {
-                break;
+                return false;
             }
         }
+        true
     }
 
     /// Reset the result counter
@@ -274,6 +554,7 @@ mod tests {
                     context_lines: 1,
                     max_results: 10,
                     max_line_length: 100,
+                    multiline: false,
                 };
 
                 // Collect results
@@ -296,4 +577,186 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_parse_json_match_multibyte_offsets() {
+        // "héllo wörld" - 'é' and 'ö' are two bytes each, so "wörld" spans bytes 7..13
+        let line = r#"{"type":"match","data":{"path":{"text":"src/greet.txt"},"lines":{"text":"héllo wörld\n"},"line_number":4,"absolute_offset":0,"submatches":[{"match":{"text":"wörld"},"start":7,"end":13}]}}"#;
+
+        match RipgrepService::parse_json_event(line) {
+            Some(RipgrepEvent::Match {
+                path,
+                line_number,
+                text,
+                submatches,
+            }) => {
+                assert_eq!(path, PathBuf::from("src/greet.txt"));
+                assert_eq!(line_number, 4);
+                assert_eq!(text, "héllo wörld");
+                assert_eq!(submatches, vec![7..13]);
+                assert_eq!(&text[submatches[0].clone()], "wörld");
+            }
+            other => panic!("Expected a match event, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_json_ignores_non_line_events() {
+        let begin = r#"{"type":"begin","data":{"path":{"text":"a.txt"}}}"#;
+        assert_eq!(RipgrepService::parse_json_event(begin), None);
+        assert_eq!(RipgrepService::parse_json_event("not json"), None);
+    }
+
+    #[test]
+    fn test_truncate_match_clips_ranges_on_char_boundary() {
+        let service = RipgrepService {
+            binary_path: PathBuf::from("rg"),
+            version: None,
+            capabilities: RipgrepCapabilities::from_version_output(""),
+            result_count: Arc::new(AtomicUsize::new(0)),
+        };
+
+        // The limit counts visible characters, so all five 'é's (10 bytes) are kept
+        let (content, ranges) = service.truncate_match("ééééé match", vec![0..4, 11..16], 8);
+        assert_eq!(content, "ééééé...");
+        assert_eq!(ranges, vec![0..4]);
+    }
+
+    #[test]
+    fn test_truncate_match_keeps_colour_codes_whole() {
+        let service = RipgrepService {
+            binary_path: PathBuf::from("rg"),
+            version: None,
+            capabilities: RipgrepCapabilities::from_version_output(""),
+            result_count: Arc::new(AtomicUsize::new(0)),
+        };
+
+        let line = "ab\x1b[31mcdefgh\x1b[0m";
+        let colored = 2..13;
+        let (content, ranges) = service.truncate_match(line, vec![colored], 6);
+        assert_eq!(content, "ab\x1b[31mc\x1b[0m...");
+        assert_eq!(ranges, vec![2..8]);
+    }
+
+    #[test]
+    fn test_explicit_binary_preference_is_honored() {
+        let temp_dir = TempDir::new().unwrap();
+        let missing = temp_dir.path().join("missing-rg");
+        assert!(matches!(
+            RipgrepService::with_preference(&RipgrepBinaryPreference::Explicit(missing.clone())),
+            Err(RipgrepError::ConfiguredBinaryNotFound(path)) if path == missing
+        ));
+
+        // A stand-in binary that only answers --version
+        let fake = temp_dir.path().join("rg");
+        std::fs::write(&fake, "#!/bin/sh\necho 'ripgrep 13.0.0'\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&fake, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        let service =
+            RipgrepService::with_preference(&RipgrepBinaryPreference::Explicit(fake.clone()))
+                .unwrap();
+        assert_eq!(service.binary_path(), fake.as_path());
+        if cfg!(unix) {
+            assert_eq!(service.version(), Some("ripgrep 13.0.0"));
+            assert_eq!(service.describe(), format!("ripgrep 13.0.0 ({})", fake.display()));
+        }
+    }
+
+    #[test]
+    fn test_binary_preference_config_format() {
+        let explicit: RipgrepBinaryPreference =
+            serde_json::from_str(r#"{"explicit": "/opt/rg"}"#).unwrap();
+        assert_eq!(explicit, RipgrepBinaryPreference::Explicit(PathBuf::from("/opt/rg")));
+
+        let bundled: RipgrepBinaryPreference = serde_json::from_str(r#""bundled_first""#).unwrap();
+        assert_eq!(bundled, RipgrepBinaryPreference::BundledFirst);
+        assert_eq!(RipgrepBinaryPreference::default(), RipgrepBinaryPreference::SystemFirst);
+    }
+
+    #[test]
+    fn test_parse_version_output_into_capabilities() {
+        let output = "ripgrep 14.1.1 (rev f08e57bec0)\n\nfeatures:+pcre2\nsimd(compile):+SSE2,+SSSE3,-AVX2\n";
+        let capabilities = RipgrepCapabilities::from_version_output(output);
+        assert_eq!(
+            capabilities,
+            RipgrepCapabilities {
+                version: Some(RipgrepVersion::new(14, 1, 1)),
+                json: true,
+                multiline: true,
+                pcre2: true,
+            }
+        );
+
+        let old = RipgrepCapabilities::from_version_output("ripgrep 0.9.0\n-SIMD -AVX (compiled)\n");
+        assert_eq!(old.version, Some(RipgrepVersion::new(0, 9, 0)));
+        assert!(!old.json && !old.multiline && !old.pcre2);
+
+        assert_eq!(RipgrepVersion::parse("ripgrep 11.0"), Some(RipgrepVersion::new(11, 0, 0)));
+        assert_eq!(RipgrepVersion::parse("grep (GNU grep) 3.11"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_limit_keeps_matches_buffered_for_the_current_file() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // A stand-in rg that reports five matches in one file
+        let temp_dir = TempDir::new().unwrap();
+        let fake = temp_dir.path().join("rg");
+        let mut script = String::from(
+            "#!/bin/sh\nif [ \"$1\" = --version ]; then echo 'ripgrep 14.0.0'; exit 0; fi\n",
+        );
+        for n in 1..=5 {
+            script.push_str(&format!(
+                "printf '%s\\n' '{{\"type\":\"match\",\"data\":{{\"path\":{{\"text\":\"a.txt\"}},\"lines\":{{\"text\":\"needle {n}\\n\"}},\"line_number\":{n},\"submatches\":[{{\"start\":0,\"end\":6}}]}}}}'\n"
+            ));
+        }
+        std::fs::write(&fake, script).unwrap();
+        std::fs::set_permissions(&fake, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let service =
+            RipgrepService::with_preference(&RipgrepBinaryPreference::Explicit(fake)).unwrap();
+
+        // The count starts over for each search
+        for _ in 0..2 {
+            let config = SearchConfig {
+                pattern: "needle".to_string(),
+                max_results: 3,
+                ..SearchConfig::default()
+            };
+            let mut lines = Vec::new();
+            service
+                .search(temp_dir.path(), config, |result| {
+                    lines.push(result.line_number);
+                    true
+                })
+                .unwrap();
+            assert_eq!(lines, vec![1, 2, 3]);
+            assert_eq!(service.get_count(), 3);
+        }
+    }
+
+    #[test]
+    fn test_search_rejects_unsupported_features() {
+        let service = RipgrepService {
+            binary_path: PathBuf::from("rg"),
+            version: Some("ripgrep 0.9.0".to_string()),
+            capabilities: RipgrepCapabilities::from_version_output("ripgrep 0.9.0"),
+            result_count: Arc::new(AtomicUsize::new(0)),
+        };
+        let temp_dir = TempDir::new().unwrap();
+
+        let config = SearchConfig {
+            pattern: "needle".to_string(),
+            ..SearchConfig::default()
+        };
+        let err = service.search(temp_dir.path(), config, |_| true).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "ripgrep 0.9.0 doesn't support --json (needs 0.10.0 or newer)"
+        );
+    }
 }
@@ -0,0 +1,254 @@
+//! Ordering search results by relevance rather than the order ripgrep found them in
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use super::SearchResult;
+
+/// How much each signal counts towards a result's score; 0 turns a signal off
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RankingWeights {
+    /// The pattern matched as a whole word rather than part of one
+    pub exact_match: f64,
+    /// The line defines a symbol: `fn`, `struct`, `class`, `def`, ...
+    pub definition: f64,
+    /// Subtracted when the match is inside a comment
+    pub comment: f64,
+    /// The file was modified recently, fading over about a day
+    pub recency: f64,
+    /// The file is close to the current file in the directory tree
+    pub proximity: f64,
+}
+
+impl Default for RankingWeights {
+    fn default() -> Self {
+        Self {
+            exact_match: 2.0,
+            definition: 3.0,
+            comment: 2.0,
+            recency: 1.0,
+            proximity: 1.0,
+        }
+    }
+}
+
+/// Where the search happened, for the signals that depend on it
+#[derive(Debug, Clone)]
+pub struct RankContext<'a> {
+    pub pattern: &'a str,
+    /// The file being worked on, if any
+    pub current_file: Option<&'a Path>,
+    pub now: SystemTime,
+}
+
+/// What a matched line is, judged from its text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineKind {
+    Definition,
+    Comment,
+    Code,
+}
+
+/// Keywords that start a definition in the languages samus outlines
+const DEFINITION_KEYWORDS: &[&str] = &[
+    "fn", "struct", "enum", "trait", "impl", "type", "mod", "const", "static", "macro_rules!",
+    "class", "interface", "function", "def",
+];
+
+/// Modifiers that may come before a definition keyword
+const DEFINITION_MODIFIERS: &[&str] = &["pub", "pub(crate)", "pub(super)", "export", "default", "async", "unsafe"];
+
+const COMMENT_MARKERS: &[&str] = &["//", "#", "/*", "*", "--", "<!--"];
+
+/// Whether `line` is a comment, a definition, or other code
+pub fn classify_line(line: &str) -> LineKind {
+    let trimmed = line.trim_start();
+    // `#[derive]` and `#!` are Rust attributes, not comments
+    if COMMENT_MARKERS.iter().any(|marker| trimmed.starts_with(marker))
+        && !trimmed.starts_with("#[")
+        && !trimmed.starts_with("#!")
+    {
+        return LineKind::Comment;
+    }
+
+    let keyword = trimmed
+        .split_whitespace()
+        .find(|word| !DEFINITION_MODIFIERS.contains(word));
+    match keyword {
+        Some(word) if DEFINITION_KEYWORDS.contains(&word) => LineKind::Definition,
+        _ => LineKind::Code,
+    }
+}
+
+/// Whether the first match on the line starts after a trailing `//` comment marker
+fn match_in_trailing_comment(result: &SearchResult) -> bool {
+    let Some(start) = result.match_ranges.first().map(|range| range.start) else {
+        return false;
+    };
+    result.line_content[..start.min(result.line_content.len())].contains("//")
+}
+
+/// Whether some match is the whole pattern, not touching other identifier characters
+fn is_exact_match(result: &SearchResult, pattern: &str) -> bool {
+    let line = &result.line_content;
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    result.match_ranges.iter().any(|range| {
+        let Some(text) = line.get(range.clone()) else {
+            return false;
+        };
+        let before = line[..range.start].chars().next_back();
+        let after = line[range.end..].chars().next();
+        text == pattern && !before.is_some_and(is_ident) && !after.is_some_and(is_ident)
+    })
+}
+
+/// 1 for the current file's directory, falling off with each directory between them
+fn proximity(path: &Path, current: &Path) -> f64 {
+    let dir = path.parent().unwrap_or(path);
+    let current_dir = current.parent().unwrap_or(current);
+    let shared = dir
+        .components()
+        .zip(current_dir.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let apart = dir.components().count() + current_dir.components().count() - 2 * shared;
+    1.0 / (1.0 + apart as f64)
+}
+
+/// 1 for a file modified just now, a half after a day, and so on
+fn recency(modified: Option<SystemTime>, now: SystemTime) -> f64 {
+    let Some(modified) = modified else {
+        return 0.0;
+    };
+    let age = now.duration_since(modified).unwrap_or(Duration::ZERO);
+    let days = age.as_secs_f64() / (24.0 * 60.0 * 60.0);
+    1.0 / (1.0 + days)
+}
+
+/// Relevance of one result; higher is better
+pub fn score(
+    result: &SearchResult,
+    ctx: &RankContext,
+    weights: &RankingWeights,
+    modified: Option<SystemTime>,
+) -> f64 {
+    let mut score = 0.0;
+    if is_exact_match(result, ctx.pattern) {
+        score += weights.exact_match;
+    }
+    match classify_line(&result.line_content) {
+        LineKind::Definition => score += weights.definition,
+        LineKind::Comment => score -= weights.comment,
+        LineKind::Code if match_in_trailing_comment(result) => score -= weights.comment,
+        LineKind::Code => {}
+    }
+    score += weights.recency * recency(modified, ctx.now);
+    if let Some(current) = ctx.current_file {
+        score += weights.proximity * proximity(&result.file_path, current);
+    }
+    score
+}
+
+/// Sort `results` best first. Equally relevant results keep ripgrep's order.
+pub fn rank_results(results: &mut Vec<SearchResult>, ctx: &RankContext, weights: &RankingWeights) {
+    // One metadata lookup per file, not per match
+    let mut modified: HashMap<PathBuf, Option<SystemTime>> = HashMap::new();
+    let mut scored: Vec<(f64, SearchResult)> = results
+        .drain(..)
+        .map(|result| {
+            let modified = *modified.entry(result.file_path.clone()).or_insert_with(|| {
+                std::fs::metadata(&result.file_path)
+                    .and_then(|meta| meta.modified())
+                    .ok()
+            });
+            (score(&result, ctx, weights, modified), result)
+        })
+        .collect();
+
+    // Stable, so ties stay in the order ripgrep found them
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    results.extend(scored.into_iter().map(|(_, result)| result));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(path: &str, line: &str, pattern: &str) -> SearchResult {
+        let start = line.find(pattern).unwrap();
+        let range = start..start + pattern.len();
+        SearchResult {
+            file_path: PathBuf::from(path),
+            line_number: 1,
+            line_content: line.to_string(),
+            match_ranges: vec![range],
+            context_before: Vec::new(),
+            context_after: Vec::new(),
+        }
+    }
+
+    fn ctx(pattern: &str) -> RankContext<'_> {
+        RankContext {
+            pattern,
+            current_file: None,
+            now: SystemTime::now(),
+        }
+    }
+
+    #[test]
+    fn test_classify_line() {
+        assert_eq!(classify_line("    pub fn parse_config() {"), LineKind::Definition);
+        assert_eq!(classify_line("class Parser:"), LineKind::Definition);
+        assert_eq!(classify_line("export async function load() {"), LineKind::Definition);
+        assert_eq!(classify_line("  // calls parse_config"), LineKind::Comment);
+        assert_eq!(classify_line("# parse the config"), LineKind::Comment);
+        assert_eq!(classify_line("#[derive(Debug)]"), LineKind::Code);
+        assert_eq!(classify_line("parse_config(&path)?;"), LineKind::Code);
+    }
+
+    #[test]
+    fn test_definition_outranks_comment() {
+        let mut results = vec![
+            result("src/a.rs", "// parse_config is called once at startup", "parse_config"),
+            result("src/b.rs", "let config = parse_config(&path)?;", "parse_config"),
+            result("src/c.rs", "pub fn parse_config(path: &Path) -> Config {", "parse_config"),
+        ];
+        rank_results(&mut results, &ctx("parse_config"), &RankingWeights::default());
+
+        let order: Vec<&str> = results.iter().map(|r| r.file_path.to_str().unwrap()).collect();
+        assert_eq!(order, ["src/c.rs", "src/b.rs", "src/a.rs"]);
+    }
+
+    #[test]
+    fn test_exact_and_nearby_matches_rank_higher() {
+        let weights = RankingWeights {
+            definition: 0.0,
+            comment: 0.0,
+            recency: 0.0,
+            ..RankingWeights::default()
+        };
+        let mut results = vec![
+            result("src/a.rs", "reload_config();", "load_config"),
+            result("src/b.rs", "load_config();", "load_config"),
+        ];
+        rank_results(&mut results, &ctx("load_config"), &weights);
+        assert_eq!(results[0].file_path, PathBuf::from("src/b.rs"));
+
+        let current = PathBuf::from("src/ui/app.rs");
+        let ctx = RankContext {
+            current_file: Some(&current),
+            ..ctx("x")
+        };
+        let mut results = vec![
+            result("tests/x.rs", "x", "x"),
+            result("src/services/x.rs", "x", "x"),
+            result("src/ui/x.rs", "x", "x"),
+        ];
+        rank_results(&mut results, &ctx, &weights);
+        let order: Vec<&str> = results.iter().map(|r| r.file_path.to_str().unwrap()).collect();
+        assert_eq!(order, ["src/ui/x.rs", "src/services/x.rs", "tests/x.rs"]);
+    }
+}
@@ -1,13 +1,32 @@
-use parking_lot::RwLock;
-use std::{collections::HashMap, path::Path, sync::Arc};
+use parking_lot::{Mutex, RwLock};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    sync::{
+        Arc, LazyLock,
+        atomic::{AtomicU64, Ordering},
+    },
+};
 use thiserror::Error;
-use tree_sitter::{Language, Parser, Query, QueryCursor, Tree};
+use tree_sitter::{Language, Parser, Point, Query, QueryCursor, Tree};
+
+use crate::config::LogLevel;
+use crate::logging;
 
 // Module for service initialization
 pub mod service_init;
+// Module bounding concurrent parses
+pub mod parse_queue;
+// Module caching extracted symbols per file
+pub mod symbol_cache;
+// Module keeping a workspace symbol index in step with file changes
+pub mod symbol_index;
 
 // Re-export service initialization
 pub use service_init::initialize_service;
+pub use parse_queue::ParseQueue;
+pub use symbol_cache::SymbolCache;
+pub use symbol_index::{IndexChange, SymbolIndex, run_incremental_index};
 
 // Error types for tree-sitter operations
 #[derive(Error, Debug)]
@@ -16,14 +35,19 @@ pub enum TreeSitterError {
     UnsupportedLanguage(String),
     #[error("Parser initialization failed: {0}")]
     ParserError(String),
-    #[error("File size exceeds limit")]
-    FileSizeExceeded,
+    #[error("File size {size} bytes exceeds limit of {limit} bytes")]
+    FileSizeExceeded { size: usize, limit: usize },
     #[error("Failed to parse file: {0}")]
     ParseError(String),
     #[error("Query error: {0}")]
     QueryError(String),
 }
 
+/// Languages whose grammar failed to load, with the reason. Shared by the whole process
+/// since a grammar that fails once fails every time.
+static UNAVAILABLE_LANGUAGES: LazyLock<RwLock<HashMap<SupportedLanguage, String>>> =
+    LazyLock::new(Default::default);
+
 // Supported languages enum
 #[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum SupportedLanguage {
@@ -35,56 +59,214 @@ pub enum SupportedLanguage {
 }
 
 impl SupportedLanguage {
-    fn get_language(&self) -> Language {
+    pub const ALL: [Self; 5] = [
+        Self::JavaScript,
+        Self::TypeScript,
+        Self::Python,
+        Self::Rust,
+        Self::Markdown,
+    ];
+
+    pub(crate) fn get_language(&self) -> Language {
         match self {
             Self::JavaScript => tree_sitter_javascript::language(),
             Self::TypeScript => tree_sitter_typescript::language_typescript(),
             Self::Python => tree_sitter_python::language(),
-            // Temporarily using JavaScript to avoid version issues
-            Self::Rust => tree_sitter_javascript::language(),
+            Self::Rust => tree_sitter_rust::language(),
             // Temporarily using JavaScript to avoid version issues
             Self::Markdown => tree_sitter_javascript::language(),
         }
     }
 
+    /// Node kinds `extract_definitions` reports for this language unless overridden
+    pub fn default_definition_kinds(&self) -> HashSet<String> {
+        let kinds: &[&str] = match self {
+            Self::JavaScript | Self::TypeScript => {
+                &["function_declaration", "class_declaration", "method_definition"]
+            }
+            Self::Python => &["class_definition", "function_definition"],
+            Self::Rust => &["function_item", "struct_item", "impl_item"],
+            Self::Markdown => &[],
+        };
+        kinds.iter().map(|kind| kind.to_string()).collect()
+    }
+
+    /// Language for files with extension `ext`, unless its grammar is unavailable
     pub fn from_extension(ext: &str) -> Option<Self> {
-        match ext.to_lowercase().as_str() {
-            "js" => Some(Self::JavaScript),
-            "ts" => Some(Self::TypeScript),
-            "py" => Some(Self::Python),
-            "rs" => Some(Self::Rust),
-            "md" | "markdown" => Some(Self::Markdown),
-            _ => None,
+        let language = match ext.to_lowercase().as_str() {
+            "js" => Self::JavaScript,
+            "ts" => Self::TypeScript,
+            "py" => Self::Python,
+            "rs" => Self::Rust,
+            "md" | "markdown" => Self::Markdown,
+            _ => return None,
+        };
+        language.is_available().then_some(language)
+    }
+
+    /// Whether the grammar loads into a parser, e.g. isn't built for another ABI version
+    fn load_grammar(self) -> Result<(), TreeSitterError> {
+        Parser::new()
+            .set_language(self.get_language())
+            .map_err(|e| TreeSitterError::ParserError(e.to_string()))
+    }
+
+    /// False once the grammar has failed to load
+    pub fn is_available(&self) -> bool {
+        !UNAVAILABLE_LANGUAGES.read().contains_key(self)
+    }
+
+    /// Stop offering this language, warning the first time
+    pub fn mark_unavailable(self, reason: &str) {
+        let mut unavailable = UNAVAILABLE_LANGUAGES.write();
+        if unavailable.contains_key(&self) {
+            return;
         }
+        unavailable.insert(self, reason.to_string());
+        logging::log(
+            LogLevel::Warn,
+            "tree_sitter",
+            format!("{:?} support disabled: its grammar failed to load: {}", self, reason),
+        );
     }
+
+    #[cfg(test)]
+    fn mark_available(self) {
+        UNAVAILABLE_LANGUAGES.write().remove(&self);
+    }
+}
+
+/// Try loading every grammar with `load`, disabling the languages that fail.
+/// Returns the languages disabled by this check.
+pub fn check_grammars_with(
+    load: impl Fn(SupportedLanguage) -> Result<(), TreeSitterError>,
+) -> Vec<SupportedLanguage> {
+    SupportedLanguage::ALL
+        .into_iter()
+        .filter(|language| language.is_available())
+        .filter_map(|language| {
+            let error = load(language).err()?;
+            language.mark_unavailable(&error.to_string());
+            Some(language)
+        })
+        .collect()
+}
+
+/// Disable any language whose grammar doesn't load with this tree-sitter runtime
+pub fn check_grammars() -> Vec<SupportedLanguage> {
+    check_grammars_with(SupportedLanguage::load_grammar)
+}
+
+/// Language of `path`, going by its extension
+fn language_for_path(path: &Path) -> Result<SupportedLanguage, TreeSitterError> {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .ok_or_else(|| TreeSitterError::UnsupportedLanguage("No file extension".to_string()))?;
+
+    SupportedLanguage::from_extension(ext)
+        .ok_or_else(|| TreeSitterError::UnsupportedLanguage(ext.to_string()))
 }
 
 // Parser pool for each language
 type ParserPool = Arc<RwLock<Vec<Parser>>>;
 
+/// Snapshot of the compiled-query cache counters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct QueryCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+}
+
+impl QueryCacheStats {
+    /// Fraction of lookups served from the cache, or 0 before any lookups
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+/// Most idle query cursors kept for reuse
+const MAX_POOLED_CURSORS: usize = 8;
+
+/// How often query cursors were freshly allocated versus taken from the pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CursorPoolStats {
+    pub created: u64,
+    pub reused: u64,
+    pub idle: usize,
+}
+
+/// Caps on how much a single query may collect; `None` means unlimited
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueryLimits {
+    /// Most matches returned
+    pub max_matches: Option<usize>,
+    /// Only nodes within the first `max_bytes` of the source are searched
+    pub max_bytes: Option<usize>,
+}
+
+/// Matches from a limited query, and whether a limit cut them short
+#[derive(Debug, Clone, Default)]
+pub struct QueryResults {
+    pub matches: Vec<QueryMatch>,
+    pub truncated: bool,
+}
+
 pub struct TreeSitterService {
     parser_pools: HashMap<SupportedLanguage, ParserPool>,
     max_file_size: usize,
     max_parsers_per_lang: usize,
+    parse_queue: ParseQueue,
+    definition_kinds: RwLock<HashMap<SupportedLanguage, HashSet<String>>>,
+    query_cache: RwLock<HashMap<(SupportedLanguage, String), Arc<Query>>>,
+    query_cache_hits: AtomicU64,
+    query_cache_misses: AtomicU64,
+    cursor_pool: Mutex<Vec<QueryCursor>>,
+    cursors_created: AtomicU64,
+    cursors_reused: AtomicU64,
 }
 
 impl TreeSitterService {
+    /// Create a service that allows as many concurrent parses as there are parsers per language
     pub fn new(max_file_size: usize, max_parsers_per_lang: usize) -> Self {
+        Self::with_concurrency(max_file_size, max_parsers_per_lang, max_parsers_per_lang)
+    }
+
+    /// Create a service where at most `max_concurrent_parses` parses run at once;
+    /// further parses wait in a queue
+    pub fn with_concurrency(
+        max_file_size: usize,
+        max_parsers_per_lang: usize,
+        max_concurrent_parses: usize,
+    ) -> Self {
         let mut service = Self {
             parser_pools: HashMap::new(),
             max_file_size,
             max_parsers_per_lang,
+            parse_queue: ParseQueue::new(max_concurrent_parses),
+            definition_kinds: RwLock::new(HashMap::new()),
+            query_cache: RwLock::new(HashMap::new()),
+            query_cache_hits: AtomicU64::new(0),
+            query_cache_misses: AtomicU64::new(0),
+            cursor_pool: Mutex::new(Vec::with_capacity(MAX_POOLED_CURSORS)),
+            cursors_created: AtomicU64::new(0),
+            cursors_reused: AtomicU64::new(0),
         };
 
-        // Initialize parser pools for all supported languages
-        for lang in [
-            SupportedLanguage::JavaScript,
-            SupportedLanguage::TypeScript,
-            SupportedLanguage::Python,
-            SupportedLanguage::Rust,
-            SupportedLanguage::Markdown,
-        ] {
+        // Initialize parser pools and definition kinds for all supported languages
+        check_grammars();
+        for lang in SupportedLanguage::ALL {
             service.init_parser_pool(lang);
+            service
+                .definition_kinds
+                .write()
+                .insert(lang, lang.default_definition_kinds());
         }
 
         service
@@ -95,24 +277,40 @@ impl TreeSitterService {
         self.parser_pools.insert(language, pool);
     }
 
-    fn get_or_create_parser(&self, language: SupportedLanguage) -> Result<Parser, TreeSitterError> {
+    /// Check out a parser; it goes back to the pool when the returned guard is dropped
+    fn get_or_create_parser(
+        &self,
+        language: SupportedLanguage,
+    ) -> Result<PooledParser<'_>, TreeSitterError> {
         let pool = self
             .parser_pools
             .get(&language)
             .ok_or_else(|| TreeSitterError::UnsupportedLanguage(format!("{:?}", language)))?;
 
         // Try to get an existing parser from the pool
-        if let Some(mut parser) = pool.write().pop() {
-            parser.reset();
-            return Ok(parser);
-        }
+        let pooled = pool.write().pop();
+        let parser = match pooled {
+            Some(mut parser) => {
+                parser.reset();
+                parser
+            }
+            None => {
+                // Create new parser if pool is empty
+                let mut parser = Parser::new();
+                if let Err(e) = parser.set_language(language.get_language()) {
+                    // Don't retry a grammar that can't load on every parse
+                    language.mark_unavailable(&e.to_string());
+                    return Err(TreeSitterError::ParserError(e.to_string()));
+                }
+                parser
+            }
+        };
 
-        // Create new parser if pool is empty
-        let mut parser = Parser::new();
-        parser
-            .set_language(language.get_language())
-            .map_err(|e| TreeSitterError::ParserError(e.to_string()))?;
-        Ok(parser)
+        Ok(PooledParser {
+            service: self,
+            language,
+            parser: Some(parser),
+        })
     }
 
     fn return_parser(&self, language: SupportedLanguage, parser: Parser) {
@@ -124,35 +322,125 @@ impl TreeSitterService {
         }
     }
 
-    pub fn parse_file(&self, path: &Path, content: &str) -> Result<Tree, TreeSitterError> {
-        if content.len() > self.max_file_size {
-            return Err(TreeSitterError::FileSizeExceeded);
+    /// Number of idle parsers pooled for `language`
+    pub fn available_parsers(&self, language: SupportedLanguage) -> usize {
+        self.parser_pools
+            .get(&language)
+            .map(|pool| pool.read().len())
+            .unwrap_or(0)
+    }
+
+    /// Check out a query cursor; it goes back to the pool when the returned guard is dropped
+    fn acquire_cursor(&self) -> PooledCursor<'_> {
+        let pooled = self.cursor_pool.lock().pop();
+        let cursor = match pooled {
+            Some(cursor) => {
+                self.cursors_reused.fetch_add(1, Ordering::Relaxed);
+                cursor
+            }
+            None => {
+                self.cursors_created.fetch_add(1, Ordering::Relaxed);
+                QueryCursor::new()
+            }
+        };
+
+        PooledCursor {
+            service: self,
+            cursor: Some(cursor),
         }
+    }
 
-        let ext = path
-            .extension()
-            .and_then(|e| e.to_str())
-            .ok_or_else(|| TreeSitterError::UnsupportedLanguage("No file extension".to_string()))?;
+    /// Clear any ranges or limits a caller set, so the next user starts fresh
+    fn return_cursor(&self, mut cursor: QueryCursor) {
+        cursor.set_byte_range(0..usize::MAX);
+        cursor.set_point_range(Point::new(0, 0)..Point::new(usize::MAX, usize::MAX));
+        cursor.set_match_limit(u32::MAX);
 
-        let language = SupportedLanguage::from_extension(ext)
-            .ok_or_else(|| TreeSitterError::UnsupportedLanguage(ext.to_string()))?;
+        let mut pool = self.cursor_pool.lock();
+        if pool.len() < MAX_POOLED_CURSORS {
+            pool.push(cursor);
+        }
+    }
+
+    /// Allocation counts for query cursors
+    pub fn cursor_pool_stats(&self) -> CursorPoolStats {
+        CursorPoolStats {
+            created: self.cursors_created.load(Ordering::Relaxed),
+            reused: self.cursors_reused.load(Ordering::Relaxed),
+            idle: self.cursor_pool.lock().len(),
+        }
+    }
+
+    pub fn parse_queue(&self) -> &ParseQueue {
+        &self.parse_queue
+    }
 
+    /// Run `f` with a pooled parser while holding a parse-queue slot.
+    /// The parser goes back to the pool on every path out of `f`, including panics.
+    fn with_parser<T>(
+        &self,
+        language: SupportedLanguage,
+        f: impl FnOnce(&mut Parser) -> Result<T, TreeSitterError>,
+    ) -> Result<T, TreeSitterError> {
+        let _permit = self.parse_queue.acquire();
         let mut parser = self.get_or_create_parser(language)?;
-        let tree = parser
-            .parse(content, None)
-            .ok_or_else(|| TreeSitterError::ParseError("Failed to parse content".to_string()))?;
+        f(&mut parser)
+    }
+
+    pub fn parse_file(&self, path: &Path, content: &str) -> Result<Tree, TreeSitterError> {
+        self.parse_with_language(language_for_path(path)?, content)
+    }
+
+    /// Parse `content` as `language`, for snippets that don't come from a file
+    pub fn parse_with_language(
+        &self,
+        language: SupportedLanguage,
+        content: &str,
+    ) -> Result<Tree, TreeSitterError> {
+        if content.len() > self.max_file_size {
+            return Err(TreeSitterError::FileSizeExceeded {
+                size: content.len(),
+                limit: self.max_file_size,
+            });
+        }
+        if !language.is_available() {
+            return Err(TreeSitterError::UnsupportedLanguage(format!("{:?}", language)));
+        }
+
+        self.with_parser(language, |parser| {
+            parser
+                .parse(content, None)
+                .ok_or_else(|| TreeSitterError::ParseError("Failed to parse content".to_string()))
+        })
+    }
+
+    /// Replace the node kinds treated as definitions for `language`
+    pub fn set_definition_kinds(&self, language: SupportedLanguage, kinds: HashSet<String>) {
+        self.definition_kinds.write().insert(language, kinds);
+    }
 
-        self.return_parser(language, parser);
-        Ok(tree)
+    /// Node kinds currently treated as definitions for `language`
+    pub fn definition_kinds(&self, language: SupportedLanguage) -> HashSet<String> {
+        self.definition_kinds
+            .read()
+            .get(&language)
+            .cloned()
+            .unwrap_or_default()
     }
 
-    pub fn extract_definitions(&self, tree: &Tree, content: &str) -> Vec<CodeDefinition> {
+    pub fn extract_definitions(
+        &self,
+        language: SupportedLanguage,
+        tree: &Tree,
+        content: &str,
+    ) -> Vec<CodeDefinition> {
         let mut definitions = Vec::new();
         let root_node = tree.root_node();
+        let kinds = self.definition_kinds(language);
 
         // Traverse AST and collect definitions
         let mut cursor = root_node.walk();
-        self.traverse_definitions(&mut cursor, content, &mut definitions);
+        self.traverse_definitions(&mut cursor, content, &kinds, &mut definitions);
 
         definitions
     }
@@ -161,31 +449,25 @@ impl TreeSitterService {
         &self,
         cursor: &mut tree_sitter::TreeCursor,
         content: &str,
+        kinds: &HashSet<String>,
         definitions: &mut Vec<CodeDefinition>,
     ) {
         loop {
             let node = cursor.node();
 
             // Check if current node is a definition
-            match node.kind() {
-                "function_declaration"
-                | "class_declaration"
-                | "method_definition"
-                | "function_item"
-                | "struct_item"
-                | "impl_item"
-                | "class_definition"
-                | "function_definition" => {
-                    if let Some(name_node) = self.find_definition_name(&node) {
-                        definitions.push(CodeDefinition {
-                            name: self.get_node_text(name_node, content),
-                            kind: node.kind().to_string(),
-                            start_line: node.start_position().row + 1,
-                            end_line: node.end_position().row + 1,
-                        });
-                    }
-                }
-                _ => {}
+            let name_node = if kinds.contains(node.kind()) {
+                self.find_definition_name(&node)
+            } else {
+                None
+            };
+            if let Some(name_node) = name_node {
+                definitions.push(CodeDefinition {
+                    name: self.get_node_text(name_node, content),
+                    kind: node.kind().to_string(),
+                    start_line: node.start_position().row + 1,
+                    end_line: node.end_position().row + 1,
+                });
             }
 
             // Continue traversing
@@ -208,7 +490,8 @@ impl TreeSitterService {
 
         loop {
             let current = cursor.node();
-            if current.kind() == "identifier" {
+            // Rust names its structs, enums and traits with type identifiers
+            if matches!(current.kind(), "identifier" | "type_identifier") {
                 return Some(current);
             }
 
@@ -232,12 +515,60 @@ impl TreeSitterService {
         tree: &Tree,
         content: &str,
     ) -> Result<Vec<QueryMatch>, TreeSitterError> {
-        let lang = language.get_language();
-        let query =
-            Query::new(lang, query_str).map_err(|e| TreeSitterError::QueryError(e.to_string()))?;
+        self.run_query_with_limits(language, query_str, tree, content, QueryLimits::default())
+            .map(|results| results.matches)
+    }
 
-        let matches = self.execute_query(&query, tree.root_node(), content);
-        Ok(matches)
+    /// Run a query, stopping at the match cap and searching only within the byte limit
+    pub fn run_query_with_limits(
+        &self,
+        language: SupportedLanguage,
+        query_str: &str,
+        tree: &Tree,
+        content: &str,
+        limits: QueryLimits,
+    ) -> Result<QueryResults, TreeSitterError> {
+        let query = self.cached_query(language, query_str)?;
+
+        Ok(self.execute_query_with_limits(&query, tree.root_node(), content, limits))
+    }
+
+    /// Compile `query_str` once per language and reuse it afterwards
+    fn cached_query(
+        &self,
+        language: SupportedLanguage,
+        query_str: &str,
+    ) -> Result<Arc<Query>, TreeSitterError> {
+        let key = (language, query_str.to_string());
+        if let Some(query) = self.query_cache.read().get(&key) {
+            self.query_cache_hits.fetch_add(1, Ordering::Relaxed);
+            metrics::counter!("tree_sitter_query_cache_hits", 1);
+            return Ok(query.clone());
+        }
+
+        self.query_cache_misses.fetch_add(1, Ordering::Relaxed);
+        metrics::counter!("tree_sitter_query_cache_misses", 1);
+
+        let query = Arc::new(
+            Query::new(language.get_language(), query_str)
+                .map_err(|e| TreeSitterError::QueryError(e.to_string()))?,
+        );
+        self.query_cache.write().insert(key, query.clone());
+        Ok(query)
+    }
+
+    /// Hit/miss counts and size of the compiled-query cache
+    pub fn query_cache_stats(&self) -> QueryCacheStats {
+        QueryCacheStats {
+            hits: self.query_cache_hits.load(Ordering::Relaxed),
+            misses: self.query_cache_misses.load(Ordering::Relaxed),
+            entries: self.query_cache.read().len(),
+        }
+    }
+
+    /// Drop every cached query. Hit/miss counters keep accumulating.
+    pub fn clear_query_cache(&self) {
+        self.query_cache.write().clear();
     }
 
     // Execute a query and get matches
@@ -247,41 +578,80 @@ impl TreeSitterService {
         node: tree_sitter::Node,
         content: &str,
     ) -> Vec<QueryMatch> {
-        let mut cursor = QueryCursor::new();
-        let matches = cursor.matches(query, node, content.as_bytes());
+        self.execute_query_with_limits(query, node, content, QueryLimits::default())
+            .matches
+    }
 
-        matches
-            .map(|query_match| {
-                let captures = query_match
-                    .captures
-                    .iter()
-                    .map(|capture| {
-                        let capture_node = capture.node;
-                        let capture_name =
-                            query.capture_names()[capture.index as usize].to_string();
-
-                        QueryCapture {
-                            index: capture.index as usize,
-                            name: capture_name,
-                            text: self.get_node_text(capture_node, content),
-                            start_position: (
-                                capture_node.start_position().row + 1,
-                                capture_node.start_position().column,
-                            ),
-                            end_position: (
-                                capture_node.end_position().row + 1,
-                                capture_node.end_position().column,
-                            ),
-                        }
-                    })
-                    .collect();
-
-                QueryMatch {
-                    pattern_index: query_match.pattern_index,
-                    captures,
+    /// Execute a query under `limits`. `truncated` is set when matches were dropped
+    /// because of the cap, or when the byte limit left part of `node` unsearched.
+    pub fn execute_query_with_limits(
+        &self,
+        query: &Query,
+        node: tree_sitter::Node,
+        content: &str,
+        limits: QueryLimits,
+    ) -> QueryResults {
+        let mut cursor = self.acquire_cursor();
+        if let Some(max_matches) = limits.max_matches {
+            // Also bounds the matches tree-sitter keeps in progress internally
+            cursor.set_match_limit(max_matches.clamp(1, u32::MAX as usize) as u32);
+        }
+        if let Some(max_bytes) = limits.max_bytes {
+            cursor.set_byte_range(0..max_bytes);
+        }
+
+        let mut results = QueryResults::default();
+        for query_match in cursor.matches(query, node, content.as_bytes()) {
+            if limits.max_matches.is_some_and(|max| results.matches.len() >= max) {
+                results.truncated = true;
+                break;
+            }
+            results
+                .matches
+                .push(self.to_query_match(query, &query_match, content));
+        }
+
+        results.truncated |= cursor.did_exceed_match_limit()
+            || limits.max_bytes.is_some_and(|max| node.end_byte() > max);
+        results
+    }
+
+    fn to_query_match(
+        &self,
+        query: &Query,
+        query_match: &tree_sitter::QueryMatch,
+        content: &str,
+    ) -> QueryMatch {
+        let captures = query_match
+            .captures
+            .iter()
+            .map(|capture| {
+                let capture_node = capture.node;
+                let capture_name = query.capture_names()[capture.index as usize].to_string();
+
+                QueryCapture {
+                    index: capture.index as usize,
+                    name: capture_name,
+                    text: self.get_node_text(capture_node, content),
+                    start_position: (
+                        capture_node.start_position().row + 1,
+                        capture_node.start_position().column,
+                    ),
+                    end_position: (
+                        capture_node.end_position().row + 1,
+                        capture_node.end_position().column,
+                    ),
+                    byte_range: capture_node.byte_range(),
+                    node_kind: capture_node.kind().to_string(),
+                    is_named: capture_node.is_named(),
                 }
             })
-            .collect()
+            .collect();
+
+        QueryMatch {
+            pattern_index: query_match.pattern_index,
+            captures,
+        }
     }
 
     // Use predefined queries from the queries module
@@ -314,15 +684,16 @@ impl TreeSitterService {
 
     // Find all symbols (functions, classes, methods, etc.) in the file
     pub fn find_symbols(&self, path: &Path, content: &str) -> Result<Vec<Symbol>, TreeSitterError> {
-        let tree = self.parse_file(path, content)?;
-        let ext = path
-            .extension()
-            .and_then(|e| e.to_str())
-            .ok_or_else(|| TreeSitterError::UnsupportedLanguage("No file extension".to_string()))?;
-
-        let language = SupportedLanguage::from_extension(ext)
-            .ok_or_else(|| TreeSitterError::UnsupportedLanguage(ext.to_string()))?;
+        self.find_symbols_in_str(language_for_path(path)?, content)
+    }
 
+    /// Symbols in `content` parsed as `language`, for snippets that don't come from a file
+    pub fn find_symbols_in_str(
+        &self,
+        language: SupportedLanguage,
+        content: &str,
+    ) -> Result<Vec<Symbol>, TreeSitterError> {
+        let tree = self.parse_with_language(language, content)?;
         let def_matches = self.get_definitions(language, &tree, content)?;
 
         // Convert QueryMatch to Symbol
@@ -351,7 +722,7 @@ impl TreeSitterService {
     }
 
     // Helper method to determine symbol kind
-    fn determine_symbol_kind(&self, capture_name: &str) -> SymbolKind {
+    pub(crate) fn determine_symbol_kind(&self, capture_name: &str) -> SymbolKind {
         if capture_name.contains("function") {
             SymbolKind::Function
         } else if capture_name.contains("class") {
@@ -374,6 +745,63 @@ impl TreeSitterService {
     }
 }
 
+/// A parser checked out of a pool, returned to it on drop
+struct PooledParser<'a> {
+    service: &'a TreeSitterService,
+    language: SupportedLanguage,
+    parser: Option<Parser>,
+}
+
+impl std::ops::Deref for PooledParser<'_> {
+    type Target = Parser;
+
+    fn deref(&self) -> &Parser {
+        self.parser.as_ref().expect("parser already returned")
+    }
+}
+
+impl std::ops::DerefMut for PooledParser<'_> {
+    fn deref_mut(&mut self) -> &mut Parser {
+        self.parser.as_mut().expect("parser already returned")
+    }
+}
+
+impl Drop for PooledParser<'_> {
+    fn drop(&mut self) {
+        if let Some(parser) = self.parser.take() {
+            self.service.return_parser(self.language, parser);
+        }
+    }
+}
+
+/// A query cursor checked out of the pool, returned to it on drop
+struct PooledCursor<'a> {
+    service: &'a TreeSitterService,
+    cursor: Option<QueryCursor>,
+}
+
+impl std::ops::Deref for PooledCursor<'_> {
+    type Target = QueryCursor;
+
+    fn deref(&self) -> &QueryCursor {
+        self.cursor.as_ref().expect("cursor already returned")
+    }
+}
+
+impl std::ops::DerefMut for PooledCursor<'_> {
+    fn deref_mut(&mut self) -> &mut QueryCursor {
+        self.cursor.as_mut().expect("cursor already returned")
+    }
+}
+
+impl Drop for PooledCursor<'_> {
+    fn drop(&mut self) {
+        if let Some(cursor) = self.cursor.take() {
+            self.service.return_cursor(cursor);
+        }
+    }
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct CodeDefinition {
     pub name: String,
@@ -395,6 +823,15 @@ pub struct QueryCapture {
     pub text: String,
     pub start_position: (usize, usize), // (line, column)
     pub end_position: (usize, usize),   // (line, column)
+    /// Byte offsets of the captured node in the source
+    #[serde(default)]
+    pub byte_range: std::ops::Range<usize>,
+    /// Grammar node type, e.g. "identifier" or "fn"
+    #[serde(default)]
+    pub node_kind: String,
+    /// False for anonymous nodes such as keywords and punctuation
+    #[serde(default)]
+    pub is_named: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -420,3 +857,333 @@ pub struct Symbol {
 
 // Module for language-specific queries
 pub mod queries;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failing_grammar_disables_its_language() {
+        // Markdown, since no other test parses it and the language is process-wide
+        let language = SupportedLanguage::Markdown;
+        let warnings = || {
+            logging::recent(usize::MAX)
+                .iter()
+                .filter(|r| r.message.starts_with("Markdown support disabled"))
+                .count()
+        };
+        let disabled = check_grammars_with(|language| match language {
+            SupportedLanguage::Markdown => Err(TreeSitterError::ParserError(
+                "Incompatible language version 99".to_string(),
+            )),
+            _ => Ok(()),
+        });
+        assert_eq!(disabled, vec![language]);
+        assert!(!language.is_available());
+        assert_eq!(SupportedLanguage::from_extension("md"), None);
+        assert_eq!(SupportedLanguage::from_extension("rs"), Some(SupportedLanguage::Rust));
+
+        // Later parses skip it rather than trying the grammar again
+        let service = TreeSitterService::new(1024, 2);
+        assert!(matches!(
+            service.parse_file(Path::new("README.md"), "# Title"),
+            Err(TreeSitterError::UnsupportedLanguage(_))
+        ));
+        assert_eq!(service.available_parsers(language), 0);
+
+        // Left out of directory outlines too
+        let temp = tempfile::TempDir::new().unwrap();
+        std::fs::write(temp.path().join("notes.md"), "# Notes").unwrap();
+        std::fs::write(temp.path().join("lib.rs"), "fn f() {}").unwrap();
+        let (files, _) = crate::ui::outline::outline_files(
+            temp.path(),
+            &[],
+            10,
+            &crate::fs::samusignore::SamusIgnore::default(),
+        )
+        .unwrap();
+        assert_eq!(files, vec![temp.path().join("lib.rs")]);
+
+        // Warned about once, however often it's reported
+        language.mark_unavailable("again");
+        assert_eq!(warnings(), 1);
+
+        language.mark_available();
+        assert_eq!(SupportedLanguage::from_extension("md"), Some(language));
+    }
+
+    #[test]
+    fn test_parse_failure_returns_parser_to_pool() {
+        let service = TreeSitterService::new(1024, 2);
+        let language = SupportedLanguage::JavaScript;
+
+        service.parse_file(Path::new("ok.js"), "let a = 1;").unwrap();
+        assert_eq!(service.available_parsers(language), 1);
+
+        let result: Result<(), _> = service.with_parser(language, |_| {
+            Err(TreeSitterError::ParseError("Failed to parse content".to_string()))
+        });
+        assert!(matches!(result, Err(TreeSitterError::ParseError(_))));
+        assert_eq!(service.available_parsers(language), 1);
+        assert_eq!(service.parse_queue().in_flight(), 0);
+    }
+
+    #[test]
+    fn test_repeated_parse_failures_do_not_drain_pool() {
+        let service = TreeSitterService::new(64, 2);
+        let language = SupportedLanguage::Python;
+
+        service.parse_file(Path::new("a.py"), "x = 1").unwrap();
+        assert_eq!(service.available_parsers(language), 1);
+
+        for _ in 0..10 {
+            let result: Result<Tree, _> = service.with_parser(language, |_| {
+                Err(TreeSitterError::ParseError("Failed to parse content".to_string()))
+            });
+            assert!(result.is_err());
+        }
+        assert_eq!(service.available_parsers(language), 1);
+
+        // Oversized content is rejected before a parser is checked out
+        let oversized = "x = 1\n".repeat(20);
+        assert!(matches!(
+            service.parse_file(Path::new("big.py"), &oversized),
+            Err(TreeSitterError::FileSizeExceeded { size: 120, limit: 64 })
+        ));
+        assert_eq!(service.available_parsers(language), 1);
+
+        // Even a panic while parsing hands the parser back
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _: Result<(), _> = service.with_parser(language, |_| panic!("parser blew up"));
+        }));
+        assert!(panicked.is_err());
+        assert_eq!(service.available_parsers(language), 1);
+        assert_eq!(service.parse_queue().in_flight(), 0);
+    }
+
+    #[test]
+    fn test_configured_definition_kinds() {
+        let service = TreeSitterService::new(4096, 2);
+        let content = "struct Point { x: i32 }\n\nenum Shape {\n    Circle,\n    Square,\n}\n\nfn area() {}\n";
+        let tree = service.parse_file(Path::new("shapes.rs"), content).unwrap();
+
+        let names = |service: &TreeSitterService| -> Vec<String> {
+            service
+                .extract_definitions(SupportedLanguage::Rust, &tree, content)
+                .into_iter()
+                .map(|d| d.name)
+                .collect()
+        };
+        assert_eq!(names(&service), vec!["Point", "area"]);
+
+        let mut kinds = service.definition_kinds(SupportedLanguage::Rust);
+        kinds.insert("enum_item".to_string());
+        service.set_definition_kinds(SupportedLanguage::Rust, kinds);
+
+        let definitions = service.extract_definitions(SupportedLanguage::Rust, &tree, content);
+        let shape = definitions.iter().find(|d| d.name == "Shape").unwrap();
+        assert_eq!(shape.kind, "enum_item");
+        assert_eq!((shape.start_line, shape.end_line), (3, 6));
+        assert_eq!(names(&service), vec!["Point", "Shape", "area"]);
+
+        // Other languages keep their own defaults
+        assert!(!service
+            .definition_kinds(SupportedLanguage::Python)
+            .contains("enum_item"));
+    }
+
+    #[test]
+    fn test_query_cache_hits() {
+        let service = TreeSitterService::new(4096, 2);
+        let content = "function a() {}\nfunction b() {}\n";
+        let tree = service.parse_file(Path::new("a.js"), content).unwrap();
+        let query = "(function_declaration name: (identifier) @name)";
+
+        let first = service
+            .run_query(SupportedLanguage::JavaScript, query, &tree, content)
+            .unwrap();
+        assert_eq!(first.len(), 2);
+        assert_eq!(
+            service.query_cache_stats(),
+            QueryCacheStats { hits: 0, misses: 1, entries: 1 }
+        );
+
+        for expected_hits in 1..=3 {
+            service
+                .run_query(SupportedLanguage::JavaScript, query, &tree, content)
+                .unwrap();
+            let stats = service.query_cache_stats();
+            assert_eq!(stats.hits, expected_hits);
+            assert_eq!(stats.misses, 1);
+        }
+
+        // Invalid queries are counted as misses but not cached
+        assert!(service
+            .run_query(SupportedLanguage::JavaScript, "(not_a_node)", &tree, content)
+            .is_err());
+        assert_eq!(service.query_cache_stats().entries, 1);
+
+        service.clear_query_cache();
+        let stats = service.query_cache_stats();
+        assert_eq!(stats.entries, 0);
+        assert_eq!(stats.hits, 3);
+        assert!((stats.hit_rate() - 0.6).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_query_cursors_are_reused() {
+        let service = TreeSitterService::new(4096, 2);
+        let content = "function a() {}\nfunction b() {}\n";
+        let tree = service.parse_file(Path::new("a.js"), content).unwrap();
+        let query = "(function_declaration name: (identifier) @name)";
+
+        for _ in 0..100 {
+            let matches = service
+                .run_query(SupportedLanguage::JavaScript, query, &tree, content)
+                .unwrap();
+            assert_eq!(matches.len(), 2);
+        }
+
+        // One allocation serves every sequential query
+        assert_eq!(
+            service.cursor_pool_stats(),
+            CursorPoolStats { created: 1, reused: 99, idle: 1 }
+        );
+
+        // A range left on a cursor doesn't leak into the next query
+        {
+            let mut cursor = service.acquire_cursor();
+            cursor.set_byte_range(0..1);
+        }
+        let matches = service
+            .run_query(SupportedLanguage::JavaScript, query, &tree, content)
+            .unwrap();
+        assert_eq!(matches.len(), 2);
+        assert_eq!(service.cursor_pool_stats().created, 1);
+    }
+
+    #[test]
+    fn test_query_limits_truncate_results() {
+        let service = TreeSitterService::new(1024 * 1024, 2);
+        let content: String = (0..1000).map(|i| format!("function f{}() {{}}\n", i)).collect();
+        let tree = service.parse_file(Path::new("many.js"), &content).unwrap();
+        let query = "(function_declaration name: (identifier) @name)";
+
+        let all = service
+            .run_query_with_limits(
+                SupportedLanguage::JavaScript,
+                query,
+                &tree,
+                &content,
+                QueryLimits::default(),
+            )
+            .unwrap();
+        assert_eq!(all.matches.len(), 1000);
+        assert!(!all.truncated);
+
+        let capped = service
+            .run_query_with_limits(
+                SupportedLanguage::JavaScript,
+                query,
+                &tree,
+                &content,
+                QueryLimits { max_matches: Some(10), max_bytes: None },
+            )
+            .unwrap();
+        assert_eq!(capped.matches.len(), 10);
+        assert!(capped.truncated);
+        assert_eq!(capped.matches[0].captures[0].text, "f0");
+
+        // Each line is 17 bytes, so the first 5 lines fit in 85 bytes
+        let byte_limited = service
+            .run_query_with_limits(
+                SupportedLanguage::JavaScript,
+                query,
+                &tree,
+                &content,
+                QueryLimits { max_matches: None, max_bytes: Some(85) },
+            )
+            .unwrap();
+        assert!(byte_limited.truncated);
+        assert!(byte_limited.matches.len() <= 6, "{}", byte_limited.matches.len());
+
+        // Limits don't stick to the pooled cursor
+        let again = service
+            .run_query(SupportedLanguage::JavaScript, query, &tree, &content)
+            .unwrap();
+        assert_eq!(again.len(), 1000);
+    }
+
+    #[test]
+    fn test_captures_report_node_kind_and_namedness() {
+        let service = TreeSitterService::new(1024, 2);
+        let content = "pub fn parse() {}";
+        let tree = service.parse_file(Path::new("lib.rs"), content).unwrap();
+
+        let matches = service
+            .run_query(
+                SupportedLanguage::Rust,
+                r#"(function_item "fn" @keyword name: (identifier) @name body: (_) @body)"#,
+                &tree,
+                content,
+            )
+            .unwrap();
+        let captures: Vec<(&str, &str, bool)> = matches[0]
+            .captures
+            .iter()
+            .map(|c| (c.name.as_str(), c.node_kind.as_str(), c.is_named))
+            .collect();
+        assert_eq!(
+            captures,
+            vec![
+                ("keyword", "fn", false),
+                ("name", "identifier", true),
+                ("body", "block", true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_typescript_classes_are_reported_as_classes() {
+        let service = TreeSitterService::new(1024 * 1024, 2);
+        let snippet = "class Shape {\n  area(): number { return 0; }\n}\n\ninterface Named {}\n";
+
+        let symbols = service
+            .find_symbols_in_str(SupportedLanguage::TypeScript, snippet)
+            .unwrap();
+        let kinds: Vec<(&str, &SymbolKind)> =
+            symbols.iter().map(|s| (s.name.as_str(), &s.kind)).collect();
+        assert!(kinds.contains(&("Shape", &SymbolKind::Class)), "{:?}", kinds);
+        assert!(kinds.contains(&("area", &SymbolKind::Method)), "{:?}", kinds);
+        assert!(kinds.contains(&("Named", &SymbolKind::Interface)), "{:?}", kinds);
+    }
+
+    #[test]
+    fn test_parse_snippet_without_a_path() {
+        let service = TreeSitterService::new(1024, 2);
+        let snippet = "struct Point { x: i32 }\n\nfn origin() -> Point {\n    Point { x: 0 }\n}\n";
+
+        let tree = service.parse_with_language(SupportedLanguage::Rust, snippet).unwrap();
+        assert_eq!(tree.root_node().kind(), "source_file");
+        assert!(!tree.root_node().has_error());
+
+        let symbols = service.find_symbols_in_str(SupportedLanguage::Rust, snippet).unwrap();
+        let names: Vec<&str> = symbols.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["Point", "origin"]);
+        assert_eq!(symbols[1].start_line, 3);
+
+        assert!(matches!(
+            service.parse_with_language(SupportedLanguage::Rust, &"x".repeat(2048)),
+            Err(TreeSitterError::FileSizeExceeded { size: 2048, limit: 1024 })
+        ));
+    }
+
+    #[test]
+    fn test_concurrency_limit_is_configurable() {
+        let service = TreeSitterService::with_concurrency(1024, 4, 2);
+        assert_eq!(service.parse_queue().limit(), 2);
+
+        let service = TreeSitterService::new(1024, 3);
+        assert_eq!(service.parse_queue().limit(), 3);
+    }
+}
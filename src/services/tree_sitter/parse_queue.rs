@@ -0,0 +1,118 @@
+use parking_lot::{Condvar, Mutex};
+
+/// Bounds how many parses run at once.
+///
+/// Callers past the limit block in `acquire` until a running parse finishes, so a burst
+/// of indexing work queues up instead of draining every parser pool at the same time.
+pub struct ParseQueue {
+    limit: usize,
+    active: Mutex<usize>,
+    released: Condvar,
+}
+
+/// A slot in the parse queue, released when dropped
+pub struct ParsePermit<'a> {
+    queue: &'a ParseQueue,
+}
+
+impl ParseQueue {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            // A zero limit would block every parse forever
+            limit: limit.max(1),
+            active: Mutex::new(0),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Wait for a free slot
+    pub fn acquire(&self) -> ParsePermit<'_> {
+        let mut active = self.active.lock();
+        while *active >= self.limit {
+            self.released.wait(&mut active);
+        }
+        *active += 1;
+        ParsePermit { queue: self }
+    }
+
+    /// Take a slot only if one is free right now
+    pub fn try_acquire(&self) -> Option<ParsePermit<'_>> {
+        let mut active = self.active.lock();
+        if *active >= self.limit {
+            return None;
+        }
+        *active += 1;
+        Some(ParsePermit { queue: self })
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Number of parses currently holding a slot
+    pub fn in_flight(&self) -> usize {
+        *self.active.lock()
+    }
+}
+
+impl Drop for ParsePermit<'_> {
+    fn drop(&mut self) {
+        *self.queue.active.lock() -= 1;
+        self.queue.released.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_permits_bounded_by_limit() {
+        let queue = ParseQueue::new(2);
+
+        let first = queue.acquire();
+        let _second = queue.acquire();
+        assert_eq!(queue.in_flight(), 2);
+        assert!(queue.try_acquire().is_none());
+
+        drop(first);
+        assert_eq!(queue.in_flight(), 1);
+        assert!(queue.try_acquire().is_some());
+    }
+
+    #[test]
+    fn test_concurrent_parses_never_exceed_limit() {
+        let queue = Arc::new(ParseQueue::new(3));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        let workers: Vec<_> = (0..12)
+            .map(|_| {
+                let queue = queue.clone();
+                let peak = peak.clone();
+                thread::spawn(move || {
+                    let _permit = queue.acquire();
+                    peak.fetch_max(queue.in_flight(), Ordering::SeqCst);
+                    thread::sleep(Duration::from_millis(5));
+                })
+            })
+            .collect();
+
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        assert!(peak.load(Ordering::SeqCst) <= 3);
+        assert_eq!(queue.in_flight(), 0);
+    }
+
+    #[test]
+    fn test_zero_limit_is_clamped() {
+        let queue = ParseQueue::new(0);
+        assert_eq!(queue.limit(), 1);
+        assert!(queue.try_acquire().is_some());
+    }
+}
@@ -71,10 +71,6 @@ lazy_static! {
             components: Query::new(
                 tree_sitter_typescript::language_typescript(),
                 r#"
-                (jsx_element
-                    open_tag: (jsx_opening_element
-                        name: (_) @component.name)) @component.definition
-
                 (variable_declaration
                     (variable_declarator
                         name: (identifier) @component.name
@@ -118,26 +114,18 @@ lazy_static! {
         // Rust Queries
         m.insert(SupportedLanguage::Rust, LanguageQueries {
             definitions: Query::new(
-                tree_sitter_javascript::language(),
+                tree_sitter_rust::language(),
                 r#"
-                // Using JavaScript language temporarily due to version issues
-                (function_declaration
+                (function_item
                     name: (identifier) @function.name) @function.definition
 
-                (class_declaration
-                    name: (identifier) @class.name) @class.definition
+                (struct_item
+                    name: (type_identifier) @struct.name) @struct.definition
                 "#,
             ).unwrap(),
 
-            components: Query::new(
-                tree_sitter_javascript::language(),
-                r#"
-                // Using JavaScript language temporarily due to version issues
-                (jsx_element
-                    open_tag: (jsx_opening_element
-                        name: (_) @component.name)) @component.definition
-                "#,
-            ).unwrap(),
+            // Rust has no component-like construct
+            components: Query::new(tree_sitter_rust::language(), "").unwrap(),
         });
 
         // Markdown Queries
@@ -145,7 +133,7 @@ lazy_static! {
             definitions: Query::new(
                 tree_sitter_javascript::language(),
                 r#"
-                // Using JavaScript language temporarily due to version issues
+                ; Using JavaScript language temporarily due to version issues
                 (comment) @comment.content
                 "#,
             ).unwrap(),
@@ -153,7 +141,7 @@ lazy_static! {
             components: Query::new(
                 tree_sitter_javascript::language(),
                 r#"
-                // Using JavaScript language temporarily due to version issues
+                ; Using JavaScript language temporarily due to version issues
                 (comment) @comment.content
                 "#,
             ).unwrap(),
@@ -4,6 +4,7 @@ use std::sync::Arc;
 
 const DEFAULT_MAX_FILE_SIZE: usize = 5 * 1024 * 1024; // 5 MB
 const DEFAULT_MAX_PARSERS_PER_LANG: usize = 4;
+const DEFAULT_MAX_CONCURRENT_PARSES: usize = 4;
 
 pub fn initialize_service(config: &Config) -> Arc<TreeSitterService> {
     // Extract configuration values or use defaults
@@ -15,8 +16,16 @@ pub fn initialize_service(config: &Config) -> Arc<TreeSitterService> {
         .get_usize("tree_sitter.max_parsers_per_lang")
         .unwrap_or(DEFAULT_MAX_PARSERS_PER_LANG);
     
+    let max_concurrent_parses = config
+        .get_usize("tree_sitter.max_concurrent_parses")
+        .unwrap_or(DEFAULT_MAX_CONCURRENT_PARSES);
+    
     // Create the service with the configured values
-    let service = TreeSitterService::new(max_file_size, max_parsers_per_lang);
+    let service = TreeSitterService::with_concurrency(
+        max_file_size,
+        max_parsers_per_lang,
+        max_concurrent_parses,
+    );
     
     Arc::new(service)
 }
\ No newline at end of file
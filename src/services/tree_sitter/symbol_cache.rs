@@ -0,0 +1,188 @@
+use parking_lot::Mutex;
+use std::{
+    collections::VecDeque,
+    hash::{DefaultHasher, Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use super::{Symbol, TreeSitterError, TreeSitterService};
+
+/// Files whose symbols are kept by default
+pub const DEFAULT_SYMBOL_CACHE_CAPACITY: usize = 32;
+
+struct CacheEntry {
+    path: PathBuf,
+    content_hash: u64,
+    symbols: Arc<Vec<Symbol>>,
+}
+
+/// Read-through LRU of extracted symbols, keyed on path and content hash.
+///
+/// An entry is only reused while the file's content hashes the same, so an edited file
+/// is re-parsed and its stale entry replaced.
+pub struct SymbolCache {
+    capacity: usize,
+    /// Most recently used first
+    entries: Mutex<VecDeque<CacheEntry>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl SymbolCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: Mutex::new(VecDeque::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Symbols for `path`, parsing with `service` only when `content` isn't cached
+    pub fn symbols(
+        &self,
+        service: &TreeSitterService,
+        path: &Path,
+        content: &str,
+    ) -> Result<Arc<Vec<Symbol>>, TreeSitterError> {
+        self.get_or_insert_with(path, content, || service.find_symbols(path, content))
+    }
+
+    /// Cached symbols for `path` at `content`, or the result of `extract` if there are none
+    pub fn get_or_insert_with<E>(
+        &self,
+        path: &Path,
+        content: &str,
+        extract: impl FnOnce() -> Result<Vec<Symbol>, E>,
+    ) -> Result<Arc<Vec<Symbol>>, E> {
+        let content_hash = hash_content(content);
+
+        {
+            let mut entries = self.entries.lock();
+            if let Some(index) = entries.iter().position(|entry| entry.path == path) {
+                if entries[index].content_hash == content_hash {
+                    let entry = entries.remove(index).expect("index is in bounds");
+                    let symbols = entry.symbols.clone();
+                    entries.push_front(entry);
+                    self.hits.fetch_add(1, Ordering::Relaxed);
+                    return Ok(symbols);
+                }
+                // The file changed since it was cached
+                entries.remove(index);
+            }
+        }
+
+        // Parse without holding the lock so other files aren't held up
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let symbols = Arc::new(extract()?);
+
+        let mut entries = self.entries.lock();
+        entries.retain(|entry| entry.path != path);
+        entries.push_front(CacheEntry {
+            path: path.to_path_buf(),
+            content_hash,
+            symbols: symbols.clone(),
+        });
+        entries.truncate(self.capacity);
+
+        Ok(symbols)
+    }
+
+    /// Forget `path`'s symbols
+    pub fn invalidate(&self, path: &Path) {
+        self.entries.lock().retain(|entry| entry.path != path);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.lock().is_empty()
+    }
+
+    /// (hits, misses) since the cache was created
+    pub fn stats(&self) -> (u64, u64) {
+        (
+            self.hits.load(Ordering::Relaxed),
+            self.misses.load(Ordering::Relaxed),
+        )
+    }
+}
+
+impl Default for SymbolCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_SYMBOL_CACHE_CAPACITY)
+    }
+}
+
+fn hash_content(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::tree_sitter::SymbolKind;
+    use std::cell::Cell;
+    use std::convert::Infallible;
+
+    fn symbol(name: &str) -> Symbol {
+        Symbol {
+            name: name.to_string(),
+            kind: SymbolKind::Function,
+            start_line: 1,
+            end_line: 1,
+        }
+    }
+
+    #[test]
+    fn test_unchanged_content_skips_parsing() {
+        let cache = SymbolCache::new(4);
+        let parses = Cell::new(0);
+        let parse = |name: &str| {
+            parses.set(parses.get() + 1);
+            Ok::<_, Infallible>(vec![symbol(name)])
+        };
+        let path = Path::new("src/a.js");
+
+        let first = cache.get_or_insert_with(path, "function a() {}", || parse("a")).unwrap();
+        let second = cache.get_or_insert_with(path, "function a() {}", || parse("a")).unwrap();
+        assert_eq!(parses.get(), 1);
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(cache.stats(), (1, 1));
+
+        // Edited content is parsed again and replaces the old entry
+        let edited = cache.get_or_insert_with(path, "function b() {}", || parse("b")).unwrap();
+        assert_eq!(parses.get(), 2);
+        assert_eq!(edited[0].name, "b");
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_least_recently_used_is_evicted() {
+        let cache = SymbolCache::new(2);
+        let parse = || Ok::<_, Infallible>(Vec::new());
+
+        cache.get_or_insert_with(Path::new("a"), "", parse).unwrap();
+        cache.get_or_insert_with(Path::new("b"), "", parse).unwrap();
+        // Touch "a" so "b" becomes the oldest
+        cache.get_or_insert_with(Path::new("a"), "", parse).unwrap();
+        cache.get_or_insert_with(Path::new("c"), "", parse).unwrap();
+
+        assert_eq!(cache.len(), 2);
+        let (hits, misses) = cache.stats();
+        cache.get_or_insert_with(Path::new("a"), "", parse).unwrap();
+        cache.get_or_insert_with(Path::new("b"), "", parse).unwrap();
+        assert_eq!(cache.stats(), (hits + 1, misses + 1));
+
+        cache.invalidate(Path::new("a"));
+        assert_eq!(cache.len(), 1);
+    }
+}
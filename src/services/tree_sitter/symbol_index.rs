@@ -0,0 +1,242 @@
+use parking_lot::RwLock;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::sync::mpsc;
+
+use crate::config::LogLevel;
+use crate::fs::SamusIgnore;
+use crate::logging;
+
+use super::{Symbol, SupportedLanguage, TreeSitterError, TreeSitterService};
+
+/// How long to wait for more changes before re-indexing a burst of them
+pub const DEFAULT_REINDEX_DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// A filesystem change relevant to the index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndexChange {
+    /// The file was created or modified; re-parse it
+    Changed(PathBuf),
+    /// The file was deleted; drop its symbols
+    Removed(PathBuf),
+}
+
+impl IndexChange {
+    pub fn path(&self) -> &Path {
+        match self {
+            IndexChange::Changed(path) | IndexChange::Removed(path) => path,
+        }
+    }
+}
+
+/// Symbols of every indexed workspace file, kept up to date one path at a time
+pub struct SymbolIndex {
+    service: Arc<TreeSitterService>,
+    files: HashMap<PathBuf, Vec<Symbol>>,
+    /// Paths left out of the index
+    samusignore: SamusIgnore,
+}
+
+impl SymbolIndex {
+    pub fn new(service: Arc<TreeSitterService>) -> Self {
+        Self {
+            service,
+            files: HashMap::new(),
+            samusignore: SamusIgnore::default(),
+        }
+    }
+
+    /// Leave files excluded by the workspace's `.samusignore` out of the index
+    pub fn with_samusignore(mut self, samusignore: SamusIgnore) -> Self {
+        self.samusignore = samusignore;
+        self
+    }
+
+    /// Parse `path` and replace its symbols. Files in unsupported languages, or
+    /// excluded by `.samusignore`, are skipped.
+    pub fn index_file(&mut self, path: &Path) -> Result<(), TreeSitterError> {
+        if self.samusignore.is_ignored(path, false) {
+            self.remove_file(path);
+            return Ok(());
+        }
+
+        let supported = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(SupportedLanguage::from_extension)
+            .is_some();
+        if !supported {
+            return Ok(());
+        }
+
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| TreeSitterError::ParseError(format!("{}: {}", path.display(), e)))?;
+        let symbols = self.service.find_symbols(path, &content)?;
+        self.files.insert(path.to_path_buf(), symbols);
+        Ok(())
+    }
+
+    pub fn remove_file(&mut self, path: &Path) {
+        self.files.remove(path);
+    }
+
+    /// Re-parse a changed file, or drop a removed (or no longer readable) one
+    pub fn apply(&mut self, change: &IndexChange) -> Result<(), TreeSitterError> {
+        match change {
+            IndexChange::Changed(path) if path.is_file() => self.index_file(path),
+            IndexChange::Changed(path) | IndexChange::Removed(path) => {
+                self.remove_file(path);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn symbols(&self, path: &Path) -> Option<&[Symbol]> {
+        self.files.get(path).map(Vec::as_slice)
+    }
+
+    /// Every indexed symbol named `name`, with the file it is in
+    pub fn find(&self, name: &str) -> Vec<(&Path, &Symbol)> {
+        self.files
+            .iter()
+            .flat_map(|(path, symbols)| symbols.iter().map(move |s| (path.as_path(), s)))
+            .filter(|(_, symbol)| symbol.name == name)
+            .collect()
+    }
+
+    pub fn file_count(&self) -> usize {
+        self.files.len()
+    }
+}
+
+/// Apply changes from `changes` to `index` until the channel closes.
+///
+/// Changes arriving within `debounce` of each other are batched, and only the latest
+/// change per path is applied, so a file saved several times in a row is parsed once.
+pub async fn run_incremental_index(
+    index: Arc<RwLock<SymbolIndex>>,
+    mut changes: mpsc::Receiver<IndexChange>,
+    debounce: Duration,
+) {
+    while let Some(first) = changes.recv().await {
+        let mut pending = vec![first];
+        let mut closed = false;
+        loop {
+            match tokio::time::timeout(debounce, changes.recv()).await {
+                Ok(Some(change)) => {
+                    pending.retain(|p| p.path() != change.path());
+                    pending.push(change);
+                }
+                Ok(None) => {
+                    closed = true;
+                    break;
+                }
+                Err(_) => break,
+            }
+        }
+
+        let mut index = index.write();
+        for change in &pending {
+            if let Err(e) = index.apply(change) {
+                logging::log(
+                    LogLevel::Warn,
+                    "index",
+                    format!("Failed to re-index {}: {}", change.path().display(), e),
+                );
+            }
+        }
+        drop(index);
+
+        if closed {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn names(index: &SymbolIndex, path: &Path) -> Vec<String> {
+        index
+            .symbols(path)
+            .unwrap_or_default()
+            .iter()
+            .map(|s| s.name.clone())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_modified_file_is_reindexed() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("lib.py");
+        std::fs::write(&path, "def before():\n    pass\n").unwrap();
+
+        let service = Arc::new(TreeSitterService::new(1024 * 1024, 2));
+        let index = Arc::new(RwLock::new(SymbolIndex::new(service)));
+        index.write().index_file(&path).unwrap();
+        assert!(names(&index.read(), &path).contains(&"before".to_string()));
+
+        let (tx, rx) = mpsc::channel(16);
+        let worker = tokio::spawn(run_incremental_index(
+            index.clone(),
+            rx,
+            Duration::from_millis(20),
+        ));
+
+        // Two quick saves are debounced into one re-parse of the final content
+        std::fs::write(&path, "def during():\n    pass\n").unwrap();
+        tx.send(IndexChange::Changed(path.clone())).await.unwrap();
+        std::fs::write(&path, "def after():\n    pass\n").unwrap();
+        tx.send(IndexChange::Changed(path.clone())).await.unwrap();
+        drop(tx);
+        worker.await.unwrap();
+
+        let symbols = names(&index.read(), &path);
+        assert!(symbols.contains(&"after".to_string()), "{:?}", symbols);
+        assert!(!symbols.contains(&"before".to_string()));
+    }
+
+    #[test]
+    fn test_removed_file_drops_symbols() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("lib.rs");
+        std::fs::write(&path, "fn gone() {}\n").unwrap();
+
+        let service = Arc::new(TreeSitterService::new(1024 * 1024, 2));
+        let mut index = SymbolIndex::new(service);
+        index.apply(&IndexChange::Changed(path.clone())).unwrap();
+        assert_eq!(index.find("gone").len(), 1);
+
+        std::fs::remove_file(&path).unwrap();
+        index.apply(&IndexChange::Removed(path.clone())).unwrap();
+        assert!(index.symbols(&path).is_none());
+        assert_eq!(index.file_count(), 0);
+    }
+
+    #[test]
+    fn test_samusignore_keeps_files_out_of_index() {
+        let temp = TempDir::new().unwrap();
+        std::fs::create_dir(temp.path().join("vendor")).unwrap();
+        let kept = temp.path().join("lib.rs");
+        let skipped = temp.path().join("vendor/dep.rs");
+        std::fs::write(&kept, "fn kept() {}\n").unwrap();
+        std::fs::write(&skipped, "fn skipped() {}\n").unwrap();
+        std::fs::write(temp.path().join(".samusignore"), "vendor/\n").unwrap();
+
+        let service = Arc::new(TreeSitterService::new(1024 * 1024, 2));
+        let mut index =
+            SymbolIndex::new(service).with_samusignore(SamusIgnore::load(temp.path()));
+        index.apply(&IndexChange::Changed(kept)).unwrap();
+        index.apply(&IndexChange::Changed(skipped)).unwrap();
+
+        assert_eq!(index.find("kept").len(), 1);
+        assert!(index.find("skipped").is_empty());
+        assert_eq!(index.file_count(), 1);
+    }
+}
@@ -0,0 +1,163 @@
+//! Helpers for text that may contain ANSI escape sequences
+
+/// Sequence that resets all colours and text attributes
+pub const RESET: &str = "\x1b[0m";
+
+const ESC: u8 = 0x1b;
+const BEL: u8 = 0x07;
+
+/// Byte length of the escape sequence starting at `start`, which must point at an ESC.
+///
+/// Handles CSI (`ESC [ ... final`), OSC (`ESC ] ... BEL` or `ESC ] ... ESC \`) and
+/// two-byte escapes. An unterminated sequence runs to the end of the input.
+fn escape_len(bytes: &[u8], start: usize) -> usize {
+    let mut i = start + 1;
+    match bytes.get(i) {
+        Some(b'[') => {
+            i += 1;
+            while let Some(&b) = bytes.get(i) {
+                i += 1;
+                if (0x40..=0x7e).contains(&b) {
+                    break;
+                }
+            }
+        }
+        Some(b']') => {
+            i += 1;
+            while let Some(&b) = bytes.get(i) {
+                if b == BEL {
+                    i += 1;
+                    break;
+                }
+                if b == ESC && bytes.get(i + 1) == Some(&b'\\') {
+                    i += 2;
+                    break;
+                }
+                i += 1;
+            }
+        }
+        Some(b) if b.is_ascii() => i += 1,
+        _ => {}
+    }
+    i - start
+}
+
+/// Number of visible characters in `text`, ignoring escape sequences
+pub fn visible_width(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    let mut width = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == ESC {
+            i += escape_len(bytes, i);
+        } else {
+            let ch_len = text[i..].chars().next().map_or(1, char::len_utf8);
+            i += ch_len;
+            width += 1;
+        }
+    }
+    width
+}
+
+/// `text` with every escape sequence removed
+pub fn strip(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut plain = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == ESC {
+            i += escape_len(bytes, i);
+        } else {
+            let ch = text[i..].chars().next().unwrap();
+            plain.push(ch);
+            i += ch.len_utf8();
+        }
+    }
+    plain
+}
+
+/// Byte offset where `text` must be cut to keep at most `max_width` visible characters.
+///
+/// The offset always lands on a char boundary and never inside an escape sequence.
+pub fn visible_prefix_end(text: &str, max_width: usize) -> usize {
+    let bytes = text.as_bytes();
+    let mut width = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == ESC {
+            i += escape_len(bytes, i);
+        } else {
+            if width == max_width {
+                break;
+            }
+            i += text[i..].chars().next().map_or(1, char::len_utf8);
+            width += 1;
+        }
+    }
+    i
+}
+
+/// Shorten `text` to `max_width` visible characters, ending with `marker`.
+///
+/// Escape sequences don't count towards the width and are never split. If the kept
+/// text contains any escape codes a reset is appended so styles don't leak past it.
+pub fn truncate_visible(text: &str, max_width: usize, marker: &str) -> String {
+    if visible_width(text) <= max_width {
+        return text.to_string();
+    }
+
+    let keep = max_width.saturating_sub(visible_width(marker));
+    let kept = &text[..visible_prefix_end(text, keep)];
+    let mut truncated = kept.to_string();
+    if kept.as_bytes().contains(&ESC) {
+        truncated.push_str(RESET);
+    }
+    truncated.push_str(marker);
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_visible_width_ignores_escapes() {
+        assert_eq!(visible_width("plain"), 5);
+        assert_eq!(visible_width("\x1b[1;31mred\x1b[0m"), 3);
+        assert_eq!(visible_width("\x1b]0;title\x07héllo"), 5);
+    }
+
+    #[test]
+    fn test_strip_removes_escapes() {
+        assert_eq!(strip("plain"), "plain");
+        assert_eq!(strip("\x1b[1;31mréd\x1b[0m \x1b]0;title\x07ok\x1b[K"), "réd ok");
+    }
+
+    #[test]
+    fn test_truncate_plain_text() {
+        assert_eq!(truncate_visible("short", 10, "..."), "short");
+        assert_eq!(truncate_visible("abcdefghij", 6, "..."), "abc...");
+        assert_eq!(truncate_visible("héllo wörld", 6, "…"), "héllo…");
+    }
+
+    #[test]
+    fn test_truncate_inside_escape_code() {
+        // Byte-based truncation at 8 would land in the middle of "\x1b[1;31m"
+        let line = "ab\x1b[1;31mcdefgh\x1b[0mij";
+        let truncated = truncate_visible(line, 8, "...");
+
+        assert_eq!(truncated, "ab\x1b[1;31mcde\x1b[0m...");
+        assert_eq!(visible_width(&truncated), 8);
+
+        // Cutting right before the colour starts keeps the escape but still resets
+        let truncated = truncate_visible(line, 5, "...");
+        assert_eq!(truncated, "ab\x1b[1;31m\x1b[0m...");
+    }
+
+    #[test]
+    fn test_unterminated_escape_is_not_split() {
+        let line = "abcdef\x1b[38;5;";
+        assert_eq!(visible_prefix_end(line, 6), line.len());
+        assert_eq!(visible_width(line), 6);
+    }
+}
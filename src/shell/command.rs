@@ -1,10 +1,12 @@
+use crate::context::CancellationToken;
 use crate::error::TaskError;
 use crate::ui::OutputManager;
+use crate::ui::output::BufferWriter;
 use std::path::PathBuf;
-use std::process::{Command, Stdio};
-use std::time::Duration;
-use tokio::io::{AsyncBufReadExt, BufReader};
-use tokio::process::Command as AsyncCommand;
+use std::process::{Command, ExitStatus, Stdio};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::{Child, Command as AsyncCommand};
 use tokio::sync::mpsc;
 use tokio::time::timeout;
 
@@ -18,10 +20,199 @@ pub struct ShellCommandResult {
     pub stdout: String,
     pub stderr: String,
     pub timed_out: bool,
+    /// Whether the command was cancelled and sent SIGINT
+    pub interrupted: bool,
 }
 
+/// Output lines of a command started by `spawn_streaming`, and the handle its result
+/// arrives on once it exits
+pub type StreamingCommand = (
+    mpsc::Receiver<String>,
+    tokio::task::JoinHandle<Result<ShellCommandResult, TaskError>>,
+);
+
 /// Default command timeout in seconds
-const DEFAULT_TIMEOUT: u64 = 300;
+pub const DEFAULT_TIMEOUT: u64 = 300;
+
+/// How long an interrupted command gets to exit after SIGINT before it's killed
+const INTERRUPT_GRACE: Duration = Duration::from_secs(2);
+
+/// How often a running command checks for cancellation
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How a command we were waiting on finished
+enum WaitOutcome {
+    Exited(ExitStatus),
+    Interrupted(ExitStatus),
+    TimedOut,
+}
+
+/// Send `signal` to the process group led by `pid`. Commands are started as group
+/// leaders, so this reaches the command and anything it started.
+#[cfg(target_family = "unix")]
+fn signal_group(pid: u32, signal: libc::c_int) -> std::io::Result<()> {
+    // A negative pid signals the whole group
+    let rc = unsafe { libc::kill(-(pid as libc::pid_t), signal) };
+    if rc == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+/// Ask a running child to stop. On Unix this sends SIGINT to the child's process
+/// group, so the program and anything it started can clean up as they would after
+/// Ctrl+C in a terminal.
+#[cfg(target_family = "unix")]
+pub fn interrupt_child(child: &mut Child) -> std::io::Result<()> {
+    // No pid means the child has already been reaped
+    match child.id() {
+        Some(pid) => signal_group(pid, libc::SIGINT),
+        None => Ok(()),
+    }
+}
+
+/// Without process groups to signal, cancellation kills the child
+#[cfg(not(target_family = "unix"))]
+pub fn interrupt_child(child: &mut Child) -> std::io::Result<()> {
+    child.start_kill()
+}
+
+/// SIGKILL an async child's process group, so nothing it started lives on holding its
+/// output pipes open. Errors are ignored since the group may already be gone.
+#[cfg(target_family = "unix")]
+fn kill_child_group(child: &Child) {
+    if let Some(pid) = child.id() {
+        let _ = signal_group(pid, libc::SIGKILL);
+    }
+}
+
+#[cfg(not(target_family = "unix"))]
+fn kill_child_group(_child: &Child) {}
+
+/// `interrupt_child` for a blocking child. Errors are ignored since it may have exited.
+fn interrupt_blocking_child(child: &mut std::process::Child) {
+    #[cfg(target_family = "unix")]
+    let _ = signal_group(child.id(), libc::SIGINT);
+    #[cfg(not(target_family = "unix"))]
+    let _ = child.kill();
+}
+
+/// `kill_child_group` for a blocking child
+fn kill_blocking_child(child: &mut std::process::Child) {
+    #[cfg(target_family = "unix")]
+    let _ = signal_group(child.id(), libc::SIGKILL);
+    #[cfg(not(target_family = "unix"))]
+    let _ = child.kill();
+}
+
+/// Everything left in a child's output pipe
+fn read_pipe(pipe: Option<impl std::io::Read>) -> Vec<u8> {
+    let mut buf = Vec::new();
+    if let Some(mut pipe) = pipe {
+        let _ = pipe.read_to_end(&mut buf);
+    }
+    buf
+}
+
+/// `value` quoted for a POSIX shell, left bare when that's unambiguous
+fn shell_quote(value: &str) -> String {
+    let bare = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c));
+    if bare {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+/// Split a timeout prefix such as `30s` or `10m` off a `!command`, e.g. `!600s make`.
+/// Returns the timeout in seconds, if there was one, and the command itself.
+pub fn parse_timeout_prefix(input: &str) -> (Option<u64>, &str) {
+    let input = input.trim_start();
+    let Some((first, rest)) = input.split_once(char::is_whitespace) else {
+        return (None, input);
+    };
+    let secs = if let Some(n) = first.strip_suffix('s') {
+        n.parse::<u64>().ok()
+    } else if let Some(n) = first.strip_suffix('m') {
+        n.parse::<u64>().ok().and_then(|n| n.checked_mul(60))
+    } else {
+        None
+    };
+
+    match secs {
+        Some(secs) if secs > 0 && !rest.trim().is_empty() => (Some(secs), rest.trim_start()),
+        _ => (None, input),
+    }
+}
+
+/// The next piece of streamed output, ended by `\n` or `\r`, and whether it was a lone
+/// `\r`, which progress bars use to redraw their line. `None` at the end of the stream.
+async fn next_segment<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+) -> std::io::Result<Option<(String, bool)>> {
+    let mut bytes = Vec::new();
+    loop {
+        let available = reader.fill_buf().await?;
+        if available.is_empty() {
+            let rest = String::from_utf8_lossy(&bytes).into_owned();
+            return Ok((!bytes.is_empty()).then_some((rest, false)));
+        }
+
+        let Some(end) = available.iter().position(|b| *b == b'\n' || *b == b'\r') else {
+            let len = available.len();
+            bytes.extend_from_slice(available);
+            reader.consume(len);
+            continue;
+        };
+        let mut carriage_return = available[end] == b'\r';
+        bytes.extend_from_slice(&available[..end]);
+        reader.consume(end + 1);
+
+        // `\r\n` is just a line ending
+        if carriage_return && reader.fill_buf().await?.first() == Some(&b'\n') {
+            reader.consume(1);
+            carriage_return = false;
+        }
+        return Ok(Some((String::from_utf8_lossy(&bytes).into_owned(), carriage_return)));
+    }
+}
+
+/// Read `pipe` to the end into `writer`, as a terminal would show it
+async fn collect_output(pipe: Option<impl AsyncRead + Unpin>, writer: BufferWriter) {
+    let Some(pipe) = pipe else {
+        return;
+    };
+    let mut reader = BufReader::new(pipe);
+    while let Ok(Some((line, progress))) = next_segment(&mut reader).await {
+        if progress {
+            writer.push_progress(line);
+        } else {
+            writer.push(line);
+        }
+    }
+}
+
+/// The lines collected in `id`, each ended by a newline, after a note of how many
+/// earlier lines were dropped from it
+fn buffered_text(buffers: &OutputManager, id: uuid::Uuid) -> String {
+    let mut text = match buffers.buffer_evicted_lines(id) {
+        0 => String::new(),
+        evicted => format!("[{} earlier lines of output dropped]\n", evicted),
+    };
+    for line in buffers.buffer_lines(id) {
+        text.push_str(&line);
+        text.push('\n');
+    }
+    text
+}
+
+fn wait_error(e: std::io::Error) -> TaskError {
+    TaskError::ExecutionFailed(format!("Failed to wait for command: {}", e))
+}
 
 /// Platform-specific shell detection
 #[cfg(target_family = "unix")]
@@ -39,6 +230,7 @@ fn detect_shell() -> (String, Vec<String>) {
 }
 
 /// A shell command that can be executed.
+#[derive(Clone)]
 pub struct ShellCommand {
     program: String,
     args: Vec<String>,
@@ -46,6 +238,7 @@ pub struct ShellCommand {
     env_vars: Vec<(String, String)>,
     timeout_secs: u64,
     use_shell: bool,
+    cancel: Option<CancellationToken>,
 }
 
 impl ShellCommand {
@@ -57,6 +250,7 @@ impl ShellCommand {
             env_vars: Vec::new(),
             timeout_secs: DEFAULT_TIMEOUT,
             use_shell: false,
+            cancel: None,
         }
     }
 
@@ -90,24 +284,56 @@ impl ShellCommand {
         self
     }
 
-    /// Execute the command and return the result.
-    pub fn execute(&self) -> Result<ShellCommandResult, TaskError> {
-        let (mut cmd, args) = if self.use_shell {
+    /// Interrupt the command with SIGINT once `token` is cancelled
+    pub fn cancel_token(mut self, token: CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Program and arguments as one line, for running through a shell
+    fn joined_command(&self) -> String {
+        let mut full_command = self.program.clone();
+        for arg in &self.args {
+            full_command.push(' ');
+            full_command.push_str(arg);
+        }
+        full_command
+    }
+
+    /// The command as it would be typed into a POSIX shell, including the working
+    /// directory and environment overrides, without running anything
+    pub fn command_line(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(dir) = &self.working_dir {
+            parts.push(format!("cd {} &&", shell_quote(&dir.to_string_lossy())));
+        }
+        for (key, value) in &self.env_vars {
+            parts.push(format!("{}={}", key, shell_quote(value)));
+        }
+        if self.use_shell {
             let (shell, shell_args) = detect_shell();
-            let mut full_command = self.program.clone();
-            for arg in &self.args {
-                full_command.push(' ');
-                full_command.push_str(arg);
-            }
+            parts.push(shell_quote(&shell));
+            parts.extend(shell_args.iter().map(|arg| shell_quote(arg)));
+            parts.push(shell_quote(&self.joined_command()));
+        } else {
+            parts.push(shell_quote(&self.program));
+            parts.extend(self.args.iter().map(|arg| shell_quote(arg)));
+        }
+        parts.join(" ")
+    }
 
-            let cmd = Command::new(shell);
-            let mut args = shell_args;
-            args.push(full_command);
-            (cmd, args)
+    /// Async command with piped output. On Unix the child gets its own process group,
+    /// so a cancellation can signal it without touching the app.
+    fn async_command(&self) -> AsyncCommand {
+        let (shell_cmd, args) = if self.use_shell {
+            let (shell, mut shell_args) = detect_shell();
+            shell_args.push(self.joined_command());
+            (shell, shell_args)
         } else {
-            (Command::new(&self.program), self.args.clone())
+            (self.program.clone(), self.args.clone())
         };
 
+        let mut cmd = AsyncCommand::new(shell_cmd);
         cmd.args(args);
 
         if let Some(ref dir) = self.working_dir {
@@ -121,42 +347,97 @@ impl ShellCommand {
         cmd.stdout(Stdio::piped());
         cmd.stderr(Stdio::piped());
 
-        let output = cmd
-            .output()
+        #[cfg(target_family = "unix")]
+        cmd.process_group(0);
+
+        cmd
+    }
+
+    /// Wait for `child`, killing it on timeout and interrupting it on cancellation
+    async fn wait_for_exit(&self, child: &mut Child) -> Result<WaitOutcome, TaskError> {
+        let deadline = Instant::now() + Duration::from_secs(self.timeout_secs);
+
+        loop {
+            if let Ok(status) = timeout(CANCEL_POLL_INTERVAL, child.wait()).await {
+                return status.map(WaitOutcome::Exited).map_err(wait_error);
+            }
+
+            if self.cancel.as_ref().is_some_and(|token| token.is_cancelled()) {
+                let _ = interrupt_child(child);
+                let status = match timeout(INTERRUPT_GRACE, child.wait()).await {
+                    Ok(status) => status.map_err(wait_error)?,
+                    Err(_) => {
+                        // Ignored SIGINT; stop it for good
+                        kill_child_group(child);
+                        let _ = child.kill().await;
+                        child.wait().await.map_err(wait_error)?
+                    }
+                };
+                return Ok(WaitOutcome::Interrupted(status));
+            }
+
+            if Instant::now() >= deadline {
+                kill_child_group(child);
+                let _ = child.kill().await;
+                return Ok(WaitOutcome::TimedOut);
+            }
+        }
+    }
+
+    /// Run the command to completion without blocking the runtime, collecting its output.
+    /// Honors the timeout and the cancellation token.
+    pub async fn execute_async(&self) -> Result<ShellCommandResult, TaskError> {
+        let mut child = self
+            .async_command()
+            .spawn()
             .map_err(|e| TaskError::ExecutionFailed(format!("Failed to execute command: {}", e)))?;
 
-        let result = ShellCommandResult {
-            exit_code: output.status.code(),
-            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
-            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
-            timed_out: false,
+        // Drain both pipes while waiting so a chatty child can't fill one and stall. The
+        // buffers keep only the newest lines of a runaway command such as `yes`, and
+        // progress bar redraws collapse into one line.
+        let buffers = OutputManager::new();
+        let (stdout_id, stderr_id) = (buffers.create_buffer(), buffers.create_buffer());
+        let stdout_task = tokio::spawn(collect_output(
+            child.stdout.take(),
+            buffers.buffer_writer(stdout_id),
+        ));
+        let stderr_task = tokio::spawn(collect_output(
+            child.stderr.take(),
+            buffers.buffer_writer(stderr_id),
+        ));
+
+        let outcome = self.wait_for_exit(&mut child).await?;
+        let _ = stdout_task.await;
+        let _ = stderr_task.await;
+
+        let (exit_code, timed_out, interrupted) = match outcome {
+            WaitOutcome::Exited(status) => (status.code(), false, false),
+            WaitOutcome::Interrupted(status) => (status.code(), false, true),
+            WaitOutcome::TimedOut => (None, true, false),
         };
 
-        Ok(result)
+        Ok(ShellCommandResult {
+            exit_code,
+            stdout: buffered_text(&buffers, stdout_id),
+            stderr: buffered_text(&buffers, stderr_id),
+            timed_out,
+            interrupted,
+        })
     }
 
-    /// Execute the command asynchronously and stream the output.
-    pub async fn execute_streaming(
-        &self,
-        output_mgr: Option<&OutputManager>,
-        buffer_id: Option<uuid::Uuid>,
-    ) -> Result<(mpsc::Receiver<String>, ShellCommandResult), TaskError> {
-        let (shell_cmd, args) = if self.use_shell {
+    /// Execute the command and return the result, blocking the calling thread.
+    /// Honors the timeout and the cancellation token, like `execute_async`.
+    pub fn execute(&self) -> Result<ShellCommandResult, TaskError> {
+        let (mut cmd, args) = if self.use_shell {
             let (shell, shell_args) = detect_shell();
-            let mut full_command = self.program.clone();
-            for arg in &self.args {
-                full_command.push(' ');
-                full_command.push_str(arg);
-            }
-
+            let cmd = Command::new(shell);
             let mut args = shell_args;
-            args.push(full_command);
-            (shell, args)
+            args.push(self.joined_command());
+            (cmd, args)
         } else {
-            (self.program.clone(), self.args.clone())
+            (Command::new(&self.program), self.args.clone())
         };
 
-        let mut cmd = AsyncCommand::new(shell_cmd);
         cmd.args(args);
 
         if let Some(ref dir) = self.working_dir {
@@ -170,10 +451,106 @@ impl ShellCommand {
         cmd.stdout(Stdio::piped());
         cmd.stderr(Stdio::piped());
 
+        // Own process group, so a timeout can stop anything the command started too
+        #[cfg(target_family = "unix")]
+        std::os::unix::process::CommandExt::process_group(&mut cmd, 0);
+
         let mut child = cmd
             .spawn()
             .map_err(|e| TaskError::ExecutionFailed(format!("Failed to execute command: {}", e)))?;
 
+        // Drain both pipes on their own threads so a chatty child can't fill one and stall
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+        let stdout_reader = std::thread::spawn(move || read_pipe(stdout));
+        let stderr_reader = std::thread::spawn(move || read_pipe(stderr));
+
+        let outcome = self.wait_for_exit_blocking(&mut child)?;
+        let stdout = stdout_reader.join().unwrap_or_default();
+        let stderr = stderr_reader.join().unwrap_or_default();
+
+        let (exit_code, timed_out, interrupted) = match outcome {
+            WaitOutcome::Exited(status) => (status.code(), false, false),
+            WaitOutcome::Interrupted(status) => (status.code(), false, true),
+            WaitOutcome::TimedOut => (None, true, false),
+        };
+
+        Ok(ShellCommandResult {
+            exit_code,
+            stdout: String::from_utf8_lossy(&stdout).to_string(),
+            stderr: String::from_utf8_lossy(&stderr).to_string(),
+            timed_out,
+            interrupted,
+        })
+    }
+
+    /// Blocking counterpart of `wait_for_exit`, for a `std::process::Child`
+    fn wait_for_exit_blocking(
+        &self,
+        child: &mut std::process::Child,
+    ) -> Result<WaitOutcome, TaskError> {
+        let deadline = Instant::now() + Duration::from_secs(self.timeout_secs);
+        // Start polling quickly so short commands return promptly
+        let mut poll = Duration::from_millis(1);
+
+        loop {
+            if let Some(status) = child.try_wait().map_err(wait_error)? {
+                return Ok(WaitOutcome::Exited(status));
+            }
+
+            if self.cancel.as_ref().is_some_and(|token| token.is_cancelled()) {
+                interrupt_blocking_child(child);
+                let grace_deadline = Instant::now() + INTERRUPT_GRACE;
+                while Instant::now() < grace_deadline {
+                    if let Some(status) = child.try_wait().map_err(wait_error)? {
+                        return Ok(WaitOutcome::Interrupted(status));
+                    }
+                    std::thread::sleep(CANCEL_POLL_INTERVAL);
+                }
+                // Ignored SIGINT; stop it for good
+                kill_blocking_child(child);
+                let status = child.wait().map_err(wait_error)?;
+                return Ok(WaitOutcome::Interrupted(status));
+            }
+
+            if Instant::now() >= deadline {
+                kill_blocking_child(child);
+                let _ = child.wait();
+                return Ok(WaitOutcome::TimedOut);
+            }
+
+            std::thread::sleep(poll);
+            poll = (poll * 2).min(CANCEL_POLL_INTERVAL);
+        }
+    }
+
+    /// Execute the command asynchronously and stream the output.
+    pub async fn execute_streaming(
+        &self,
+        output_mgr: Option<&OutputManager>,
+        buffer_id: Option<uuid::Uuid>,
+    ) -> Result<(mpsc::Receiver<String>, ShellCommandResult), TaskError> {
+        let (rx, wait) = self.spawn_streaming(output_mgr, buffer_id)?;
+        let result = wait.await.map_err(|e| {
+            TaskError::ExecutionFailed(format!("Command wait task failed: {}", e))
+        })??;
+        Ok((rx, result))
+    }
+
+    /// Start the command and stream its output without waiting for it to finish.
+    ///
+    /// The returned handle resolves to the result once the command exits. Must be called
+    /// within a Tokio runtime.
+    pub fn spawn_streaming(
+        &self,
+        output_mgr: Option<&OutputManager>,
+        buffer_id: Option<uuid::Uuid>,
+    ) -> Result<StreamingCommand, TaskError> {
+        let mut child = self
+            .async_command()
+            .spawn()
+            .map_err(|e| TaskError::ExecutionFailed(format!("Failed to execute command: {}", e)))?;
+
         let stdout = child
             .stdout
             .take()
@@ -187,19 +564,25 @@ impl ShellCommand {
         let (tx, rx) = mpsc::channel(100);
         let tx_clone = tx.clone();
 
-        // Set up output handling
-        let output_sender = if let (Some(mgr), Some(_id)) = (output_mgr, buffer_id) {
-            Some(mgr.get_sender())
+        // Set up output handling; the buffer writer keeps lines within the manager's limits
+        let output_sender = if let (Some(mgr), Some(id)) = (output_mgr, buffer_id) {
+            Some((mgr.get_sender(), mgr.buffer_writer(id)))
         } else {
             None
         };
 
         // Spawn a task to read stdout
-        if let Some(sender) = output_sender.clone() {
+        if let Some((sender, writer)) = output_sender.clone() {
             let buffer_id = buffer_id.unwrap();
             tokio::spawn(async move {
-                let mut reader = BufReader::new(stdout).lines();
-                while let Ok(Some(line)) = reader.next_line().await {
+                let mut reader = BufReader::new(stdout);
+                while let Ok(Some((line, progress))) = next_segment(&mut reader).await {
+                    // Progress redraws only update the buffer; listeners get whole lines
+                    if progress {
+                        writer.push_progress(line);
+                        continue;
+                    }
+                    writer.push(line.clone());
                     let _ = tx.send(line.clone()).await;
                     if let Some(ref sender) = sender {
                         let _ = sender
@@ -218,11 +601,16 @@ impl ShellCommand {
         }
 
         // Spawn a task to read stderr
-        if let Some(sender) = output_sender {
+        if let Some((sender, writer)) = output_sender {
             let buffer_id = buffer_id.unwrap();
             tokio::spawn(async move {
-                let mut reader = BufReader::new(stderr).lines();
-                while let Ok(Some(line)) = reader.next_line().await {
+                let mut reader = BufReader::new(stderr);
+                while let Ok(Some((line, progress))) = next_segment(&mut reader).await {
+                    if progress {
+                        writer.push_progress(line);
+                        continue;
+                    }
+                    writer.push(line.clone());
                     let _ = tx_clone.send(line.clone()).await;
                     if let Some(ref sender) = sender {
                         let _ = sender
@@ -240,66 +628,39 @@ impl ShellCommand {
             });
         }
 
-        // Wait for the command to complete with timeout
-        let status = match timeout(Duration::from_secs(self.timeout_secs), child.wait()).await {
-            Ok(result) => result.map_err(|e| {
-                TaskError::ExecutionFailed(format!("Failed to wait for command: {}", e))
-            })?,
-            Err(_) => {
-                let _ = child.kill().await;
-                return Ok((
-                    rx,
-                    ShellCommandResult {
+        // Wait for the command to complete, honoring the timeout and cancellation
+        let command = self.clone();
+        let wait = tokio::spawn(async move {
+            let (status, interrupted) = match command.wait_for_exit(&mut child).await? {
+                WaitOutcome::Exited(status) => (status, false),
+                WaitOutcome::Interrupted(status) => (status, true),
+                WaitOutcome::TimedOut => {
+                    return Ok(ShellCommandResult {
                         exit_code: None,
                         stdout: String::new(),
                         stderr: String::new(),
                         timed_out: true,
-                    },
-                ));
-            }
-        };
-
-        let result = ShellCommandResult {
-            exit_code: status.code(),
-            stdout: String::new(), // Content is streamed via the channel
-            stderr: String::new(), // Content is streamed via the channel
-            timed_out: false,
-        };
+                        interrupted: false,
+                    });
+                }
+            };
+
+            Ok(ShellCommandResult {
+                exit_code: status.code(),
+                stdout: String::new(), // Content is streamed via the channel
+                stderr: String::new(), // Content is streamed via the channel
+                timed_out: false,
+                interrupted,
+            })
+        });
 
-        Ok((rx, result))
+        Ok((rx, wait))
     }
 
     /// Spawn an asynchronous command and return the child process handle
     pub fn spawn(&self) -> Result<tokio::process::Child, TaskError> {
-        let (shell_cmd, args) = if self.use_shell {
-            let (shell, mut shell_args) = detect_shell();
-            let mut full_command = self.program.clone();
-            for arg in &self.args {
-                full_command.push(' ');
-                full_command.push_str(arg);
-            }
-
-            shell_args.push(full_command);
-            (shell, shell_args)
-        } else {
-            (self.program.clone(), self.args.clone())
-        };
-
-        let mut cmd = AsyncCommand::new(shell_cmd);
-        cmd.args(args);
-
-        if let Some(ref dir) = self.working_dir {
-            cmd.current_dir(dir);
-        }
-
-        for (key, value) in &self.env_vars {
-            cmd.env(key, value);
-        }
-
-        cmd.stdout(Stdio::piped());
-        cmd.stderr(Stdio::piped());
-
-        cmd.spawn()
+        self.async_command()
+            .spawn()
             .map_err(|e| TaskError::ExecutionFailed(format!("Failed to spawn command: {}", e)))
     }
 }
@@ -307,6 +668,7 @@ impl ShellCommand {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ui::output::{DEFAULT_MAX_BUFFER_LINES, OutputLimits};
     use std::time::Instant;
     use tokio::runtime::Runtime;
 
@@ -331,6 +693,41 @@ mod tests {
         assert_eq!(cmd.stdout.trim(), "test_value");
     }
 
+    #[test]
+    fn test_next_segment_splits_progress_redraws() {
+        let rt = Runtime::new().unwrap();
+        let segments = rt.block_on(async {
+            let mut reader: &[u8] = b"start\r\n10%\r60%\rdone\ntail";
+            let mut segments = Vec::new();
+            while let Some(segment) = next_segment(&mut reader).await.unwrap() {
+                segments.push(segment);
+            }
+            segments
+        });
+        let expected = [
+            ("start", false),
+            ("10%", true),
+            ("60%", true),
+            ("done", false),
+            ("tail", false),
+        ];
+        assert_eq!(
+            segments,
+            expected.map(|(text, progress)| (text.to_string(), progress))
+        );
+    }
+
+    #[test]
+    fn test_parse_timeout_prefix() {
+        assert_eq!(parse_timeout_prefix("30s make test"), (Some(30), "make test"));
+        assert_eq!(parse_timeout_prefix(" 10m  cargo build"), (Some(600), "cargo build"));
+        // Not a timeout: no command after it, zero, or just a word ending in s/m
+        assert_eq!(parse_timeout_prefix("30s"), (None, "30s"));
+        assert_eq!(parse_timeout_prefix("0s ls"), (None, "0s ls"));
+        assert_eq!(parse_timeout_prefix("ls -la"), (None, "ls -la"));
+        assert_eq!(parse_timeout_prefix("rm stale.txt"), (None, "rm stale.txt"));
+    }
+
     #[test]
     fn test_command_timeout() {
         let rt = Runtime::new().unwrap();
@@ -350,6 +747,121 @@ mod tests {
         assert!(elapsed.as_secs() < 5);
     }
 
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn test_cancel_interrupts_sleep() {
+        let rt = Runtime::new().unwrap();
+        let token = CancellationToken::new();
+        let canceller = token.clone();
+
+        let start = Instant::now();
+        let result = rt.block_on(async {
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                canceller.cancel();
+            });
+            ShellCommand::new("sleep")
+                .arg("30")
+                .cancel_token(token)
+                .execute_async()
+                .await
+                .unwrap()
+        });
+
+        // Exited on SIGINT itself, well before the grace period would have killed it
+        assert!(result.interrupted);
+        assert!(!result.timed_out);
+        assert_eq!(result.exit_code, None);
+        assert!(start.elapsed() < INTERRUPT_GRACE, "{:?}", start.elapsed());
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn test_cancel_lets_child_handle_sigint() {
+        let rt = Runtime::new().unwrap();
+        let token = CancellationToken::new();
+        let canceller = token.clone();
+
+        let result = rt.block_on(async {
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                canceller.cancel();
+            });
+            ShellCommand::new("trap 'echo cleaned up; exit 3' INT; sleep 30")
+                .use_shell(true)
+                .cancel_token(token)
+                .execute_async()
+                .await
+                .unwrap()
+        });
+
+        assert!(result.interrupted);
+        assert_eq!(result.exit_code, Some(3));
+        assert_eq!(result.stdout.trim(), "cleaned up");
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn test_collected_output_is_capped() {
+        let rt = Runtime::new().unwrap();
+        let result = rt.block_on(async {
+            ShellCommand::new("yes | head -n 20000")
+                .use_shell(true)
+                .execute_async()
+                .await
+                .unwrap()
+        });
+
+        let lines: Vec<&str> = result.stdout.lines().collect();
+        assert_eq!(lines.len(), DEFAULT_MAX_BUFFER_LINES + 1);
+        assert_eq!(lines[0], "[10000 earlier lines of output dropped]");
+        assert!(lines[1..].iter().all(|line| *line == "y"));
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn test_dropped_lines_are_noted_on_their_own_stream() {
+        let rt = Runtime::new().unwrap();
+        let result = rt.block_on(async {
+            ShellCommand::new("yes | head -n 20000 >&2; echo done")
+                .use_shell(true)
+                .execute_async()
+                .await
+                .unwrap()
+        });
+
+        assert_eq!(result.stdout, "done\n");
+        let stderr: Vec<&str> = result.stderr.lines().collect();
+        assert_eq!(stderr.len(), DEFAULT_MAX_BUFFER_LINES + 1);
+        assert_eq!(stderr[0], "[10000 earlier lines of output dropped]");
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn test_collected_output_collapses_progress_redraws() {
+        let rt = Runtime::new().unwrap();
+        let result = rt.block_on(async {
+            ShellCommand::new("printf '10%%\\r50%%\\r100%%\\ndone\\r\\n'")
+                .use_shell(true)
+                .execute_async()
+                .await
+                .unwrap()
+        });
+        assert_eq!(result.stdout, "100%\ndone\n");
+    }
+
+    #[test]
+    fn test_command_line_quotes_what_the_shell_would_split() {
+        let cmd = ShellCommand::new("grep")
+            .args(&["-rn", "it's here", "src/"])
+            .working_dir(PathBuf::from("/tmp/my project"))
+            .env("RUST_LOG", "debug");
+        assert_eq!(
+            cmd.command_line(),
+            "cd '/tmp/my project' && RUST_LOG=debug grep -rn 'it'\\''s here' src/"
+        );
+    }
+
     #[test]
     fn test_shell_command() {
         let cmd = ShellCommand::new("echo $PATH")
@@ -447,4 +959,36 @@ mod tests {
             assert!(lines.iter().any(|line| line.contains("error")));
         });
     }
+
+    #[test]
+    fn test_streaming_buffer_stays_within_cap() {
+        let rt = Runtime::new().unwrap();
+
+        rt.block_on(async {
+            let output_mgr = OutputManager::new().with_limits(OutputLimits {
+                max_lines_per_buffer: 500,
+                ..OutputLimits::default()
+            });
+            let buffer_id = output_mgr.create_buffer();
+
+            let (mut rx, handle) = ShellCommand::new("seq")
+                .arg("1")
+                .arg("50000")
+                .spawn_streaming(Some(&output_mgr), Some(buffer_id))
+                .unwrap();
+
+            // The channel closes once the readers have pushed every line
+            let mut streamed = 0;
+            while rx.recv().await.is_some() {
+                streamed += 1;
+            }
+            assert_eq!(streamed, 50_000);
+            assert_eq!(handle.await.unwrap().unwrap().exit_code, Some(0));
+
+            let lines = output_mgr.buffer_lines(buffer_id);
+            assert_eq!(lines.len(), 500);
+            assert_eq!(lines.last().map(String::as_str), Some("50000"));
+            assert_eq!(output_mgr.stats().evicted_lines, 49_500);
+        });
+    }
 }
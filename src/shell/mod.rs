@@ -1,3 +1,4 @@
+pub mod ansi;
 pub mod command;
 pub mod terminal;
 
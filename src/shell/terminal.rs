@@ -5,8 +5,9 @@ use crossterm::{
     cursor, execute,
     terminal::{Clear, ClearType, disable_raw_mode, enable_raw_mode},
 };
+use serde::{Deserialize, Serialize};
 use std::io::stdout;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use tokio::sync::{Mutex as AsyncMutex, mpsc};
 use uuid::Uuid;
@@ -24,12 +25,20 @@ impl TerminalInstance {
     }
 }
 
+/// A terminal's title and working directory, as saved between sessions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedTerminal {
+    pub title: String,
+    pub working_dir: PathBuf,
+}
+
 /// Interface for interacting with a terminal.
 pub trait Terminal: Send + Sync {
     /// Execute a command in the terminal.
     fn execute_command(&self, command: ShellCommand) -> Result<ShellCommandResult, TaskError>;
 
-    /// Execute a command and stream its output.
+    /// Start a command and stream its output. Returns as soon as the command is running;
+    /// must be called within a Tokio runtime.
     fn execute_streaming(
         &self,
         command: ShellCommand,
@@ -79,6 +88,66 @@ impl TerminalManager {
         })?;
         Ok(instances.clone())
     }
+
+    /// Working directory of a specific instance
+    pub fn working_directory_of(&self, instance: &TerminalInstance) -> Option<PathBuf> {
+        self.working_dirs.lock().ok()?.get(&instance.id).cloned()
+    }
+
+    /// Titles and working directories of all instances, in the order they were created
+    pub fn snapshot(&self) -> Result<Vec<SavedTerminal>, TaskError> {
+        let instances = self.get_instances()?;
+        Ok(instances
+            .iter()
+            .filter_map(|instance| {
+                Some(SavedTerminal {
+                    title: instance.title.clone(),
+                    working_dir: self.working_directory_of(instance)?,
+                })
+            })
+            .collect())
+    }
+
+    /// Write `snapshot` to `path` as JSON
+    pub fn save_state(&self, path: &Path) -> Result<(), TaskError> {
+        let content = serde_json::to_string_pretty(&self.snapshot()?)
+            .map_err(|e| TaskError::SerializationError(e.to_string()))?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Recreate the terminals saved at `path`, returning the new instances.
+    ///
+    /// A missing file restores nothing. A saved directory that no longer exists falls
+    /// back to the current directory.
+    pub fn restore_state(&self, path: &Path) -> Result<Vec<TerminalInstance>, TaskError> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let saved: Vec<SavedTerminal> = serde_json::from_str(&content).map_err(|e| {
+            TaskError::SerializationError(format!("{}: {}", path.display(), e))
+        })?;
+
+        let mut restored = Vec::with_capacity(saved.len());
+        for terminal in saved {
+            let instance = self.create_instance(terminal.title)?;
+            if terminal.working_dir.is_dir() {
+                let mut working_dirs = self.working_dirs.lock().map_err(|_| {
+                    TaskError::ExecutionFailed(
+                        "Failed to acquire lock for working directories".to_string(),
+                    )
+                })?;
+                working_dirs.insert(instance.id, terminal.working_dir);
+            }
+            restored.push(instance);
+        }
+        Ok(restored)
+    }
 }
 
 impl Terminal for TerminalManager {
@@ -129,9 +198,13 @@ impl Terminal for TerminalManager {
 
         let command = command.working_dir(working_dir.clone());
 
-        let runtime = tokio::runtime::Handle::current();
-        let (rx, _) =
-            runtime.block_on(async { command.execute_streaming(output_mgr, buffer_id).await })?;
+        // Start the command on the current runtime and hand back its output right away,
+        // rather than blocking this thread (or nesting a runtime) until it finishes
+        let runtime = tokio::runtime::Handle::try_current().map_err(|_| {
+            TaskError::ExecutionFailed("Streaming execution needs a Tokio runtime".to_string())
+        })?;
+        let _guard = runtime.enter();
+        let (rx, _) = command.spawn_streaming(output_mgr, buffer_id)?;
 
         Ok(rx)
     }
@@ -224,8 +297,9 @@ impl Terminal for TerminalManager {
             TaskError::ExecutionFailed("Failed to acquire lock for active instance".to_string())
         })?;
 
+        // The screen isn't cleared here: views are redrawn by the TUI, and clearing
+        // would toggle raw mode underneath it
         *active = Some(instance.clone());
-        self.clear_screen()?;
 
         Ok(())
     }
@@ -336,4 +410,80 @@ mod tests {
             assert_eq!(output.trim(), "test");
         });
     }
+
+    #[tokio::test]
+    async fn test_streaming_from_async_task() {
+        let manager = Arc::new(TerminalManager::new());
+        manager.create_instance("Test Terminal".to_string()).unwrap();
+
+        let task_manager = manager.clone();
+        let output = tokio::spawn(async move {
+            let mut rx = task_manager
+                .execute_streaming(ShellCommand::new("echo").arg("from task"), None, None)
+                .unwrap();
+            rx.recv().await
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(output.unwrap().trim(), "from task");
+    }
+
+    #[test]
+    fn test_streaming_without_runtime_errors() {
+        let manager = TerminalManager::new();
+        manager.create_instance("Test Terminal".to_string()).unwrap();
+
+        let result = manager.execute_streaming(ShellCommand::new("echo"), None, None);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_save_and_restore_working_directories() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let build_dir = temp.path().join("build");
+        let gone_dir = temp.path().join("gone");
+        std::fs::create_dir_all(&build_dir).unwrap();
+        std::fs::create_dir_all(&gone_dir).unwrap();
+        let state_file = temp.path().join("state").join("terminals.json");
+
+        let manager = TerminalManager::new();
+        let build = manager.create_instance("build".to_string()).unwrap();
+        manager.set_working_directory(build_dir.clone()).unwrap();
+        let logs = manager.create_instance("logs".to_string()).unwrap();
+        manager.switch_to(&logs).unwrap();
+        manager.set_working_directory(gone_dir.clone()).unwrap();
+        assert_eq!(manager.working_directory_of(&build), Some(build_dir.clone()));
+        manager.save_state(&state_file).unwrap();
+        std::fs::remove_dir(&gone_dir).unwrap();
+
+        let reopened = TerminalManager::new();
+        let restored = reopened.restore_state(&state_file).unwrap();
+        let titles: Vec<&str> = restored.iter().map(|i| i.title.as_str()).collect();
+        assert_eq!(titles, vec!["build", "logs"]);
+        assert_eq!(reopened.working_directory_of(&restored[0]), Some(build_dir));
+        // The deleted directory falls back to the current directory
+        assert_eq!(
+            reopened.working_directory_of(&restored[1]),
+            Some(std::env::current_dir().unwrap())
+        );
+
+        let missing = TerminalManager::new();
+        assert!(missing.restore_state(&temp.path().join("none.json")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_execute_command_times_out() {
+        let manager = TerminalManager::new();
+        manager.create_instance("Test Terminal".to_string()).unwrap();
+
+        let started = std::time::Instant::now();
+        let result = manager
+            .execute_command(ShellCommand::new("sleep").arg("30").timeout(1))
+            .unwrap();
+
+        assert!(result.timed_out);
+        assert_eq!(result.exit_code, None);
+        assert!(started.elapsed() < std::time::Duration::from_secs(10));
+    }
 }
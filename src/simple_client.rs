@@ -2,6 +2,7 @@ use std::io::{self, Write};
 
 use crate::config::McpServerConfig;
 use crate::mcp::client::OpenRouterClient;
+use crate::secret::Secret;
 
 /// A simple CLI client for testing OpenRouter connection
 pub async fn run_simple_client() -> Result<(), Box<dyn std::error::Error>> {
@@ -27,8 +28,11 @@ pub async fn run_simple_client() -> Result<(), Box<dyn std::error::Error>> {
         id: "openrouter".to_string(),
         name: "OpenRouter".to_string(),
         url: "https://openrouter.ai/api/v1/chat/completions".to_string(),
-        api_key: Some(api_key),
+        api_key: Some(Secret::new(api_key)),
         enabled: true,
+        headers: McpServerConfig::default_headers(),
+        requests_per_minute: None,
+        context_token_budget: None,
     };
 
     // Initialize client
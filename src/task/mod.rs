@@ -4,6 +4,7 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::context::CancellationToken;
 use crate::error::TaskError;
 use crate::fs::operations::FileSystem;
 
@@ -34,6 +35,8 @@ pub struct Task {
     pub id: TaskId,
     pub name: String,
     pub params: Value,
+    /// Set to ask the handler to stop early; handlers that can't be interrupted ignore it
+    pub cancel: CancellationToken,
 }
 
 impl Task {
@@ -42,6 +45,7 @@ impl Task {
             id: Uuid::new_v4().to_string(),
             name: name.to_string(),
             params,
+            cancel: CancellationToken::new(),
         }
     }
 }
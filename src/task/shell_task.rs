@@ -2,7 +2,10 @@ use crate::error::TaskError;
 use crate::task::{Task, TaskContext, TaskHandler, TaskResult};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
-use std::process::Command;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::shell::command::{DEFAULT_TIMEOUT, ShellCommand};
 
 #[derive(Debug, Deserialize)]
 #[serde(tag = "type")]
@@ -13,6 +16,18 @@ pub enum ShellTaskRequest {
         args: Option<Vec<String>>,
         #[serde(default)]
         capture_stderr: bool,
+        /// Directory to run in; defaults to the process's current directory
+        #[serde(default)]
+        working_dir: Option<String>,
+        /// Environment variables set for the command on top of the app's own
+        #[serde(default)]
+        env: BTreeMap<String, String>,
+        /// Return the command line that would run, as text, instead of running it
+        #[serde(default)]
+        dry_run: bool,
+        /// Seconds before the command is killed; defaults to `DEFAULT_TIMEOUT`
+        #[serde(default)]
+        timeout_secs: Option<u64>,
     },
     #[serde(rename = "list_directory")]
     ListDirectory {
@@ -27,8 +42,17 @@ pub struct ShellTaskResponse {
     pub output: String,
     pub exit_code: Option<i32>,
     pub success: bool,
+    /// Whether the command was cancelled before it finished
+    #[serde(default)]
+    pub interrupted: bool,
+    /// Whether the command was killed for running past its timeout
+    #[serde(default)]
+    pub timed_out: bool,
 }
 
+/// Seconds a directory listing may run before it's killed
+const LIST_DIRECTORY_TIMEOUT_SECS: u64 = 30;
+
 pub struct ShellTaskHandler;
 
 impl ShellTaskHandler {
@@ -37,10 +61,21 @@ impl ShellTaskHandler {
     }
 }
 
+/// `dir` made absolute against the current directory, which is also the default
+fn resolve_working_dir(dir: Option<String>) -> PathBuf {
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let dir = match dir {
+        Some(dir) => cwd.join(dir),
+        None => cwd,
+    };
+    dir.canonicalize().unwrap_or(dir)
+}
+
 #[async_trait]
 impl TaskHandler for ShellTaskHandler {
     async fn handle_task(&self, task: Task, _ctx: &TaskContext) -> Result<TaskResult, TaskError> {
         // Deserialize the task request
+        let cancel = task.cancel.clone();
         let request: ShellTaskRequest = serde_json::from_value(task.params)
             .map_err(|e| TaskError::InvalidParameter(format!("Invalid parameters: {}", e)))?;
         
@@ -49,53 +84,46 @@ impl TaskHandler for ShellTaskHandler {
                 command,
                 args,
                 capture_stderr,
+                working_dir,
+                env,
+                dry_run,
+                timeout_secs,
             } => {
-                // Build command
-                let args = args.unwrap_or_default();
-                
-                // Run command
-                let output = if cfg!(target_os = "windows") {
-                    let mut cmd = Command::new("cmd");
-                    cmd.arg("/C").arg(&command);
-                    for arg in args {
-                        cmd.arg(arg);
-                    }
-                    if capture_stderr {
-                        cmd.stderr(std::process::Stdio::piped());
-                    }
-                    cmd.output()
-                } else {
-                    let mut cmd = Command::new(&command);
-                    for arg in args {
-                        cmd.arg(arg);
-                    }
-                    if capture_stderr {
-                        cmd.stderr(std::process::Stdio::piped());
-                    }
-                    cmd.output()
-                };
-                
-                match output {
-                    Ok(output) => {
-                        let mut stdout = String::from_utf8_lossy(&output.stdout).to_string();
-                        
-                        if capture_stderr && !output.stderr.is_empty() {
-                            stdout.push_str("\nSTDERR:\n");
-                            stdout.push_str(&String::from_utf8_lossy(&output.stderr));
-                        }
-                        
-                        let response = ShellTaskResponse {
-                            output: stdout,
-                            exit_code: output.status.code(),
-                            success: output.status.success(),
-                        };
-                        
-                        Ok(TaskResult::Json(serde_json::to_value(response).unwrap()))
-                    },
-                    Err(e) => {
-                        Err(TaskError::ExecutionFailed(format!("Failed to execute command: {}", e)))
-                    }
+                // Build command; Windows still goes through cmd /C
+                let mut cmd = ShellCommand::new(&command)
+                    .use_shell(cfg!(target_os = "windows"))
+                    .timeout(timeout_secs.unwrap_or(DEFAULT_TIMEOUT))
+                    .cancel_token(cancel);
+                for arg in args.unwrap_or_default() {
+                    cmd = cmd.arg(&arg);
+                }
+                cmd = cmd.working_dir(resolve_working_dir(working_dir));
+                for (key, value) in &env {
+                    cmd = cmd.env(key, value);
+                }
+
+                if dry_run {
+                    return Ok(TaskResult::Text(cmd.command_line()));
+                }
+
+                // Run without blocking the runtime so a cancel can interrupt the child
+                let output = cmd.execute_async().await?;
+
+                let mut stdout = output.stdout;
+                if capture_stderr && !output.stderr.is_empty() {
+                    stdout.push_str("\nSTDERR:\n");
+                    stdout.push_str(&output.stderr);
                 }
+
+                let response = ShellTaskResponse {
+                    output: stdout,
+                    exit_code: output.exit_code,
+                    success: output.exit_code == Some(0),
+                    interrupted: output.interrupted,
+                    timed_out: output.timed_out,
+                };
+
+                Ok(TaskResult::Json(serde_json::to_value(response).unwrap()))
             },
             
             ShellTaskRequest::ListDirectory { path, recursive } => {
@@ -114,30 +142,93 @@ impl TaskHandler for ShellTaskHandler {
                     }
                 };
                 
-                // Run command
-                let mut cmd = Command::new(command);
-                for arg in args {
-                    cmd.arg(arg);
-                }
-                let cmd_result = cmd.output();
-                
-                match cmd_result {
-                    Ok(output) => {
-                        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-                        
-                        let response = ShellTaskResponse {
-                            output: stdout,
-                            exit_code: output.status.code(),
-                            success: output.status.success(),
-                        };
-                        
-                        Ok(TaskResult::Json(serde_json::to_value(response).unwrap()))
-                    },
-                    Err(e) => {
-                        Err(TaskError::ExecutionFailed(format!("Failed to list directory: {}", e)))
-                    }
-                }
+                // A plain synchronous listing, bounded so a huge tree can't hang the app
+                let cmd = ShellCommand::new(command)
+                    .args(&args)
+                    .timeout(LIST_DIRECTORY_TIMEOUT_SECS)
+                    .cancel_token(cancel);
+                let output = tokio::task::spawn_blocking(move || cmd.execute())
+                    .await
+                    .map_err(|e| TaskError::ExecutionFailed(e.to_string()))?
+                    .map_err(|e| {
+                        TaskError::ExecutionFailed(format!("Failed to list directory: {}", e))
+                    })?;
+
+                let response = ShellTaskResponse {
+                    output: output.stdout,
+                    exit_code: output.exit_code,
+                    success: output.exit_code == Some(0),
+                    interrupted: output.interrupted,
+                    timed_out: output.timed_out,
+                };
+
+                Ok(TaskResult::Json(serde_json::to_value(response).unwrap()))
             }
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::operations::LocalFileSystem;
+    use crate::task::{TaskManager, TaskManagerTrait, TaskRegistry};
+    use serde_json::json;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_dry_run_returns_the_command_without_running_it() {
+        let temp = TempDir::new().unwrap();
+        let mut registry = TaskRegistry::new();
+        registry.register("shell", Arc::new(ShellTaskHandler::new()));
+        let manager = TaskManager::new(Arc::new(LocalFileSystem::new()), Arc::new(registry));
+
+        let task = Task::new(
+            "shell",
+            json!({
+                "type": "execute",
+                "command": "touch",
+                "args": ["created by dry run"],
+                "working_dir": temp.path(),
+                "env": {"RUST_LOG": "debug"},
+                "dry_run": true,
+            }),
+        );
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let result = rt.block_on(manager.execute_task(task)).unwrap();
+
+        let TaskResult::Text(line) = result else {
+            panic!("expected the command line as text");
+        };
+        let dir = temp.path().canonicalize().unwrap();
+        assert_eq!(
+            line,
+            format!("cd {} && RUST_LOG=debug touch 'created by dry run'", dir.display())
+        );
+        assert_eq!(std::fs::read_dir(temp.path()).unwrap().count(), 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_list_directory_reports_the_listing() {
+        let temp = TempDir::new().unwrap();
+        std::fs::write(temp.path().join("listed.txt"), "").unwrap();
+        let mut registry = TaskRegistry::new();
+        registry.register("shell", Arc::new(ShellTaskHandler::new()));
+        let manager = TaskManager::new(Arc::new(LocalFileSystem::new()), Arc::new(registry));
+
+        let task = Task::new(
+            "shell",
+            json!({"type": "list_directory", "path": temp.path(), "recursive": false}),
+        );
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let TaskResult::Json(response) = rt.block_on(manager.execute_task(task)).unwrap() else {
+            panic!("expected a JSON response");
+        };
+
+        assert!(response["output"].as_str().unwrap().contains("listed.txt"));
+        assert_eq!(response["success"], true);
+        assert_eq!(response["timed_out"], false);
+    }
+}
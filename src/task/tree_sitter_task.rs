@@ -2,7 +2,7 @@ use crate::error::TaskError;
 use crate::services::tree_sitter::{SupportedLanguage, TreeSitterService};
 use crate::task::{Task, TaskContext, TaskHandler, TaskResult};
 use crate::tools::{
-    parse_file, parse_code_string, search_definitions, search_components, run_custom_query,
+    ToJson, parse_file, parse_code_string, search_definitions, search_components, run_custom_query,
 };
 use async_trait::async_trait;
 use serde::Deserialize;
@@ -79,7 +79,7 @@ impl TaskHandler for TreeSitterTaskHandler {
                 let result = parse_file(&self.service, path, &content)
                     .map_err(|e| TaskError::from(e))?;
                 
-                Ok(TaskResult::Json(serde_json::to_value(result).unwrap()))
+                Ok(TaskResult::Json(result.to_json()?))
             },
             
             TreeSitterTaskRequest::SearchDefinitions { file_path } => {
@@ -93,7 +93,7 @@ impl TaskHandler for TreeSitterTaskHandler {
                 let result = search_definitions(&self.service, path, &content)
                     .map_err(|e| TaskError::from(e))?;
                 
-                Ok(TaskResult::Json(serde_json::to_value(result).unwrap()))
+                Ok(TaskResult::Json(result.to_json()?))
             },
             
             TreeSitterTaskRequest::SearchComponents { file_path } => {
@@ -107,7 +107,7 @@ impl TaskHandler for TreeSitterTaskHandler {
                 let result = search_components(&self.service, path, &content)
                     .map_err(|e| TaskError::from(e))?;
                 
-                Ok(TaskResult::Json(serde_json::to_value(result).unwrap()))
+                Ok(TaskResult::Json(result.to_json()?))
             },
             
             TreeSitterTaskRequest::CustomQuery { file_path, query } => {
@@ -121,7 +121,7 @@ impl TaskHandler for TreeSitterTaskHandler {
                 let result = run_custom_query(&self.service, path, &content, &query)
                     .map_err(|e| TaskError::from(e))?;
                 
-                Ok(TaskResult::Json(serde_json::to_value(result).unwrap()))
+                Ok(TaskResult::Json(result.to_json()?))
             },
             
             TreeSitterTaskRequest::ParseString { content, language } => {
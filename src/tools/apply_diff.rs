@@ -1,18 +1,21 @@
 use std::{
-    fs::{self, File},
+    fs::File,
     io::{self, Read, Write},
     path::{Path, PathBuf},
 };
 
 use thiserror::Error;
 
+use super::backup::BackupStore;
 use crate::{
+    config::{AutoApplyConfig, BackupConfig, LogLevel},
     error::TaskError,
+    fs::safe_join,
+    logging,
+    tools::ToJson,
     ui::diff::{DiffConfig, DiffVisualization},
 };
 
-const BACKUP_EXTENSION: &str = ".bak";
-
 #[derive(Debug, Error)]
 pub enum ApplyDiffError {
     #[error("File not found: {0}")]
@@ -30,9 +33,15 @@ pub enum ApplyDiffError {
     #[error("Content mismatch between file and diff")]
     ContentMismatch,
 
+    #[error("Diff block starting at line {0} overlaps a previous block")]
+    OverlappingBlocks(usize),
+
     #[error("Failed to create backup: {0}")]
     BackupFailed(String),
 
+    #[error("No backup found for {0}")]
+    NoBackup(PathBuf),
+
     #[error("Failed to update file: {0}")]
     UpdateFailed(String),
 
@@ -62,6 +71,22 @@ pub struct ApplyDiffResult {
     pub backup_path: Option<PathBuf>,
 }
 
+/// The preview is a UI widget, so it's serialized as unified diff text
+impl serde::Serialize for ApplyDiffResult {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("ApplyDiffResult", 4)?;
+        state.serialize_field("changes_applied", &self.changes_applied)?;
+        state.serialize_field("backup_path", &self.backup_path)?;
+        state.serialize_field("summary", &self.preview.stats().to_string())?;
+        state.serialize_field("diff", &self.preview.unified_text())?;
+        state.end()
+    }
+}
+
+impl ToJson for ApplyDiffResult {}
+
 pub fn parse_diff_block(content: &str) -> Result<DiffBlock, ApplyDiffError> {
     let parts: Vec<&str> = content.split("=======").collect();
     if parts.len() != 2 {
@@ -111,55 +136,193 @@ pub fn validate_path(
     workspace_root: impl AsRef<Path>,
 ) -> Result<PathBuf, ApplyDiffError> {
     let path = path.as_ref();
-    let canonical_path = path
-        .canonicalize()
-        .map_err(|_| ApplyDiffError::NotFound(path.to_path_buf()))?;
-    let workspace_root = workspace_root
-        .as_ref()
-        .canonicalize()
-        .map_err(|_| ApplyDiffError::OutsideWorkspace)?;
-
-    if !canonical_path.starts_with(workspace_root) {
-        return Err(ApplyDiffError::OutsideWorkspace);
+    let canonical_path = safe_join(workspace_root, path).map_err(|err| match err {
+        TaskError::PathOutsideWorkspace(_) => ApplyDiffError::OutsideWorkspace,
+        _ => ApplyDiffError::NotFound(path.to_path_buf()),
+    })?;
+
+    if !canonical_path.is_file() {
+        return Err(ApplyDiffError::NotFound(path.to_path_buf()));
     }
 
     Ok(canonical_path)
 }
 
-pub fn create_backup(path: &Path) -> Result<PathBuf, ApplyDiffError> {
-    let extension_string = path.extension()
-        .map(|ext| format!("{}.{}", ext.to_string_lossy(), BACKUP_EXTENSION))
-        .unwrap_or_else(|| BACKUP_EXTENSION.to_string());
-        
-    let backup_path = path.with_extension(extension_string);
+/// Back up `path` as `backups` is configured, pruning old versions
+pub fn create_backup(
+    path: &Path,
+    workspace_root: impl AsRef<Path>,
+    backups: &BackupConfig,
+) -> Result<PathBuf, ApplyDiffError> {
+    BackupStore::new(workspace_root, backups.clone()).create(path)
+}
+
+/// Undo the last change to `path` (relative to the workspace root) from its newest backup
+pub fn restore_backup(
+    path: impl AsRef<Path>,
+    workspace_root: impl AsRef<Path>,
+    backups: &BackupConfig,
+) -> Result<PathBuf, ApplyDiffError> {
+    let canonical_path = validate_path(&path, &workspace_root)?;
+    BackupStore::new(workspace_root, backups.clone()).restore(&canonical_path)
+}
+
+/// Apply `blocks` to `original_content`.
+///
+/// Every block's `start_line` refers to the original content, so blocks are applied in
+/// ascending order and each one is shifted by the net line-count change of the blocks
+/// applied before it.
+fn apply_blocks(original_content: &str, blocks: &[DiffBlock]) -> Result<String, ApplyDiffError> {
+    let mut ordered: Vec<&DiffBlock> = blocks.iter().collect();
+    ordered.sort_by_key(|block| block.start_line);
+
+    let mut lines: Vec<&str> = original_content.lines().collect();
+    let mut offset: isize = 0;
+    // End (exclusive, 0-based) of the previous block in original line numbers
+    let mut previous_end = 0;
+
+    for block in ordered {
+        let start = block.start_line - 1;
+        if start < previous_end {
+            return Err(ApplyDiffError::OverlappingBlocks(block.start_line));
+        }
+
+        let removed = block.original.lines().count();
+        let added = block.replacement.lines().count();
+        let at = (start as isize + offset) as usize;
+
+        lines.splice(at..at + removed, block.replacement.lines());
+
+        offset += added as isize - removed as isize;
+        previous_end = start + removed;
+    }
+
+    Ok(lines.join("\n"))
+}
+
+fn parse_diff_blocks(diff_content: &str) -> Result<Vec<DiffBlock>, ApplyDiffError> {
+    diff_content
+        .split("<<<<<<< SEARCH")
+        .filter(|block| !block.trim().is_empty())
+        .map(parse_diff_block)
+        .collect()
+}
+
+/// The search/replace blocks in a model's response, without the prose around them
+pub fn extract_diff(response: &str) -> Option<&str> {
+    let start = response.find("<<<<<<< SEARCH")?;
+    let end = response.rfind(">>>>>>> REPLACE")? + ">>>>>>> REPLACE".len();
+    (start < end).then(|| &response[start..end])
+}
+
+/// Whether an agent's edit can go straight in or needs the user to confirm it
+#[derive(Debug, Clone, PartialEq)]
+pub enum EditDecision {
+    AutoApply,
+    /// Why the edit has to be confirmed
+    Confirm(String),
+}
 
-    fs::copy(path, &backup_path).map_err(|e| ApplyDiffError::BackupFailed(e.to_string()))?;
-    Ok(backup_path)
+/// Decide whether `diff_content` is small and safe enough for `policy` to auto-apply
+/// to `path`. Paths outside the workspace are an error, as they are when applying.
+pub fn review_edit(
+    path: impl AsRef<Path>,
+    workspace_root: impl AsRef<Path>,
+    diff_content: &str,
+    policy: &AutoApplyConfig,
+) -> Result<EditDecision, ApplyDiffError> {
+    validate_path(&path, &workspace_root)?;
+    let blocks = parse_diff_blocks(diff_content)?;
+
+    if !policy.enabled {
+        return Ok(EditDecision::Confirm("auto-apply is off".to_string()));
+    }
+    if blocks.len() > policy.max_blocks {
+        return Ok(EditDecision::Confirm(format!(
+            "{} blocks (auto-apply allows {})",
+            blocks.len(),
+            policy.max_blocks
+        )));
+    }
+    if blocks.iter().any(|block| block.replacement.trim().is_empty()) {
+        return Ok(EditDecision::Confirm("deletes lines".to_string()));
+    }
+    let changed: usize = blocks
+        .iter()
+        .map(|block| block.original.lines().count().max(block.replacement.lines().count()))
+        .sum();
+    if changed > policy.max_changed_lines {
+        return Ok(EditDecision::Confirm(format!(
+            "{} changed lines (auto-apply allows {})",
+            changed, policy.max_changed_lines
+        )));
+    }
+    Ok(EditDecision::AutoApply)
+}
+
+/// What `auto_apply_diff` did with an edit
+pub enum AutoApplyOutcome {
+    Applied(ApplyDiffResult),
+    /// Nothing was written; the edit waits for the user, for this reason
+    NeedsConfirmation(String),
+}
+
+/// Apply `diff_content` if `policy` lets it skip confirmation, logging that it did
+pub fn auto_apply_diff(
+    path: impl AsRef<Path>,
+    workspace_root: impl AsRef<Path>,
+    diff_content: &str,
+    backups: &BackupConfig,
+    policy: &AutoApplyConfig,
+) -> Result<AutoApplyOutcome, ApplyDiffError> {
+    match review_edit(&path, &workspace_root, diff_content, policy)? {
+        EditDecision::AutoApply => {
+            let result = apply_diff_with_backups(&path, workspace_root, diff_content, backups)?;
+            logging::log(
+                LogLevel::Info,
+                "edits",
+                format!(
+                    "Auto-applied edit to {} ({}):\n{}",
+                    path.as_ref().display(),
+                    result.preview.stats(),
+                    result.preview.unified_text()
+                ),
+            );
+            Ok(AutoApplyOutcome::Applied(result))
+        }
+        EditDecision::Confirm(reason) => Ok(AutoApplyOutcome::NeedsConfirmation(reason)),
+    }
 }
 
+/// Apply `diff_content` to `path`, backing it up next to itself first
 pub fn apply_diff(
     path: impl AsRef<Path>,
     workspace_root: impl AsRef<Path>,
     diff_content: &str,
 ) -> Result<ApplyDiffResult, ApplyDiffError> {
-    let canonical_path = validate_path(&path, workspace_root)?;
+    apply_diff_with_backups(path, workspace_root, diff_content, &BackupConfig::default())
+}
+
+/// Apply `diff_content` to `path`, backing it up as `backups` is configured
+pub fn apply_diff_with_backups(
+    path: impl AsRef<Path>,
+    workspace_root: impl AsRef<Path>,
+    diff_content: &str,
+    backups: &BackupConfig,
+) -> Result<ApplyDiffResult, ApplyDiffError> {
+    let canonical_path = validate_path(&path, &workspace_root)?;
 
     // Read original file content
     let mut file = File::open(&canonical_path)?;
     let mut original_content = String::new();
     file.read_to_string(&mut original_content)?;
 
-    // Parse diff blocks
-    let blocks: Vec<DiffBlock> = diff_content
-        .split("<<<<<<< SEARCH")
-        .filter(|block| !block.trim().is_empty())
-        .map(parse_diff_block)
-        .collect::<Result<Vec<_>, _>>()?;
+    let blocks = parse_diff_blocks(diff_content)?;
 
     // Validate content matches
     for block in &blocks {
         let original_lines: Vec<&str> = original_content.lines().collect();
-        if block.start_line > original_lines.len() {
+        if block.start_line == 0 || block.start_line > original_lines.len() {
             return Err(ApplyDiffError::ContentMismatch);
         }
 
@@ -177,28 +340,13 @@ pub fn apply_diff(
     }
 
     // Create diff visualization for preview
-    let mut new_content = original_content.clone();
-    for block in &blocks {
-        let lines: Vec<&str> = new_content.lines().collect();
-        let prefix = lines[..block.start_line - 1].join("\n");
-        let suffix = lines[block.start_line - 1 + block.original.lines().count()..].join("\n");
-
-        new_content = if prefix.is_empty() && suffix.is_empty() {
-            block.replacement.clone()
-        } else if prefix.is_empty() {
-            format!("{}\n{}", block.replacement, suffix)
-        } else if suffix.is_empty() {
-            format!("{}\n{}", prefix, block.replacement)
-        } else {
-            format!("{}\n{}\n{}", prefix, block.replacement, suffix)
-        };
-    }
+    let new_content = apply_blocks(&original_content, &blocks)?;
 
     let preview = DiffVisualization::new(original_content.clone(), new_content.clone())
         .with_config(DiffConfig::default());
 
     // Create backup and apply changes
-    let backup_path = create_backup(&canonical_path)?;
+    let backup_path = create_backup(&canonical_path, workspace_root, backups)?;
 
     // Write updated content
     let mut file =
@@ -216,7 +364,7 @@ pub fn apply_diff(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs::write;
+    use std::fs::{self, write};
     use tempfile::TempDir;
 
     #[test]
@@ -306,4 +454,209 @@ updated line 3
             "updated line 1\nline 2\nupdated line 3\nline 4"
         );
     }
+
+    #[test]
+    fn test_apply_diff_blocks_changing_line_counts() {
+        let temp = TempDir::new().unwrap();
+        let test_file = temp.path().join("test.txt");
+        write(&test_file, "a\nb\nc\nd\ne\nf").unwrap();
+
+        // Blocks are listed out of order; the first (line 2) grows by two lines and the
+        // second (line 4) shrinks by one, so line 6 must still be found by its original number
+        let diff_content = r#"<<<<<<< SEARCH
+:start_line:6
+-------
+f
+=======
+F
+>>>>>>> REPLACE
+
+<<<<<<< SEARCH
+:start_line:2
+-------
+b
+=======
+b1
+b2
+b3
+>>>>>>> REPLACE
+
+<<<<<<< SEARCH
+:start_line:4
+-------
+d
+e
+=======
+DE
+>>>>>>> REPLACE"#;
+
+        apply_diff(&test_file, temp.path(), diff_content).unwrap();
+
+        let updated_content = fs::read_to_string(&test_file).unwrap();
+        assert_eq!(updated_content, "a\nb1\nb2\nb3\nc\nDE\nF");
+    }
+
+    #[test]
+    fn test_apply_diff_overlapping_blocks() {
+        let temp = TempDir::new().unwrap();
+        let test_file = temp.path().join("test.txt");
+        write(&test_file, "line 1\nline 2\nline 3").unwrap();
+
+        let diff_content = r#"<<<<<<< SEARCH
+:start_line:1
+-------
+line 1
+line 2
+=======
+merged
+>>>>>>> REPLACE
+
+<<<<<<< SEARCH
+:start_line:2
+-------
+line 2
+=======
+other
+>>>>>>> REPLACE"#;
+
+        let result = apply_diff(&test_file, temp.path(), diff_content);
+        assert!(matches!(result, Err(ApplyDiffError::OverlappingBlocks(2))));
+    }
+
+    #[test]
+    fn test_result_json_shape() {
+        let temp = TempDir::new().unwrap();
+        let test_file = temp.path().join("test.txt");
+        write(&test_file, "line 1\nline 2\nline 3").unwrap();
+
+        let diff_content = r#"<<<<<<< SEARCH
+:start_line:2
+-------
+line 2
+=======
+updated line 2
+>>>>>>> REPLACE"#;
+
+        let result = apply_diff(&test_file, temp.path(), diff_content).unwrap();
+        let backup = result.backup_path.clone().unwrap();
+        assert_eq!(
+            result.to_json().unwrap(),
+            serde_json::json!({
+                "changes_applied": true,
+                "backup_path": backup,
+                "summary": "1 file changed, 1 insertion(+), 1 deletion(-)",
+                "diff": "@@ -1,3 +1,3 @@\n line 1\n-line 2\n+updated line 2\n line 3\n\\ No newline at end of file\n"
+            })
+        );
+    }
+
+    #[test]
+    fn test_validate_path_rejects_traversal() {
+        let temp = TempDir::new().unwrap();
+        let workspace = temp.path().join("workspace");
+        fs::create_dir(&workspace).unwrap();
+        write(workspace.join("inside.txt"), "inside").unwrap();
+        write(temp.path().join("outside.txt"), "outside").unwrap();
+
+        assert_eq!(
+            validate_path("inside.txt", &workspace).unwrap(),
+            workspace.canonicalize().unwrap().join("inside.txt")
+        );
+        assert!(matches!(
+            validate_path("../outside.txt", &workspace),
+            Err(ApplyDiffError::OutsideWorkspace)
+        ));
+        assert!(matches!(
+            validate_path("missing.txt", &workspace),
+            Err(ApplyDiffError::NotFound(_))
+        ));
+    }
+
+    fn auto_apply_policy() -> AutoApplyConfig {
+        AutoApplyConfig {
+            enabled: true,
+            ..AutoApplyConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_one_line_edit_auto_applies() {
+        let temp = TempDir::new().unwrap();
+        let test_file = temp.path().join("test.txt");
+        write(&test_file, "line 1\nline 2\nline 3").unwrap();
+
+        let diff_content = r#"<<<<<<< SEARCH
+:start_line:2
+-------
+line 2
+=======
+line two
+>>>>>>> REPLACE"#;
+
+        let policy = auto_apply_policy();
+        assert_eq!(
+            review_edit(&test_file, temp.path(), diff_content, &policy).unwrap(),
+            EditDecision::AutoApply
+        );
+        let outcome =
+            auto_apply_diff(&test_file, temp.path(), diff_content, &BackupConfig::default(), &policy)
+                .unwrap();
+        assert!(matches!(outcome, AutoApplyOutcome::Applied(_)));
+        assert_eq!(fs::read_to_string(&test_file).unwrap(), "line 1\nline two\nline 3");
+        assert!(
+            logging::recent(50)
+                .iter()
+                .any(|record| record.target == "edits" && record.message.contains("line two"))
+        );
+
+        // The same edit is confirmed while auto-apply is off
+        let off = AutoApplyConfig::default();
+        assert!(matches!(
+            review_edit(&test_file, temp.path(), diff_content, &off).unwrap(),
+            EditDecision::Confirm(_)
+        ));
+    }
+
+    #[test]
+    fn test_larger_edits_still_need_confirmation() {
+        let temp = TempDir::new().unwrap();
+        let test_file = temp.path().join("test.txt");
+        let content = "line 1\nline 2\nline 3";
+        write(&test_file, content).unwrap();
+        let policy = auto_apply_policy();
+
+        let multi_hunk = r#"<<<<<<< SEARCH
+:start_line:1
+-------
+line 1
+=======
+line one
+>>>>>>> REPLACE
+
+<<<<<<< SEARCH
+:start_line:3
+-------
+line 3
+=======
+line three
+>>>>>>> REPLACE"#;
+        let outcome =
+            auto_apply_diff(&test_file, temp.path(), multi_hunk, &BackupConfig::default(), &policy)
+                .unwrap();
+        assert!(
+            matches!(&outcome, AutoApplyOutcome::NeedsConfirmation(reason) if reason.contains("2 blocks"))
+        );
+        assert_eq!(fs::read_to_string(&test_file).unwrap(), content);
+
+        let deletion = r#"<<<<<<< SEARCH
+:start_line:2
+-------
+line 2
+=======
+>>>>>>> REPLACE"#;
+        assert_eq!(
+            review_edit(&test_file, temp.path(), deletion, &policy).unwrap(),
+            EditDecision::Confirm("deletes lines".to_string())
+        );
+    }
 }
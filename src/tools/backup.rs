@@ -0,0 +1,281 @@
+//! Copies of files taken before `apply_diff` changes them, and restoring from them
+//!
+//! Without a backup directory each file gets a single `<name>.bak` next to it, replaced
+//! on every edit. With one, backups mirror the workspace layout under it as
+//! `<dir>/<relative path>.<unix millis>.bak`, so several versions of a file can be kept
+//! and pruned by count or age.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::apply_diff::ApplyDiffError;
+use crate::config::BackupConfig;
+
+const BACKUP_EXTENSION: &str = "bak";
+
+const MILLIS_PER_DAY: u128 = 24 * 60 * 60 * 1000;
+
+/// Backups for the files of one workspace
+#[derive(Debug, Clone)]
+pub struct BackupStore {
+    root: PathBuf,
+    config: BackupConfig,
+}
+
+impl BackupStore {
+    pub fn new(workspace_root: impl AsRef<Path>, config: BackupConfig) -> Self {
+        let root = workspace_root.as_ref();
+        Self {
+            root: root.canonicalize().unwrap_or_else(|_| root.to_path_buf()),
+            config,
+        }
+    }
+
+    /// The backup directory, resolved against the workspace root, if one is configured
+    pub fn dir(&self) -> Option<PathBuf> {
+        self.config.dir.as_ref().map(|dir| self.root.join(dir))
+    }
+
+    /// Copy `path` (absolute, inside the workspace) to a new backup, then prune its old
+    /// versions. Returns the backup's path.
+    pub fn create(&self, path: &Path) -> Result<PathBuf, ApplyDiffError> {
+        let backup_path = match self.versioned_prefix(path)? {
+            None => sibling_backup(path),
+            Some(prefix) => {
+                if let Some(parent) = prefix.parent() {
+                    fs::create_dir_all(parent)
+                        .map_err(|e| ApplyDiffError::BackupFailed(e.to_string()))?;
+                }
+                // Always newer than the last version, even within the same millisecond
+                let newest = stamped_versions(&prefix).last().map(|(stamp, _)| stamp + 1);
+                versioned_path(&prefix, now_millis().max(newest.unwrap_or(0)))
+            }
+        };
+
+        fs::copy(path, &backup_path).map_err(|e| ApplyDiffError::BackupFailed(e.to_string()))?;
+        self.prune(path)?;
+        Ok(backup_path)
+    }
+
+    /// Backups of `path`, oldest first
+    pub fn versions(&self, path: &Path) -> Result<Vec<PathBuf>, ApplyDiffError> {
+        let Some(prefix) = self.versioned_prefix(path)? else {
+            let backup = sibling_backup(path);
+            return Ok(if backup.is_file() { vec![backup] } else { Vec::new() });
+        };
+        Ok(stamped_versions(&prefix)
+            .into_iter()
+            .map(|(_, path)| path)
+            .collect())
+    }
+
+    /// Delete the versions of `path` beyond `keep_versions` or older than
+    /// `max_age_days`. The newest backup is always kept. Returns what was deleted.
+    pub fn prune(&self, path: &Path) -> Result<Vec<PathBuf>, ApplyDiffError> {
+        let Some(prefix) = self.versioned_prefix(path)? else {
+            return Ok(Vec::new());
+        };
+
+        let mut versions = stamped_versions(&prefix);
+        // Never prune the newest, whatever the policy says
+        versions.pop();
+
+        let excess = self
+            .config
+            .keep_versions
+            .map_or(0, |keep| (versions.len() + 1).saturating_sub(keep));
+        let cutoff = self
+            .config
+            .max_age_days
+            .map(|days| now_millis().saturating_sub(u128::from(days) * MILLIS_PER_DAY));
+
+        let mut removed = Vec::new();
+        for (index, (stamp, backup)) in versions.into_iter().enumerate() {
+            let expired = cutoff.is_some_and(|cutoff| stamp < cutoff);
+            if index < excess || expired {
+                fs::remove_file(&backup)?;
+                removed.push(backup);
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Put the newest backup of `path` back in its place and delete that backup, so
+    /// restoring again steps further back. Returns the backup that was used.
+    pub fn restore(&self, path: &Path) -> Result<PathBuf, ApplyDiffError> {
+        let backup = self
+            .versions(path)?
+            .pop()
+            .ok_or_else(|| ApplyDiffError::NoBackup(path.to_path_buf()))?;
+
+        fs::copy(&backup, path).map_err(|e| ApplyDiffError::UpdateFailed(e.to_string()))?;
+        fs::remove_file(&backup)?;
+        Ok(backup)
+    }
+
+    /// `<dir>/<relative path>`, which versions are named after, when there's a backup dir
+    fn versioned_prefix(&self, path: &Path) -> Result<Option<PathBuf>, ApplyDiffError> {
+        let Some(dir) = self.dir() else {
+            return Ok(None);
+        };
+        let relative = path
+            .strip_prefix(&self.root)
+            .map_err(|_| ApplyDiffError::OutsideWorkspace)?;
+        Ok(Some(dir.join(relative)))
+    }
+}
+
+/// `src/main.rs` is backed up in place as `src/main.rs.bak`
+fn sibling_backup(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(BACKUP_EXTENSION);
+    path.with_file_name(name)
+}
+
+fn versioned_path(prefix: &Path, stamp: u128) -> PathBuf {
+    let mut name = prefix.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.{}", stamp, BACKUP_EXTENSION));
+    prefix.with_file_name(name)
+}
+
+/// Versions next to `prefix` with their timestamps, oldest first
+fn stamped_versions(prefix: &Path) -> Vec<(u128, PathBuf)> {
+    let (Some(parent), Some(name)) = (prefix.parent(), prefix.file_name()) else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(parent) else {
+        return Vec::new();
+    };
+
+    let name = name.to_string_lossy();
+    let mut versions: Vec<(u128, PathBuf)> = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let file_name = entry.file_name();
+            let stamp = file_name
+                .to_str()?
+                .strip_prefix(name.as_ref())?
+                .strip_prefix('.')?
+                .strip_suffix(BACKUP_EXTENSION)?
+                .strip_suffix('.')?
+                .parse()
+                .ok()?;
+            Some((stamp, entry.path()))
+        })
+        .collect();
+    versions.sort();
+    versions
+}
+
+fn now_millis() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_millis()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn workspace() -> (TempDir, PathBuf) {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir_all(temp.path().join("src")).unwrap();
+        let file = temp.path().canonicalize().unwrap().join("src/main.rs");
+        fs::write(&file, "v0").unwrap();
+        (temp, file)
+    }
+
+    fn central(keep_versions: Option<usize>, max_age_days: Option<u64>) -> BackupConfig {
+        BackupConfig {
+            dir: Some(PathBuf::from(".backups")),
+            keep_versions,
+            max_age_days,
+        }
+    }
+
+    #[test]
+    fn test_backups_beside_the_file_by_default() {
+        let (temp, file) = workspace();
+        let store = BackupStore::new(temp.path(), BackupConfig::default());
+
+        let backup = store.create(&file).unwrap();
+        assert_eq!(backup, file.with_file_name("main.rs.bak"));
+        fs::write(&file, "v1").unwrap();
+
+        assert_eq!(store.restore(&file).unwrap(), backup);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "v0");
+        assert!(!backup.exists());
+        assert!(matches!(store.restore(&file), Err(ApplyDiffError::NoBackup(_))));
+    }
+
+    #[test]
+    fn test_keeps_only_the_newest_versions() {
+        let (temp, file) = workspace();
+        let store = BackupStore::new(temp.path(), central(Some(2), None));
+
+        for version in 1..=4 {
+            let backup = store.create(&file).unwrap();
+            assert!(backup.starts_with(temp.path().canonicalize().unwrap().join(".backups/src")));
+            fs::write(&file, format!("v{}", version)).unwrap();
+        }
+
+        let versions = store.versions(&file).unwrap();
+        assert_eq!(versions.len(), 2);
+        let contents: Vec<String> =
+            versions.iter().map(|v| fs::read_to_string(v).unwrap()).collect();
+        assert_eq!(contents, ["v2", "v3"]);
+        // Nothing is left next to the file itself
+        assert!(!file.with_file_name("main.rs.bak").exists());
+    }
+
+    #[test]
+    fn test_prunes_versions_older_than_max_age() {
+        let (temp, file) = workspace();
+        let store = BackupStore::new(temp.path(), central(None, Some(7)));
+        let prefix = store.dir().unwrap().join("src/main.rs");
+        fs::create_dir_all(prefix.parent().unwrap()).unwrap();
+
+        let old = versioned_path(&prefix, now_millis() - 8 * MILLIS_PER_DAY);
+        let recent = versioned_path(&prefix, now_millis() - MILLIS_PER_DAY);
+        fs::write(&old, "ancient").unwrap();
+        fs::write(&recent, "yesterday").unwrap();
+
+        let newest = store.create(&file).unwrap();
+        assert_eq!(store.versions(&file).unwrap(), vec![recent, newest]);
+        assert!(!old.exists());
+
+        // However old, the only backup left is kept
+        let store = BackupStore::new(temp.path(), central(None, Some(0)));
+        fs::write(&file, "v1").unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+        let last = store.create(&file).unwrap();
+        assert_eq!(store.versions(&file).unwrap(), vec![last]);
+    }
+
+    #[test]
+    fn test_restore_steps_back_through_the_backup_dir() {
+        let (temp, file) = workspace();
+        let store = BackupStore::new(temp.path(), central(Some(10), None));
+
+        store.create(&file).unwrap();
+        fs::write(&file, "v1").unwrap();
+        store.create(&file).unwrap();
+        fs::write(&file, "v2").unwrap();
+
+        store.restore(&file).unwrap();
+        assert_eq!(fs::read_to_string(&file).unwrap(), "v1");
+        store.restore(&file).unwrap();
+        assert_eq!(fs::read_to_string(&file).unwrap(), "v0");
+        assert!(matches!(store.restore(&file), Err(ApplyDiffError::NoBackup(_))));
+
+        // Files outside the workspace have no place in the backup dir
+        let outside = TempDir::new().unwrap();
+        let stray = outside.path().join("stray.rs");
+        fs::write(&stray, "").unwrap();
+        assert!(matches!(store.create(&stray), Err(ApplyDiffError::OutsideWorkspace)));
+    }
+}
@@ -1,14 +1,22 @@
 use crate::error::TaskError;
+use crate::tools::ToJson;
 use crate::services::tree_sitter::{
-    QueryMatch, SupportedLanguage, TreeSitterError, TreeSitterService,
+    QueryLimits, QueryMatch, SupportedLanguage, TreeSitterError, TreeSitterService,
 };
 use std::path::Path;
+use tree_sitter::{Query, QueryErrorKind};
+
+/// Most matches a custom query returns
+pub const MAX_CUSTOM_QUERY_MATCHES: usize = 1000;
 
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct SearchResult {
     pub file_path: String,
     pub language: String,
     pub matches: Vec<QueryResult>,
+    /// Set when matches were dropped to stay under the result cap
+    #[serde(default)]
+    pub truncated: bool,
 }
 
 #[derive(serde::Serialize, serde::Deserialize)]
@@ -27,6 +35,8 @@ pub struct CaptureResult {
     pub end_column: usize,
 }
 
+impl ToJson for SearchResult {}
+
 /// Error types specific to code search operations
 #[derive(thiserror::Error, Debug)]
 pub enum CodeSearchError {
@@ -36,6 +46,12 @@ pub enum CodeSearchError {
     UnsupportedLanguage(String),
     #[error("Invalid query: {0}")]
     QueryError(String),
+    #[error("Invalid query at line {line}, column {column}: {message}")]
+    InvalidQuery {
+        line: usize,
+        column: usize,
+        message: String,
+    },
     #[error("File not found: {0}")]
     FileNotFound(String),
 }
@@ -56,6 +72,26 @@ impl From<CodeSearchError> for TaskError {
     }
 }
 
+impl From<tree_sitter::QueryError> for CodeSearchError {
+    fn from(err: tree_sitter::QueryError) -> Self {
+        let message = match err.kind {
+            QueryErrorKind::Syntax => "invalid syntax".to_string(),
+            QueryErrorKind::NodeType => format!("unknown node type \"{}\"", err.message),
+            QueryErrorKind::Field => format!("unknown field \"{}\"", err.message),
+            QueryErrorKind::Capture => format!("unknown capture \"@{}\"", err.message),
+            QueryErrorKind::Predicate => format!("invalid predicate: {}", err.message),
+            QueryErrorKind::Structure => "pattern can never match in this language".to_string(),
+            QueryErrorKind::Language => err.message.clone(),
+        };
+
+        Self::InvalidQuery {
+            line: err.row + 1,
+            column: err.column + 1,
+            message,
+        }
+    }
+}
+
 /// Convert QueryMatch to our QueryResult format
 fn convert_match(query_match: QueryMatch) -> QueryResult {
     QueryResult {
@@ -100,6 +136,7 @@ pub fn search_definitions(
         file_path: file_path.to_string_lossy().to_string(),
         language: format!("{:?}", language),
         matches: matches.into_iter().map(convert_match).collect(),
+        truncated: false,
     })
 }
 
@@ -128,10 +165,13 @@ pub fn search_components(
         file_path: file_path.to_string_lossy().to_string(),
         language: format!("{:?}", language),
         matches: matches.into_iter().map(convert_match).collect(),
+        truncated: false,
     })
 }
 
-/// Run a custom query on a file
+/// Run a custom query on a file, returning at most `MAX_CUSTOM_QUERY_MATCHES` matches.
+/// The query is checked against the file's language first so mistakes are reported
+/// with their position.
 pub fn run_custom_query(
     service: &TreeSitterService,
     file_path: &Path,
@@ -147,15 +187,131 @@ pub fn run_custom_query(
     let language = SupportedLanguage::from_extension(ext)
         .ok_or_else(|| CodeSearchError::UnsupportedLanguage(ext.to_string()))?;
 
+    // Validate the query before doing any parsing
+    Query::new(language.get_language(), query_string)?;
+
     // Parse the file
     let tree = service.parse_file(file_path, content)?;
     
     // Run the custom query
-    let matches = service.run_query(language, query_string, &tree, content)?;
+    let limits = QueryLimits {
+        max_matches: Some(MAX_CUSTOM_QUERY_MATCHES),
+        ..QueryLimits::default()
+    };
+    let results = service.run_query_with_limits(language, query_string, &tree, content, limits)?;
     
     Ok(SearchResult {
         file_path: file_path.to_string_lossy().to_string(),
         language: format!("{:?}", language),
-        matches: matches.into_iter().map(convert_match).collect(),
+        matches: results.matches.into_iter().map(convert_match).collect(),
+        truncated: results.truncated,
     })
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service() -> TreeSitterService {
+        TreeSitterService::new(1024 * 1024, 2)
+    }
+
+    #[test]
+    fn test_custom_query_returns_captures() {
+        let content = "fn alpha() {}\nfn beta() {}\n";
+        let result = run_custom_query(
+            &service(),
+            Path::new("lib.rs"),
+            content,
+            "(function_item name: (identifier) @fn.name)",
+        )
+        .unwrap();
+
+        let names: Vec<(&str, &str, usize)> = result
+            .matches
+            .iter()
+            .flat_map(|m| &m.captures)
+            .map(|c| (c.name.as_str(), c.text.as_str(), c.start_line))
+            .collect();
+        assert_eq!(names, vec![("fn.name", "alpha", 1), ("fn.name", "beta", 2)]);
+        assert!(!result.truncated);
+    }
+
+    #[test]
+    fn test_invalid_query_reports_position() {
+        let err = run_custom_query(
+            &service(),
+            Path::new("lib.rs"),
+            "fn alpha() {}",
+            "(function_item)\n(not_a_rust_node) @x",
+        )
+        .err()
+        .unwrap();
+
+        match &err {
+            CodeSearchError::InvalidQuery { line, column, message } => {
+                assert_eq!((*line, *column), (2, 2));
+                assert_eq!(message, "unknown node type \"not_a_rust_node\"");
+            }
+            other => panic!("expected InvalidQuery, got {:?}", other),
+        }
+        assert_eq!(
+            err.to_string(),
+            "Invalid query at line 2, column 2: unknown node type \"not_a_rust_node\""
+        );
+
+        let err = run_custom_query(&service(), Path::new("lib.rs"), "", "(function_item").err();
+        assert!(matches!(
+            err,
+            Some(CodeSearchError::InvalidQuery { ref message, .. }) if message == "invalid syntax"
+        ));
+    }
+
+    #[test]
+    fn test_custom_query_results_are_capped() {
+        let content: String = (0..MAX_CUSTOM_QUERY_MATCHES + 5)
+            .map(|i| format!("fn f{}() {{}}\n", i))
+            .collect();
+        let result = run_custom_query(
+            &service(),
+            Path::new("many.rs"),
+            &content,
+            "(function_item) @fn",
+        )
+        .unwrap();
+
+        assert_eq!(result.matches.len(), MAX_CUSTOM_QUERY_MATCHES);
+        assert!(result.truncated);
+    }
+
+    #[test]
+    fn test_result_json_shape() {
+        let result = run_custom_query(
+            &service(),
+            Path::new("lib.rs"),
+            "fn alpha() {}",
+            "(function_item name: (identifier) @fn.name)",
+        )
+        .unwrap();
+
+        assert_eq!(
+            result.to_json().unwrap(),
+            serde_json::json!({
+                "file_path": "lib.rs",
+                "language": "Rust",
+                "truncated": false,
+                "matches": [{
+                    "pattern_index": 0,
+                    "captures": [{
+                        "name": "fn.name",
+                        "text": "alpha",
+                        "start_line": 1,
+                        "start_column": 3,
+                        "end_line": 1,
+                        "end_column": 8
+                    }]
+                }]
+            })
+        );
+    }
+}
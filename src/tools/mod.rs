@@ -1,11 +1,29 @@
 mod apply_diff;
+mod backup;
+mod paged_reader;
 mod read_file;
 mod tree_parser;
 mod code_search;
 
-pub use tree_parser::{TreeParserError, parse_file, parse_code_string};
-pub use code_search::{CodeSearchError, 
+use crate::error::TaskError;
+
+pub use apply_diff::{
+    ApplyDiffError, AutoApplyOutcome, EditDecision, apply_diff_with_backups, auto_apply_diff, extract_diff,
+    restore_backup, review_edit,
+};
+pub use backup::BackupStore;
+pub use paged_reader::{Page, PagedReader};
+pub use read_file::FileError;
+pub use tree_parser::{ParseFileResult, ParsedNode, TreeParserError, parse_file, parse_code_string};
+pub use code_search::{CaptureResult, CodeSearchError, QueryResult, SearchResult,
                      search_definitions, search_components, run_custom_query};
 
 // Re-export core tool types and functions
 pub type Result<T> = std::result::Result<T, crate::error::TaskError>;
+
+/// A tool result that can be fed back to the model as JSON
+pub trait ToJson: serde::Serialize {
+    fn to_json(&self) -> Result<serde_json::Value> {
+        serde_json::to_value(self).map_err(|e| TaskError::SerializationError(e.to_string()))
+    }
+}
@@ -0,0 +1,278 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Seek, SeekFrom},
+    path::Path,
+};
+
+use super::read_file::{FileError, is_binary_file};
+
+/// One window of lines from a `PagedReader`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page {
+    /// Zero-based page index
+    pub number: usize,
+    /// One-based line number of the first line in the page
+    pub start_line: usize,
+    pub lines: Vec<String>,
+    /// Whether this page reaches the end of the file
+    pub is_last: bool,
+}
+
+/// Reads a file a fixed number of lines at a time without loading it whole.
+///
+/// Only the byte offset of each page start is kept, so memory stays proportional to the
+/// number of pages visited rather than the file size. The total line count is estimated
+/// from the bytes scanned so far until the reader reaches the end of the file.
+pub struct PagedReader {
+    reader: BufReader<File>,
+    page_size: usize,
+    file_len: u64,
+    /// Byte offset where each indexed page starts; entry 0 is always 0
+    page_offsets: Vec<u64>,
+    /// Exact line count, known once the end of the file has been reached
+    total_lines: Option<usize>,
+    /// Furthest point read so far, as (bytes, lines), used for estimation
+    scanned: (u64, usize),
+    current_page: Option<usize>,
+}
+
+impl PagedReader {
+    pub fn open(path: impl AsRef<Path>, page_size: usize) -> Result<Self, FileError> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|_| FileError::NotFound(path.to_path_buf()))?;
+
+        if is_binary_file(&file)? {
+            return Err(FileError::BinaryFile);
+        }
+
+        let file_len = file.metadata()?.len();
+        Ok(Self {
+            reader: BufReader::new(file),
+            page_size: page_size.max(1),
+            file_len,
+            page_offsets: vec![0],
+            total_lines: if file_len == 0 { Some(0) } else { None },
+            scanned: (0, 0),
+            current_page: None,
+        })
+    }
+
+    pub fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    /// Index of the page most recently returned
+    pub fn current_page(&self) -> Option<usize> {
+        self.current_page
+    }
+
+    /// Advance to the next page; `None` once past the end of the file
+    pub fn next_page(&mut self) -> Result<Option<Page>, FileError> {
+        let next = self.current_page.map_or(0, |page| page + 1);
+        self.page(next)
+    }
+
+    /// Step back to the previous page; `None` when already on the first page
+    pub fn prev_page(&mut self) -> Result<Option<Page>, FileError> {
+        match self.current_page {
+            Some(page) if page > 0 => self.page(page - 1),
+            _ => Ok(None),
+        }
+    }
+
+    /// Jump to page `number`, indexing forward as needed
+    pub fn page(&mut self, number: usize) -> Result<Option<Page>, FileError> {
+        if !self.index_to(number)? {
+            return Ok(None);
+        }
+
+        self.reader.seek(SeekFrom::Start(self.page_offsets[number]))?;
+        let mut lines = Vec::with_capacity(self.page_size);
+        let mut bytes = 0u64;
+        let mut buf = Vec::new();
+        while lines.len() < self.page_size {
+            buf.clear();
+            let read = self.reader.read_until(b'\n', &mut buf)?;
+            if read == 0 {
+                break;
+            }
+            bytes += read as u64;
+            lines.push(decode_line(&buf));
+        }
+
+        if lines.is_empty() {
+            return Ok(None);
+        }
+
+        let start_line = number * self.page_size + 1;
+        let end_offset = self.page_offsets[number] + bytes;
+        self.record_scan(end_offset, start_line - 1 + lines.len());
+
+        let is_last = end_offset >= self.file_len;
+        if is_last {
+            self.total_lines = Some(start_line - 1 + lines.len());
+        } else if self.page_offsets.len() == number + 1 {
+            self.page_offsets.push(end_offset);
+        }
+
+        self.current_page = Some(number);
+        Ok(Some(Page {
+            number,
+            start_line,
+            lines,
+            is_last,
+        }))
+    }
+
+    /// Exact line count if the end of the file has been reached, otherwise an estimate
+    /// extrapolated from the average line length seen so far
+    pub fn estimated_total_lines(&self) -> usize {
+        if let Some(total) = self.total_lines {
+            return total;
+        }
+        let (bytes, lines) = self.scanned;
+        if bytes == 0 {
+            return 0;
+        }
+        ((lines as f64) * (self.file_len as f64) / (bytes as f64)).ceil() as usize
+    }
+
+    /// Whether `estimated_total_lines` is exact
+    pub fn is_total_exact(&self) -> bool {
+        self.total_lines.is_some()
+    }
+
+    /// Make sure the start offset of page `number` is known.
+    /// Returns false if the file ends before that page.
+    fn index_to(&mut self, number: usize) -> Result<bool, FileError> {
+        while self.page_offsets.len() <= number {
+            let last = self.page_offsets.len() - 1;
+            let mut offset = self.page_offsets[last];
+            self.reader.seek(SeekFrom::Start(offset))?;
+
+            // Skip a page's worth of lines without keeping them
+            let mut buf = Vec::new();
+            let mut skipped = 0;
+            while skipped < self.page_size {
+                buf.clear();
+                let read = self.reader.read_until(b'\n', &mut buf)?;
+                if read == 0 {
+                    break;
+                }
+                offset += read as u64;
+                skipped += 1;
+            }
+            self.record_scan(offset, last * self.page_size + skipped);
+
+            if offset >= self.file_len {
+                self.total_lines = Some(last * self.page_size + skipped);
+                return Ok(false);
+            }
+            self.page_offsets.push(offset);
+        }
+        Ok(true)
+    }
+
+    fn record_scan(&mut self, bytes: u64, lines: usize) {
+        if bytes > self.scanned.0 {
+            self.scanned = (bytes, lines);
+        }
+    }
+}
+
+fn decode_line(buf: &[u8]) -> String {
+    let line = buf.strip_suffix(b"\n").unwrap_or(buf);
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    String::from_utf8_lossy(line).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::write;
+    use tempfile::TempDir;
+
+    fn large_file(temp: &TempDir, lines: usize) -> std::path::PathBuf {
+        let path = temp.path().join("large.log");
+        let content: String = (1..=lines).map(|i| format!("log line {}\n", i)).collect();
+        write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_paging_forward_and_backward() {
+        let temp = TempDir::new().unwrap();
+        let path = large_file(&temp, 10_050);
+        let mut reader = PagedReader::open(&path, 100).unwrap();
+
+        let first = reader.next_page().unwrap().unwrap();
+        assert_eq!(first.number, 0);
+        assert_eq!(first.start_line, 1);
+        assert_eq!(first.lines.len(), 100);
+        assert_eq!(first.lines[0], "log line 1");
+        assert!(!first.is_last);
+        assert!(reader.prev_page().unwrap().is_none());
+
+        let second = reader.next_page().unwrap().unwrap();
+        assert_eq!(second.start_line, 101);
+        assert_eq!(second.lines[99], "log line 200");
+
+        let back = reader.prev_page().unwrap().unwrap();
+        assert_eq!(back, first);
+
+        // Walk to the end
+        let mut last = back;
+        while let Some(page) = reader.next_page().unwrap() {
+            last = page;
+        }
+        assert_eq!(last.number, 100);
+        assert!(last.is_last);
+        assert_eq!(last.lines.len(), 50);
+        assert_eq!(last.lines[49], "log line 10050");
+        assert!(reader.is_total_exact());
+        assert_eq!(reader.estimated_total_lines(), 10_050);
+
+        // And back again from the end
+        let previous = reader.prev_page().unwrap().unwrap();
+        assert_eq!(previous.number, 99);
+        assert_eq!(previous.start_line, 9_901);
+        assert_eq!(previous.lines[0], "log line 9901");
+    }
+
+    #[test]
+    fn test_random_access_and_estimate() {
+        let temp = TempDir::new().unwrap();
+        let path = large_file(&temp, 5_000);
+        let mut reader = PagedReader::open(&path, 250).unwrap();
+
+        let page = reader.page(7).unwrap().unwrap();
+        assert_eq!(page.lines[0], "log line 1751");
+        assert!(!reader.is_total_exact());
+
+        // Lines vary in width, so the estimate is close but not exact
+        let estimate = reader.estimated_total_lines();
+        assert!((4_500..=5_500).contains(&estimate), "estimate {}", estimate);
+
+        assert!(reader.page(20).unwrap().is_none());
+        assert_eq!(reader.estimated_total_lines(), 5_000);
+    }
+
+    #[test]
+    fn test_small_and_empty_files() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("small.txt");
+        write(&path, "one\r\ntwo\nthree").unwrap();
+
+        let mut reader = PagedReader::open(&path, 10).unwrap();
+        let page = reader.next_page().unwrap().unwrap();
+        assert_eq!(page.lines, vec!["one", "two", "three"]);
+        assert!(page.is_last);
+        assert!(reader.next_page().unwrap().is_none());
+
+        let empty = temp.path().join("empty.txt");
+        write(&empty, "").unwrap();
+        let mut reader = PagedReader::open(&empty, 10).unwrap();
+        assert!(reader.next_page().unwrap().is_none());
+        assert_eq!(reader.estimated_total_lines(), 0);
+    }
+}
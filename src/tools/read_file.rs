@@ -5,6 +5,8 @@ use std::{
 };
 
 use crate::error::TaskError;
+use crate::fs::safe_join;
+use crate::tools::ToJson;
 
 const MAX_READ_FILE_LINES: usize = 10000;
 const BINARY_CHECK_SIZE: usize = 8000;
@@ -39,19 +41,23 @@ impl From<FileError> for TaskError {
     }
 }
 
+#[derive(serde::Serialize)]
 pub struct FileStats {
     pub total_lines: usize,
     pub is_truncated: bool,
     pub is_binary: bool,
 }
 
+#[derive(serde::Serialize)]
 pub struct ReadFileResult {
     pub content: String,
     pub stats: FileStats,
 }
 
+impl ToJson for ReadFileResult {}
+
 /// Checks if a file appears to be binary by examining its first N bytes
-fn is_binary_file(mut file: &File) -> io::Result<bool> {
+pub(super) fn is_binary_file(mut file: &File) -> io::Result<bool> {
     let mut buffer = vec![0; BINARY_CHECK_SIZE];
     let bytes_read = file.read(&mut buffer)?;
     buffer.truncate(bytes_read);
@@ -117,17 +123,13 @@ pub fn read_file_with_lines(
     let path = path.as_ref();
 
     // Validate path is within workspace
-    let canonical_path = path
-        .canonicalize()
-        .map_err(|_| FileError::NotFound(path.to_path_buf()))?;
-    let workspace_root = workspace_root.as_ref().canonicalize()?;
-
-    if !canonical_path.starts_with(workspace_root) {
-        return Err(FileError::OutsideWorkspace);
-    }
+    let resolved = safe_join(workspace_root, path).map_err(|err| match err {
+        TaskError::PathOutsideWorkspace(_) => FileError::OutsideWorkspace,
+        _ => FileError::NotFound(path.to_path_buf()),
+    })?;
 
     // Open and check if binary
-    let mut file = File::open(path).map_err(|_| FileError::NotFound(path.to_path_buf()))?;
+    let mut file = File::open(&resolved).map_err(|_| FileError::NotFound(path.to_path_buf()))?;
     let is_binary = is_binary_file(&file)?;
 
     if is_binary {
@@ -208,4 +210,36 @@ mod tests {
         let result = read_file_with_lines(&test_file, temp.path(), None, None);
         assert!(matches!(result, Err(FileError::BinaryFile)));
     }
+
+    #[test]
+    fn test_result_json_shape() {
+        let temp = TempDir::new().unwrap();
+        let test_file = temp.path().join("test.txt");
+        write(&test_file, "line 1\nline 2").unwrap();
+
+        let result = read_file_with_lines(&test_file, temp.path(), None, None).unwrap();
+        assert_eq!(
+            result.to_json().unwrap(),
+            serde_json::json!({
+                "content": "1 | line 1\n2 | line 2\n",
+                "stats": {"total_lines": 2, "is_truncated": false, "is_binary": false}
+            })
+        );
+    }
+
+    #[test]
+    fn test_paths_resolve_against_workspace() {
+        let temp = TempDir::new().unwrap();
+        std::fs::create_dir(temp.path().join("src")).unwrap();
+        write(temp.path().join("src/lib.rs"), "pub fn lib() {}").unwrap();
+
+        let result = read_file_with_lines("src/lib.rs", temp.path(), None, None).unwrap();
+        assert!(result.content.contains("1 | pub fn lib() {}"));
+
+        let escape = read_file_with_lines("src/../../etc/passwd", temp.path(), None, None);
+        assert!(matches!(escape, Err(FileError::OutsideWorkspace)));
+
+        let missing = read_file_with_lines("src/missing.rs", temp.path(), None, None);
+        assert!(matches!(missing, Err(FileError::NotFound(_))));
+    }
 }
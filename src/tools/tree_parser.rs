@@ -1,7 +1,10 @@
 use crate::error::TaskError;
+use crate::tools::ToJson;
 use crate::services::tree_sitter::{
-    SupportedLanguage, Symbol, TreeSitterError, TreeSitterService,
+    SupportedLanguage, Symbol, SymbolKind, TreeSitterError, TreeSitterService,
 };
+use std::cmp::Reverse;
+use std::ops::Range;
 use std::path::Path;
 
 #[derive(serde::Serialize, serde::Deserialize)]
@@ -9,6 +12,26 @@ pub struct ParseFileResult {
     pub file_path: String,
     pub symbols: Vec<Symbol>,
     pub language: String,
+    /// Definitions with exact source spans, ordered by position
+    #[serde(default)]
+    pub nodes: Vec<ParsedNode>,
+}
+
+impl ToJson for ParseFileResult {}
+
+/// A definition in the parsed source, located precisely enough to edit in place
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ParsedNode {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub start_line: usize,
+    pub end_line: usize,
+    /// Byte offsets of the whole definition, e.g. a function including its body
+    pub byte_range: Range<usize>,
+    /// Byte offsets of the definition's name
+    pub name_range: Range<usize>,
+    /// Index in `nodes` of the innermost definition enclosing this one
+    pub parent: Option<usize>,
 }
 
 /// Error types specific to tree parser operations
@@ -28,7 +51,7 @@ impl From<TreeSitterError> for TreeParserError {
     fn from(err: TreeSitterError) -> Self {
         match err {
             TreeSitterError::UnsupportedLanguage(lang) => Self::UnsupportedLanguage(lang),
-            TreeSitterError::FileSizeExceeded => Self::FileSizeExceeded,
+            TreeSitterError::FileSizeExceeded { .. } => Self::FileSizeExceeded,
             _ => Self::ParseError(err.to_string()),
         }
     }
@@ -57,35 +80,149 @@ pub fn parse_file(
 
     // Find symbols in the file
     let symbols = service.find_symbols(file_path, content)?;
+    let nodes = definition_nodes(service, file_path, content, language)?;
 
     Ok(ParseFileResult {
         file_path: file_path.to_string_lossy().to_string(),
         symbols,
         language: format!("{:?}", language),
+        nodes,
     })
 }
 
+/// Collect definitions with their byte spans and link each to its enclosing definition
+fn definition_nodes(
+    service: &TreeSitterService,
+    file_path: &Path,
+    content: &str,
+    language: SupportedLanguage,
+) -> Result<Vec<ParsedNode>, TreeParserError> {
+    let tree = service.parse_file(file_path, content)?;
+    let matches = service.get_definitions(language, &tree, content)?;
+
+    let mut nodes: Vec<ParsedNode> = matches
+        .iter()
+        .filter_map(|m| {
+            let name = m.captures.iter().find(|c| c.name.contains(".name"))?;
+            let definition = m
+                .captures
+                .iter()
+                .find(|c| c.name.ends_with(".definition"))
+                .unwrap_or(name);
+
+            Some(ParsedNode {
+                name: name.text.clone(),
+                kind: service.determine_symbol_kind(&definition.name),
+                start_line: definition.start_position.0,
+                end_line: definition.end_position.0,
+                byte_range: definition.byte_range.clone(),
+                name_range: name.byte_range.clone(),
+                parent: None,
+            })
+        })
+        .collect();
+
+    // Outer definitions sort before the ones they contain. The same node can match
+    // more than one pattern (e.g. an exported function), so drop repeats.
+    nodes.sort_by_key(|node| (node.byte_range.start, Reverse(node.byte_range.end)));
+    nodes.dedup_by(|a, b| a.byte_range == b.byte_range && a.name_range == b.name_range);
+
+    let mut enclosing: Vec<usize> = Vec::new();
+    for i in 0..nodes.len() {
+        while let Some(&outer) = enclosing.last() {
+            if nodes[i].byte_range.end <= nodes[outer].byte_range.end {
+                break;
+            }
+            enclosing.pop();
+        }
+        nodes[i].parent = enclosing.last().copied();
+        enclosing.push(i);
+    }
+
+    Ok(nodes)
+}
+
 /// Parse code definitions from a string with a specified language
 pub fn parse_code_string(
     service: &TreeSitterService,
     content: &str,
     language: SupportedLanguage,
 ) -> Result<Vec<Symbol>, TreeParserError> {
-    // Create a temporary file path with the right extension
-    let ext = match language {
-        SupportedLanguage::JavaScript => "js",
-        SupportedLanguage::TypeScript => "ts",
-        SupportedLanguage::Python => "py",
-        SupportedLanguage::Rust => "rs",
-        SupportedLanguage::Markdown => "md",
-    };
-    
-    // Create a longer-lived temporary path
-    let temp_path_str = format!("temp.{}", ext);
-    let temp_path = Path::new(&temp_path_str);
-    
-    // Find symbols in the content using the temporary path
-    let symbols = service.find_symbols(temp_path, content)?;
-    
-    Ok(symbols)
-}
\ No newline at end of file
+    Ok(service.find_symbols_in_str(language, content)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "struct Point {\n    x: i32,\n}\n\nfn norm(p: &Point) -> i32 {\n    fn abs(x: i32) -> i32 {\n        x.abs()\n    }\n    abs(p.x)\n}\n\nfn main() {}\n";
+
+    #[test]
+    fn test_byte_ranges_map_back_to_source() {
+        let service = TreeSitterService::new(1024 * 1024, 2);
+        let result = parse_file(&service, Path::new("point.rs"), SOURCE).unwrap();
+
+        let spans: Vec<(&str, &str)> = result
+            .nodes
+            .iter()
+            .map(|node| (&SOURCE[node.name_range.clone()], &SOURCE[node.byte_range.clone()]))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                ("Point", "struct Point {\n    x: i32,\n}"),
+                (
+                    "norm",
+                    "fn norm(p: &Point) -> i32 {\n    fn abs(x: i32) -> i32 {\n        x.abs()\n    }\n    abs(p.x)\n}"
+                ),
+                ("abs", "fn abs(x: i32) -> i32 {\n        x.abs()\n    }"),
+                ("main", "fn main() {}"),
+            ]
+        );
+
+        for node in &result.nodes {
+            assert_eq!(SOURCE[node.name_range.clone()], node.name);
+        }
+        assert_eq!(result.nodes[0].kind, SymbolKind::Struct);
+        assert_eq!((result.nodes[2].start_line, result.nodes[2].end_line), (6, 8));
+    }
+
+    #[test]
+    fn test_parent_links_follow_nesting() {
+        let service = TreeSitterService::new(1024 * 1024, 2);
+        let result = parse_file(&service, Path::new("point.rs"), SOURCE).unwrap();
+
+        let parents: Vec<Option<usize>> = result.nodes.iter().map(|node| node.parent).collect();
+        assert_eq!(parents, vec![None, None, Some(1), None]);
+
+        // Existing fields are still filled in
+        assert_eq!(result.language, "Rust");
+        assert_eq!(result.symbols.len(), 4);
+    }
+
+    #[test]
+    fn test_result_json_shape() {
+        let service = TreeSitterService::new(1024 * 1024, 2);
+        let result = parse_file(&service, Path::new("main.rs"), "fn main() {}\n").unwrap();
+
+        assert_eq!(
+            result.to_json().unwrap(),
+            serde_json::json!({
+                "file_path": "main.rs",
+                "language": "Rust",
+                "symbols": [
+                    {"name": "main", "kind": "Function", "start_line": 1, "end_line": 1}
+                ],
+                "nodes": [{
+                    "name": "main",
+                    "kind": "Function",
+                    "start_line": 1,
+                    "end_line": 1,
+                    "byte_range": {"start": 0, "end": 12},
+                    "name_range": {"start": 3, "end": 7},
+                    "parent": null
+                }]
+            })
+        );
+    }
+}
@@ -1,17 +1,52 @@
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
-use std::collections::VecDeque;
-use std::path::Path;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{Duration, Instant};
 
-use crate::mcp::client::OpenRouterClient;
-use crate::services::tree_sitter::TreeSitterService;
+use crate::activity::{ActivityEvent, ActivityLog};
+use crate::config::{Config, LlmTask};
+use crate::cqrs::EventStore;
+use crate::cqrs::tool_calls;
+use crate::error::SamusError;
+use crate::fs::{SamusIgnore, WorkspacePaths};
+use crate::fs::operations::{FileSystem, LocalFileSystem};
+use crate::mcp::client::{
+    ChatCompletion, ChatTurn, DEFAULT_MODEL, OpenRouterClient, Role, continuation_turns,
+};
+use crate::mcp::context::{estimate_tokens, model_context_length};
+use crate::oneshot::piped_context_turn;
+use crate::shell::terminal::TerminalManager;
+use crate::services::ripgrep::ranking::{RankContext, rank_results};
+use crate::services::tree_sitter::{
+    IndexChange, SymbolCache, SymbolIndex, TreeSitterError, TreeSitterService,
+};
 use crate::task::TaskManagerTrait;
+use crate::tools::AutoApplyOutcome;
+use crate::ui::context_files::PinnedFiles;
+use crate::ui::diff::{DiffConfig, DiffViewMode, DiffVisualization};
+use crate::ui::grep::GroupedResults;
 use crate::ui::input::{InputCommand, InputHandler, InputMode};
+use crate::ui::outline::{
+    MAX_INDEXED_FILES, MAX_OUTLINE_FILES, outline_files, parse_outline_args, symbol_row,
+    symbol_row_count,
+};
 use crate::ui::output::OutputManager;
+use crate::ui::pager::ResultPager;
+use crate::ui::plain_text::lines_to_plain_text;
+use crate::ui::prompt_template::{PromptContext, PromptTemplateRegistry};
+use crate::ui::recent_files::RecentFiles;
+use crate::ui::search::SearchOptions;
+use crate::ui::spinner::Spinner;
+use crate::ui::status::{Health, StatusReport};
+use crate::ui::terminal::TerminalViewManager;
 
-/// Maximum number of chat messages to keep in history
-const MAX_CHAT_HISTORY: usize = 100;
+/// Lines moved by PageUp/PageDown in the Terminals view
+const TERMINAL_SCROLL_LINES: usize = 10;
+
+/// Most recent slow-handler warnings `/metrics` lists
+const MAX_SLOW_HANDLERS_SHOWN: usize = 5;
 
 /// Represents different view types for the main area
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -22,6 +57,33 @@ pub enum MainViewType {
     LlmResponse,
     Search,
     CodeOutline,
+    Terminals,
+}
+
+impl MainViewType {
+    /// Names accepted by `from_name`, as used in `ui.default_view`
+    pub const NAMES: [&'static str; 7] = [
+        "file_tree",
+        "git_diff",
+        "shell_output",
+        "llm_response",
+        "search",
+        "code_outline",
+        "terminals",
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "file_tree" => Some(Self::FileTree),
+            "git_diff" => Some(Self::GitDiff),
+            "shell_output" => Some(Self::ShellOutput),
+            "llm_response" => Some(Self::LlmResponse),
+            "search" => Some(Self::Search),
+            "code_outline" => Some(Self::CodeOutline),
+            "terminals" => Some(Self::Terminals),
+            _ => None,
+        }
+    }
 }
 
 /// Represents a chat message with metadata
@@ -32,12 +94,54 @@ pub struct ChatMessage {
     pub timestamp: Instant,
 }
 
+/// Appended to an assistant message the model cut off at its token limit
+pub const TRUNCATION_MARKER: &str = "\n\n(truncated — /continue to get more)";
+
+/// The latest prompt and everything received for it so far, kept so `/continue`
+/// can resume a truncated answer
+#[derive(Debug, Clone)]
+pub struct LlmExchange {
+    pub prompt: String,
+    pub response: String,
+    pub truncated: bool,
+    /// Model that answered, so `/continue` and `/retry` stay on it
+    pub model: String,
+}
+
+/// Estimated size of a prompt before it's sent
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromptPreview {
+    /// Tokens in the whole request, including pinned and piped context
+    pub tokens: usize,
+    pub model: String,
+    /// The model's context window, if known
+    pub context_length: Option<usize>,
+}
+
+impl PromptPreview {
+    pub fn exceeds_context(&self) -> bool {
+        self.context_length.is_some_and(|length| self.tokens > length)
+    }
+
+    /// Status bar text, e.g. "~120 tokens"
+    pub fn summary(&self) -> String {
+        match self.context_length {
+            Some(length) if self.exceeds_context() => format!(
+                "~{} tokens, over the {} token limit of {}",
+                self.tokens, length, self.model
+            ),
+            _ => format!("~{} tokens", self.tokens),
+        }
+    }
+}
+
 /// Represents a code symbol for display
 #[derive(Debug, Clone)]
 pub struct DisplaySymbol {
     pub name: String,
     pub kind: String,
     pub line: usize,
+    /// Absolute path of the file; shown relative to the workspace
     pub path: String,
 }
 
@@ -53,11 +157,52 @@ pub struct App {
     pub input_mode: InputMode,
     pub command_history: VecDeque<String>,
     pub history_index: Option<usize>,
+    pub command_history_limit: usize,
+    pub command_history_file: Option<PathBuf>,
 
     // Chat state
     pub chat_messages: VecDeque<ChatMessage>,
+    pub chat_history_limit: usize,
     pub llm_client: Option<OpenRouterClient>,
+    /// Configured MCP servers, for `/status`
+    pub mcp_servers: Vec<crate::config::McpServerConfig>,
+    /// Input piped to samus at startup, sent as context with each request
+    pub piped_context: Option<String>,
+    /// Files pinned with `/context-add`, sent as context with each request
+    pub pinned_files: PinnedFiles,
+    /// Where edited files are backed up, and so where `/undo` restores them from
+    backups: crate::config::BackupConfig,
+    /// Which edits `/apply` makes without asking first
+    auto_apply: crate::config::AutoApplyConfig,
+    /// File whose edit `/apply` is waiting to have confirmed
+    pending_edit: Option<String>,
+    /// Whether `/new` clears the chat view as well as the conversation
+    clear_chat_on_new: bool,
+    /// `/new` was refused pending confirmation; another `/new` goes ahead
+    new_conversation_pending: bool,
+    /// Command shown by a `!?` dry run, run for real by `/run`
+    previewed_command: Option<String>,
+    /// Whether the shell task in flight is a dry run
+    shell_dry_run: bool,
+    /// Models chosen per kind of request, from `task_models` in the config
+    pub task_models: HashMap<LlmTask, String>,
+    /// JSON-lines record of messages and tool calls, from `--events`
+    pub activity: Option<ActivityLog>,
+    /// Tool whose result is still being worked on in the background
+    pending_tool: Option<String>,
     pub is_processing: bool,
+    /// Most recent LLM exchange, for `/continue`
+    pub last_exchange: Option<LlmExchange>,
+    /// Earlier exchanges of the conversation, oldest first, sent ahead of each prompt
+    pub conversation: Vec<ChatTurn>,
+    /// Whether the in-flight LLM request continues `last_exchange`
+    pub continuing_response: bool,
+    /// Prompt templates usable as slash commands, e.g. `/explain`
+    pub prompt_templates: PromptTemplateRegistry,
+    /// Slash command (without the `/`) queued to run once startup finishes
+    pub startup_command: Option<String>,
+    /// User message loaded back into the input; submitting rewinds the chat to before it
+    pub editing_message: Option<ChatMessage>,
 
     // View state
     pub current_main_view: MainViewType,
@@ -66,45 +211,220 @@ pub struct App {
 
     // Code analysis state
     pub tree_sitter_service: Option<Arc<TreeSitterService>>,
+    /// Symbols of recently outlined files, reused while their content is unchanged
+    pub symbol_cache: SymbolCache,
     pub current_file_symbols: Vec<DisplaySymbol>,
+    /// How many of `current_file_symbols` the Code Outline view shows
+    pub outline_pager: ResultPager,
+    /// Index into `current_file_symbols` of the symbol Enter jumps to
+    pub outline_selected: usize,
+    /// Files recently referenced or outlined, for `/recent`
+    pub recent_files: RecentFiles,
+    /// Symbols across the workspace for `/symbol`, built on first use
+    pub symbol_index: Option<SymbolIndex>,
+    /// Score threshold and result cap for fuzzy matches such as `/recent <query>`
+    pub fuzzy_options: SearchOptions,
+    /// Absolute path of the file last referenced or outlined
     pub current_file_path: Option<String>,
+    /// Resolves typed paths and shortens displayed ones
+    pub workspace: WorkspacePaths,
 
     // Task management
-    pub task_manager: Option<Arc<crate::task::TaskManager>>,
+    pub task_manager: Option<Arc<dyn TaskManagerTrait>>,
+    /// Store and session id the task manager records tool calls under, for `/history`
+    pub tool_call_log: Option<(Arc<dyn EventStore>, String)>,
+    /// Cancellation flag for the running `/grep`, if any
+    pub grep_cancel: Option<Arc<AtomicBool>>,
+    /// Which ripgrep binary `/grep` runs
+    pub ripgrep_binary: crate::services::ripgrep::RipgrepBinaryPreference,
+    /// Weights `/grep` results are ranked by before they're shown
+    pub search_ranking: crate::services::ripgrep::ranking::RankingWeights,
+    /// Results of the most recent `/grep`, shown in the Search view
+    pub last_grep: Option<crate::ui::grep::GrepOutcome>,
+    /// `last_grep` results grouped by file for the Search view
+    pub grep_groups: GroupedResults,
+    /// How many rows of `grep_groups` the Search view shows
+    pub grep_pager: ResultPager,
+    /// Cancellation for the running `!command`, if any
+    pub shell_cancel: Option<crate::context::CancellationToken>,
+    /// Seconds a `!command` runs before it's killed, unless it gives its own timeout
+    pub command_timeout_secs: u64,
+    /// Timeout of the running `!command`, to report if it's hit
+    shell_timeout: Option<u64>,
+    /// Terminal views opened with `/terminal new`; `!commands` run in the active one
+    pub terminals: TerminalViewManager,
+    /// Concrete manager behind `terminals`, for saving them between sessions
+    terminal_manager: Arc<TerminalManager>,
+    terminal_state_file: Option<PathBuf>,
+    /// Terminal that the running `!command` belongs to; its output is appended there
+    pub shell_terminal: Option<uuid::Uuid>,
+
+    /// Diff shown in the GitDiff view, from `/diff <old> <new>`
+    pub active_diff: Option<DiffVisualization>,
+    /// File whose lines `active_diff` shows on its new side
+    active_diff_path: Option<PathBuf>,
+    /// Mode new diffs open in; follows the last mode switch
+    pub diff_view_mode: DiffViewMode,
+
+    /// Busy indicator shown while `is_processing`
+    pub spinner: Spinner,
 
     // Application timing
     pub tick_rate: Duration,
     pub last_tick: Instant,
+    /// Whether anything shown on screen may have changed since the last draw
+    needs_redraw: bool,
 }
 
 impl App {
     pub fn new() -> Self {
-        Self {
+        Self::with_config(&Config::default())
+    }
+
+    /// Create the app using history limits and persistence settings from `config`
+    pub fn with_config(config: &Config) -> Self {
+        let command_history_limit = config.history.command_limit;
+        let chat_history_limit = config.history.chat_limit.max(1);
+
+        let mut prompt_templates = PromptTemplateRegistry::with_defaults();
+        for template in &config.prompt_templates {
+            prompt_templates.register(template.clone());
+        }
+
+        let terminal_manager = Arc::new(TerminalManager::new());
+
+        let mut app = Self {
             input_handler: InputHandler::new(),
             output_manager: OutputManager::new(),
 
             input_text: String::new(),
             cursor_position: 0,
             input_mode: InputMode::Normal,
-            command_history: VecDeque::with_capacity(50),
+            command_history: VecDeque::with_capacity(command_history_limit),
             history_index: None,
+            command_history_limit,
+            command_history_file: config.history.command_history_file.clone(),
 
-            chat_messages: VecDeque::with_capacity(MAX_CHAT_HISTORY),
+            chat_messages: VecDeque::with_capacity(chat_history_limit),
+            chat_history_limit,
             llm_client: None,
+            mcp_servers: config.mcp_servers.clone(),
+            piped_context: None,
+            pinned_files: PinnedFiles::with_template(config.context.file_template.clone()),
+            backups: config.backups.clone(),
+            auto_apply: config.auto_apply.clone(),
+            pending_edit: None,
+            clear_chat_on_new: config.history.clear_chat_on_new,
+            new_conversation_pending: false,
+            previewed_command: None,
+            shell_dry_run: false,
+            task_models: config.task_models.clone(),
+            activity: None,
+            pending_tool: None,
             is_processing: false,
+            last_exchange: None,
+            conversation: Vec::new(),
+            continuing_response: false,
+            prompt_templates,
+            editing_message: None,
+            startup_command: config
+                .ui
+                .startup_command
+                .as_deref()
+                .map(|cmd| cmd.trim().trim_start_matches('/').to_string())
+                .filter(|cmd| !cmd.is_empty()),
 
-            current_main_view: MainViewType::ShellOutput,
+            current_main_view: config
+                .ui
+                .default_view
+                .as_deref()
+                .and_then(MainViewType::from_name)
+                .unwrap_or(MainViewType::ShellOutput),
             should_quit: false,
             displaying_completion: false,
 
             tree_sitter_service: None,
+            symbol_cache: SymbolCache::default(),
             current_file_symbols: Vec::new(),
+            outline_pager: ResultPager::new(config.ui.result_page_size()),
+            outline_selected: 0,
+            recent_files: RecentFiles::new(config.history.recent_files_limit)
+                .with_file(config.history.recent_files_file.clone()),
+            fuzzy_options: SearchOptions::from_config(&config.ui),
+            symbol_index: None,
             current_file_path: None,
+            workspace: WorkspacePaths::from_current_dir(),
             
             task_manager: None,
+            tool_call_log: None,
+            grep_cancel: None,
+            ripgrep_binary: config.ripgrep.binary.clone(),
+            search_ranking: config.ripgrep.ranking.clone(),
+            last_grep: None,
+            grep_groups: GroupedResults::default(),
+            grep_pager: ResultPager::new(config.ui.result_page_size()),
+            shell_cancel: None,
+            command_timeout_secs: config
+                .terminal
+                .command_timeout_secs
+                .unwrap_or(crate::shell::command::DEFAULT_TIMEOUT),
+            shell_timeout: None,
+            terminals: TerminalViewManager::new(terminal_manager.clone())
+                .with_frequency_file(config.history.command_frequency_file.clone())
+                .with_scroll_behavior(config.terminal.scroll_behavior),
+            terminal_manager,
+            terminal_state_file: config.terminal.state_file.clone(),
+            shell_terminal: None,
+
+            active_diff: None,
+            active_diff_path: None,
+            diff_view_mode: config.ui.diff_view_mode,
+
+            spinner: Spinner::new(config.ui.spinner),
 
-            tick_rate: Duration::from_millis(250),
+            tick_rate: config.ui.tick_rate(),
             last_tick: Instant::now(),
+            needs_redraw: true,
+        };
+
+        app.load_command_history();
+        app.restore_terminals();
+        app
+    }
+
+    /// Reopen the terminals saved by the last session
+    fn restore_terminals(&mut self) {
+        let Some(path) = &self.terminal_state_file else {
+            return;
+        };
+
+        match self.terminal_manager.restore_state(path) {
+            Ok(instances) => {
+                for instance in instances {
+                    self.terminals.add_view(instance);
+                }
+            }
+            Err(e) => crate::logging::log(
+                crate::config::LogLevel::Warn,
+                "terminal",
+                format!("Failed to restore terminals from {}: {}", path.display(), e),
+            ),
+        }
+    }
+
+    /// Save open terminals' titles and working directories for the next session
+    pub fn save_terminals(&self) {
+        let Some(path) = &self.terminal_state_file else {
+            return;
+        };
+
+        // Best effort, like command history
+        if let Err(e) = self.terminal_manager.save_state(path) {
+            crate::logging::log(
+                crate::config::LogLevel::Warn,
+                "terminal",
+                format!("Failed to save terminals to {}: {}", path.display(), e),
+            );
         }
     }
     
@@ -119,10 +439,22 @@ impl App {
     }
     
     /// Set the task manager
-    pub fn set_task_manager(&mut self, task_manager: Arc<crate::task::TaskManager>) {
+    pub fn set_task_manager(&mut self, task_manager: Arc<dyn TaskManagerTrait>) {
         self.task_manager = Some(task_manager);
     }
 
+    /// Where the task manager records tool calls, so `/history` can replay them
+    pub fn set_tool_call_log(&mut self, store: Arc<dyn EventStore>, session_id: impl Into<String>) {
+        self.tool_call_log = Some((store, session_id.into()));
+    }
+
+    /// Run the configured startup command, if any; call once services are initialized
+    pub fn run_startup_command(&mut self) {
+        if let Some(command) = self.startup_command.take() {
+            self.process_slash_command(&command);
+        }
+    }
+
     /// Initialize TreeSitter service
     pub fn init_tree_sitter(&mut self, max_file_size: usize, max_parsers_per_lang: usize) {
         self.tree_sitter_service = Some(Arc::new(TreeSitterService::new(
@@ -136,27 +468,51 @@ impl App {
         &mut self,
         config: crate::config::McpServerConfig,
     ) -> Result<(), crate::error::TaskError> {
-        // Initialize with Claude 3.5 Haiku as the default model
-        let client = OpenRouterClient::new(config, "anthropic/claude-3-haiku".to_string())?;
+        // Start on the configured chat model, or Claude 3 Haiku
+        let model = self
+            .task_models
+            .get(&LlmTask::Chat)
+            .map(String::as_str)
+            .unwrap_or(DEFAULT_MODEL);
+        let client = OpenRouterClient::new(config, model.to_string())?;
         self.llm_client = Some(client);
         Ok(())
     }
 
-    /// Add a message to the chat history
-    pub fn add_chat_message(&mut self, content: String, is_user: bool) {
-        if self.chat_messages.len() >= MAX_CHAT_HISTORY {
-            self.chat_messages.pop_front();
+    /// Keep input piped to samus at startup as context for every request
+    pub fn load_piped_input(&mut self, content: String) {
+        if content.trim().is_empty() {
+            return;
         }
+        let lines = content.lines().count();
+        self.piped_context = Some(content);
+        self.add_chat_message(
+            format!(
+                "Loaded {} line{} from stdin; it's sent along with each question",
+                lines,
+                if lines == 1 { "" } else { "s" }
+            ),
+            false,
+        );
+    }
 
+    /// Add a message to the chat history
+    pub fn add_chat_message(&mut self, content: String, is_user: bool) {
         self.chat_messages.push_back(ChatMessage {
             content,
             is_user,
             timestamp: Instant::now(),
         });
+
+        // Evict the oldest messages first
+        while self.chat_messages.len() > self.chat_history_limit {
+            self.chat_messages.pop_front();
+        }
     }
 
     /// Add a command to history
     pub fn add_to_history(&mut self, command: String) {
+        let command = history_entry(&command);
         if command.is_empty()
             || (self
                 .command_history
@@ -166,12 +522,51 @@ impl App {
             return;
         }
 
-        if self.command_history.len() >= 50 {
-            self.command_history.pop_back();
-        }
-
+        // Newest commands live at the front, so the oldest are evicted from the back
         self.command_history.push_front(command);
+        self.command_history.truncate(self.command_history_limit);
         self.history_index = None;
+
+        self.save_command_history();
+    }
+
+    /// Load persisted command history, if a history file is configured
+    fn load_command_history(&mut self) {
+        let Some(path) = &self.command_history_file else {
+            return;
+        };
+
+        let Ok(content) = std::fs::read_to_string(path) else {
+            return;
+        };
+
+        if let Ok(commands) = serde_json::from_str::<Vec<String>>(&content) {
+            // Older files may hold repeats or unmasked keys; clean them up on the way in
+            let mut commands: Vec<String> = commands.iter().map(|c| history_entry(c)).collect();
+            commands.retain(|c| !c.is_empty());
+            commands.dedup();
+            self.command_history = commands.into_iter().collect();
+            self.command_history.truncate(self.command_history_limit);
+        }
+    }
+
+    /// Persist command history so it survives restarts
+    fn save_command_history(&self) {
+        let Some(path) = &self.command_history_file else {
+            return;
+        };
+
+        if path
+            .parent()
+            .is_some_and(|parent| std::fs::create_dir_all(parent).is_err())
+        {
+            return;
+        }
+
+        // Persisting history is best effort; a failure here shouldn't interrupt input
+        if let Ok(content) = serde_json::to_string(&self.command_history) {
+            let _ = std::fs::write(path, content);
+        }
     }
 
     /// Process input text
@@ -184,8 +579,40 @@ impl App {
             return;
         }
 
-        self.add_to_history(input.clone());
-        self.add_chat_message(input.clone(), true);
+        if let Some(edited) = self.editing_message.take() {
+            self.rewind_before(&edited);
+        }
+
+        // Anything other than a repeated /new withdraws the offer to clear
+        if input.trim() != "/new" {
+            self.new_conversation_pending = false;
+        }
+        // and an edit waiting on /apply must be confirmed straight away
+        if !input.trim().starts_with("/apply") {
+            self.pending_edit = None;
+        }
+        // Likewise a previewed command must be run straight away
+        if input.trim() != "/run" && !input.starts_with("!?") {
+            self.previewed_command = None;
+        }
+
+        // Never echo or persist a raw API key typed into /config
+        let display_input = history_entry(&input);
+        self.add_to_history(display_input.clone());
+        self.add_chat_message(display_input.clone(), true);
+
+        let tool = activity_tool(&display_input);
+        match &tool {
+            Some((tool, input)) => self.emit(ActivityEvent::ToolCall {
+                tool: tool.clone(),
+                input: input.clone(),
+            }),
+            None => self.emit(ActivityEvent::Message {
+                role: Role::User,
+                content: display_input,
+            }),
+        }
+        let was_processing = self.is_processing;
         
         // Set current view to ShellOutput and hide input area - this makes output fill the screen
         self.current_main_view = MainViewType::ShellOutput;
@@ -195,6 +622,9 @@ impl App {
         if input.starts_with('/') {
             // Slash command
             self.process_slash_command(&input[1..]);
+        } else if let Some(command) = input.strip_prefix("!?") {
+            // Bash command to show but not run
+            self.preview_bash_command(command.trim());
         } else if input.starts_with('!') {
             // Bash command
             self.process_bash_command(&input[1..]);
@@ -205,49 +635,375 @@ impl App {
             // Normal input - send to LLM
             self.send_to_llm(input);
         }
+
+        // Commands that finish straight away report their reply now; background
+        // work reports when its result is picked up in on_tick
+        if let Some((tool, _)) = tool {
+            if !was_processing && self.is_processing {
+                self.pending_tool = Some(tool);
+            } else {
+                let output = self
+                    .chat_messages
+                    .back()
+                    .filter(|msg| !msg.is_user)
+                    .map(|msg| msg.content.clone())
+                    .unwrap_or_default();
+                self.emit(ActivityEvent::ToolResult {
+                    tool,
+                    success: !output.starts_with("Error"),
+                    output,
+                });
+            }
+        }
+    }
+
+    /// Record `event` in the activity stream, if one was requested
+    fn emit(&self, event: ActivityEvent) {
+        if let Some(activity) = &self.activity {
+            activity.emit(event);
+        }
+    }
+
+    /// Load the most recent user message into the input for editing (`/edit` or Alt+E).
+    /// Returns `false` when there's no user message to edit.
+    pub fn edit_last_message(&mut self) -> bool {
+        let Some(message) = self
+            .chat_messages
+            .iter()
+            .rev()
+            .find(|msg| msg.is_user && msg.content != "/edit")
+        else {
+            return false;
+        };
+
+        self.input_text = message.content.clone();
+        self.cursor_position = self.input_text.len();
+        self.editing_message = Some(message.clone());
+        self.displaying_completion = false;
+        true
+    }
+
+    /// Drop `message` and everything after it, along with the LLM exchange it started.
+    /// A response still in flight belongs to the discarded turn, so it's ignored.
+    fn rewind_before(&mut self, message: &ChatMessage) {
+        let position = self.chat_messages.iter().rposition(|msg| {
+            msg.is_user && msg.timestamp == message.timestamp && msg.content == message.content
+        });
+        if let Some(idx) = position {
+            self.chat_messages.truncate(idx);
+        }
+
+        if self.is_processing {
+            self.output_manager.discard_llm_response();
+            self.is_processing = false;
+        }
+        self.last_exchange = None;
+        self.continuing_response = false;
     }
 
     /// Send user input to LLM and handle the response
     pub fn send_to_llm(&mut self, prompt: String) {
+        self.send_task_to_llm(LlmTask::Chat, prompt);
+    }
+
+    /// Model a `task` request goes to: its configured model, otherwise the current one.
+    /// Chat always uses the current model so `/model` keeps working.
+    pub fn model_for(&self, task: LlmTask) -> Option<String> {
+        let configured = match task {
+            LlmTask::Chat => None,
+            task => self.task_models.get(&task).cloned(),
+        };
+        configured.or_else(|| Some(self.llm_client.as_ref()?.get_model().to_string()))
+    }
+
+    /// Send `prompt` to the model configured for `task`
+    pub fn send_task_to_llm(&mut self, task: LlmTask, prompt: String) {
+        let model = self.model_for(task);
+        self.send_with_model(prompt, model);
+    }
+
+    fn send_with_model(&mut self, prompt: String, model: Option<String>) {
         // Mark as processing
         self.is_processing = true;
 
         // Check if client is initialized
-        if let Some(client) = self.llm_client.clone() {
+        if let (Some(mut client), Some(model)) = (self.llm_client.clone(), model) {
             // Create a message indicating we're waiting for a response
             self.add_chat_message("Thinking...".to_string(), false);
 
-            // Use a thread to handle the async request without blocking the UI
-            let (tx, rx) = std::sync::mpsc::channel();
+            client.set_model(model.clone());
+            self.archive_last_exchange();
+            self.last_exchange = Some(LlmExchange {
+                prompt: prompt.clone(),
+                response: String::new(),
+                truncated: false,
+                model,
+            });
+            self.continuing_response = false;
+            self.dispatch_llm_request(client, vec![ChatTurn::user(prompt)]);
+        } else {
+            // No client configured
+            let message = "Error: LLM client not initialized. Use /config to set up OpenRouter.";
+            self.emit(ActivityEvent::Error {
+                message: message.to_string(),
+            });
+            self.add_chat_message(message.to_string(), false);
+            self.is_processing = false;
+            self.displaying_completion = false;
+        }
+    }
+
+    /// Move the last exchange into `conversation`, unless it never got an answer
+    fn archive_last_exchange(&mut self) {
+        let Some(exchange) = self.last_exchange.take() else {
+            return;
+        };
+        if !exchange.response.is_empty() {
+            self.conversation.push(ChatTurn::user(exchange.prompt));
+            self.conversation.push(ChatTurn::assistant(exchange.response));
+        }
+    }
 
-            // Clone necessary values for the thread
-            let prompt_clone = prompt.clone();
+    /// `turns` preceded by the piped input and the current contents of pinned files
+    pub fn with_context(&mut self, turns: Vec<ChatTurn>) -> Vec<ChatTurn> {
+        self.pinned_files.refresh();
+        self.context_turns().into_iter().chain(turns).collect()
+    }
 
-            // Spawn a thread to handle the async request
-            std::thread::spawn(move || {
-                // Create a tokio runtime for async operations
-                let rt = tokio::runtime::Runtime::new().unwrap();
+    /// Turns sent ahead of every request, using pinned files as last read
+    fn context_turns(&self) -> Vec<ChatTurn> {
+        self.piped_context
+            .as_deref()
+            .map(piped_context_turn)
+            .into_iter()
+            .chain(self.pinned_files.context_turn(&self.workspace))
+            .collect()
+    }
 
-                // Execute the chat request
-                let result = rt.block_on(async { client.chat(prompt_clone).await });
+    /// Estimated size of the request the current input would send, or `None` if the
+    /// input is empty or a command
+    pub fn prompt_preview(&self) -> Option<PromptPreview> {
+        let input = self.input_text.trim();
+        if input.is_empty() || input.starts_with(['/', '!', '@']) {
+            return None;
+        }
 
-                // Send the result back to the main thread
-                tx.send(result).unwrap();
-            });
+        let mut turns = self.context_turns();
+        turns.extend(self.conversation.iter().cloned());
+        turns.push(ChatTurn::user(input));
+        let model = self
+            .model_for(LlmTask::Chat)
+            .unwrap_or_else(|| DEFAULT_MODEL.to_string());
+        // Only what's already cached; this runs on every draw
+        let context_length = match &self.llm_client {
+            Some(client) => client.cached_context_length(&model),
+            None => model_context_length(&model),
+        };
+        Some(PromptPreview {
+            tokens: estimate_tokens(&turns),
+            context_length,
+            model,
+        })
+    }
 
-            // Store the receiver for later checking in on_tick
-            self.output_manager.store_receiver(rx);
-        } else {
-            // No client configured
-            self.add_chat_message(
-                "Error: LLM client not initialized. Use /config to set up OpenRouter.".to_string(),
-                false,
-            );
+    /// Everything a request sends: the context, the conversation so far, then `request`
+    pub fn request_turns(&mut self, request: Vec<ChatTurn>) -> Vec<ChatTurn> {
+        let history = self.conversation.iter().cloned().chain(request).collect();
+        self.with_context(history)
+    }
+
+    /// Run a chat request on a worker thread; the result is picked up in on_tick.
+    /// Older turns may be trimmed to fit the context budget, but never `request`'s.
+    fn dispatch_llm_request(&mut self, client: OpenRouterClient, request: Vec<ChatTurn>) {
+        let keep_last = request.len();
+        let turns = self.request_turns(request);
+
+        // Use a thread to handle the async request without blocking the UI
+        let (tx, rx) = std::sync::mpsc::channel();
+
+        // Spawn a thread to handle the async request
+        std::thread::spawn(move || {
+            // Create a tokio runtime for async operations
+            let rt = tokio::runtime::Runtime::new().unwrap();
+
+            // Execute the chat request
+            let result = rt.block_on(async { client.complete_keeping(&turns, keep_last).await });
+
+            // Send the result back to the main thread
+            let _ = tx.send(result);
+        });
+
+        // Store the receiver for later checking in on_tick
+        self.output_manager.store_receiver(rx);
+    }
+
+    /// Follow-up conversation for `/continue`, if the last response was truncated
+    pub fn continuation_request(&self) -> Option<Vec<ChatTurn>> {
+        let exchange = self.last_exchange.as_ref().filter(|e| e.truncated)?;
+        Some(continuation_turns(&exchange.prompt, &exchange.response))
+    }
+
+    /// Start a fresh conversation, asking first if one is under way. Command history,
+    /// pinned files, piped input and the current file are kept.
+    fn new_conversation_command(&mut self) -> String {
+        let active = self.last_exchange.is_some() || self.is_processing;
+        if active && !std::mem::take(&mut self.new_conversation_pending) {
+            self.new_conversation_pending = true;
+            return "This discards the current conversation. Type /new again to confirm"
+                .to_string();
+        }
+        self.new_conversation_pending = false;
+
+        if self.is_processing {
+            self.output_manager.discard_llm_response();
             self.is_processing = false;
-            self.displaying_completion = false;
+        }
+        self.last_exchange = None;
+        self.conversation.clear();
+        self.continuing_response = false;
+        self.editing_message = None;
+        if self.clear_chat_on_new {
+            self.chat_messages.clear();
+        }
+        "Started a new conversation".to_string()
+    }
+
+    /// Get unstuck without restarting: cancel whatever is running, drop the results still
+    /// awaited and go back to a clean input. The chat and conversation are kept.
+    fn reset_command(&mut self) -> String {
+        let cancelled = [self.cancel_grep(), self.cancel_shell()]
+            .into_iter()
+            .filter(|&cancelled| cancelled)
+            .count();
+        let dropped = self.output_manager.discard_pending();
+        let was_processing = std::mem::take(&mut self.is_processing);
+
+        self.displaying_completion = false;
+        self.continuing_response = false;
+        self.pending_tool = None;
+        self.shell_dry_run = false;
+        self.shell_timeout = None;
+        self.shell_terminal = None;
+        self.new_conversation_pending = false;
+        self.pending_edit = None;
+        self.editing_message = None;
+        self.input_text.clear();
+        self.cursor_position = 0;
+        self.spinner.stop();
+
+        if cancelled == 0 && dropped == 0 && !was_processing {
+            return "Reset: nothing was running".to_string();
+        }
+        let message = format!(
+            "Reset: cancelled {} {}, dropped {} pending {}",
+            cancelled,
+            if cancelled == 1 { "task" } else { "tasks" },
+            dropped,
+            if dropped == 1 { "result" } else { "results" }
+        );
+        crate::logging::log(crate::config::LogLevel::Info, "app", &message);
+        message
+    }
+
+    /// Ask the model to carry on from a truncated response
+    fn continue_command(&mut self) -> String {
+        // Another request would replace the receiver the running one answers on
+        if self.is_processing {
+            return "A response is still in progress".to_string();
+        }
+        let Some(turns) = self.continuation_request() else {
+            return "Nothing to continue: the last response wasn't truncated".to_string();
+        };
+        let Some(mut client) = self.llm_client.clone() else {
+            return "Error: LLM client not initialized. Use /config to set up OpenRouter.".to_string();
+        };
+        if let Some(exchange) = &self.last_exchange {
+            client.set_model(exchange.model.clone());
+        }
+
+        self.is_processing = true;
+        self.continuing_response = true;
+        self.add_chat_message("Thinking...".to_string(), false);
+        self.dispatch_llm_request(client, turns);
+        "Continuing the previous response...".to_string()
+    }
+
+    /// Prompt for `/retry`: the last prompt sent to the model plus an optional steering hint
+    pub fn retry_prompt(&self, hint: &str) -> Option<String> {
+        let prompt = &self.last_exchange.as_ref()?.prompt;
+        let hint = hint.trim();
+        if hint.is_empty() {
+            Some(prompt.clone())
+        } else {
+            Some(format!("{}\n\n{}", prompt, hint))
+        }
+    }
+
+    /// Re-send the last prompt, replacing the previous answer (format: /retry [hint])
+    fn retry_command(&mut self, hint: &str) -> String {
+        if self.is_processing {
+            return "A response is still in progress".to_string();
+        }
+        let Some(prompt) = self.retry_prompt(hint) else {
+            return "Nothing to retry: no prompt has been sent yet".to_string();
+        };
+        if self.llm_client.is_none() {
+            return "Error: LLM client not initialized. Use /config to set up OpenRouter.".to_string();
+        }
+
+        let model = self.last_exchange.as_ref().map(|e| e.model.clone());
+        self.discard_last_reply();
+        // The retried exchange is replaced rather than kept in the conversation
+        self.last_exchange = None;
+        self.send_with_model(prompt, model);
+        "Retrying the last prompt...".to_string()
+    }
+
+    /// Drop the newest assistant message before the `/retry` entry, if there is one
+    fn discard_last_reply(&mut self) {
+        let retry_entry = self
+            .chat_messages
+            .back()
+            .is_some_and(|msg| msg.is_user && msg.content.starts_with("/retry"));
+        let reply_idx = self.chat_messages.len().checked_sub(1 + retry_entry as usize);
+
+        if let Some(idx) = reply_idx.filter(|&idx| !self.chat_messages[idx].is_user) {
+            self.chat_messages.remove(idx);
         }
     }
 
+    /// Show a completed response, appending to the truncated message when continuing
+    fn apply_llm_completion(&mut self, completion: ChatCompletion) {
+        let truncated = completion.is_truncated();
+        let marker = if truncated { TRUNCATION_MARKER } else { "" };
+
+        if self.continuing_response {
+            self.continuing_response = false;
+            if let Some(exchange) = &mut self.last_exchange {
+                exchange.response.push_str(&completion.content);
+                exchange.truncated = truncated;
+            }
+
+            let previous = self
+                .chat_messages
+                .iter_mut()
+                .rev()
+                .find(|msg| !msg.is_user && msg.content.ends_with(TRUNCATION_MARKER));
+            if let Some(message) = previous {
+                let kept = message.content.len() - TRUNCATION_MARKER.len();
+                message.content.truncate(kept);
+                message.content.push_str(&completion.content);
+                message.content.push_str(marker);
+                return;
+            }
+        } else if let Some(exchange) = &mut self.last_exchange {
+            exchange.response = completion.content.clone();
+            exchange.truncated = truncated;
+        }
+
+        self.add_chat_message(format!("{}{}", completion.content, marker), false);
+    }
+
     /// Process LLM response when available
     pub fn check_llm_response(&mut self) {
         if let Some(result) = self.output_manager.check_llm_response() {
@@ -260,14 +1016,32 @@ impl App {
                 self.chat_messages.remove(thinking_idx);
             }
 
+            // Template commands are answered by the model rather than a tool result
+            self.pending_tool = None;
+
             match result {
-                Ok(content) => {
+                Ok(completion) => {
+                    self.emit(ActivityEvent::Message {
+                        role: Role::Assistant,
+                        content: completion.content.clone(),
+                    });
                     // Add the actual response
-                    self.add_chat_message(content, false);
+                    self.apply_llm_completion(completion);
+                }
+                Err(e @ crate::error::TaskError::EmptyResponse { .. }) => {
+                    self.continuing_response = false;
+                    self.emit(ActivityEvent::Error {
+                        message: e.to_string(),
+                    });
+                    // Not a failure as such, but a blank message would look like one
+                    self.add_chat_message(e.to_string(), false);
                 }
                 Err(e) => {
-                    // Add error message
-                    self.add_chat_message(format!("Error: {}", e), false);
+                    self.continuing_response = false;
+                    self.emit(ActivityEvent::Error {
+                        message: e.to_string(),
+                    });
+                    self.add_chat_message(SamusError::from(e).user_message(), false);
                 }
             }
 
@@ -284,7 +1058,15 @@ impl App {
     fn process_slash_command(&mut self, command: &str) {
         let response = match command.trim() {
             "help" => {
-                "Available commands: /help, /quit, /search, /diff, /model, /outline, /ls, /dir".to_string()
+                let templates: Vec<String> = self
+                    .prompt_templates
+                    .templates()
+                    .map(|t| format!("/{}", t.name))
+                    .collect();
+                format!(
+                    "Available commands: /help, /quit, /search, /grep, /diff, /model, /outline, /symbol, /refresh, /recent, /ls, /dir, /tree, /terminal, /metrics, /history, /status, /models, /new, /reset, /continue, /retry, /edit, /apply, /undo, /run, /save-output, /context-add, /context-remove, /context-list\nPrompt templates: {}",
+                    templates.join(", ")
+                )
             }
             "quit" => {
                 self.should_quit = true;
@@ -294,38 +1076,643 @@ impl App {
                 self.current_main_view = MainViewType::Search;
                 "Switched to search view".to_string()
             }
-            cmd if cmd.starts_with("diff") => {
-                self.current_main_view = MainViewType::GitDiff;
-                "Showing diff view".to_string()
-            }
+            cmd if cmd.starts_with("diff") => self.diff_command(&cmd["diff".len()..]),
+            cmd if cmd == "models" || cmd.starts_with("models ") => self.models_command(cmd),
             cmd if cmd.starts_with("model") => self.set_model_command(cmd).to_string(),
             cmd if cmd.starts_with("config") => self.configure_openrouter_command(cmd).to_string(),
             cmd if cmd.starts_with("outline") => {
                 self.current_main_view = MainViewType::CodeOutline;
                 self.show_code_outline(cmd)
             }
+            cmd if cmd == "grep" || cmd.starts_with("grep ") => self.grep_command(cmd),
+            cmd if cmd.starts_with("tree") => self.tree_command(cmd),
+            cmd if cmd.starts_with("terminal") => self.terminal_command(cmd),
+            "metrics" => self.metrics_command(),
+            "history" => self.history_command(),
+            "status" => self.status_command(),
+            cmd if cmd == "symbol" || cmd.starts_with("symbol ") => {
+                self.symbol_command(&cmd["symbol".len()..])
+            }
+            cmd if cmd == "recent" || cmd.starts_with("recent ") => {
+                self.recent_command(&cmd["recent".len()..])
+            }
+            "refresh" => {
+                self.current_main_view = MainViewType::CodeOutline;
+                self.refresh_command()
+            }
+            "continue" => self.continue_command(),
+            "new" => self.new_conversation_command(),
+            "reset" => self.reset_command(),
+            "run" => match self.previewed_command.take() {
+                Some(command) => {
+                    self.process_bash_command(&command);
+                    return;
+                }
+                None => "Nothing to run: preview a command with !?<command> first".to_string(),
+            },
+            "edit" => {
+                if self.edit_last_message() {
+                    "Editing your last message; press Enter to resend it".to_string()
+                } else {
+                    "Nothing to edit: no previous message".to_string()
+                }
+            }
+            cmd if cmd == "retry" || cmd.starts_with("retry ") => self.retry_command(&cmd["retry".len()..]),
+            cmd if cmd.starts_with("save-output") => self.save_output_command(&cmd["save-output".len()..]),
+            cmd if cmd.starts_with("context-") => self.context_files_command(cmd),
+            cmd if cmd == "apply" || cmd.starts_with("apply ") => self.apply_command(&cmd["apply".len()..]),
+            cmd if cmd == "undo" || cmd.starts_with("undo ") => self.undo_command(&cmd["undo".len()..]),
             cmd if cmd.starts_with("ls") || cmd.starts_with("dir") => {
                 self.list_directory_command(cmd)
             }
+            cmd if self
+                .prompt_templates
+                .get(cmd.split_whitespace().next().unwrap_or(""))
+                .is_some() =>
+            {
+                self.template_command(cmd)
+            }
             _ => "Unknown command. Try /help for a list of commands.".to_string(),
         };
 
         self.add_chat_message(response, false);
     }
 
-    /// List directory contents using the shell task handler
-    fn list_directory_command(&mut self, cmd: &str) -> String {
-        // Parse path from command (format: /ls [path] or /dir [path])
-        let parts: Vec<&str> = cmd.split_whitespace().collect();
-        let path = if parts.len() >= 2 {
-            parts[1].to_string()
+    /// Search the workspace with ripgrep (format: /grep <pattern> [glob])
+    fn grep_command(&mut self, cmd: &str) -> String {
+        let Some((pattern, glob)) = crate::ui::grep::parse_grep_args(&cmd["grep".len()..]) else {
+            return "Usage: /grep <pattern> [glob]".to_string();
+        };
+
+        let workspace = self.workspace.root().to_path_buf();
+
+        // Mark as processing
+        self.is_processing = true;
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.grep_cancel = Some(cancel.clone());
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let pattern_clone = pattern.clone();
+        let binary = self.ripgrep_binary.clone();
+
+        // Ripgrep is a blocking subprocess, so run it off the UI thread
+        std::thread::spawn(move || {
+            let result = crate::ui::grep::run_grep(
+                &workspace,
+                &pattern_clone,
+                glob,
+                crate::ui::grep::DEFAULT_GREP_MAX_RESULTS,
+                &binary,
+                &cancel,
+            )
+            .map_err(|e| crate::error::TaskError::Tool(e.to_string()));
+
+            // Nobody is waiting any more if the search was abandoned with /reset
+            let _ = tx.send(result);
+        });
+
+        // Store receiver for later checking
+        self.output_manager.store_grep_receiver(rx);
+
+        format!("Searching for \"{}\" (Ctrl+C to cancel)", pattern)
+    }
+
+    /// Manage terminal views (format: /terminal [new [title] | next | layout])
+    fn terminal_command(&mut self, cmd: &str) -> String {
+        let args = cmd["terminal".len()..].trim();
+        let (action, rest) = args.split_once(' ').unwrap_or((args, ""));
+
+        let message = match action {
+            "" => format!("{} terminal(s) open", self.terminals.views().len()),
+            "new" => {
+                let title = match rest.trim() {
+                    "" => format!("Terminal {}", self.terminals.views().len() + 1),
+                    title => title.to_string(),
+                };
+                let message = match self.terminals.create_view(title) {
+                    Ok(view) => format!("Opened terminal \"{}\"", view.instance.title),
+                    Err(e) => return format!("Error opening terminal: {}", e),
+                };
+                self.save_terminals();
+                message
+            }
+            "next" => match self.terminals.focus_next() {
+                Ok(()) => match self.terminals.active_view() {
+                    Some(view) => format!("Switched to terminal \"{}\"", view.instance.title),
+                    None => "No terminals open. Use /terminal new [title]".to_string(),
+                },
+                Err(e) => return format!("Error switching terminal: {}", e),
+            },
+            "layout" => {
+                self.terminals.cycle_layout();
+                format!("Terminal layout: {:?}", self.terminals.layout())
+            }
+            _ => return "Usage: /terminal [new [title] | next | layout]".to_string(),
+        };
+
+        self.current_main_view = MainViewType::Terminals;
+        message
+    }
+
+    /// Print a directory tree (format: /tree [path] [--depth N])
+    fn tree_command(&mut self, cmd: &str) -> String {
+        let (path, depth) = match crate::ui::tree::parse_tree_args(&cmd["tree".len()..]) {
+            Ok(args) => args,
+            Err(e) => return format!("{}\nUsage: /tree [path] [--depth N]", e),
+        };
+
+        let samusignore = SamusIgnore::load(self.workspace.root());
+        match crate::ui::tree::build_tree(&path, depth, &samusignore) {
+            Ok(tree) => tree,
+            Err(e) => format!("Error building tree: {}", e),
+        }
+    }
+
+    /// Fill a prompt template from the command arguments and the current file
+    /// (format: /<template> [file[:start-end]] [question])
+    pub fn template_prompt(&self, cmd: &str) -> Result<String, String> {
+        let mut parts = cmd.trim().splitn(2, char::is_whitespace);
+        let name = parts.next().unwrap_or("");
+        let args = parts.next().unwrap_or("").trim();
+        let template = self
+            .prompt_templates
+            .get(name)
+            .ok_or_else(|| format!("Unknown prompt template: {}", name))?;
+
+        // An explicit file comes first; otherwise fall back to the file last referenced
+        let (first, rest) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+        let (path, range) = crate::ui::prompt_template::parse_file_arg(first);
+        let explicit = self.workspace.resolve(path);
+        let (file, range, question) = if !first.is_empty() && explicit.is_file() {
+            (explicit, range, rest.trim())
+        } else if let Some(current) = &self.current_file_path {
+            (PathBuf::from(current), None, args)
         } else {
-            ".".to_string()  // Current directory by default
+            return Err(format!("Usage: /{} <file[:start-end]> [question]", name));
         };
-        
-        // Parse recursive flag
-        let recursive = parts.len() >= 3 && parts[2].eq_ignore_ascii_case("-r");
-        
+
+        let display = self.workspace.display(&file);
+        let content = std::fs::read_to_string(&file)
+            .map_err(|e| format!("Error reading file {}: {}", display, e))?;
+
+        Ok(template.render(&PromptContext {
+            selection: Some(crate::ui::prompt_template::select_lines(&content, range)),
+            file: Some(display),
+            question: question.to_string(),
+        }))
+    }
+
+    /// Send a filled prompt template to the LLM
+    fn template_command(&mut self, cmd: &str) -> String {
+        match self.template_prompt(cmd) {
+            Ok(prompt) => {
+                let name = cmd.split_whitespace().next().unwrap_or("");
+                self.send_task_to_llm(LlmTask::for_template(name), prompt);
+                format!("Sent /{} prompt", cmd.split_whitespace().next().unwrap_or(""))
+            }
+            Err(e) => e,
+        }
+    }
+
+    /// Pin files as context for every request
+    /// (format: /context-add <path> | /context-remove <path> | /context-list)
+    fn context_files_command(&mut self, cmd: &str) -> String {
+        let (action, path) = cmd.split_once(' ').unwrap_or((cmd, ""));
+        let path = path.trim();
+        match (action, path.is_empty()) {
+            ("context-list", _) => {
+                if self.pinned_files.is_empty() {
+                    return "No pinned files. Use /context-add <path>".to_string();
+                }
+                self.pinned_files.refresh();
+                let lines: Vec<String> = self
+                    .pinned_files
+                    .files()
+                    .iter()
+                    .map(|file| {
+                        let note = if file.truncated { ", truncated" } else { "" };
+                        let path = self.workspace.display(&file.path);
+                        format!("  {} ({} bytes{})", path, file.len, note)
+                    })
+                    .collect();
+                format!("Pinned files:\n{}", lines.join("\n"))
+            }
+            ("context-add", false) => {
+                let file = self.workspace.resolve(path);
+                let display = self.workspace.display(&file);
+                match self.pinned_files.add(&file) {
+                    Ok(pinned) if pinned.truncated => format!(
+                        "Pinned {} (only the first {} of {} bytes will be sent)",
+                        display,
+                        pinned.content.len(),
+                        pinned.len
+                    ),
+                    Ok(_) => format!("Pinned {}", display),
+                    Err(e) => format!("Error pinning {}: {}", display, e),
+                }
+            }
+            ("context-remove", false) => {
+                let file = self.workspace.resolve(path);
+                let display = self.workspace.display(&file);
+                if self.pinned_files.remove(&file) {
+                    format!("Unpinned {}", display)
+                } else {
+                    format!("{} isn't pinned", display)
+                }
+            }
+            _ => "Usage: /context-add <path> | /context-remove <path> | /context-list".to_string(),
+        }
+    }
+
+    /// Apply the search/replace blocks in the last response to a file (format: /apply <file>).
+    /// Edits the auto-apply policy allows go straight in; others wait for a second /apply.
+    fn apply_command(&mut self, args: &str) -> String {
+        let path = args.trim();
+        if path.is_empty() {
+            return "Usage: /apply <file>".to_string();
+        }
+        let Some(diff) = self
+            .last_exchange
+            .as_ref()
+            .and_then(|exchange| crate::tools::extract_diff(&exchange.response))
+            .map(str::to_string)
+        else {
+            return "Nothing to apply: the last response has no search/replace blocks".to_string();
+        };
+
+        let display = self.workspace.display(self.workspace.resolve(path));
+        let root = self.workspace.root();
+        let outcome = if self.pending_edit.take().as_deref() == Some(path) {
+            crate::tools::apply_diff_with_backups(path, root, &diff, &self.backups)
+                .map(AutoApplyOutcome::Applied)
+        } else {
+            crate::tools::auto_apply_diff(path, root, &diff, &self.backups, &self.auto_apply)
+        };
+
+        match outcome {
+            Ok(AutoApplyOutcome::Applied(result)) => {
+                self.on_file_changed(&self.workspace.resolve(path));
+                format!(
+                    "Applied edit to {} ({}); /undo {} reverts it",
+                    display,
+                    result.preview.stats(),
+                    path
+                )
+            }
+            Ok(AutoApplyOutcome::NeedsConfirmation(reason)) => {
+                self.pending_edit = Some(path.to_string());
+                format!(
+                    "The edit to {} needs confirmation: {}. Type /apply {} again to apply it",
+                    display, reason, path
+                )
+            }
+            Err(e) => format!("Error applying edit to {}: {}", display, e),
+        }
+    }
+
+    /// Restore a file from its newest backup (format: /undo <file>)
+    fn undo_command(&mut self, args: &str) -> String {
+        let path = args.trim();
+        if path.is_empty() {
+            return "Usage: /undo <file>".to_string();
+        }
+
+        let display = self.workspace.display(self.workspace.resolve(path));
+        match crate::tools::restore_backup(path, self.workspace.root(), &self.backups) {
+            Ok(_) => {
+                self.on_file_changed(&self.workspace.resolve(path));
+                format!("Restored {} from its last backup", display)
+            }
+            Err(e) => format!("Error restoring {}: {}", display, e),
+        }
+    }
+
+    /// Plain text of the current view, if it's one that can be exported
+    pub fn view_plain_text(&self) -> Option<String> {
+        let lines = crate::ui::tui::view_lines(self)?;
+        Some(lines_to_plain_text(&lines))
+    }
+
+    /// Write the shell output or LLM response view to a file (format: /save-output <path>)
+    fn save_output_command(&mut self, args: &str) -> String {
+        let path = args.trim();
+        if path.is_empty() {
+            return "Usage: /save-output <path>".to_string();
+        }
+        let Some(text) = self.view_plain_text() else {
+            return "Nothing to save: only the shell output and LLM response views can be saved"
+                .to_string();
+        };
+
+        let file = self.workspace.resolve(path);
+        let display = self.workspace.display(&file);
+        let target = file.to_string_lossy().to_string();
+        let result = tokio::runtime::Runtime::new()
+            .map_err(crate::error::TaskError::from)
+            .and_then(|rt| rt.block_on(LocalFileSystem::new().write_to_file(&target, &text)));
+
+        match result {
+            Ok(()) => format!("Saved {} bytes to {}", text.len(), display),
+            Err(e) => format!("Error saving output to {}: {}", display, e),
+        }
+    }
+
+    /// List the tools run this session, oldest first
+    fn history_command(&self) -> String {
+        let Some((store, session_id)) = &self.tool_call_log else {
+            return "Tool calls aren't being recorded in this session".to_string();
+        };
+        let rt = match tokio::runtime::Runtime::new() {
+            Ok(rt) => rt,
+            Err(e) => return format!("Error reading the tool call history: {}", e),
+        };
+        match rt.block_on(tool_calls::timeline(store.as_ref(), session_id)) {
+            Ok(timeline) => timeline,
+            Err(e) => format!(
+                "Error reading the tool call history: {}",
+                SamusError::from(e).user_message()
+            ),
+        }
+    }
+
+    /// Report runtime metrics: the tree-sitter query cache and cursor pool
+    fn metrics_command(&self) -> String {
+        let mut report = match &self.tree_sitter_service {
+            Some(service) => {
+                let stats = service.query_cache_stats();
+                let cursors = service.cursor_pool_stats();
+                format!(
+                    "Tree-sitter query cache\n  hits: {}\n  misses: {}\n  entries: {}\n  hit rate: {:.1}%\n\
+                     Query cursors\n  created: {}\n  reused: {}\n  idle: {}",
+                    stats.hits,
+                    stats.misses,
+                    stats.entries,
+                    stats.hit_rate() * 100.0,
+                    cursors.created,
+                    cursors.reused,
+                    cursors.idle
+                )
+            }
+            None => "Tree-sitter service not initialized".to_string(),
+        };
+
+        let slow: Vec<_> = crate::logging::recent(usize::MAX)
+            .into_iter()
+            .filter(|record| record.target == crate::logging::SLOW_HANDLER_TARGET)
+            .collect();
+        if !slow.is_empty() {
+            report.push_str(&format!("\nSlow actor handlers: {}", slow.len()));
+            for record in slow.iter().rev().take(MAX_SLOW_HANDLERS_SHOWN) {
+                report.push_str(&format!("\n  {}", record.message));
+            }
+        }
+        report
+    }
+
+    /// Summarize the LLM client, ripgrep, tree-sitter, MCP servers and workspace
+    fn status_command(&self) -> String {
+        let llm = match &self.llm_client {
+            Some(client) => Health::Ok(format!("model {}", client.get_model())),
+            None => Health::Unconfigured(
+                "not configured; set OPEN_ROUTER_API_KEY or use /config <api_key>".to_string(),
+            ),
+        };
+
+        StatusReport {
+            llm,
+            ripgrep: Health::of_ripgrep(&self.ripgrep_binary),
+            tree_sitter: Health::of_tree_sitter(self.tree_sitter_service.is_some()),
+            mcp_servers: self
+                .mcp_servers
+                .iter()
+                .map(|server| (server.name.clone(), Health::of_mcp_server(server)))
+                .collect(),
+            workspace: self.workspace.root().to_path_buf(),
+        }
+        .render()
+    }
+
+    /// Cancel a running `/grep`, if there is one
+    pub fn cancel_grep(&mut self) -> bool {
+        match self.grep_cancel.take() {
+            Some(cancel) => {
+                cancel.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Select and expand/collapse file groups in the Search view; returns whether `key` was used
+    fn handle_search_view_key(&mut self, key: KeyEvent) -> bool {
+        if self.current_main_view != MainViewType::Search
+            || !self.input_text.is_empty()
+            || self.grep_groups.is_empty()
+            || key.modifiers != KeyModifiers::NONE
+        {
+            return false;
+        }
+
+        match key.code {
+            KeyCode::Up => self.grep_groups.select_previous(),
+            KeyCode::Down => self.grep_groups.select_next(),
+            KeyCode::Enter => self.grep_groups.toggle_selected(),
+            KeyCode::Char(' ') => return self.grep_pager.load_more(self.grep_groups.row_count()),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Select symbols in the Code Outline view, jump to one with Enter, or load another
+    /// page with Space; returns whether `key` was used
+    fn handle_outline_view_key(&mut self, key: KeyEvent) -> bool {
+        if self.current_main_view != MainViewType::CodeOutline
+            || !self.input_text.is_empty()
+            || self.current_file_symbols.is_empty()
+            || key.modifiers != KeyModifiers::NONE
+        {
+            return false;
+        }
+
+        match key.code {
+            KeyCode::Up => self.outline_selected = self.outline_selected.saturating_sub(1),
+            KeyCode::Down => {
+                if self.outline_selected + 1 < self.current_file_symbols.len() {
+                    self.outline_selected += 1;
+                }
+                // Keep the selection on screen rather than behind the "load more" hint
+                let row = symbol_row(&self.current_file_symbols, self.outline_selected);
+                if row >= self.outline_pager.limit() {
+                    self.outline_pager
+                        .load_more(symbol_row_count(&self.current_file_symbols));
+                }
+            }
+            KeyCode::Enter => {
+                if let Err(message) = self.open_selected_symbol() {
+                    self.add_chat_message(message, false);
+                }
+            }
+            KeyCode::Char(' ') => {
+                return self
+                    .outline_pager
+                    .load_more(symbol_row_count(&self.current_file_symbols));
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Back to the first page and symbol, for a new outline
+    fn reset_outline_view(&mut self) {
+        self.outline_pager.reset();
+        self.outline_selected = 0;
+    }
+
+    /// Show the selected symbol's file in the diff view, scrolled to the symbol. The diff
+    /// already open is reused when it's of that file; otherwise the file is shown
+    /// unchanged. Returns the line scrolled to.
+    fn open_selected_symbol(&mut self) -> Result<usize, String> {
+        let Some(symbol) = self.current_file_symbols.get(self.outline_selected) else {
+            return Err("No symbol selected".to_string());
+        };
+        let path = PathBuf::from(&symbol.path);
+        let line = symbol.line;
+
+        if self.active_diff.is_none() || self.active_diff_path.as_ref() != Some(&path) {
+            let content = std::fs::read_to_string(&path).map_err(|e| {
+                format!("Error reading {}: {}", self.workspace.display(&path), e)
+            })?;
+            let diff = DiffVisualization::new(content.clone(), content).with_config(DiffConfig {
+                view_mode: self.diff_view_mode,
+                ..DiffConfig::default()
+            });
+            self.active_diff = Some(diff);
+            self.active_diff_path = Some(path);
+        }
+        if let Some(diff) = &mut self.active_diff {
+            diff.scroll_to_line(line);
+        }
+        self.current_main_view = MainViewType::GitDiff;
+        Ok(line)
+    }
+
+    /// Layout and scrolling keys for the Terminals view; returns whether `key` was used
+    fn handle_terminals_view_key(&mut self, key: KeyEvent) -> bool {
+        if self.current_main_view != MainViewType::Terminals {
+            return false;
+        }
+
+        match (key.code, key.modifiers) {
+            (KeyCode::Char('l'), KeyModifiers::CONTROL) => self.terminals.cycle_layout(),
+            (KeyCode::PageUp, KeyModifiers::NONE) => {
+                if let Some(view) = self.terminals.active_view_mut() {
+                    view.scroll_up(TERMINAL_SCROLL_LINES);
+                }
+            }
+            (KeyCode::PageDown, KeyModifiers::NONE) => {
+                if let Some(view) = self.terminals.active_view_mut() {
+                    view.scroll_down(TERMINAL_SCROLL_LINES);
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Mode switching key for the GitDiff view; returns whether `key` was used
+    fn handle_diff_view_key(&mut self, key: KeyEvent) -> bool {
+        if self.current_main_view != MainViewType::GitDiff {
+            return false;
+        }
+
+        match (key.code, key.modifiers) {
+            (KeyCode::Char('l'), KeyModifiers::CONTROL) => {
+                self.cycle_diff_view_mode();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Show the diff view, compare two files, or switch the diff view mode
+    /// (format: /diff [mode | <old-file> <new-file>])
+    fn diff_command(&mut self, args: &str) -> String {
+        let args: Vec<&str> = args.split_whitespace().collect();
+        match args.as_slice() {
+            [] => {
+                self.current_main_view = MainViewType::GitDiff;
+                "Showing diff view".to_string()
+            }
+            ["mode"] => {
+                let mode = self.cycle_diff_view_mode();
+                format!("Diff view mode: {}", mode.label())
+            }
+            [old, new] => {
+                let old_file = self.workspace.resolve(old);
+                let new_file = self.workspace.resolve(new);
+                let read = |path: &Path| {
+                    std::fs::read_to_string(path)
+                        .map_err(|e| format!("Error reading {}: {}", self.workspace.display(path), e))
+                };
+                let (old_content, new_content) = match (read(&old_file), read(&new_file)) {
+                    (Ok(old_content), Ok(new_content)) => (old_content, new_content),
+                    (Err(e), _) | (_, Err(e)) => return e,
+                };
+
+                let diff = DiffVisualization::new(old_content, new_content).with_config(DiffConfig {
+                    view_mode: self.diff_view_mode,
+                    ..DiffConfig::default()
+                });
+                let stats = diff.stats();
+                self.active_diff = Some(diff);
+                self.active_diff_path = Some(new_file.clone());
+                self.current_main_view = MainViewType::GitDiff;
+                format!(
+                    "Showing diff of {} and {}: {} (Ctrl+L or /diff mode switches view)",
+                    self.workspace.display(&old_file),
+                    self.workspace.display(&new_file),
+                    stats
+                )
+            }
+            _ => "Usage: /diff [mode | <old-file> <new-file>]".to_string(),
+        }
+    }
+
+    /// Move the shown diff, and the mode later diffs open in, to the next view mode
+    pub fn cycle_diff_view_mode(&mut self) -> DiffViewMode {
+        let mode = match &mut self.active_diff {
+            Some(diff) => diff.cycle_view_mode(),
+            None => self.diff_view_mode.next(),
+        };
+        self.diff_view_mode = mode;
+        mode
+    }
+
+    /// Interrupt a running `!command` with SIGINT, if there is one
+    pub fn cancel_shell(&mut self) -> bool {
+        match self.shell_cancel.take() {
+            Some(cancel) => {
+                cancel.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// List directory contents using the shell task handler
+    fn list_directory_command(&mut self, cmd: &str) -> String {
+        // Parse path from command (format: /ls [path] or /dir [path])
+        let parts: Vec<&str> = cmd.split_whitespace().collect();
+        let path = if parts.len() >= 2 {
+            parts[1].to_string()
+        } else {
+            ".".to_string()  // Current directory by default
+        };
+        
+        // Parse recursive flag
+        let recursive = parts.len() >= 3 && parts[2].eq_ignore_ascii_case("-r");
+        
         // Check if task manager is available
         if let Some(task_manager) = &self.task_manager {
             use crate::task::Task;
@@ -354,7 +1741,7 @@ impl App {
                 let result = rt.block_on(async { task_manager_clone.execute_task(task).await });
                 
                 // Send result back to main thread
-                tx.send(result).unwrap();
+                let _ = tx.send(result);
             });
             
             // Store receiver for later checking
@@ -372,14 +1759,18 @@ impl App {
     
     /// Show code outline for a file
     fn show_code_outline(&mut self, cmd: &str) -> String {
-        // Parse file path if provided
-        let parts: Vec<&str> = cmd.split_whitespace().collect();
-        let file_path = if parts.len() >= 2 {
-            parts[1].to_string()
+        // Parse optional path and extension filter
+        let args = cmd.strip_prefix("outline").unwrap_or("");
+        let args = match parse_outline_args(args) {
+            Ok(args) => args,
+            Err(e) => return format!("{}\nUsage: /outline <file|dir> [--ext rs,py]", e),
+        };
+        let file_path = if let Some(path) = &args.path {
+            self.workspace.resolve(path).display().to_string()
         } else if let Some(path) = &self.current_file_path {
             path.clone()
         } else {
-            return "Usage: /outline <file_path>".to_string();
+            return "Usage: /outline <file|dir> [--ext rs,py]".to_string();
         };
 
         // Check if TreeSitter service is initialized
@@ -388,43 +1779,345 @@ impl App {
             None => return "Error: TreeSitter service not initialized.".to_string(),
         };
 
+        if Path::new(&file_path).is_dir() {
+            return self.outline_directory(&service, &file_path, &args.extensions);
+        }
+        self.outline_file(&service, &file_path)
+    }
+
+    /// Outline a single file, making it the current file
+    fn outline_file(&mut self, service: &Arc<TreeSitterService>, file_path: &str) -> String {
+        let display_path = self.workspace.display(file_path);
+
         // Try to read the file
-        match std::fs::read_to_string(&file_path) {
+        match std::fs::read_to_string(file_path) {
             Ok(content) => {
                 // Try to parse the file and extract symbols
-                let path = Path::new(&file_path);
-                match service.find_symbols(path, &content) {
+                let path = Path::new(file_path);
+                match self.symbol_cache.symbols(service, path, &content) {
                     Ok(symbols) => {
                         // Convert symbols to display symbols
                         self.current_file_symbols = symbols
-                            .into_iter()
+                            .iter()
                             .map(|s| DisplaySymbol {
-                                name: s.name,
+                                name: s.name.clone(),
                                 kind: format!("{:?}", s.kind),
                                 line: s.start_line,
-                                path: file_path.clone(),
+                                path: file_path.to_string(),
                             })
                             .collect();
+                        self.reset_outline_view();
 
-                        self.current_file_path = Some(file_path.clone());
+                        self.current_file_path = Some(file_path.to_string());
+                        self.recent_files.touch(&self.workspace.resolve(path));
                         format!(
                             "Found {} symbols in {}",
                             self.current_file_symbols.len(),
-                            file_path
+                            display_path
                         )
                     }
-                    Err(e) => {
-                        format!("Error parsing file: {}", e)
-                    }
+                    Err(e) => outline_error_message(&display_path, &e),
                 }
             }
             Err(e) => {
-                format!("Error reading file {}: {}", file_path, e)
+                format!("Error reading file {}: {}", display_path, e)
+            }
+        }
+    }
+
+    /// List recent files, or re-open one with its outline by number or by the best fuzzy
+    /// match for a query (format: /recent [number | query])
+    fn recent_command(&mut self, args: &str) -> String {
+        let query = args.trim();
+        if self.recent_files.files().is_empty() {
+            return "No recent files yet: reference one with @file or /outline <file>".to_string();
+        }
+        if query.is_empty() {
+            let mut lines = vec!["Recent files (/recent <number | query> to open one):".to_string()];
+            for (i, file) in self.recent_files.files().iter().enumerate() {
+                lines.push(format!("  {}. {}", i + 1, self.workspace.display(file)));
+            }
+            return lines.join("\n");
+        }
+
+        let target = match query.parse::<usize>() {
+            Ok(number) => number
+                .checked_sub(1)
+                .and_then(|index| self.recent_files.files().get(index))
+                .cloned(),
+            Err(_) => self
+                .recent_files
+                .filter(query, &self.workspace, &self.fuzzy_options)
+                .first()
+                .map(|file| file.to_path_buf()),
+        };
+        let Some(target) = target else {
+            return format!("No recent file matches '{}'", query);
+        };
+
+        if !target.is_file() {
+            self.recent_files.remove(&target);
+            return format!(
+                "{} no longer exists; removed it from recent files",
+                self.workspace.display(&target)
+            );
+        }
+        let service = match &self.tree_sitter_service {
+            Some(service) => service.clone(),
+            None => return "Error: TreeSitter service not initialized.".to_string(),
+        };
+        self.current_main_view = MainViewType::CodeOutline;
+        self.outline_file(&service, &target.display().to_string())
+    }
+
+    /// Re-read and re-parse the current file (or directory) so its outline matches
+    /// what's on disk after an edit
+    fn refresh_command(&mut self) -> String {
+        let Some(current) = self.current_file_path.clone() else {
+            return "Nothing to refresh: open a file with /outline <file> or @file first".to_string();
+        };
+        let service = match &self.tree_sitter_service {
+            Some(service) => service.clone(),
+            None => return "Error: TreeSitter service not initialized.".to_string(),
+        };
+
+        let path = Path::new(&current);
+        if !path.exists() {
+            self.symbol_cache.invalidate(path);
+            self.current_file_symbols.clear();
+            self.reset_outline_view();
+            self.current_file_path = None;
+            return format!(
+                "{} no longer exists; cleared its outline",
+                self.workspace.display(&current)
+            );
+        }
+        if path.is_dir() {
+            return self.outline_directory(&service, &current, &[]);
+        }
+        self.outline_file(&service, &current)
+    }
+
+    /// Keep the symbol index, and the outline if it shows the file, current when a file
+    /// is modified or deleted on disk, as a file watcher's `Modified` and `Deleted` events report
+    pub fn on_file_changed(&mut self, path: &Path) {
+        if let Some(index) = &mut self.symbol_index {
+            let change = IndexChange::Changed(self.workspace.resolve(path));
+            if let Err(e) = index.apply(&change) {
+                crate::logging::log(
+                    crate::config::LogLevel::Warn,
+                    "index",
+                    format!("Failed to re-index {}: {}", change.path().display(), e),
+                );
+            }
+        }
+
+        let Some(current) = &self.current_file_path else {
+            return;
+        };
+        if self.workspace.resolve(path) != Path::new(current) {
+            return;
+        }
+
+        let message = self.refresh_command();
+        // A quiet re-parse needs no mention, but an outline vanishing does
+        if self.current_file_path.is_none() {
+            self.add_chat_message(message, false);
+        }
+    }
+
+    /// List where `name` is defined across the workspace, in the Code Outline view
+    /// (format: /symbol <name>)
+    fn symbol_command(&mut self, args: &str) -> String {
+        let name = args.trim();
+        if name.is_empty() {
+            return "Usage: /symbol <name>".to_string();
+        }
+        let service = match &self.tree_sitter_service {
+            Some(service) => service.clone(),
+            None => return "Error: TreeSitter service not initialized.".to_string(),
+        };
+        let index = match self.symbol_index.take() {
+            Some(index) => index,
+            None => match self.build_symbol_index(service) {
+                Ok(index) => index,
+                Err(e) => return format!("Error indexing the workspace: {}", e),
+            },
+        };
+
+        let mut found: Vec<DisplaySymbol> = index
+            .find(name)
+            .into_iter()
+            .map(|(path, symbol)| DisplaySymbol {
+                name: symbol.name.clone(),
+                kind: format!("{:?}", symbol.kind),
+                line: symbol.start_line,
+                path: path.display().to_string(),
+            })
+            .collect();
+        found.sort_by(|a, b| a.path.cmp(&b.path).then(a.line.cmp(&b.line)));
+        let indexed = index.file_count();
+        self.symbol_index = Some(index);
+
+        if found.is_empty() {
+            return format!("No definition of {} in {} indexed files", name, indexed);
+        }
+        self.current_main_view = MainViewType::CodeOutline;
+        self.current_file_symbols = found;
+        self.reset_outline_view();
+        self.current_file_path = None;
+        format!(
+            "Found {} definitions of {} in {} indexed files",
+            self.current_file_symbols.len(),
+            name,
+            indexed
+        )
+    }
+
+    /// Index every supported workspace file that `.samusignore` doesn't exclude
+    fn build_symbol_index(
+        &self,
+        service: Arc<TreeSitterService>,
+    ) -> Result<SymbolIndex, crate::error::TaskError> {
+        let root = self.workspace.root();
+        let samusignore = SamusIgnore::load(root);
+        let (files, _) = outline_files(root, &[], MAX_INDEXED_FILES, &samusignore)?;
+
+        let mut index = SymbolIndex::new(service).with_samusignore(samusignore);
+        for file in &files {
+            if let Err(e) = index.index_file(file) {
+                crate::logging::log(
+                    crate::config::LogLevel::Warn,
+                    "index",
+                    format!("Failed to index {}: {}", file.display(), e),
+                );
             }
         }
+        Ok(index)
+    }
+
+    /// Outline every supported file under a directory, grouped by file
+    fn outline_directory(
+        &mut self,
+        service: &Arc<TreeSitterService>,
+        dir: &str,
+        extensions: &[String],
+    ) -> String {
+        let display_dir = self.workspace.display(dir);
+        let samusignore = SamusIgnore::load(self.workspace.root());
+        let (files, capped) = match outline_files(Path::new(dir), extensions, MAX_OUTLINE_FILES, &samusignore) {
+            Ok(result) => result,
+            Err(e) => return format!("Error listing {}: {}", display_dir, e),
+        };
+
+        let mut display_symbols = Vec::new();
+        let mut outlined = 0;
+        let mut failed = 0;
+        for file in &files {
+            let symbols = std::fs::read_to_string(file)
+                .ok()
+                .and_then(|content| self.symbol_cache.symbols(service, file, &content).ok());
+            let Some(symbols) = symbols else {
+                failed += 1;
+                continue;
+            };
+            outlined += 1;
+
+            let path = file.display().to_string();
+            display_symbols.extend(symbols.iter().map(|s| DisplaySymbol {
+                name: s.name.clone(),
+                kind: format!("{:?}", s.kind),
+                line: s.start_line,
+                path: path.clone(),
+            }));
+        }
+
+        self.current_file_symbols = display_symbols;
+        self.reset_outline_view();
+        self.current_file_path = Some(dir.to_string());
+
+        let mut message = format!(
+            "Found {} symbols in {} files under {}",
+            self.current_file_symbols.len(),
+            outlined,
+            display_dir
+        );
+        if failed > 0 {
+            message.push_str(&format!(" ({} files could not be parsed)", failed));
+        }
+        if capped {
+            message.push_str(&format!(" (stopped after {} files)", MAX_OUTLINE_FILES));
+        }
+        message
     }
 
     /// Handle model setting command
+    /// List the available models (format: /models [--refresh]). The list is cached and
+    /// only fetched again once it expires or when asked to refresh.
+    fn models_command(&mut self, cmd: &str) -> String {
+        let refresh = match cmd["models".len()..].trim() {
+            "" => false,
+            "--refresh" => true,
+            _ => return "Usage: /models [--refresh]".to_string(),
+        };
+        let Some(client) = self.llm_client.clone() else {
+            return "Error: LLM client not initialized. Use /config to set up OpenRouter."
+                .to_string();
+        };
+
+        self.is_processing = true;
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            let result = rt.block_on(async {
+                if refresh {
+                    client.refresh_models().await
+                } else {
+                    client.models().await
+                }
+            });
+            let _ = tx.send(result);
+        });
+        self.output_manager.store_models_receiver(rx);
+        "Fetching models...".to_string()
+    }
+
+    /// Check for a finished `/models`
+    fn check_models_result(&mut self) {
+        let Some(result) = self.output_manager.check_models_result() else {
+            return;
+        };
+        if let Some(pending_idx) = self
+            .chat_messages
+            .iter()
+            .position(|msg| !msg.is_user && msg.content == "Fetching models...")
+        {
+            self.chat_messages.remove(pending_idx);
+        }
+
+        let (output, success) = match result {
+            Ok(models) => {
+                let current = self.llm_client.as_ref().map(|c| c.get_model().to_string());
+                let lines: Vec<String> = models
+                    .iter()
+                    .map(|model| {
+                        let marker = if current.as_deref() == Some(&model.id) { "*" } else { " " };
+                        match model.context_length {
+                            Some(length) => format!("{} {} ({} tokens)", marker, model.id, length),
+                            None => format!("{} {}", marker, model.id),
+                        }
+                    })
+                    .collect();
+                (format!("{} models:\n{}", models.len(), lines.join("\n")), true)
+            }
+            Err(e) => (format!("Error fetching models: {}", e), false),
+        };
+        self.emit_pending_result("models", output.clone(), success);
+        self.add_chat_message(output, false);
+        self.is_processing = false;
+        self.displaying_completion = true;
+    }
+
     fn set_model_command(&mut self, cmd: &str) -> String {
         // Check if client exists
         if self.llm_client.is_none() {
@@ -470,8 +2163,11 @@ impl App {
             id: "openrouter".to_string(),
             name: "OpenRouter".to_string(),
             url: "https://openrouter.ai/api/v1/chat/completions".to_string(),
-            api_key: Some(api_key.to_string()),
+            api_key: Some(crate::secret::Secret::new(api_key)),
             enabled: true,
+            headers: crate::config::McpServerConfig::default_headers(),
+            requests_per_minute: None,
+            context_token_budget: None,
         };
 
         // Initialize client
@@ -483,8 +2179,38 @@ impl App {
 
     /// Process bash commands
     fn process_bash_command(&mut self, command: &str) {
+        // `!30s <command>` overrides the configured timeout for this command
+        let (timeout, command) = crate::shell::command::parse_timeout_prefix(command);
+        let timeout = timeout.unwrap_or(self.command_timeout_secs);
+
+        // `cd` changes the active terminal's directory rather than running in a subshell
+        if let Some(message) = self.change_terminal_directory(command) {
+            self.current_main_view = MainViewType::Terminals;
+            if let Some(view) = self.terminals.active_view_mut() {
+                view.push_output(&format!("$ {}\n{}", command, message));
+            }
+            self.add_chat_message(message, false);
+            return;
+        }
+
         self.current_main_view = MainViewType::ShellOutput;
-        self.add_chat_message(format!("Executing bash command: {}", command), false);
+
+        // Run in the active terminal's working directory, if a terminal is open
+        let working_dir = self.terminals.working_directory();
+        match self.terminals.active_view() {
+            Some(view) => self.add_chat_message(
+                format!("Executing bash command in {}: {}", view.instance.title, command),
+                false,
+            ),
+            None => {
+                self.add_chat_message(format!("Executing bash command: {}", command), false)
+            }
+        }
+        self.terminals.record_command(command);
+        self.shell_terminal = self.terminals.active_view().map(|view| view.instance.id());
+        if let Some(id) = self.shell_terminal {
+            self.terminals.append_output(id, &format!("$ {}", command));
+        }
         
         // Check if task manager is available
         if let Some(task_manager) = &self.task_manager {
@@ -495,11 +2221,15 @@ impl App {
             let task = Task::new("shell", json!({
                 "type": "execute",
                 "command": command,
-                "capture_stderr": true
+                "capture_stderr": true,
+                "working_dir": working_dir,
+                "timeout_secs": timeout,
             }));
             
-            // Mark as processing
+            // Mark as processing; Ctrl+C interrupts the command through its token
             self.is_processing = true;
+            self.shell_cancel = Some(task.cancel.clone());
+            self.shell_timeout = Some(timeout);
             
             // Clone task manager for thread
             let task_manager_clone = task_manager.clone();
@@ -514,7 +2244,7 @@ impl App {
                 let result = rt.block_on(async { task_manager_clone.execute_task(task).await });
                 
                 // Send result back to main thread
-                tx.send(result).unwrap();
+                let _ = tx.send(result);
             });
             
             // Store receiver for later checking
@@ -526,30 +2256,99 @@ impl App {
         }
     }
 
-    /// Process file references
-    fn process_file_reference(&mut self, path: &str) {
-        self.current_main_view = MainViewType::FileTree;
-        self.add_chat_message(format!("Referencing file: {}", path), false);
+    /// Show the exact command line `command` would run as, without running it.
+    /// `/run` then runs it.
+    fn preview_bash_command(&mut self, command: &str) {
+        let Some(task_manager) = self.task_manager.clone() else {
+            self.add_chat_message("Error: Task manager not initialized.".to_string(), false);
+            return;
+        };
 
-        // Try to parse the file with TreeSitter if the service is available
+        let task = crate::task::Task::new(
+            "shell",
+            serde_json::json!({
+                "type": "execute",
+                "command": command,
+                "working_dir": self.terminals.working_directory(),
+                "dry_run": true,
+            }),
+        );
+        self.current_main_view = MainViewType::ShellOutput;
+        self.add_chat_message(format!("Previewing bash command: {}", command), false);
+        self.previewed_command = Some(command.to_string());
+        self.shell_dry_run = true;
+        self.is_processing = true;
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            let _ = tx.send(rt.block_on(task_manager.execute_task(task)));
+        });
+        self.output_manager.store_shell_receiver(rx);
+    }
+
+    /// Handle `cd <dir>` for the active terminal. Returns `None` if `command` isn't a `cd`
+    /// or no terminal is open.
+    fn change_terminal_directory(&mut self, command: &str) -> Option<String> {
+        let target = command.trim().strip_prefix("cd")?;
+        if !target.is_empty() && !target.starts_with(char::is_whitespace) {
+            return None;
+        }
+        let current = self.terminals.working_directory()?;
+
+        let target = target.trim();
+        let dir = match target {
+            "" | "~" => std::env::var_os("HOME").map(PathBuf::from)?,
+            _ => current.join(target),
+        };
+        let Ok(dir) = dir.canonicalize() else {
+            return Some(format!("cd: no such directory: {}", target));
+        };
+        if !dir.is_dir() {
+            return Some(format!("cd: not a directory: {}", target));
+        }
+
+        if let Err(e) = self.terminals.set_working_directory(dir.clone()) {
+            return Some(format!("cd: {}", e));
+        }
+        self.save_terminals();
+        Some(format!("Working directory: {}", dir.display()))
+    }
+
+    /// Process file references
+    fn process_file_reference(&mut self, path: &str) {
+        self.current_main_view = MainViewType::FileTree;
+        self.add_chat_message(format!("Referencing file: {}", path), false);
+
+        let resolved = self.workspace.resolve(path);
+        if resolved.is_file() {
+            self.recent_files.touch(&resolved);
+        }
+
+        // Try to parse the file with TreeSitter if the service is available
         if let Some(service) = &self.tree_sitter_service {
+            // Keep the absolute path so later commands don't depend on how it was typed
+            let path = self.workspace.resolve(path).display().to_string();
+            let path = path.as_str();
+
             // Try to read and parse the file
             self.current_file_path = Some(path.to_string());
             match std::fs::read_to_string(path) {
                 Ok(content) => {
                     let path_obj = Path::new(path);
-                    match service.find_symbols(path_obj, &content) {
+                    match self.symbol_cache.symbols(service, path_obj, &content) {
                         Ok(symbols) => {
                             // Convert symbols to display symbols
                             self.current_file_symbols = symbols
-                                .into_iter()
+                                .iter()
                                 .map(|s| DisplaySymbol {
-                                    name: s.name,
+                                    name: s.name.clone(),
                                     kind: format!("{:?}", s.kind),
                                     line: s.start_line,
                                     path: path.to_string(),
                                 })
                                 .collect();
+                            self.reset_outline_view();
 
                             self.add_chat_message(
                                 format!(
@@ -560,7 +2359,8 @@ impl App {
                             );
                         }
                         Err(e) => {
-                            self.add_chat_message(format!("Error parsing file: {}", e), false);
+                            let message = outline_error_message(&self.workspace.display(path), &e);
+                            self.add_chat_message(message, false);
                         }
                     }
                 }
@@ -576,14 +2376,59 @@ impl App {
         self.current_main_view = view_type;
     }
 
+    /// Insert pasted text at the cursor as-is; newlines in it never submit the input
+    pub fn handle_paste(&mut self, text: &str) {
+        self.request_redraw();
+        if self.displaying_completion {
+            self.displaying_completion = false;
+        }
+
+        // Terminals send pasted line breaks as carriage returns
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        self.cursor_position = self.cursor_position.min(self.input_text.len());
+        self.input_text.insert_str(self.cursor_position, &text);
+        self.cursor_position += text.len();
+    }
+
     /// Handle key events
     pub fn handle_key_event(&mut self, key: KeyEvent) -> Option<InputCommand> {
+        self.request_redraw();
+
         // Reset cursor position if it's somehow outside bounds
         // This is a safety check to prevent string boundary errors
         self.cursor_position = self.cursor_position.min(self.input_text.len());
         
+        // In the Search view, Up/Down/Enter browse the file groups while the input is empty.
+        // This comes before the key bindings, which claim Up/Down for task selection.
+        if self.handle_search_view_key(key) {
+            return Some(InputCommand::None);
+        }
+
+        // Space with nothing typed loads more symbols in the Code Outline view
+        if self.handle_outline_view_key(key) {
+            return Some(InputCommand::None);
+        }
+
+        // Ctrl+L cycles the layout of the Terminals view; PageUp/PageDown scroll its output
+        if self.handle_terminals_view_key(key) {
+            return Some(InputCommand::None);
+        }
+
+        // Ctrl+L cycles the diff view mode in the GitDiff view
+        if self.handle_diff_view_key(key) {
+            return Some(InputCommand::None);
+        }
+
         // First, check for custom key bindings from the input handler
         let command = self.input_handler.handle_key_event(key);
+        if command == InputCommand::CancelTask {
+            self.cancel_grep();
+            self.cancel_shell();
+        }
+        if command == InputCommand::EditLastMessage {
+            self.edit_last_message();
+            return Some(InputCommand::None);
+        }
         if command != InputCommand::None {
             // Process scrolling commands
             // We no longer handle scrolling commands
@@ -862,24 +2707,108 @@ impl App {
         }
     }
 
+    /// Mark the screen as out of date so the next frame is drawn
+    pub fn request_redraw(&mut self) {
+        self.needs_redraw = true;
+    }
+
+    /// Whether a frame should be drawn, clearing the request
+    pub fn take_redraw(&mut self) -> bool {
+        std::mem::take(&mut self.needs_redraw)
+    }
+
     /// Update app state on tick
     pub fn on_tick(&mut self) {
         self.last_tick = Instant::now();
 
-        // Check for LLM responses and shell command results
+        // Check for LLM responses and shell command results. Background work only
+        // lands here, and the spinner animates meanwhile, so both need a redraw.
         if self.is_processing {
             self.check_llm_response();
             self.check_shell_result();
+            self.check_grep_result();
+            self.check_models_result();
+            self.request_redraw();
+        }
+
+        // Keep the busy indicator in step with processing
+        if self.is_processing {
+            self.spinner.start();
+            self.spinner.tick();
+        } else {
+            self.spinner.stop();
+        }
+    }
+
+    /// Check for a finished `/grep`
+    fn check_grep_result(&mut self) {
+        if let Some(result) = self.output_manager.check_grep_result() {
+            self.grep_cancel = None;
+
+            // Drop the pending "Searching for" message
+            if let Some(pending_idx) = self.chat_messages.iter().position(|msg| {
+                !msg.is_user && msg.content.starts_with("Searching for")
+            }) {
+                self.chat_messages.remove(pending_idx);
+            }
+
+            let (output, success) = match result {
+                Ok(mut outcome) => {
+                    let current_file = self
+                        .current_file_path
+                        .as_ref()
+                        .map(|path| self.workspace.resolve(path));
+                    let ctx = RankContext {
+                        pattern: &outcome.pattern,
+                        current_file: current_file.as_deref(),
+                        now: std::time::SystemTime::now(),
+                    };
+                    rank_results(&mut outcome.results, &ctx, &self.search_ranking);
+
+                    let output = outcome.render(&self.workspace);
+                    self.add_chat_message(output.clone(), false);
+                    self.grep_groups = GroupedResults::from_results(&outcome.results);
+                    self.grep_pager.reset();
+                    self.last_grep = Some(outcome);
+                    (output, true)
+                }
+                Err(e) => {
+                    let message = SamusError::from(e).user_message();
+                    self.add_chat_message(message.clone(), false);
+                    (message, false)
+                }
+            };
+            self.emit_pending_result("grep", output, success);
+
+            self.is_processing = false;
+            self.displaying_completion = true;
         }
     }
     
+    /// Report the result of the tool call that started background work, named
+    /// `fallback` if it didn't come from typed input
+    fn emit_pending_result(&mut self, fallback: &str, output: String, success: bool) {
+        let tool = self.pending_tool.take().unwrap_or_else(|| fallback.to_string());
+        self.emit(ActivityEvent::ToolResult {
+            tool,
+            output,
+            success,
+        });
+    }
+
     /// Check for shell command results
     fn check_shell_result(&mut self) {
         if let Some(result) = self.output_manager.check_shell_result() {
+            self.shell_cancel = None;
+            let timeout = self.shell_timeout.take();
+            let terminal = self.shell_terminal.take();
+            let dry_run = std::mem::take(&mut self.shell_dry_run);
+
             // Find and remove any "Executing..." or similar pending message
             // This follows the same pattern as check_llm_response for consistency
             if let Some(pending_idx) = self.chat_messages.iter().position(|msg| 
-                !msg.is_user && (msg.content.starts_with("Executing bash command:") || 
+                !msg.is_user && (msg.content.starts_with("Executing bash command") || 
+                                msg.content.starts_with("Previewing bash command") ||
                                 msg.content.starts_with("Listing"))
             ) {
                 // Only remove if it's the most recent message from the assistant
@@ -889,25 +2818,76 @@ impl App {
             }
 
             match result {
+                Ok(crate::task::TaskResult::Text(line)) if dry_run => {
+                    self.emit_pending_result("shell", line.clone(), true);
+                    self.add_chat_message(format!("Would run: {}\nType /run to run it", line), false);
+                }
                 Ok(task_result) => {
                     // Convert task result to string based on its type
-                    let result_str = match task_result {
-                        crate::task::TaskResult::Text(text) => text,
+                    let result_str = match &task_result {
+                        crate::task::TaskResult::Text(text) => text.clone(),
                         crate::task::TaskResult::Json(json) => format!("{}", json),
                         crate::task::TaskResult::Binary(bytes) => format!("[Binary data: {} bytes]", bytes.len()),
                     };
                     
+                    // Output of a command run in a terminal also goes to that terminal's view
+                    if let Some(id) = terminal {
+                        let output = match &task_result {
+                            crate::task::TaskResult::Json(json) => json
+                                .get("output")
+                                .and_then(|output| output.as_str())
+                                .unwrap_or(&result_str),
+                            _ => &result_str,
+                        };
+                        self.terminals.append_output(id, output);
+                    }
+
                     // Add the result to chat messages and update view
+                    let success = match &task_result {
+                        crate::task::TaskResult::Json(json) => {
+                            json.get("success").and_then(|s| s.as_bool()).unwrap_or(true)
+                        }
+                        _ => true,
+                    };
+                    self.emit_pending_result("shell", result_str.clone(), success);
                     self.add_chat_message(result_str, false);
-                    
-                    // Switch to shell output view to make results more visible
-                    if self.current_main_view != MainViewType::ShellOutput {
-                        self.current_main_view = MainViewType::ShellOutput;
+
+                    let timed_out = match &task_result {
+                        crate::task::TaskResult::Json(json) => {
+                            json.get("timed_out").and_then(|t| t.as_bool()).unwrap_or(false)
+                        }
+                        _ => false,
+                    };
+                    if let (true, Some(secs)) = (timed_out, timeout) {
+                        let message = format!(
+                            "Command timed out after {}s and was killed; prefix it with a longer timeout, e.g. !{}s <command>",
+                            secs,
+                            secs * 2
+                        );
+                        if let Some(id) = terminal {
+                            self.terminals.append_output(id, &message);
+                        }
+                        self.add_chat_message(message, false);
                     }
+                    
+                    // Switch to the terminal or shell output view to make results more visible
+                    self.current_main_view = if terminal.is_some() {
+                        MainViewType::Terminals
+                    } else {
+                        MainViewType::ShellOutput
+                    };
                 }
                 Err(e) => {
+                    if dry_run {
+                        self.previewed_command = None;
+                    }
+                    let message = SamusError::from(e).user_message();
+                    if let Some(id) = terminal {
+                        self.terminals.append_output(id, &message);
+                    }
+                    self.emit_pending_result("shell", message.clone(), false);
                     // Add error message
-                    self.add_chat_message(format!("Error executing command: {}", e), false);
+                    self.add_chat_message(message, false);
                 }
             }
             
@@ -938,8 +2918,1560 @@ impl App {
     }
 }
 
+/// Explain why a file has no outline, with specific wording for the errors users can act on
+pub fn outline_error_message(path: &str, error: &TreeSitterError) -> String {
+    match error {
+        TreeSitterError::FileSizeExceeded { size, limit } => format!(
+            "{} is too large for an outline ({} bytes, limit {})",
+            path, size, limit
+        ),
+        TreeSitterError::UnsupportedLanguage(_) => {
+            match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+                Some(ext) => format!("No outline support for .{} files", ext),
+                None => format!("No outline support for {}: it has no file extension", path),
+            }
+        }
+        other => format!("Error parsing file: {}", other),
+    }
+}
+
 impl Default for App {
     fn default() -> Self {
         Self::new()
     }
 }
+
+/// Form of `input` that's safe to show and keep in history: the key passed to
+/// `/config` is masked, as is any other secret the app knows about
+fn history_entry(input: &str) -> String {
+    match input.strip_prefix("/config") {
+        Some(args) if args.starts_with(char::is_whitespace) && !args.trim().is_empty() => {
+            format!("/config {}", crate::secret::MASK)
+        }
+        _ => crate::secret::redact(input),
+    }
+}
+
+/// Tool name and input for a slash command, `!command` or `@file`; `None` for chat
+fn activity_tool(input: &str) -> Option<(String, String)> {
+    let (tool, rest) = match input.chars().next()? {
+        '/' => {
+            let command = &input[1..];
+            command.split_once(' ').unwrap_or((command, ""))
+        }
+        '!' => ("shell", &input[1..]),
+        '@' => ("file", &input[1..]),
+        _ => return None,
+    };
+    Some((tool.to_string(), rest.trim().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn config_with_limits(chat_limit: usize, command_limit: usize) -> Config {
+        let mut config = Config::without_persistence();
+        config.history.chat_limit = chat_limit;
+        config.history.command_limit = command_limit;
+        config
+    }
+
+    /// Server on a closed port, for tests that only care about the request being made
+    fn unreachable_server() -> crate::config::McpServerConfig {
+        crate::config::McpServerConfig {
+            id: "openrouter".to_string(),
+            name: "OpenRouter".to_string(),
+            url: "http://127.0.0.1:9/chat".to_string(),
+            api_key: Some(crate::secret::Secret::new("test-key")),
+            enabled: true,
+            headers: crate::config::McpServerConfig::default_headers(),
+            requests_per_minute: None,
+            context_token_budget: None,
+        }
+    }
+
+    #[test]
+    fn test_status_reports_each_subsystem() {
+        let dir = tempdir().unwrap();
+        let mut disabled = unreachable_server();
+        disabled.name = "Local".to_string();
+        disabled.enabled = false;
+        let mut config = config_with_limits(10, 10);
+        config.mcp_servers = vec![unreachable_server(), disabled];
+
+        let mut app = App::with_config(&config);
+        app.workspace = WorkspacePaths::new(dir.path());
+        app.process_slash_command("status");
+        let status = app.chat_messages.back().unwrap().content.clone();
+        assert!(status.contains("[off] LLM: not configured"), "{}", status);
+        assert!(status.contains("[off] Tree-sitter: service not initialized"), "{}", status);
+        assert!(status.contains("Ripgrep: "), "{}", status);
+        assert!(status.contains("[ok] OpenRouter: enabled, http://127.0.0.1:9/chat"), "{}", status);
+        assert!(status.contains("[off] Local: disabled"), "{}", status);
+        assert!(status.contains(&format!("Workspace: {}", app.workspace.root().display())));
+
+        app.init_llm_client(unreachable_server()).unwrap();
+        app.init_tree_sitter(1_000_000, 1);
+        app.process_slash_command("status");
+        let status = app.chat_messages.back().unwrap().content.clone();
+        assert!(status.contains(&format!("[ok] LLM: model {}", DEFAULT_MODEL)), "{}", status);
+        assert!(status.contains("[ok] Tree-sitter: JavaScript"), "{}", status);
+    }
+
+    #[test]
+    fn test_chat_history_limit_evicts_oldest() {
+        let mut app = App::with_config(&config_with_limits(3, 10));
+
+        for i in 0..5 {
+            app.add_chat_message(format!("message {}", i), true);
+        }
+
+        let contents: Vec<&str> = app.chat_messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["message 2", "message 3", "message 4"]);
+    }
+
+    #[test]
+    fn test_command_history_limit_evicts_oldest() {
+        let mut app = App::with_config(&config_with_limits(10, 2));
+
+        app.add_to_history("first".to_string());
+        app.add_to_history("second".to_string());
+        app.add_to_history("third".to_string());
+
+        assert_eq!(app.command_history, VecDeque::from(vec!["third".to_string(), "second".to_string()]));
+    }
+
+    #[test]
+    fn test_limit_changed_at_runtime() {
+        let mut app = App::with_config(&config_with_limits(10, 10));
+        for i in 0..5 {
+            app.add_chat_message(format!("message {}", i), false);
+        }
+
+        app.chat_history_limit = 2;
+        app.add_chat_message("latest".to_string(), false);
+
+        let contents: Vec<&str> = app.chat_messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["message 4", "latest"]);
+    }
+
+    #[test]
+    fn test_command_history_persists_across_sessions() {
+        let dir = tempdir().unwrap();
+        let mut config = config_with_limits(10, 10);
+        config.history.command_history_file = Some(dir.path().join("history.json"));
+
+        let mut app = App::with_config(&config);
+        app.add_to_history("!ls".to_string());
+        app.add_to_history("/help".to_string());
+
+        let restored = App::with_config(&config);
+        assert_eq!(restored.command_history, VecDeque::from(vec!["/help".to_string(), "!ls".to_string()]));
+    }
+
+    #[test]
+    fn test_loaded_command_history_is_deduped_and_capped() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("history.json");
+        std::fs::write(&file, r#"["/help", "/help", "!ls", "!ls", "/tree", "/outline", "/grep x"]"#).unwrap();
+
+        let mut config = config_with_limits(10, 3);
+        config.history.command_history_file = Some(file);
+        let app = App::with_config(&config);
+
+        assert_eq!(
+            app.command_history,
+            VecDeque::from(vec!["/help".to_string(), "!ls".to_string(), "/tree".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_config_keys_are_not_persisted_in_history() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("history.json");
+        let key = "sk-or-v1-history0123456789abcdef";
+
+        // An older file that stored a key in plain text is scrubbed when loaded
+        std::fs::write(&file, format!(r#"["/config {}"]"#, key)).unwrap();
+        let mut config = config_with_limits(10, 10);
+        config.history.command_history_file = Some(file.clone());
+        let mut app = App::with_config(&config);
+        let masked = format!("/config {}", crate::secret::MASK);
+        assert_eq!(app.command_history, VecDeque::from(vec![masked.clone()]));
+
+        app.add_to_history(format!("/config   {}", key));
+        app.add_to_history("/configure".to_string());
+        let saved = std::fs::read_to_string(&file).unwrap();
+        assert!(!saved.contains(key), "{}", saved);
+
+        let restored = App::with_config(&config);
+        assert_eq!(
+            restored.command_history,
+            VecDeque::from(vec!["/configure".to_string(), masked])
+        );
+    }
+
+    #[test]
+    fn test_empty_llm_response_shows_notice() {
+        let mut app = App::with_config(&config_with_limits(10, 10));
+        app.add_chat_message("Thinking...".to_string(), false);
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        app.output_manager.store_receiver(rx);
+        tx.send(Err(crate::error::TaskError::EmptyResponse {
+            finish_reason: Some("content_filter".to_string()),
+        }))
+        .unwrap();
+
+        app.check_llm_response();
+
+        let last = app.chat_messages.back().unwrap();
+        assert!(!last.is_user);
+        assert_eq!(
+            last.content,
+            "The model returned an empty response (content was filtered)"
+        );
+        assert!(app.chat_messages.iter().all(|m| m.content != "Thinking..."));
+        assert!(!app.is_processing);
+    }
+
+    #[test]
+    fn test_llm_errors_are_shown_by_category() {
+        let mut app = App::with_config(&config_with_limits(10, 10));
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        app.output_manager.store_receiver(rx);
+        tx.send(Err(crate::error::TaskError::RateLimited {
+            retry_after: Duration::from_secs(3),
+        }))
+        .unwrap();
+        app.check_llm_response();
+
+        assert_eq!(
+            app.chat_messages.back().unwrap().content,
+            "Model error: Rate limit reached: too many requests queued, try again in 3s"
+        );
+    }
+
+    fn completion(content: &str, finish_reason: &str) -> ChatCompletion {
+        ChatCompletion {
+            content: content.to_string(),
+            finish_reason: Some(finish_reason.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_truncated_response_offers_continue() {
+        let mut app = App::with_config(&config_with_limits(10, 10));
+        app.last_exchange = Some(LlmExchange {
+            prompt: "Explain traits".to_string(),
+            response: String::new(),
+            truncated: false,
+            model: DEFAULT_MODEL.to_string(),
+        });
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        app.output_manager.store_receiver(rx);
+        tx.send(Ok(completion("Traits define", "length"))).unwrap();
+        app.check_llm_response();
+
+        let last = app.chat_messages.back().unwrap();
+        assert_eq!(last.content, format!("Traits define{}", TRUNCATION_MARKER));
+
+        // /continue replays the conversation and asks for more
+        assert_eq!(
+            app.continuation_request().unwrap(),
+            vec![
+                ChatTurn::user("Explain traits"),
+                ChatTurn::assistant("Traits define"),
+                ChatTurn::user(crate::mcp::client::CONTINUE_PROMPT),
+            ]
+        );
+
+        // The continuation is appended to the same message
+        app.continuing_response = true;
+        let (tx, rx) = std::sync::mpsc::channel();
+        app.output_manager.store_receiver(rx);
+        tx.send(Ok(completion(" shared behaviour.", "stop"))).unwrap();
+        app.check_llm_response();
+
+        let assistant: Vec<&str> = app
+            .chat_messages
+            .iter()
+            .filter(|m| !m.is_user)
+            .map(|m| m.content.as_str())
+            .collect();
+        assert_eq!(assistant, vec!["Traits define shared behaviour."]);
+        assert!(app.continuation_request().is_none());
+    }
+
+    #[test]
+    fn test_prompts_send_the_conversation_so_far() {
+        let mut app = App::with_config(&config_with_limits(10, 10));
+        app.init_llm_client(unreachable_server()).unwrap();
+        let answer = |app: &mut App, content: &str| {
+            let (tx, rx) = std::sync::mpsc::channel();
+            app.output_manager.store_receiver(rx);
+            tx.send(Ok(completion(content, "stop"))).unwrap();
+            app.check_llm_response();
+        };
+
+        app.send_to_llm("What is a trait?".to_string());
+        answer(&mut app, "A set of methods.");
+        app.send_to_llm("Show an example".to_string());
+        assert_eq!(
+            app.request_turns(vec![ChatTurn::user("Show an example")]),
+            vec![
+                ChatTurn::user("What is a trait?"),
+                ChatTurn::assistant("A set of methods."),
+                ChatTurn::user("Show an example"),
+            ]
+        );
+
+        // A retried answer replaces the last exchange instead of joining the history
+        answer(&mut app, "trait Draw {}");
+        app.process_slash_command("retry");
+        assert_eq!(app.conversation.len(), 2);
+
+        app.process_slash_command("new");
+        app.process_slash_command("new");
+        assert!(app.conversation.is_empty());
+    }
+
+    #[test]
+    fn test_continue_waits_for_the_running_request() {
+        let mut app = App::with_config(&config_with_limits(10, 10));
+        // Point the client at a closed port; only the placeholder and guard matter here
+        app.init_llm_client(unreachable_server()).unwrap();
+        app.chat_messages.clear();
+        app.last_exchange = Some(LlmExchange {
+            prompt: "Explain traits".to_string(),
+            response: "Traits define".to_string(),
+            truncated: true,
+            model: DEFAULT_MODEL.to_string(),
+        });
+        app.add_chat_message("Explain traits".to_string(), true);
+        app.add_chat_message(format!("Traits define{}", TRUNCATION_MARKER), false);
+
+        app.process_slash_command("continue");
+        assert!(app.is_processing);
+        let contents: Vec<&str> = app.chat_messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents[2..], ["Thinking...", "Continuing the previous response..."]);
+
+        app.process_slash_command("continue");
+        assert_eq!(
+            app.chat_messages.back().unwrap().content,
+            "A response is still in progress"
+        );
+
+        // The placeholder goes once the continuation arrives
+        let (tx, rx) = std::sync::mpsc::channel();
+        app.output_manager.store_receiver(rx);
+        tx.send(Ok(completion(" shared behaviour.", "stop"))).unwrap();
+        app.check_llm_response();
+        assert!(app.chat_messages.iter().all(|m| m.content != "Thinking..."));
+        assert!(
+            app.chat_messages
+                .iter()
+                .any(|m| m.content == "Traits define shared behaviour.")
+        );
+    }
+
+    #[test]
+    fn test_continue_without_truncation() {
+        let mut app = App::with_config(&config_with_limits(10, 10));
+        app.process_slash_command("continue");
+        assert_eq!(
+            app.chat_messages.back().unwrap().content,
+            "Nothing to continue: the last response wasn't truncated"
+        );
+    }
+
+    #[test]
+    fn test_spinner_runs_while_processing() {
+        let mut app = App::with_config(&config_with_limits(10, 10));
+        app.on_tick();
+        assert!(!app.spinner.is_active());
+
+        app.is_processing = true;
+        app.on_tick();
+        let first_frame = app.spinner.frame();
+        let first_elapsed = app.spinner.elapsed();
+
+        std::thread::sleep(Duration::from_millis(20));
+        app.on_tick();
+        assert_ne!(app.spinner.frame(), first_frame);
+        assert!(app.spinner.elapsed() > first_elapsed);
+
+        app.is_processing = false;
+        app.on_tick();
+        assert!(app.spinner.status_text().is_none());
+    }
+
+    #[test]
+    fn test_explain_template_fills_prompt() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let file = temp.path().join("lib.rs");
+        std::fs::write(&file, "fn one() {}\nfn two() {}\nfn three() {}\n").unwrap();
+        let file = file.to_string_lossy().to_string();
+
+        let app = App::with_config(&config_with_limits(10, 10));
+        assert_eq!(
+            app.template_prompt(&format!("explain {}", file)).unwrap(),
+            format!(
+                "Explain what the following code from {} does.\n\n```\nfn one() {{}}\nfn two() {{}}\nfn three() {{}}\n```",
+                file
+            )
+        );
+
+        // A line range narrows the selection and trailing text becomes the question
+        assert_eq!(
+            app.template_prompt(&format!("explain {}:2-2 why two?", file)).unwrap(),
+            format!(
+                "Explain what the following code from {} does.\n\n```\nfn two() {{}}\n```\n\nwhy two?",
+                file
+            )
+        );
+
+        assert!(app.template_prompt("explain").is_err());
+    }
+
+    #[test]
+    fn test_review_uses_its_configured_model() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let file = temp.path().join("lib.rs");
+        std::fs::write(&file, "fn main() {}\n").unwrap();
+
+        let mut config = config_with_limits(10, 10);
+        config
+            .task_models
+            .insert(LlmTask::Review, "anthropic/claude-3-opus".to_string());
+        let mut app = App::with_config(&config);
+        app.init_llm_client(unreachable_server()).unwrap();
+
+        app.process_slash_command(&format!("review {}", file.display()));
+        assert_eq!(app.last_exchange.as_ref().unwrap().model, "anthropic/claude-3-opus");
+
+        // Plain chat stays on the default, and the review didn't change it
+        app.is_processing = false;
+        app.send_to_llm("hello".to_string());
+        assert_eq!(app.last_exchange.as_ref().unwrap().model, DEFAULT_MODEL);
+        assert_eq!(app.llm_client.as_ref().unwrap().get_model(), DEFAULT_MODEL);
+
+        // Tasks without their own model follow the current one
+        assert_eq!(app.model_for(LlmTask::Explain).as_deref(), Some(DEFAULT_MODEL));
+    }
+
+    #[test]
+    fn test_pinned_files_are_sent_with_each_request() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("notes.md"), "Use tabs.\n").unwrap();
+        let mut app = App::with_config(&config_with_limits(10, 10));
+        app.workspace = WorkspacePaths::new(dir.path());
+
+        app.process_slash_command("context-add notes.md");
+        assert_eq!(app.chat_messages.back().unwrap().content, "Pinned notes.md");
+
+        let turns = app.with_context(vec![ChatTurn::user("format this")]);
+        assert_eq!(turns.len(), 2);
+        assert_eq!(turns[0].role, crate::mcp::client::Role::System);
+        assert!(turns[0].content.contains("notes.md:\n```markdown\nUse tabs.\n```"));
+        assert_eq!(turns[1], ChatTurn::user("format this"));
+
+        // Edits to the file show up in the next request
+        std::fs::write(dir.path().join("notes.md"), "Use four spaces.\n").unwrap();
+        let turns = app.with_context(vec![ChatTurn::user("and now?")]);
+        assert!(turns[0].content.contains("Use four spaces."));
+        assert!(!turns[0].content.contains("Use tabs."));
+
+        app.process_slash_command("context-list");
+        assert!(app.chat_messages.back().unwrap().content.contains("notes.md (17 bytes)"));
+
+        app.process_slash_command("context-remove notes.md");
+        assert_eq!(app.chat_messages.back().unwrap().content, "Unpinned notes.md");
+        assert_eq!(
+            app.with_context(vec![ChatTurn::user("again")]),
+            vec![ChatTurn::user("again")]
+        );
+
+        app.process_slash_command("context-add missing.md");
+        assert!(app.chat_messages.back().unwrap().content.starts_with("Error pinning missing.md"));
+    }
+
+    #[test]
+    fn test_reset_recovers_from_a_stuck_state() {
+        let mut app = App::with_config(&config_with_limits(10, 10));
+        app.add_chat_message("Explain traits".to_string(), true);
+
+        // A request whose worker never answers, plus a shell command and a grep in flight
+        let (_llm_tx, llm_rx) = std::sync::mpsc::channel();
+        app.output_manager.store_receiver(llm_rx);
+        let (_shell_tx, shell_rx) = std::sync::mpsc::channel();
+        app.output_manager.store_shell_receiver(shell_rx);
+        let shell_cancel = crate::context::CancellationToken::new();
+        app.shell_cancel = Some(shell_cancel.clone());
+        let grep_cancel = Arc::new(AtomicBool::new(false));
+        app.grep_cancel = Some(grep_cancel.clone());
+        app.is_processing = true;
+
+        app.input_text = "/reset".to_string();
+        app.process_input();
+        assert_eq!(
+            app.chat_messages.back().unwrap().content,
+            "Reset: cancelled 2 tasks, dropped 2 pending results"
+        );
+        assert!(shell_cancel.is_cancelled());
+        assert!(grep_cancel.load(Ordering::Relaxed));
+        assert!(!app.is_processing);
+        assert!(!app.displaying_completion);
+        assert!(app.output_manager.check_llm_response().is_none());
+        assert!(app.chat_messages.iter().any(|m| m.content == "Explain traits"));
+
+        // Back to taking input
+        app.handle_key_event(KeyEvent::new(KeyCode::Char('h'), KeyModifiers::NONE));
+        assert_eq!(app.input_text, "h");
+        assert_eq!(app.reset_command(), "Reset: nothing was running");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_reset_while_a_worker_is_running_ignores_its_late_result() {
+        use crate::fs::operations::LocalFileSystem;
+        use crate::task::{TaskManager, TaskRegistry, shell_task::ShellTaskHandler};
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempdir().unwrap();
+        let script = dir.path().join("late.sh");
+        std::fs::write(&script, "#!/bin/sh\nsleep 0.3\necho late\n").unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let mut registry = TaskRegistry::new();
+        registry.register("shell", Arc::new(ShellTaskHandler::new()));
+        let mut app = App::with_config(&config_with_limits(10, 10));
+        app.set_task_manager(Arc::new(TaskManager::new(
+            Arc::new(LocalFileSystem::new()),
+            Arc::new(registry),
+        )));
+
+        app.process_bash_command(&script.display().to_string());
+        std::thread::sleep(Duration::from_millis(50));
+        app.on_tick();
+        assert!(app.is_processing);
+        app.process_slash_command("reset");
+        assert!(!app.is_processing);
+        let messages = app.chat_messages.len();
+
+        // The worker finishes after its receiver is gone; the app carries on without it
+        let deadline = Instant::now() + Duration::from_millis(800);
+        while Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(20));
+            app.on_tick();
+        }
+        assert!(!app.is_processing);
+        assert_eq!(app.chat_messages.len(), messages);
+
+        app.process_bash_command("echo again");
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while app.is_processing && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(20));
+            app.on_tick();
+        }
+        assert!(!app.is_processing);
+    }
+
+    #[test]
+    fn test_new_clears_the_conversation_but_keeps_pinned_context() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("notes.md"), "Use tabs.\n").unwrap();
+        let mut app = App::with_config(&config_with_limits(10, 10));
+        app.workspace = WorkspacePaths::new(dir.path());
+        app.process_slash_command("context-add notes.md");
+        app.command_history.clear();
+        app.add_to_history("/context-add notes.md".to_string());
+        app.add_chat_message("Explain traits".to_string(), true);
+        app.last_exchange = Some(LlmExchange {
+            prompt: "Explain traits".to_string(),
+            response: "Traits define shared behaviour".to_string(),
+            truncated: false,
+            model: DEFAULT_MODEL.to_string(),
+        });
+
+        // The first /new only asks, and anything else in between cancels it
+        app.process_slash_command("new");
+        assert!(app.last_exchange.is_some());
+        assert!(app.chat_messages.back().unwrap().content.contains("/new again"));
+        app.input_text = "/context-list".to_string();
+        app.process_input();
+        app.process_slash_command("new");
+        assert!(app.last_exchange.is_some());
+
+        app.process_slash_command("new");
+        assert!(app.last_exchange.is_none());
+        assert_eq!(app.chat_messages.len(), 1);
+        assert_eq!(app.chat_messages[0].content, "Started a new conversation");
+        assert_eq!(app.command_history, ["/context-list", "/context-add notes.md"]);
+        let turns = app.with_context(vec![ChatTurn::user("format this")]);
+        assert!(turns[0].content.contains("Use tabs."));
+
+        // With nothing to lose there's no confirmation
+        app.process_slash_command("new");
+        assert_eq!(app.chat_messages.len(), 1);
+    }
+
+    #[test]
+    fn test_new_can_keep_the_visible_chat() {
+        let mut config = config_with_limits(10, 10);
+        config.history.clear_chat_on_new = false;
+        let mut app = App::with_config(&config);
+        app.add_chat_message("Explain traits".to_string(), true);
+
+        app.process_slash_command("new");
+        assert_eq!(app.chat_messages.len(), 2);
+        assert_eq!(app.chat_messages[0].content, "Explain traits");
+    }
+
+    /// A finished exchange whose response suggests `diff`
+    fn exchange_with_edit(diff: &str) -> LlmExchange {
+        LlmExchange {
+            prompt: "Rename it".to_string(),
+            response: format!("Here's the change:\n\n{}\n\nThat should do it.", diff),
+            truncated: false,
+            model: DEFAULT_MODEL.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_apply_auto_applies_a_one_line_edit_but_confirms_larger_ones() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("lib.rs"), "fn old() {}\nfn keep() {}\nfn other() {}").unwrap();
+        let mut config = config_with_limits(10, 10);
+        config.auto_apply.enabled = true;
+        let mut app = App::with_config(&config);
+        app.workspace = WorkspacePaths::new(dir.path());
+
+        app.last_exchange = Some(exchange_with_edit(
+            "<<<<<<< SEARCH\n:start_line:1\n-------\nfn old() {}\n=======\nfn new() {}\n>>>>>>> REPLACE",
+        ));
+        app.process_slash_command("apply lib.rs");
+        assert!(app.chat_messages.back().unwrap().content.starts_with("Applied edit to lib.rs"));
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("lib.rs")).unwrap(),
+            "fn new() {}\nfn keep() {}\nfn other() {}"
+        );
+
+        app.last_exchange = Some(exchange_with_edit(
+            "<<<<<<< SEARCH\n:start_line:1\n-------\nfn new() {}\n=======\nfn one() {}\n>>>>>>> REPLACE\n\n\
+             <<<<<<< SEARCH\n:start_line:3\n-------\nfn other() {}\n=======\nfn three() {}\n>>>>>>> REPLACE",
+        ));
+        app.input_text = "/apply lib.rs".to_string();
+        app.process_input();
+        let asked = &app.chat_messages.back().unwrap().content;
+        assert!(asked.contains("needs confirmation: 2 blocks"), "{}", asked);
+        assert!(asked.ends_with("Type /apply lib.rs again to apply it"));
+        assert!(std::fs::read_to_string(dir.path().join("lib.rs")).unwrap().starts_with("fn new()"));
+
+        app.input_text = "/apply lib.rs".to_string();
+        app.process_input();
+        assert!(app.chat_messages.back().unwrap().content.starts_with("Applied edit to lib.rs"));
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("lib.rs")).unwrap(),
+            "fn one() {}\nfn keep() {}\nfn three() {}"
+        );
+
+        // Without a response to take blocks from there's nothing to do
+        app.last_exchange = None;
+        app.process_slash_command("apply lib.rs");
+        assert_eq!(
+            app.chat_messages.back().unwrap().content,
+            "Nothing to apply: the last response has no search/replace blocks"
+        );
+    }
+
+    #[test]
+    fn test_undo_restores_from_the_backup_dir() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("lib.rs"), "fn old() {}").unwrap();
+        let mut config = config_with_limits(10, 10);
+        config.backups.dir = Some(PathBuf::from(".samus/backups"));
+        let mut app = App::with_config(&config);
+        app.workspace = WorkspacePaths::new(dir.path());
+
+        // Confirmed, since auto-apply is off
+        app.last_exchange = Some(exchange_with_edit(
+            "<<<<<<< SEARCH\n:start_line:1\n-------\nfn old() {}\n=======\nfn new() {}\n>>>>>>> REPLACE",
+        ));
+        app.process_slash_command("apply lib.rs");
+        app.process_slash_command("apply lib.rs");
+        assert_eq!(std::fs::read_to_string(dir.path().join("lib.rs")).unwrap(), "fn new() {}");
+        let backups = crate::tools::BackupStore::new(dir.path(), config.backups.clone());
+        let versions = backups.versions(&app.workspace.root().join("lib.rs")).unwrap();
+        assert_eq!(versions.len(), 1);
+        assert!(versions[0].starts_with(app.workspace.root().join(".samus/backups")));
+        assert!(!dir.path().join("lib.rs.bak").exists());
+
+        app.process_slash_command("undo lib.rs");
+        assert_eq!(app.chat_messages.back().unwrap().content, "Restored lib.rs from its last backup");
+        assert_eq!(std::fs::read_to_string(dir.path().join("lib.rs")).unwrap(), "fn old() {}");
+
+        app.process_slash_command("undo lib.rs");
+        assert_eq!(
+            app.chat_messages.back().unwrap().content,
+            format!(
+                "Error restoring lib.rs: No backup found for {}",
+                app.workspace.root().join("lib.rs").display()
+            )
+        );
+    }
+
+    #[test]
+    fn test_prompt_preview_estimates_tokens_and_warns_over_the_limit() {
+        let mut app = App::with_config(&config_with_limits(10, 10));
+        assert_eq!(app.prompt_preview(), None);
+        app.input_text = "/help".to_string();
+        assert_eq!(app.prompt_preview(), None);
+
+        // 10 tokens of text with real tokenizers, plus a few for the message itself
+        app.input_text = "The quick brown fox jumps over the lazy dog.".to_string();
+        let preview = app.prompt_preview().unwrap();
+        assert!((10..=20).contains(&preview.tokens), "estimate was {}", preview.tokens);
+        assert_eq!(preview.model, DEFAULT_MODEL);
+        assert!(!preview.exceeds_context());
+        assert_eq!(preview.summary(), format!("~{} tokens", preview.tokens));
+
+        // Pinned context counts towards the total
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("big.txt"), "word ".repeat(2_000)).unwrap();
+        app.workspace = WorkspacePaths::new(dir.path());
+        app.process_slash_command("context-add big.txt");
+        assert!(app.prompt_preview().unwrap().tokens > preview.tokens + 2_000);
+
+        // Claude 3 Haiku takes 200k tokens
+        app.input_text = "x".repeat(1_000_000);
+        let preview = app.prompt_preview().unwrap();
+        assert!(preview.exceeds_context());
+        assert!(preview.summary().contains("over the 200000 token limit"));
+    }
+
+    #[test]
+    fn test_custom_templates_from_config() {
+        let mut config = config_with_limits(10, 10);
+        config.prompt_templates.push(crate::ui::prompt_template::PromptTemplate::new(
+            "docs",
+            "Write doc comments",
+            "Document {file}",
+        ));
+        let app = App::with_config(&config);
+        assert!(app.prompt_templates.get("docs").is_some());
+        assert!(app.prompt_templates.get("review").is_some());
+    }
+
+    #[test]
+    fn test_outline_error_messages() {
+        assert_eq!(
+            outline_error_message(
+                "data/huge.js",
+                &TreeSitterError::FileSizeExceeded { size: 12_000_000, limit: 10_000_000 }
+            ),
+            "data/huge.js is too large for an outline (12000000 bytes, limit 10000000)"
+        );
+        assert_eq!(
+            outline_error_message("notes.xyz", &TreeSitterError::UnsupportedLanguage("xyz".into())),
+            "No outline support for .xyz files"
+        );
+        assert_eq!(
+            outline_error_message(
+                "Makefile",
+                &TreeSitterError::UnsupportedLanguage("No file extension".into())
+            ),
+            "No outline support for Makefile: it has no file extension"
+        );
+        assert_eq!(
+            outline_error_message("a.js", &TreeSitterError::ParseError("bad".into())),
+            "Error parsing file: Failed to parse file: bad"
+        );
+    }
+
+    #[test]
+    fn test_outline_of_oversized_file() {
+        let temp = tempdir().unwrap();
+        let file = temp.path().join("big.py");
+        std::fs::write(&file, "x = 1\n".repeat(20)).unwrap();
+
+        let mut app = App::with_config(&config_with_limits(10, 10));
+        app.init_tree_sitter(64, 1);
+        let message = app.show_code_outline(&format!("outline {}", file.display()));
+        assert!(message.ends_with("is too large for an outline (120 bytes, limit 64)"), "{}", message);
+    }
+
+    #[test]
+    fn test_outline_navigation_jumps_to_the_selected_symbol() {
+        let temp = tempdir().unwrap();
+        let file = temp.path().join("lib.rs");
+        std::fs::write(&file, "fn alpha() {}\n\nfn beta() {}\n\n\nfn gamma() {}\n").unwrap();
+
+        let mut app = App::with_config(&config_with_limits(10, 10));
+        app.workspace = WorkspacePaths::new(temp.path());
+        app.init_tree_sitter(1024 * 1024, 10);
+        app.process_slash_command("outline lib.rs");
+        assert_eq!(app.current_file_symbols.len(), 3);
+
+        let press = |app: &mut App, code| app.handle_key_event(KeyEvent::new(code, KeyModifiers::NONE));
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Down);
+        assert_eq!(app.outline_selected, 2);
+        press(&mut app, KeyCode::Up);
+        assert_eq!(app.outline_selected, 1);
+        assert_eq!(app.current_file_symbols[1].name, "beta");
+
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.current_main_view, MainViewType::GitDiff);
+        // beta is on line 3, so two rows are scrolled past
+        assert_eq!(app.active_diff.as_ref().unwrap().scroll_offset(), 2);
+
+        app.current_main_view = MainViewType::CodeOutline;
+        press(&mut app, KeyCode::Down);
+        assert_eq!(app.open_selected_symbol(), Ok(6));
+        assert_eq!(app.active_diff.as_ref().unwrap().scroll_offset(), 5);
+    }
+
+    #[test]
+    fn test_metrics_lists_slow_actor_handlers() {
+        let app = App::with_config(&config_with_limits(10, 10));
+        crate::logging::log(
+            crate::config::LogLevel::Warn,
+            crate::logging::SLOW_HANDLER_TARGET,
+            "/system/file-ops took 1.5s to handle a message (threshold 1s)",
+        );
+
+        let report = app.metrics_command();
+        assert!(report.starts_with("Tree-sitter service not initialized"), "{}", report);
+        assert!(report.contains("\nSlow actor handlers: "), "{}", report);
+        assert!(report.contains("\n  /system/file-ops took 1.5s"), "{}", report);
+    }
+
+    #[test]
+    fn test_symbol_finds_definitions_across_the_workspace() {
+        let temp = tempdir().unwrap();
+        std::fs::create_dir_all(temp.path().join("src")).unwrap();
+        std::fs::create_dir_all(temp.path().join("vendor")).unwrap();
+        std::fs::write(temp.path().join("src/lib.rs"), "fn shared() {}\n").unwrap();
+        let other = temp.path().join("src/other.rs");
+        std::fs::write(&other, "fn other() {}\n\nfn shared() {}\n").unwrap();
+        std::fs::write(temp.path().join("vendor/dep.rs"), "fn shared() {}\n").unwrap();
+        std::fs::write(temp.path().join(".samusignore"), "vendor/\n").unwrap();
+
+        let mut app = App::with_config(&config_with_limits(10, 10));
+        app.workspace = WorkspacePaths::new(temp.path());
+        app.init_tree_sitter(1024 * 1024, 10);
+        assert_eq!(app.symbol_command(""), "Usage: /symbol <name>");
+
+        // vendor/ is left out of the index
+        let reply = app.symbol_command("shared");
+        assert_eq!(reply, "Found 2 definitions of shared in 2 indexed files");
+        assert_eq!(app.current_main_view, MainViewType::CodeOutline);
+        let found: Vec<(String, usize)> = app
+            .current_file_symbols
+            .iter()
+            .map(|s| (app.workspace.display(&s.path), s.line))
+            .collect();
+        assert_eq!(found, [("src/lib.rs".to_string(), 1), ("src/other.rs".to_string(), 3)]);
+
+        // Changed files are re-indexed, and ignored ones stay out
+        std::fs::write(&other, "fn other() {}\n").unwrap();
+        app.on_file_changed(Path::new("src/other.rs"));
+        app.on_file_changed(Path::new("vendor/dep.rs"));
+        assert_eq!(
+            app.symbol_command("shared"),
+            "Found 1 definitions of shared in 2 indexed files"
+        );
+        assert!(app.symbol_command("missing").starts_with("No definition of missing"));
+    }
+
+    #[test]
+    fn test_refresh_reparses_the_current_file() {
+        let temp = tempdir().unwrap();
+        let file = temp.path().join("lib.rs");
+        std::fs::write(&file, "fn alpha() {}\n").unwrap();
+
+        let mut app = App::with_config(&config_with_limits(10, 10));
+        app.workspace = WorkspacePaths::new(temp.path());
+        app.init_tree_sitter(1024 * 1024, 10);
+        app.show_code_outline("outline lib.rs");
+        let names = |app: &App| -> Vec<String> {
+            app.current_file_symbols.iter().map(|s| s.name.clone()).collect()
+        };
+        assert_eq!(names(&app), ["alpha"]);
+
+        std::fs::write(&file, "fn alpha() {}\nfn beta() {}\n").unwrap();
+        app.process_slash_command("refresh");
+        assert_eq!(names(&app), ["alpha", "beta"]);
+
+        // A watcher event for the file re-parses it without a command
+        std::fs::write(&file, "fn gamma() {}\n").unwrap();
+        app.on_file_changed(Path::new("lib.rs"));
+        assert_eq!(names(&app), ["gamma"]);
+
+        std::fs::remove_file(&file).unwrap();
+        app.on_file_changed(&file);
+        assert!(app.current_file_symbols.is_empty());
+        assert!(app.current_file_path.is_none());
+        assert!(app.chat_messages.back().unwrap().content.contains("no longer exists"));
+        assert!(app.refresh_command().starts_with("Nothing to refresh"));
+    }
+
+    #[test]
+    fn test_recent_files_track_references_and_switch_back() {
+        let temp = tempdir().unwrap();
+        std::fs::create_dir_all(temp.path().join("src/ui")).unwrap();
+        std::fs::write(temp.path().join("src/config.rs"), "fn load() {}\n").unwrap();
+        std::fs::write(temp.path().join("src/ui/app.rs"), "fn draw() {}\n").unwrap();
+        std::fs::write(temp.path().join("README.md"), "# readme\n").unwrap();
+
+        let mut app = App::with_config(&config_with_limits(10, 10));
+        app.workspace = WorkspacePaths::new(temp.path());
+        app.init_tree_sitter(1024 * 1024, 10);
+        assert!(app.recent_command("").starts_with("No recent files yet"));
+
+        app.process_file_reference("src/ui/app.rs");
+        app.process_file_reference("README.md");
+        app.show_code_outline("outline src/config.rs");
+        // Referencing a file again moves it back to the front
+        app.process_file_reference("src/ui/app.rs");
+        let order: Vec<String> = app
+            .recent_files
+            .files()
+            .iter()
+            .map(|file| app.workspace.display(file))
+            .collect();
+        assert_eq!(order, ["src/ui/app.rs", "src/config.rs", "README.md"]);
+
+        let matches = app.recent_files.filter("conf", &app.workspace, &app.fuzzy_options);
+        assert_eq!(matches, [temp.path().join("src/config.rs").as_path()]);
+        assert_eq!(app.recent_files.filter("", &app.workspace, &app.fuzzy_options).len(), 3);
+
+        let listing = app.recent_command("");
+        assert!(listing.contains("  1. src/ui/app.rs\n  2. src/config.rs\n  3. README.md"));
+
+        let reply = app.recent_command("conf");
+        assert!(reply.starts_with("Found 1 symbols"), "{}", reply);
+        assert_eq!(app.current_main_view, MainViewType::CodeOutline);
+        assert_eq!(app.current_file_symbols[0].name, "load");
+
+        app.recent_command("2");
+        assert_eq!(app.current_file_symbols[0].name, "draw");
+        assert!(app.recent_command("zzz").starts_with("No recent file matches"));
+    }
+
+    #[test]
+    fn test_recent_query_uses_the_configured_fuzzy_limits() {
+        let temp = tempdir().unwrap();
+        let app_with = |min_score: i64, max_results: usize| {
+            let mut config = config_with_limits(10, 10);
+            config.ui.fuzzy_min_score = Some(min_score);
+            config.ui.fuzzy_max_results = Some(max_results);
+            let mut app = App::with_config(&config);
+            app.workspace = WorkspacePaths::new(temp.path());
+            for name in ["config.rs", "conf.rs", "cfg.rs"] {
+                std::fs::write(temp.path().join(name), "fn f() {}\n").unwrap();
+                app.recent_files.touch(&temp.path().join(name));
+            }
+            app
+        };
+        let matches = |app: &App| {
+            app.recent_files
+                .filter("cf", &app.workspace, &app.fuzzy_options)
+                .len()
+        };
+
+        assert_eq!(matches(&app_with(0, 10)), 3);
+        // Raising the threshold drops weak matches, and the cap trims what's left
+        let mut strict = app_with(i64::MAX, 10);
+        assert_eq!(matches(&strict), 0);
+        assert!(strict.recent_command("cf").starts_with("No recent file matches"));
+        assert_eq!(matches(&app_with(0, 1)), 1);
+    }
+
+    #[test]
+    fn test_outline_directory_groups_symbols_by_file() {
+        let temp = tempdir().unwrap();
+        let root = temp.path();
+        std::fs::write(root.join("a.py"), "def alpha():\n    pass\n").unwrap();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/b.rs"), "fn beta() {}\n").unwrap();
+        std::fs::create_dir_all(root.join("target")).unwrap();
+        std::fs::write(root.join("target/c.rs"), "fn ignored() {}\n").unwrap();
+
+        let mut app = App::with_config(&config_with_limits(10, 10));
+        app.init_tree_sitter(1024 * 1024, 10);
+        let message = app.show_code_outline(&format!("outline {}", root.display()));
+        assert!(message.contains("in 2 files under"), "{}", message);
+
+        let groups = crate::ui::outline::group_by_file(&app.current_file_symbols);
+        let files: Vec<&str> = groups.iter().map(|(path, _)| *path).collect();
+        assert_eq!(
+            files,
+            vec![
+                root.join("a.py").display().to_string(),
+                root.join("src/b.rs").display().to_string(),
+            ]
+        );
+        assert!(groups[0].1.iter().any(|s| s.name == "alpha"));
+        assert!(groups[1].1.iter().any(|s| s.name == "beta"));
+        assert!(!app.current_file_symbols.iter().any(|s| s.name == "ignored"));
+    }
+
+    #[test]
+    fn test_startup_view_and_command() {
+        let mut config = config_with_limits(10, 10);
+        config.ui.default_view = Some("code_outline".to_string());
+        config.ui.startup_command = Some("/help".to_string());
+
+        let mut app = App::with_config(&config);
+        assert_eq!(app.current_main_view, MainViewType::CodeOutline);
+        assert_eq!(app.startup_command.as_deref(), Some("help"));
+
+        app.run_startup_command();
+        assert!(app.startup_command.is_none());
+        assert!(app.chat_messages.back().unwrap().content.starts_with("Available commands"));
+    }
+
+    #[test]
+    fn test_default_startup_view() {
+        let app = App::with_config(&config_with_limits(10, 10));
+        assert_eq!(app.current_main_view, MainViewType::ShellOutput);
+        assert!(app.startup_command.is_none());
+    }
+
+    #[test]
+    fn test_search_view_keys_toggle_groups() {
+        use crate::services::ripgrep::SearchResult;
+
+        let result = |path: &str| SearchResult {
+            file_path: PathBuf::from(path),
+            line_number: 1,
+            line_content: "needle".to_string(),
+            match_ranges: Vec::new(),
+            context_before: Vec::new(),
+            context_after: Vec::new(),
+        };
+        let mut app = App::with_config(&config_with_limits(10, 10));
+        app.grep_groups = GroupedResults::from_results(&[result("a.rs"), result("b.rs")]);
+        app.current_main_view = MainViewType::Search;
+
+        app.handle_key_event(KeyEvent::new(KeyCode::Down, KeyModifiers::NONE));
+        app.handle_key_event(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert_eq!(app.grep_groups.selected, 1);
+        assert!(!app.grep_groups.groups[1].expanded);
+        assert!(app.chat_messages.is_empty());
+    }
+
+    #[test]
+    fn test_outline_renders_a_page_and_space_loads_more() {
+        use crate::ui::outline::render_symbols;
+
+        let mut config = config_with_limits(10, 10);
+        config.ui.result_page_size = Some(20);
+        let mut app = App::with_config(&config);
+        app.current_file_symbols = (1..=100)
+            .map(|line| DisplaySymbol {
+                name: format!("f{}", line),
+                kind: "Function".to_string(),
+                line,
+                path: "/workspace/src/lib.rs".to_string(),
+            })
+            .collect();
+        app.current_main_view = MainViewType::CodeOutline;
+
+        let rendered = |app: &App| {
+            let lines = render_symbols(&app.current_file_symbols, &app.workspace, &app.outline_pager, None);
+            let rows = lines.iter().filter(|l| l.spans[0].content.contains("[line ")).count();
+            (rows, lines.last().unwrap().spans[0].content.to_string())
+        };
+        assert_eq!(rendered(&app), (20, "… 80 more rows (press Space to load 20)".to_string()));
+
+        app.handle_key_event(KeyEvent::new(KeyCode::Char(' '), KeyModifiers::NONE));
+        assert!(app.input_text.is_empty());
+        assert_eq!(rendered(&app).0, 40);
+
+        for _ in 0..3 {
+            app.handle_key_event(KeyEvent::new(KeyCode::Char(' '), KeyModifiers::NONE));
+        }
+        assert_eq!(rendered(&app), (100, "Function f100 [line 100]".to_string()));
+        // Once everything is shown, Space types again
+        app.handle_key_event(KeyEvent::new(KeyCode::Char(' '), KeyModifiers::NONE));
+        assert_eq!(app.input_text, " ");
+    }
+
+    #[test]
+    fn test_terminal_views_and_layout() {
+        use crate::ui::terminal::TerminalLayout;
+
+        let mut app = App::with_config(&config_with_limits(10, 10));
+        app.process_slash_command("terminal new build");
+        app.process_slash_command("terminal new");
+        assert_eq!(app.current_main_view, MainViewType::Terminals);
+
+        let titles: Vec<&str> = app
+            .terminals
+            .views()
+            .iter()
+            .map(|view| view.instance.title.as_str())
+            .collect();
+        assert_eq!(titles, vec!["build", "Terminal 2"]);
+        assert_eq!(app.terminals.active_view().unwrap().instance.title, "Terminal 2");
+
+        assert_eq!(app.terminals.layout(), TerminalLayout::Single);
+        app.handle_key_event(KeyEvent::new(KeyCode::Char('l'), KeyModifiers::CONTROL));
+        assert_eq!(app.terminals.layout(), TerminalLayout::HorizontalSplit);
+        app.process_slash_command("terminal layout");
+        assert_eq!(app.terminals.layout(), TerminalLayout::VerticalSplit);
+
+        app.process_slash_command("terminal next");
+        assert_eq!(app.terminals.active_view().unwrap().instance.title, "build");
+    }
+
+    #[test]
+    fn test_history_lists_the_tools_run_this_session() {
+        use crate::cqrs::RecordingTaskManager;
+        use crate::cqrs::event_store::InMemoryEventStore;
+        use crate::task::{TaskManager, TaskRegistry, shell_task::ShellTaskHandler};
+
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "hi").unwrap();
+        let mut registry = TaskRegistry::new();
+        registry.register("shell", Arc::new(ShellTaskHandler::new()));
+        let store: Arc<dyn EventStore> = Arc::new(InMemoryEventStore::new());
+        let mut app = App::with_config(&config_with_limits(10, 10));
+        assert_eq!(
+            app.history_command(),
+            "Tool calls aren't being recorded in this session"
+        );
+        app.set_task_manager(Arc::new(RecordingTaskManager::new(
+            TaskManager::new(Arc::new(LocalFileSystem::new()), Arc::new(registry)),
+            store.clone(),
+            "session-1",
+        )));
+        app.set_tool_call_log(store, "session-1");
+        assert_eq!(app.history_command(), "No tool calls recorded in this session");
+
+        app.process_slash_command(&format!("ls {}", dir.path().display()));
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while app.is_processing && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(20));
+            app.on_tick();
+        }
+
+        app.process_slash_command("history");
+        let history = &app.chat_messages.back().unwrap().content;
+        let lines: Vec<&str> = history.lines().collect();
+        assert_eq!(lines[0], "Tool calls (1):", "{}", history);
+        assert!(lines[1].starts_with("  1. shell "), "{}", lines[1]);
+        assert!(lines[1].contains("list_directory") && lines[1].contains("-> ok"), "{}", lines[1]);
+    }
+
+    #[test]
+    fn test_command_output_goes_to_its_terminal() {
+        use crate::fs::operations::LocalFileSystem;
+        use crate::task::{TaskManager, TaskRegistry, shell_task::ShellTaskHandler};
+
+        let mut registry = TaskRegistry::new();
+        registry.register("shell", Arc::new(ShellTaskHandler::new()));
+        let mut app = App::with_config(&config_with_limits(10, 10));
+        app.set_task_manager(Arc::new(TaskManager::new(
+            Arc::new(LocalFileSystem::new()),
+            Arc::new(registry),
+        )));
+
+        app.process_slash_command("terminal new first");
+        app.process_slash_command("terminal new second");
+        app.process_slash_command("terminal next");
+        app.process_bash_command("pwd");
+        // Switching terminals while the command runs doesn't redirect its output
+        app.process_slash_command("terminal next");
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while app.is_processing && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(20));
+            app.on_tick();
+        }
+
+        let cwd = std::env::current_dir().unwrap().display().to_string();
+        let views = app.terminals.views();
+        assert_eq!(views[0].output, vec!["$ pwd".to_string(), cwd]);
+        assert!(views[1].output.is_empty());
+        assert_eq!(app.current_main_view, MainViewType::Terminals);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_timeout_prefix_overrides_the_configured_timeout() {
+        use crate::fs::operations::LocalFileSystem;
+        use crate::task::{TaskManager, TaskRegistry, shell_task::ShellTaskHandler};
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempdir().unwrap();
+        let script = dir.path().join("slow.sh");
+        std::fs::write(&script, "#!/bin/sh\nsleep 5\n").unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let mut registry = TaskRegistry::new();
+        registry.register("shell", Arc::new(ShellTaskHandler::new()));
+        let mut config = config_with_limits(10, 10);
+        config.terminal.command_timeout_secs = Some(120);
+        let mut app = App::with_config(&config);
+        assert_eq!(app.command_timeout_secs, 120);
+        app.set_task_manager(Arc::new(TaskManager::new(
+            Arc::new(LocalFileSystem::new()),
+            Arc::new(registry),
+        )));
+
+        let start = Instant::now();
+        app.input_text = format!("!1s {}", script.display());
+        app.process_input();
+        assert_eq!(app.shell_timeout, Some(1));
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while app.is_processing && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(20));
+            app.on_tick();
+        }
+
+        assert!(start.elapsed() < Duration::from_secs(5));
+        let messages: Vec<&str> = app.chat_messages.iter().map(|m| m.content.as_str()).collect();
+        let output = messages[messages.len() - 2];
+        assert!(output.contains("\"timed_out\":true"), "{}", output);
+        assert!(
+            messages[messages.len() - 1].starts_with("Command timed out after 1s"),
+            "{:?}",
+            messages
+        );
+    }
+
+    #[test]
+    fn test_previewed_command_runs_only_after_run() {
+        use crate::fs::operations::LocalFileSystem;
+        use crate::task::{TaskManager, TaskRegistry, shell_task::ShellTaskHandler};
+
+        let mut registry = TaskRegistry::new();
+        registry.register("shell", Arc::new(ShellTaskHandler::new()));
+        let mut app = App::with_config(&config_with_limits(20, 10));
+        app.set_task_manager(Arc::new(TaskManager::new(
+            Arc::new(LocalFileSystem::new()),
+            Arc::new(registry),
+        )));
+        let wait = |app: &mut App| {
+            let deadline = Instant::now() + Duration::from_secs(10);
+            while app.is_processing && Instant::now() < deadline {
+                std::thread::sleep(Duration::from_millis(20));
+                app.on_tick();
+            }
+        };
+
+        let cwd = std::env::current_dir().unwrap().canonicalize().unwrap();
+        app.input_text = "!? pwd".to_string();
+        app.process_input();
+        wait(&mut app);
+        assert_eq!(
+            app.chat_messages.back().unwrap().content,
+            format!("Would run: cd {} && pwd\nType /run to run it", cwd.display())
+        );
+
+        app.input_text = "/run".to_string();
+        app.process_input();
+        wait(&mut app);
+        let output = &app.chat_messages.back().unwrap().content;
+        assert!(output.contains("\"success\":true"), "{}", output);
+
+        // The preview is used up, and anything else typed in between discards it
+        app.input_text = "/run".to_string();
+        app.process_input();
+        assert!(app.chat_messages.back().unwrap().content.starts_with("Nothing to run"));
+        app.input_text = "!?pwd".to_string();
+        app.process_input();
+        wait(&mut app);
+        app.input_text = "/help".to_string();
+        app.process_input();
+        app.input_text = "/run".to_string();
+        app.process_input();
+        assert!(app.chat_messages.back().unwrap().content.starts_with("Nothing to run"));
+    }
+
+    #[test]
+    fn test_activity_events_follow_the_session() {
+        use crate::activity::mock::SharedBuffer;
+        use crate::fs::operations::LocalFileSystem;
+        use crate::task::{TaskManager, TaskRegistry, shell_task::ShellTaskHandler};
+
+        let mut registry = TaskRegistry::new();
+        registry.register("shell", Arc::new(ShellTaskHandler::new()));
+        let mut app = App::with_config(&config_with_limits(50, 10));
+        app.set_task_manager(Arc::new(TaskManager::new(
+            Arc::new(LocalFileSystem::new()),
+            Arc::new(registry),
+        )));
+        let buffer = SharedBuffer::default();
+        app.activity = Some(ActivityLog::new(buffer.clone()));
+
+        let submit = |app: &mut App, input: &str| {
+            app.input_text = input.to_string();
+            app.process_input();
+        };
+        submit(&mut app, "/tree src --depth 1");
+        submit(&mut app, "hello");
+
+        // A reply arriving from the model
+        let (tx, rx) = std::sync::mpsc::channel();
+        app.output_manager.store_receiver(rx);
+        tx.send(Ok(completion("Hi there", "stop"))).unwrap();
+        app.check_llm_response();
+
+        submit(&mut app, "!pwd");
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while app.is_processing && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(20));
+            app.on_tick();
+        }
+
+        let events: Vec<serde_json::Value> = buffer
+            .text()
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("each line is one JSON object"))
+            .collect();
+        let summary: Vec<String> = events
+            .iter()
+            .map(|e| {
+                let kind = e["type"].as_str().unwrap();
+                let detail = e.get("tool").or(e.get("role")).and_then(|v| v.as_str());
+                format!("{}:{}", kind, detail.unwrap_or("-"))
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                "tool_call:tree",
+                "tool_result:tree",
+                "message:user",
+                "error:-",
+                "message:assistant",
+                "tool_call:shell",
+                "tool_result:shell",
+            ]
+        );
+
+        let seqs: Vec<u64> = events.iter().map(|e| e["seq"].as_u64().unwrap()).collect();
+        assert_eq!(seqs, (0..events.len() as u64).collect::<Vec<_>>());
+        assert_eq!(events[0]["input"], "src --depth 1");
+        assert_eq!(events[1]["success"], true);
+        assert_eq!(events[2]["content"], "hello");
+        assert_eq!(events[4]["content"], "Hi there");
+        assert_eq!(events[5]["input"], "pwd");
+        assert_eq!(events[6]["success"], true);
+        let cwd = std::env::current_dir().unwrap().display().to_string();
+        assert!(events[6]["output"].as_str().unwrap().contains(&cwd));
+    }
+
+    #[test]
+    fn test_terminals_restored_with_working_directory() {
+        let temp = tempdir().unwrap();
+        let sub = temp.path().join("sub");
+        std::fs::create_dir_all(&sub).unwrap();
+        let mut config = config_with_limits(10, 10);
+        config.terminal.state_file = Some(temp.path().join("terminals.json"));
+
+        let mut app = App::with_config(&config);
+        app.process_slash_command("terminal new work");
+        app.process_bash_command(&format!("cd {}", temp.path().display()));
+        app.process_bash_command("cd sub");
+        assert!(app.chat_messages.back().unwrap().content.starts_with("Working directory"));
+        app.process_bash_command("cd missing");
+        assert!(app.chat_messages.back().unwrap().content.starts_with("cd: no such directory"));
+
+        let reopened = App::with_config(&config);
+        let views = reopened.terminals.views();
+        assert_eq!(views.len(), 1);
+        assert_eq!(views[0].instance.title, "work");
+        assert_eq!(
+            reopened.terminals.working_directory(),
+            Some(sub.canonicalize().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_retry_reissues_last_prompt() {
+        let mut app = App::with_config(&config_with_limits(10, 10));
+        assert_eq!(app.retry_prompt(""), None);
+
+        // Nothing has been sent yet
+        app.process_slash_command("retry");
+        assert_eq!(
+            app.chat_messages.back().unwrap().content,
+            "Nothing to retry: no prompt has been sent yet"
+        );
+
+        // Point the client at a closed port; only the reissued request matters here
+        app.init_llm_client(unreachable_server()).unwrap();
+        app.chat_messages.clear();
+        app.last_exchange = Some(LlmExchange {
+            prompt: "Explain traits".to_string(),
+            response: "Traits are...".to_string(),
+            truncated: false,
+            model: DEFAULT_MODEL.to_string(),
+        });
+        app.add_chat_message("Explain traits".to_string(), true);
+        app.add_chat_message("Traits are...".to_string(), false);
+        app.add_chat_message("/retry be brief".to_string(), true);
+
+        assert_eq!(
+            app.retry_prompt(" be brief").as_deref(),
+            Some("Explain traits\n\nbe brief")
+        );
+        app.process_slash_command("retry be brief");
+
+        assert!(app.is_processing);
+        assert_eq!(app.last_exchange.as_ref().unwrap().prompt, "Explain traits\n\nbe brief");
+        let contents: Vec<&str> = app.chat_messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(
+            contents,
+            vec!["Explain traits", "/retry be brief", "Thinking...", "Retrying the last prompt..."]
+        );
+
+        // A second retry waits for the first to finish
+        app.process_slash_command("retry");
+        assert_eq!(
+            app.chat_messages.back().unwrap().content,
+            "A response is still in progress"
+        );
+    }
+
+    #[test]
+    fn test_edit_and_resend_rewinds_history() {
+        let mut app = App::with_config(&config_with_limits(10, 10));
+        app.add_chat_message("Explain traits".to_string(), true);
+        app.add_chat_message("Traits are...".to_string(), false);
+        app.add_chat_message("Explain lifetimes".to_string(), true);
+        app.add_chat_message("Thinking...".to_string(), false);
+
+        // The second prompt is still waiting for its response
+        let (tx, rx) = std::sync::mpsc::channel();
+        app.output_manager.store_receiver(rx);
+        app.is_processing = true;
+        app.last_exchange = Some(LlmExchange {
+            prompt: "Explain lifetimes".to_string(),
+            response: String::new(),
+            truncated: false,
+            model: DEFAULT_MODEL.to_string(),
+        });
+
+        app.handle_key_event(KeyEvent::new(KeyCode::Char('e'), KeyModifiers::ALT));
+        assert_eq!(app.input_text, "Explain lifetimes");
+
+        app.input_text = "Explain lifetimes briefly".to_string();
+        app.process_input();
+
+        let contents: Vec<&str> = app.chat_messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(
+            contents,
+            vec![
+                "Explain traits",
+                "Traits are...",
+                "Explain lifetimes briefly",
+                "Error: LLM client not initialized. Use /config to set up OpenRouter.",
+            ]
+        );
+        assert!(app.editing_message.is_none());
+        assert!(app.last_exchange.is_none());
+        assert!(!app.is_processing);
+
+        // The stale response no longer reaches the chat
+        tx.send(Ok(completion("Lifetimes are...", "stop"))).ok();
+        assert!(app.output_manager.check_llm_response().is_none());
+    }
+
+    #[test]
+    fn test_edit_command_skips_its_own_entry() {
+        let mut app = App::with_config(&config_with_limits(10, 10));
+        app.process_slash_command("edit");
+        assert_eq!(
+            app.chat_messages.back().unwrap().content,
+            "Nothing to edit: no previous message"
+        );
+
+        app.add_chat_message("!pwd".to_string(), true);
+        app.add_chat_message("/edit".to_string(), true);
+        app.process_slash_command("edit");
+        assert_eq!(app.input_text, "!pwd");
+    }
+
+    #[test]
+    fn test_save_output_writes_plain_text_of_view() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = App::with_config(&config_with_limits(10, 10));
+        app.workspace = WorkspacePaths::new(dir.path());
+        app.current_main_view = MainViewType::ShellOutput;
+        app.add_chat_message("list files".to_string(), true);
+        app.add_chat_message("# Files\n```\nsrc/main.rs\n```".to_string(), false);
+
+        let expected = app.view_plain_text().unwrap();
+        assert_eq!(
+            expected,
+            "You: list files\n\nSamus: \n# Files\n```\nsrc/main.rs\n```\n\n"
+        );
+
+        app.process_slash_command("save-output out/view.txt");
+        assert_eq!(
+            app.chat_messages.back().unwrap().content,
+            format!("Saved {} bytes to out/view.txt", expected.len())
+        );
+        let saved = std::fs::read_to_string(dir.path().join("out/view.txt")).unwrap();
+        assert_eq!(saved, expected);
+
+        app.process_slash_command("save-output");
+        assert_eq!(app.chat_messages.back().unwrap().content, "Usage: /save-output <path>");
+    }
+
+    #[test]
+    fn test_multi_line_paste_becomes_single_input() {
+        let mut app = App::with_config(&config_with_limits(10, 10));
+        app.handle_key_event(KeyEvent::new(KeyCode::Char('>'), KeyModifiers::NONE));
+        app.handle_paste("fn main() {\r\n    println!(\"hi\");\r\n}\n");
+
+        assert_eq!(app.input_text, ">fn main() {\n    println!(\"hi\");\n}\n");
+        assert_eq!(app.cursor_position, app.input_text.len());
+        // Nothing was submitted
+        assert!(app.chat_messages.is_empty());
+        assert!(app.command_history.is_empty());
+    }
+
+    #[test]
+    fn test_diff_view_mode_cycles_at_runtime() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("old.txt"), "a\nb\n").unwrap();
+        std::fs::write(dir.path().join("new.txt"), "a\nc\n").unwrap();
+
+        let mut config = config_with_limits(10, 10);
+        config.ui.diff_view_mode = DiffViewMode::Unified;
+        let mut app = App::with_config(&config);
+        app.workspace = WorkspacePaths::new(dir.path());
+
+        // New diffs open in the configured mode
+        app.process_slash_command("diff old.txt new.txt");
+        assert_eq!(app.current_main_view, MainViewType::GitDiff);
+        assert_eq!(app.active_diff.as_ref().unwrap().view_mode(), DiffViewMode::Unified);
+
+        app.handle_key_event(KeyEvent::new(KeyCode::Char('l'), KeyModifiers::CONTROL));
+        assert_eq!(app.active_diff.as_ref().unwrap().view_mode(), DiffViewMode::Inline);
+
+        app.process_slash_command("diff mode");
+        assert_eq!(app.chat_messages.back().unwrap().content, "Diff view mode: side by side");
+        assert_eq!(app.active_diff.as_ref().unwrap().view_mode(), DiffViewMode::SideBySide);
+
+        // The choice carries over to the next diff
+        app.process_slash_command("diff new.txt old.txt");
+        assert_eq!(app.active_diff.as_ref().unwrap().view_mode(), DiffViewMode::SideBySide);
+
+        app.process_slash_command("diff missing.txt new.txt");
+        assert!(app.chat_messages.back().unwrap().content.starts_with("Error reading missing.txt"));
+    }
+}
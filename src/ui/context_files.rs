@@ -0,0 +1,232 @@
+//! Files pinned with `/context-add`, sent as context with every request
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::config::{DEFAULT_FILE_TEMPLATE, LogLevel};
+use crate::fs::WorkspacePaths;
+use crate::logging;
+use crate::mcp::client::ChatTurn;
+use crate::services::tree_sitter::SupportedLanguage;
+
+/// Most of a pinned file that's sent; the rest is cut off with a note
+pub const MAX_PINNED_FILE_BYTES: usize = 16 * 1024;
+
+/// A pinned file and the contents last read from it
+#[derive(Debug, Clone)]
+pub struct PinnedFile {
+    pub path: PathBuf,
+    pub content: String,
+    /// Size of the file on disk, which may exceed what's kept in `content`
+    pub len: u64,
+    pub truncated: bool,
+    modified: Option<SystemTime>,
+}
+
+impl PinnedFile {
+    fn read(path: &Path) -> io::Result<Self> {
+        let metadata = fs::metadata(path)?;
+        if !metadata.is_file() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "not a file"));
+        }
+        let bytes = fs::read(path)?;
+        let mut content = String::from_utf8_lossy(&bytes).into_owned();
+        let truncated = content.len() > MAX_PINNED_FILE_BYTES;
+        if truncated {
+            let mut end = MAX_PINNED_FILE_BYTES;
+            while !content.is_char_boundary(end) {
+                end -= 1;
+            }
+            content.truncate(end);
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            content,
+            len: metadata.len(),
+            truncated,
+            modified: metadata.modified().ok(),
+        })
+    }
+
+    /// Whether the file on disk differs from what was last read
+    fn is_stale(&self) -> bool {
+        match fs::metadata(&self.path) {
+            Ok(metadata) => {
+                metadata.len() != self.len || metadata.modified().ok() != self.modified
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+/// Fence tag for the language of `path`, e.g. "rust", or empty if it isn't one samus knows
+pub fn language_tag(path: &Path) -> String {
+    path.extension()
+        .and_then(|ext| SupportedLanguage::from_extension(&ext.to_string_lossy()))
+        .map(|language| format!("{:?}", language).to_lowercase())
+        .unwrap_or_default()
+}
+
+/// `content` of the file at `path` wrapped in `template`, shown under `display_path`
+pub fn wrap_file(template: &str, path: &Path, display_path: &str, content: &str) -> String {
+    // `{content}` last, so placeholders inside the file are left alone
+    template
+        .replace("{lang}", &language_tag(path))
+        .replace("{path}", display_path)
+        .replace("{content}", content)
+}
+
+/// The set of pinned files, in the order they were added
+#[derive(Debug, Clone)]
+pub struct PinnedFiles {
+    files: Vec<PinnedFile>,
+    /// How each file is wrapped in the context turn; see `wrap_file`
+    template: String,
+}
+
+impl Default for PinnedFiles {
+    fn default() -> Self {
+        Self::with_template(DEFAULT_FILE_TEMPLATE)
+    }
+}
+
+impl PinnedFiles {
+    pub fn with_template(template: impl Into<String>) -> Self {
+        Self {
+            files: Vec::new(),
+            template: template.into(),
+        }
+    }
+
+    /// Pin `path`, which should be absolute, or re-read it if it's already pinned
+    pub fn add(&mut self, path: &Path) -> io::Result<&PinnedFile> {
+        let file = PinnedFile::read(path)?;
+        let index = match self.files.iter().position(|f| f.path == path) {
+            Some(index) => {
+                self.files[index] = file;
+                index
+            }
+            None => {
+                self.files.push(file);
+                self.files.len() - 1
+            }
+        };
+        Ok(&self.files[index])
+    }
+
+    /// Unpin `path`; returns false if it wasn't pinned
+    pub fn remove(&mut self, path: &Path) -> bool {
+        let before = self.files.len();
+        self.files.retain(|f| f.path != path);
+        self.files.len() != before
+    }
+
+    pub fn files(&self) -> &[PinnedFile] {
+        &self.files
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Re-read files changed since they were last read. A file that can no longer be
+    /// read keeps its previous contents.
+    pub fn refresh(&mut self) {
+        for file in &mut self.files {
+            if !file.is_stale() {
+                continue;
+            }
+            match PinnedFile::read(&file.path) {
+                Ok(updated) => *file = updated,
+                Err(e) => logging::log(
+                    LogLevel::Warn,
+                    "context",
+                    format!("Keeping old contents of {}: {}", file.path.display(), e),
+                ),
+            }
+        }
+    }
+
+    /// System turn holding every pinned file, or `None` when nothing is pinned
+    pub fn context_turn(&self, paths: &WorkspacePaths) -> Option<ChatTurn> {
+        if self.files.is_empty() {
+            return None;
+        }
+
+        let mut context = String::from("Pinned files for reference:\n");
+        for file in &self.files {
+            let wrapped = wrap_file(
+                &self.template,
+                &file.path,
+                &paths.display(&file.path),
+                file.content.trim_end(),
+            );
+            context.push_str(&format!("\n{}\n", wrapped));
+            if file.truncated {
+                context.push_str(&format!(
+                    "(only the first {} of {} bytes are shown)\n",
+                    file.content.len(),
+                    file.len
+                ));
+            }
+        }
+        Some(ChatTurn::system(context.trim_end()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_large_files_are_capped() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("big.txt");
+        // Multi-byte characters so the cap falls inside one
+        fs::write(&path, "é".repeat(MAX_PINNED_FILE_BYTES)).unwrap();
+
+        let mut pinned = PinnedFiles::default();
+        let file = pinned.add(&path).unwrap();
+        assert!(file.truncated);
+        assert!(file.content.len() <= MAX_PINNED_FILE_BYTES);
+        assert_eq!(file.len, 2 * MAX_PINNED_FILE_BYTES as u64);
+
+        let turn = pinned.context_turn(&WorkspacePaths::new(temp.path())).unwrap();
+        assert!(turn.content.contains("big.txt:\n```\néé"));
+        assert!(turn.content.ends_with(&format!(
+            "of {} bytes are shown)",
+            2 * MAX_PINNED_FILE_BYTES
+        )));
+    }
+
+    #[test]
+    fn test_pinned_files_use_the_configured_template() {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir_all(temp.path().join("src")).unwrap();
+        fs::write(temp.path().join("src/lib.rs"), "fn main() {}\n").unwrap();
+        fs::write(temp.path().join("notes.txt"), "mention {path} here\n").unwrap();
+
+        let mut pinned = PinnedFiles::with_template("File {path}:\n```{lang}\n{content}\n```");
+        pinned.add(&temp.path().join("src/lib.rs")).unwrap();
+        pinned.add(&temp.path().join("notes.txt")).unwrap();
+
+        let turn = pinned.context_turn(&WorkspacePaths::new(temp.path())).unwrap();
+        assert!(turn.content.contains("File src/lib.rs:\n```rust\nfn main() {}\n```"));
+        // No language tag for an unknown extension, and placeholders in the file stay put
+        assert!(turn.content.contains("File notes.txt:\n```\nmention {path} here\n```"));
+    }
+
+    #[test]
+    fn test_add_rejects_missing_files_and_directories() {
+        let temp = TempDir::new().unwrap();
+        let mut pinned = PinnedFiles::default();
+        assert!(pinned.add(&temp.path().join("missing.rs")).is_err());
+        assert!(pinned.add(temp.path()).is_err());
+        assert!(pinned.is_empty());
+        assert!(pinned.context_turn(&WorkspacePaths::new(temp.path())).is_none());
+    }
+}
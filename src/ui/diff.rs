@@ -3,49 +3,127 @@ use ratatui::{
     text::{Line, Span},
     widgets::{Block, Borders, Paragraph, Wrap, Widget},
 };
-use similar::{ChangeTag, TextDiff};
+use crate::shell::ansi;
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, DiffOp, DiffTag, TextDiff, group_diff_ops};
 use std::fmt;
+use std::ops::Range;
+use std::sync::OnceLock;
 
-/// Our own Change struct that wraps similar::Change functionality
-#[derive(Debug, Clone)]
-struct OurChange {
-    pub tag: ChangeTag,
-    pub value: String,
+/// One line of the diff, borrowed from the old or new content
+#[derive(Debug, Clone, Copy)]
+struct LineChange<'a> {
+    tag: ChangeTag,
+    value: &'a str,
 }
 
-impl OurChange {
-    pub fn tag(&self) -> ChangeTag {
+impl<'a> LineChange<'a> {
+    fn tag(&self) -> ChangeTag {
         self.tag
     }
 
-    pub fn value(&self) -> &str {
-        &self.value
+    fn value(&self) -> &'a str {
+        self.value
+    }
+}
+
+/// Line-level diff of the two contents. Only ranges are stored, so lines are
+/// sliced out of the contents when needed instead of being copied up front.
+#[derive(Debug)]
+struct DiffIndex {
+    ops: Vec<DiffOp>,
+    /// Byte range of each line, newline included, in the old and new content
+    old_lines: Vec<Range<usize>>,
+    new_lines: Vec<Range<usize>>,
+}
+
+impl DiffIndex {
+    fn build(old_content: &str, new_content: &str) -> Self {
+        let diff = TextDiff::from_lines(old_content, new_content);
+        Self {
+            ops: diff.ops().to_vec(),
+            old_lines: line_ranges(diff.old_slices()),
+            new_lines: line_ranges(diff.new_slices()),
+        }
     }
 }
 
+/// Byte ranges of `lines`, which are consecutive slices covering a whole string
+fn line_ranges(lines: &[&str]) -> Vec<Range<usize>> {
+    let mut start = 0;
+    lines
+        .iter()
+        .map(|line| {
+            let range = start..start + line.len();
+            start = range.end;
+            range
+        })
+        .collect()
+}
+
+/// Rows a single change renders to: one line, or several when wrapped
+type Rows = Vec<Line<'static>>;
+
 /// Represents different diff view modes
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum DiffViewMode {
     SideBySide,
+    #[default]
     Inline,
     Unified,
 }
 
+impl DiffViewMode {
+    /// The mode after this one when cycling: inline, side by side, unified
+    pub fn next(self) -> Self {
+        match self {
+            DiffViewMode::Inline => DiffViewMode::SideBySide,
+            DiffViewMode::SideBySide => DiffViewMode::Unified,
+            DiffViewMode::Unified => DiffViewMode::Inline,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DiffViewMode::Inline => "inline",
+            DiffViewMode::SideBySide => "side by side",
+            DiffViewMode::Unified => "unified",
+        }
+    }
+}
+
+/// How lines wider than `DiffConfig::max_line_width` are displayed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineOverflow {
+    /// Cut the line short and end it with an ellipsis
+    Truncate,
+    /// Continue the line on as many rows as it needs
+    Wrap,
+}
+
+/// Marker appended to truncated lines
+const ELLIPSIS: char = '…';
+
 /// Diff rendering configuration
 #[derive(Debug, Clone)]
 pub struct DiffConfig {
     pub view_mode: DiffViewMode,
     pub context_lines: usize,
+    /// Widest a rendered line may be, in characters, including its `+`/`-` prefix;
+    /// 0 disables the limit
     pub max_line_width: usize,
+    pub line_overflow: LineOverflow,
     pub syntax_highlight: bool,
 }
 
 impl Default for DiffConfig {
     fn default() -> Self {
         Self {
-            view_mode: DiffViewMode::Inline,
+            view_mode: DiffViewMode::default(),
             context_lines: 3,
             max_line_width: 120,
+            line_overflow: LineOverflow::Truncate,
             syntax_highlight: true,
         }
     }
@@ -56,39 +134,84 @@ impl Default for DiffConfig {
 pub struct DiffVisualization {
     old_content: String,
     new_content: String,
-    diff: Vec<OurChange>,
+    /// Computed on first use, so a diff that's never shown costs nothing
+    index: OnceLock<DiffIndex>,
     config: DiffConfig,
+    /// Changes scrolled past at the top of the view
     scroll_offset: usize,
 }
 
 impl DiffVisualization {
     /// Create a new diff visualization
     pub fn new(old_content: String, new_content: String) -> Self {
-        // Create our own Change struct that wraps the similar crate's functionality
-        // since the fields of similar::Change are private
-        let diff = TextDiff::from_lines(&old_content, &new_content)
-            .iter_all_changes()
-            .map(|change| OurChange {
-                tag: change.tag(),
-                value: change.value().to_string(),
-            })
-            .collect();
-
         Self {
             old_content,
             new_content,
-            diff,
+            index: OnceLock::new(),
             config: DiffConfig::default(),
             scroll_offset: 0,
         }
     }
 
+    fn index(&self) -> &DiffIndex {
+        self.index
+            .get_or_init(|| DiffIndex::build(&self.old_content, &self.new_content))
+    }
+
+    /// Every line of the diff in order: unchanged lines, and deletions before
+    /// insertions within a changed region
+    fn changes(&self) -> impl Iterator<Item = LineChange<'_>> + '_ {
+        let index = self.index();
+        index.ops.iter().flat_map(move |op| {
+            let (tag, old_range, new_range) = op.as_tag_tuple();
+            let old_tag = if tag == DiffTag::Equal {
+                ChangeTag::Equal
+            } else {
+                ChangeTag::Delete
+            };
+            let new_range = if tag == DiffTag::Equal { 0..0 } else { new_range };
+
+            let old = old_range.map(move |i| LineChange {
+                tag: old_tag,
+                value: &self.old_content[index.old_lines[i].clone()],
+            });
+            let new = new_range.map(move |i| LineChange {
+                tag: ChangeTag::Insert,
+                value: &self.new_content[index.new_lines[i].clone()],
+            });
+            old.chain(new)
+        })
+    }
+
+    /// Lines for the rows visible from the scroll position, stopping once `height`
+    /// lines are filled rather than laying out the whole diff
+    fn visible_lines(&self, rows: impl Iterator<Item = Rows>, height: u16) -> Vec<Line<'static>> {
+        let mut lines = Vec::new();
+        for row in rows.skip(self.scroll_offset) {
+            if lines.len() >= height as usize {
+                break;
+            }
+            lines.extend(row);
+        }
+        lines
+    }
+
     /// Set diff configuration
     pub fn with_config(mut self, config: DiffConfig) -> Self {
         self.config = config;
         self
     }
 
+    pub fn view_mode(&self) -> DiffViewMode {
+        self.config.view_mode
+    }
+
+    /// Switch to the next view mode and return it
+    pub fn cycle_view_mode(&mut self) -> DiffViewMode {
+        self.config.view_mode = self.config.view_mode.next();
+        self.config.view_mode
+    }
+
     /// Render diff based on current view mode
     pub fn render(&self, area: Rect, buf: &mut Buffer) {
         match self.config.view_mode {
@@ -100,29 +223,32 @@ impl DiffVisualization {
 
     /// Render inline diff view
     fn render_inline(&self, area: Rect, buf: &mut Buffer) {
-        let lines: Vec<Line> = self
-            .diff
-            .iter()
-            .filter_map(|change| match change.tag() {
-                ChangeTag::Delete => Some(Line::from(vec![Span::styled(
-                    format!("- {}", change.value()),
-                    Style::default().fg(Color::Red),
-                )])),
-                ChangeTag::Insert => Some(Line::from(vec![Span::styled(
-                    format!("+ {}", change.value()),
-                    Style::default().fg(Color::Green),
-                )])),
-                ChangeTag::Equal => Some(Line::from(change.value())),
-            })
-            .collect();
-
-        let paragraph = Paragraph::new(lines)
+        let paragraph = Paragraph::new(self.visible_lines(self.inline_rows(), area.height))
             .block(Block::default().borders(Borders::ALL).title("Inline Diff"))
             .wrap(Wrap { trim: false });
 
         Widget::render(paragraph, area, buf);
     }
 
+    #[cfg(test)]
+    fn inline_lines(&self) -> Vec<Line<'static>> {
+        self.inline_rows().flatten().collect()
+    }
+
+    fn inline_rows(&self) -> impl Iterator<Item = Rows> + '_ {
+        self.changes().map(|change| match change.tag() {
+            ChangeTag::Delete => self.fit_line(
+                &format!("- {}", change.value()),
+                Style::default().fg(Color::Red),
+            ),
+            ChangeTag::Insert => self.fit_line(
+                &format!("+ {}", change.value()),
+                Style::default().fg(Color::Green),
+            ),
+            ChangeTag::Equal => self.fit_line(change.value(), Style::default()),
+        })
+    }
+
     /// Render side-by-side diff view
     fn render_side_by_side(&self, area: Rect, buf: &mut Buffer) {
         let half_width = area.width / 2;
@@ -139,32 +265,7 @@ impl DiffVisualization {
             height: area.height,
         };
 
-        let old_lines: Vec<Line> = self
-            .diff
-            .iter()
-            .filter_map(|change| match change.tag() {
-                ChangeTag::Delete => Some(Line::from(vec![Span::styled(
-                    format!("- {}", change.value()),
-                    Style::default().fg(Color::Red),
-                )])),
-                ChangeTag::Equal => Some(Line::from(change.value())),
-                _ => None,
-            })
-            .collect();
-
-        let new_lines: Vec<Line> = self
-            .diff
-            .iter()
-            .filter_map(|change| match change.tag() {
-                ChangeTag::Insert => Some(Line::from(vec![Span::styled(
-                    format!("+ {}", change.value()),
-                    Style::default().fg(Color::Green),
-                )])),
-                ChangeTag::Equal => Some(Line::from(change.value())),
-                _ => None,
-            })
-            .collect();
-
+        let (old_lines, new_lines) = self.side_by_side_lines(area.height);
         let old_paragraph = Paragraph::new(old_lines)
             .block(Block::default().borders(Borders::ALL).title("Original"));
         let new_paragraph = Paragraph::new(new_lines)
@@ -174,41 +275,251 @@ impl DiffVisualization {
         Widget::render(new_paragraph, right_area, buf);
     }
 
-    /// Render unified diff view
-    fn render_unified(&self, area: Rect, buf: &mut Buffer) {
-        let lines: Vec<Line> = self
-            .diff
-            .iter()
-            .enumerate()
-            .filter_map(|(i, change)| match change.tag() {
-                ChangeTag::Delete => Some(Line::from(vec![Span::styled(
-                    format!("-{}: {}", i, change.value()),
+    /// Build both columns from the same aligned rows so they stay level, filling at
+    /// most `height` lines from the scroll position.
+    /// When a line wraps, the other column gets blank rows to match.
+    fn side_by_side_lines(&self, height: u16) -> (Vec<Line<'static>>, Vec<Line<'static>>) {
+        let mut old_lines: Vec<Line> = Vec::new();
+        let mut new_lines: Vec<Line> = Vec::new();
+        for (old, new) in self.side_by_side_rows().into_iter().skip(self.scroll_offset) {
+            if old_lines.len() >= height as usize {
+                break;
+            }
+            let mut left = match old {
+                Some(change) if change.tag() == ChangeTag::Delete => self.fit_line(
+                    &format!("- {}", change.value()),
                     Style::default().fg(Color::Red),
-                )])),
-                ChangeTag::Insert => Some(Line::from(vec![Span::styled(
-                    format!("+{}: {}", i, change.value()),
+                ),
+                Some(change) => self.fit_line(change.value(), Style::default()),
+                None => Vec::new(),
+            };
+            let mut right = match new {
+                Some(change) if change.tag() == ChangeTag::Insert => self.fit_line(
+                    &format!("+ {}", change.value()),
                     Style::default().fg(Color::Green),
-                )])),
-                ChangeTag::Equal => Some(Line::from(format!(" {}: {}", i, change.value()))),
-            })
-            .collect();
+                ),
+                Some(change) => self.fit_line(change.value(), Style::default()),
+                None => Vec::new(),
+            };
+
+            let height = left.len().max(right.len()).max(1);
+            left.resize(height, Line::from(""));
+            right.resize(height, Line::from(""));
+            old_lines.extend(left);
+            new_lines.extend(right);
+        }
+        (old_lines, new_lines)
+    }
+
+    /// Split or shorten `text` to fit `max_line_width`, per the overflow setting
+    fn fit_line(&self, text: &str, style: Style) -> Vec<Line<'static>> {
+        let text = text.trim_end_matches(['\n', '\r']);
+        let max = self.config.max_line_width;
+        let char_count = text.chars().count();
+
+        let pieces: Vec<String> = if max == 0 || char_count <= max {
+            vec![text.to_string()]
+        } else {
+            match self.config.line_overflow {
+                LineOverflow::Truncate => {
+                    vec![ansi::truncate_visible(text, max, ELLIPSIS.encode_utf8(&mut [0; 4]))]
+                }
+                LineOverflow::Wrap => {
+                    let chars: Vec<char> = text.chars().collect();
+                    chars.chunks(max).map(|chunk| chunk.iter().collect()).collect()
+                }
+            }
+        };
+
+        pieces
+            .into_iter()
+            .map(|piece| Line::from(Span::styled(piece, style)))
+            .collect()
+    }
+
+    /// Pair old and new lines into rows for the side-by-side view.
+    ///
+    /// Unchanged lines appear on both sides. Within a changed region the n-th deleted
+    /// line shares a row with the n-th inserted line, and whichever side runs out first
+    /// gets `None` fillers.
+    fn side_by_side_rows(&self) -> Vec<(Option<LineChange<'_>>, Option<LineChange<'_>>)> {
+        let mut rows = Vec::new();
+        let mut deletes: Vec<LineChange> = Vec::new();
+        let mut inserts: Vec<LineChange> = Vec::new();
 
-        let paragraph = Paragraph::new(lines)
+        fn flush<'a>(
+            rows: &mut Vec<(Option<LineChange<'a>>, Option<LineChange<'a>>)>,
+            deletes: &mut Vec<LineChange<'a>>,
+            inserts: &mut Vec<LineChange<'a>>,
+        ) {
+            let len = deletes.len().max(inserts.len());
+            for i in 0..len {
+                rows.push((deletes.get(i).copied(), inserts.get(i).copied()));
+            }
+            deletes.clear();
+            inserts.clear();
+        }
+
+        for change in self.changes() {
+            match change.tag() {
+                ChangeTag::Delete => deletes.push(change),
+                ChangeTag::Insert => inserts.push(change),
+                ChangeTag::Equal => {
+                    flush(&mut rows, &mut deletes, &mut inserts);
+                    rows.push((Some(change), Some(change)));
+                }
+            }
+        }
+        flush(&mut rows, &mut deletes, &mut inserts);
+
+        rows
+    }
+
+    /// Render unified diff view
+    fn render_unified(&self, area: Rect, buf: &mut Buffer) {
+        let paragraph = Paragraph::new(self.visible_lines(self.unified_rows(), area.height))
             .block(Block::default().borders(Borders::ALL).title("Unified Diff"))
             .wrap(Wrap { trim: false });
 
         Widget::render(paragraph, area, buf);
     }
 
+    #[cfg(test)]
+    fn unified_lines(&self) -> Vec<Line<'static>> {
+        self.unified_rows().flatten().collect()
+    }
+
+    fn unified_rows(&self) -> impl Iterator<Item = Rows> + '_ {
+        self.changes().enumerate().map(|(i, change)| match change.tag() {
+            ChangeTag::Delete => self.fit_line(
+                &format!("-{}: {}", i, change.value()),
+                Style::default().fg(Color::Red),
+            ),
+            ChangeTag::Insert => self.fit_line(
+                &format!("+{}: {}", i, change.value()),
+                Style::default().fg(Color::Green),
+            ),
+            ChangeTag::Equal => {
+                self.fit_line(&format!(" {}: {}", i, change.value()), Style::default())
+            }
+        })
+    }
+
     /// Navigate diff view
     pub fn scroll(&mut self, delta: isize) {
         let new_offset = self.scroll_offset as isize + delta;
         self.scroll_offset = new_offset.max(0) as usize;
     }
 
+    /// Rows scrolled past at the top of the view
+    pub fn scroll_offset(&self) -> usize {
+        self.scroll_offset
+    }
+
+    /// Scroll so the row showing `line` (1-based) of the new content is at the top of the
+    /// view in the current mode; a line past the end leaves the view where it is
+    pub fn scroll_to_line(&mut self, line: usize) {
+        let target = line.saturating_sub(1);
+        let row = match self.config.view_mode {
+            DiffViewMode::SideBySide => nth_new_line(
+                self.side_by_side_rows().into_iter().map(|(_, new)| new.is_some()),
+                target,
+            ),
+            DiffViewMode::Inline | DiffViewMode::Unified => nth_new_line(
+                self.changes().map(|change| change.tag() != ChangeTag::Delete),
+                target,
+            ),
+        };
+        if let Some(row) = row {
+            self.scroll_offset = row;
+        }
+    }
+
+    /// The diff as unified diff text, e.g. for handing to the model
+    pub fn unified_text(&self) -> String {
+        TextDiff::from_lines(&self.old_content, &self.new_content)
+            .unified_diff()
+            .context_radius(self.config.context_lines)
+            .to_string()
+    }
+
     /// Get total number of changes
     pub fn total_changes(&self) -> usize {
-        self.diff.len()
+        self.index()
+            .ops
+            .iter()
+            .map(|op| match op.as_tag_tuple() {
+                (DiffTag::Equal, old, _) => old.len(),
+                (_, old, new) => old.len() + new.len(),
+            })
+            .sum()
+    }
+
+    /// Lines added and removed, and the hunks `unified_text` groups them into
+    pub fn stats(&self) -> DiffStats {
+        let ops = &self.index().ops;
+        let mut stats = DiffStats {
+            hunks: group_diff_ops(ops.clone(), self.config.context_lines).len(),
+            ..DiffStats::default()
+        };
+        for op in ops {
+            let (tag, old, new) = op.as_tag_tuple();
+            if tag != DiffTag::Equal {
+                stats.deletions += old.len();
+                stats.insertions += new.len();
+            }
+        }
+        stats
+    }
+}
+
+/// Row of the `n`th (0-based) row that shows a line of the new content, given whether
+/// each row does
+fn nth_new_line(shows_new: impl Iterator<Item = bool>, n: usize) -> Option<usize> {
+    shows_new
+        .enumerate()
+        .filter(|(_, shows_new)| *shows_new)
+        .nth(n)
+        .map(|(row, _)| row)
+}
+
+/// Size of a diff, as `git diff --stat` counts it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiffStats {
+    pub insertions: usize,
+    pub deletions: usize,
+    pub hunks: usize,
+}
+
+impl DiffStats {
+    /// A `git`-style line for the diffs of `files` files together, e.g.
+    /// "2 files changed, 5 insertions(+), 1 deletion(-)"
+    pub fn summary(files: usize, stats: impl IntoIterator<Item = DiffStats>) -> String {
+        let total = stats.into_iter().fold(DiffStats::default(), |total, stats| DiffStats {
+            insertions: total.insertions + stats.insertions,
+            deletions: total.deletions + stats.deletions,
+            hunks: total.hunks + stats.hunks,
+        });
+        let plural = |n: usize, one: &str, many: &str| {
+            format!("{} {}", n, if n == 1 { one } else { many })
+        };
+
+        let mut parts = vec![plural(files, "file changed", "files changed")];
+        // Like git, a side with nothing on it is left out unless both are
+        if total.insertions > 0 || total.deletions == 0 {
+            parts.push(plural(total.insertions, "insertion(+)", "insertions(+)"));
+        }
+        if total.deletions > 0 {
+            parts.push(plural(total.deletions, "deletion(-)", "deletions(-)"));
+        }
+        parts.join(", ")
+    }
+}
+
+impl fmt::Display for DiffStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let files = usize::from(self.insertions + self.deletions > 0);
+        write!(f, "{}", DiffStats::summary(files, [*self]))
     }
 }
 
@@ -243,3 +554,208 @@ pub fn create_diff(
         None => diff,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_count_lines_and_hunks() {
+        let old: String = (1..=12).map(|n| format!("{}\n", n)).collect();
+        let new = old
+            .replace("\n2\n", "\ntwo\n2b\n")
+            .replace("\n11\n", "\n");
+        let diff = DiffVisualization::new(old, new);
+
+        let stats = diff.stats();
+        assert_eq!(
+            stats,
+            DiffStats {
+                insertions: 2,
+                deletions: 2,
+                hunks: 2
+            }
+        );
+        assert_eq!(stats.to_string(), "1 file changed, 2 insertions(+), 2 deletions(-)");
+
+        let added = DiffStats {
+            insertions: 1,
+            deletions: 0,
+            hunks: 1,
+        };
+        assert_eq!(added.to_string(), "1 file changed, 1 insertion(+)");
+        assert_eq!(
+            DiffStats::summary(2, [stats, added]),
+            "2 files changed, 3 insertions(+), 2 deletions(-)"
+        );
+    }
+
+    fn row_values(diff: &DiffVisualization) -> Vec<(Option<String>, Option<String>)> {
+        diff.side_by_side_rows()
+            .into_iter()
+            .map(|(old, new)| {
+                (
+                    old.map(|c| c.value().trim_end().to_string()),
+                    new.map(|c| c.value().trim_end().to_string()),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_side_by_side_changed_lines_share_row() {
+        let diff = DiffVisualization::new(
+            "a\nold one\nold two\nc\nremoved\ne\n".to_string(),
+            "a\nnew one\nnew two\nc\ne\nadded\n".to_string(),
+        );
+
+        let some = |s: &str| Some(s.to_string());
+        assert_eq!(
+            row_values(&diff),
+            vec![
+                (some("a"), some("a")),
+                (some("old one"), some("new one")),
+                (some("old two"), some("new two")),
+                (some("c"), some("c")),
+                (some("removed"), None),
+                (some("e"), some("e")),
+                (None, some("added")),
+            ]
+        );
+    }
+
+    fn line_text(line: &Line) -> String {
+        line.spans.iter().map(|span| span.content.as_ref()).collect()
+    }
+
+    fn narrow(overflow: LineOverflow) -> DiffConfig {
+        DiffConfig {
+            max_line_width: 10,
+            line_overflow: overflow,
+            ..DiffConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_long_lines_truncated() {
+        let diff = DiffVisualization::new("short\n".to_string(), "abcdefghijklmnop\n".to_string())
+            .with_config(narrow(LineOverflow::Truncate));
+
+        let inline: Vec<String> = diff.inline_lines().iter().map(line_text).collect();
+        assert_eq!(inline, vec!["- short", "+ abcdefg…"]);
+        assert!(inline.iter().all(|line| line.chars().count() <= 10));
+
+        let unified: Vec<String> = diff.unified_lines().iter().map(line_text).collect();
+        assert_eq!(unified[1], "+1: abcde…");
+    }
+
+    #[test]
+    fn test_long_lines_wrapped_keep_columns_level() {
+        let diff = DiffVisualization::new(
+            "same\nold\n".to_string(),
+            "same\nabcdefghijklmnopqrstuvwxyz\n".to_string(),
+        )
+        .with_config(narrow(LineOverflow::Wrap));
+
+        let inline: Vec<String> = diff.inline_lines().iter().map(line_text).collect();
+        assert_eq!(
+            inline,
+            vec!["same", "- old", "+ abcdefgh", "ijklmnopqr", "stuvwxyz"]
+        );
+
+        let (old, new) = diff.side_by_side_lines(u16::MAX);
+        let old: Vec<String> = old.iter().map(line_text).collect();
+        let new: Vec<String> = new.iter().map(line_text).collect();
+        assert_eq!(old, vec!["same", "- old", "", ""]);
+        assert_eq!(new, vec!["same", "+ abcdefgh", "ijklmnopqr", "stuvwxyz"]);
+    }
+
+    #[test]
+    fn test_zero_width_disables_limit() {
+        let long = "x".repeat(300);
+        let diff = DiffVisualization::new(String::new(), format!("{}\n", long)).with_config(DiffConfig {
+            max_line_width: 0,
+            ..DiffConfig::default()
+        });
+        assert_eq!(line_text(&diff.inline_lines()[0]).len(), 302);
+    }
+
+    #[test]
+    fn test_side_by_side_columns_render_level() {
+        let diff = DiffVisualization::new("a\nb\nc\n".to_string(), "a\nB\nextra\nc\n".to_string())
+            .with_config(DiffConfig {
+                view_mode: DiffViewMode::SideBySide,
+                ..DiffConfig::default()
+            });
+
+        let area = Rect::new(0, 0, 40, 8);
+        let mut buf = Buffer::empty(area);
+        diff.render(area, &mut buf);
+
+        let row = |y: u16, x_range: std::ops::Range<u16>| -> String {
+            x_range.map(|x| buf.get(x, y).symbol.clone()).collect::<String>().trim().to_string()
+        };
+        // Borders take row 0 and column 0 of each half
+        assert_eq!(row(2, 1..19), "- b");
+        assert_eq!(row(2, 21..39), "+ B");
+        assert_eq!(row(3, 1..19), "");
+        assert_eq!(row(3, 21..39), "+ extra");
+        assert_eq!(row(4, 1..19), "c");
+        assert_eq!(row(4, 21..39), "c");
+    }
+
+    #[test]
+    fn test_cycling_view_mode_changes_render() {
+        let mut diff = DiffVisualization::new("a\nb\n".to_string(), "a\nc\n".to_string());
+        let area = Rect::new(0, 0, 40, 6);
+        let top_row = |diff: &DiffVisualization| -> String {
+            let mut buf = Buffer::empty(area);
+            diff.render(area, &mut buf);
+            (0..area.width).map(|x| buf.get(x, 0).symbol.clone()).collect()
+        };
+        assert_eq!(diff.view_mode(), DiffViewMode::Inline);
+        assert!(top_row(&diff).contains("Inline Diff"));
+
+        assert_eq!(diff.cycle_view_mode(), DiffViewMode::SideBySide);
+        assert_eq!(diff.config.view_mode, DiffViewMode::SideBySide);
+        let row = top_row(&diff);
+        assert!(row.contains("Original") && row.contains("Modified"));
+
+        assert_eq!(diff.cycle_view_mode(), DiffViewMode::Unified);
+        assert!(top_row(&diff).contains("Unified Diff"));
+
+        assert_eq!(diff.cycle_view_mode(), DiffViewMode::Inline);
+        assert!(top_row(&diff).contains("Inline Diff"));
+    }
+
+    #[test]
+    fn test_render_draws_only_the_window_at_the_scroll_position() {
+        let old: String = (0..10_000).map(|i| format!("line {}\n", i)).collect();
+        let new = old.replacen("line 5000\n", "line five thousand\n", 1);
+        let mut diff = DiffVisualization::new(old, new);
+        assert_eq!(diff.total_changes(), 10_001);
+        assert_eq!(diff.changes().count(), 10_001);
+
+        let area = Rect::new(0, 0, 30, 5);
+        let first_row = |diff: &DiffVisualization| -> String {
+            let mut buf = Buffer::empty(area);
+            diff.render(area, &mut buf);
+            (1..area.width - 1).map(|x| buf.get(x, 1).symbol.clone()).collect::<String>().trim().to_string()
+        };
+        assert_eq!(first_row(&diff), "line 0");
+
+        diff.scroll(5000);
+        assert_eq!(first_row(&diff), "- line 5000");
+        diff.scroll(1);
+        assert_eq!(first_row(&diff), "+ line five thousand");
+
+        // Inline, then side by side, then unified
+        diff.cycle_view_mode();
+        assert_eq!(diff.cycle_view_mode(), DiffViewMode::Unified);
+        assert_eq!(first_row(&diff), "+5001: line five thousand");
+
+        // Lines are borrowed from the contents rather than copied
+        let change = diff.changes().nth(5001).unwrap();
+        assert!(diff.new_content.as_bytes().as_ptr_range().contains(&change.value().as_ptr()));
+    }
+}
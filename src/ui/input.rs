@@ -42,6 +42,8 @@ pub enum InputCommand {
     // Task management
     ExecuteTask,
     CancelTask,
+    /// Load the last user message back into the input to edit and resend it
+    EditLastMessage,
 
     // Mode and UI commands
     ChangeMode(InputMode),
@@ -125,6 +127,11 @@ impl InputHandler {
             KeyModifiers::CONTROL,
             InputCommand::CancelTask,
         );
+        self.bind_key(
+            KeyCode::Char('e'),
+            KeyModifiers::ALT,
+            InputCommand::EditLastMessage,
+        );
 
         // Diff controls
         self.bind_key(
@@ -0,0 +1,133 @@
+use ratatui::style::{Color, Style};
+
+/// Opening/closing marker of a fenced code block
+const FENCE: &str = "```";
+
+/// What a single line of a markdown message is, given the fences before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarkdownLineKind {
+    /// A ``` marker opening or closing a code block
+    Fence,
+    /// A line inside a code block
+    Code,
+    /// A `#`/`##` heading outside any code block
+    Heading,
+    /// Anything else
+    Text,
+}
+
+/// Classify each line of `text`, tracking code fences across lines.
+///
+/// The text may still be streaming in, so an unterminated fence is not an error:
+/// everything after it is treated as an open code block until the closer arrives.
+/// A trailing line of only one or two backticks inside a code block is a closer that
+/// hasn't fully arrived yet and is shown as a fence rather than as code.
+pub fn classify_markdown(text: &str) -> Vec<(MarkdownLineKind, &str)> {
+    scan(text).0
+}
+
+/// Whether `text` ends inside a code block whose closer hasn't arrived yet
+pub fn has_open_fence(text: &str) -> bool {
+    scan(text).1
+}
+
+/// Classify lines and report whether a code block is still open at the end
+fn scan(text: &str) -> (Vec<(MarkdownLineKind, &str)>, bool) {
+    let mut in_code_block = false;
+    let line_count = text.lines().count();
+
+    let lines = text
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            let trimmed = line.trim_start();
+            let kind = if in_code_block {
+                if trimmed.trim_end() == FENCE {
+                    in_code_block = false;
+                    MarkdownLineKind::Fence
+                } else if i + 1 == line_count && is_partial_fence(trimmed) {
+                    MarkdownLineKind::Fence
+                } else {
+                    MarkdownLineKind::Code
+                }
+            } else if trimmed.starts_with(FENCE) {
+                in_code_block = true;
+                MarkdownLineKind::Fence
+            } else if line.starts_with("# ") || line.starts_with("## ") {
+                MarkdownLineKind::Heading
+            } else {
+                MarkdownLineKind::Text
+            };
+            (kind, line)
+        })
+        .collect();
+
+    (lines, in_code_block)
+}
+
+fn is_partial_fence(line: &str) -> bool {
+    let line = line.trim_end();
+    !line.is_empty() && line.len() < FENCE.len() && line.chars().all(|c| c == '`')
+}
+
+/// Style for fence markers
+pub fn fence_style() -> Style {
+    Style::default().fg(Color::Cyan)
+}
+
+/// Style for lines inside a code block
+pub fn code_style() -> Style {
+    Style::default().fg(Color::White)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use MarkdownLineKind::*;
+
+    fn kinds(text: &str) -> Vec<MarkdownLineKind> {
+        classify_markdown(text).into_iter().map(|(kind, _)| kind).collect()
+    }
+
+    #[test]
+    fn test_closed_fence() {
+        let text = "Intro\n```rust\n# not a heading\n```\n# Heading";
+        assert_eq!(kinds(text), vec![Text, Fence, Code, Fence, Heading]);
+        assert!(!has_open_fence(text));
+    }
+
+    #[test]
+    fn test_fence_completing_across_updates() {
+        let full = "Here:\n```rust\nfn main() {}\n```\nDone";
+
+        // Feed the message in one byte at a time, as it would arrive while streaming
+        for end in 1..=full.len() {
+            for (kind, line) in classify_markdown(&full[..end]) {
+                if line.starts_with("fn") {
+                    assert_eq!(kind, Code, "code misclassified at {:?}", &full[..end]);
+                }
+                if line.starts_with('D') {
+                    assert_eq!(kind, Text, "text misclassified at {:?}", &full[..end]);
+                }
+            }
+        }
+
+        let updates = [
+            ("Here:\n```rust\nfn main", vec![Text, Fence, Code], true),
+            ("Here:\n```rust\nfn main() {}\n``", vec![Text, Fence, Code, Fence], true),
+            ("Here:\n```rust\nfn main() {}\n```", vec![Text, Fence, Code, Fence], false),
+            (full, vec![Text, Fence, Code, Fence, Text], false),
+        ];
+        for (partial, expected, open) in updates {
+            assert_eq!(kinds(partial), expected, "{:?}", partial);
+            assert_eq!(has_open_fence(partial), open, "{:?}", partial);
+        }
+    }
+
+    #[test]
+    fn test_unterminated_fence_treats_rest_as_code() {
+        let text = "```\n# comment\nplain";
+        assert_eq!(kinds(text), vec![Fence, Code, Code]);
+        assert!(has_open_fence(text));
+    }
+}
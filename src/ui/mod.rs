@@ -1,13 +1,24 @@
 pub mod app;
+pub mod context_files;
 pub mod diff;
+pub mod grep;
 pub mod input;
 pub mod logo;
+pub mod markdown;
+pub mod outline;
 pub mod output;
+pub mod pager;
+pub mod plain_text;
+pub mod prompt_template;
+pub mod recent_files;
 pub mod search;
 pub mod search_view;
+pub mod spinner;
+pub mod status;
 pub mod task_types;
 pub mod tasks;
 pub mod terminal;
+pub mod tree;
 pub mod tui;
 
 pub use output::OutputManager;
@@ -0,0 +1,215 @@
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+use std::path::{Path, PathBuf};
+
+use crate::error::TaskError;
+use crate::fs::{SamusIgnore, WorkspacePaths};
+use crate::fs::operations::{DEFAULT_IGNORE_PATTERNS, LocalFileSystem};
+use crate::services::tree_sitter::SupportedLanguage;
+use crate::ui::app::DisplaySymbol;
+use crate::ui::pager::ResultPager;
+
+/// Most files a directory `/outline` will parse
+pub const MAX_OUTLINE_FILES: usize = 200;
+
+/// Most workspace files `/symbol` indexes
+pub const MAX_INDEXED_FILES: usize = 5_000;
+
+/// How deep a directory `/outline` descends
+const MAX_OUTLINE_DEPTH: usize = 10;
+
+/// Arguments to `/outline` (format: `[path] [--ext rs,py]`)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OutlineArgs {
+    pub path: Option<String>,
+    /// Only outline files with these extensions; empty means every supported one
+    pub extensions: Vec<String>,
+}
+
+pub fn parse_outline_args(args: &str) -> Result<OutlineArgs, String> {
+    let mut parsed = OutlineArgs::default();
+
+    let mut parts = args.split_whitespace();
+    while let Some(part) = parts.next() {
+        if part == "--ext" {
+            let list = parts
+                .next()
+                .ok_or_else(|| "--ext expects a list like rs,py".to_string())?;
+            parsed.extensions = list
+                .split(',')
+                .map(|ext| ext.trim().trim_start_matches('.').to_string())
+                .filter(|ext| !ext.is_empty())
+                .collect();
+        } else if parsed.path.is_none() {
+            parsed.path = Some(part.to_string());
+        } else {
+            return Err(format!("Unexpected argument: {}", part));
+        }
+    }
+
+    Ok(parsed)
+}
+
+/// Supported source files under `dir`, skipping the default ignore patterns and
+/// anything `samusignore` excludes.
+/// Returns at most `max_files` paths, sorted, and whether more were left out.
+pub fn outline_files(
+    dir: &Path,
+    extensions: &[String],
+    max_files: usize,
+    samusignore: &SamusIgnore,
+) -> Result<(Vec<PathBuf>, bool), TaskError> {
+    let ignore: Vec<String> = DEFAULT_IGNORE_PATTERNS
+        .iter()
+        .map(|p| p.to_string())
+        .collect();
+    let entries = LocalFileSystem::new()
+        .with_samusignore(samusignore.clone())
+        .list_files_filtered(dir, Some(MAX_OUTLINE_DEPTH), &ignore)?;
+
+    let mut files: Vec<PathBuf> = entries
+        .into_iter()
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            let Some(ext) = path.extension().and_then(|ext| ext.to_str()) else {
+                return false;
+            };
+            SupportedLanguage::from_extension(ext).is_some()
+                && (extensions.is_empty() || extensions.iter().any(|wanted| wanted == ext))
+        })
+        .collect();
+
+    let capped = files.len() > max_files;
+    files.truncate(max_files);
+    Ok((files, capped))
+}
+
+/// Consecutive symbols grouped by the file they came from, in order
+pub fn group_by_file(symbols: &[DisplaySymbol]) -> Vec<(&str, Vec<&DisplaySymbol>)> {
+    let mut groups: Vec<(&str, Vec<&DisplaySymbol>)> = Vec::new();
+    for symbol in symbols {
+        match groups.last_mut() {
+            Some((path, group)) if *path == symbol.path => group.push(symbol),
+            _ => groups.push((&symbol.path, vec![symbol])),
+        }
+    }
+    groups
+}
+
+/// Rows the Code Outline view lists: every symbol, plus a heading per file when
+/// there's more than one
+pub fn symbol_row_count(symbols: &[DisplaySymbol]) -> usize {
+    let groups = group_by_file(symbols);
+    let headings = if groups.len() > 1 { groups.len() } else { 0 };
+    symbols.len() + headings
+}
+
+/// Row the symbol at `index` is listed on, counting file headings above it
+pub fn symbol_row(symbols: &[DisplaySymbol], index: usize) -> usize {
+    let groups = group_by_file(&symbols[..=index.min(symbols.len().saturating_sub(1))]);
+    let headings = if group_by_file(symbols).len() > 1 { groups.len() } else { 0 };
+    index + headings
+}
+
+/// Lines for the Code Outline view, under a header per file when outlining a directory,
+/// with the `selected` symbol highlighted.
+/// Rows past `pager`'s limit aren't built; a "load more" hint stands in for them.
+pub fn render_symbols(
+    symbols: &[DisplaySymbol],
+    paths: &WorkspacePaths,
+    pager: &ResultPager,
+    selected: Option<usize>,
+) -> Vec<Line<'static>> {
+    let mut lines = Vec::new();
+    let groups = group_by_file(symbols);
+    let multiple_files = groups.len() > 1;
+
+    let mut rows = 0;
+    let mut index = 0;
+    'groups: for (path, symbols) in groups {
+        if multiple_files {
+            if rows >= pager.limit() {
+                break;
+            }
+            rows += 1;
+            lines.push(Line::from(vec![Span::styled(
+                paths.display(path),
+                Style::default().fg(Color::Yellow),
+            )]));
+        }
+        let indent = if multiple_files { "  " } else { "" };
+
+        for symbol in symbols {
+            if rows >= pager.limit() {
+                break 'groups;
+            }
+            rows += 1;
+            let color = match symbol.kind.as_str() {
+                "Function" | "Method" => Color::Cyan,
+                "Class" | "Struct" | "Interface" => Color::Green,
+                "Variable" | "Property" => Color::Blue,
+                _ => Color::White,
+            };
+
+            let mut style = Style::default().fg(color);
+            if selected == Some(index) {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+            index += 1;
+
+            lines.push(Line::from(vec![Span::styled(
+                format!("{}{} {} [line {}]", indent, symbol.kind, symbol.name, symbol.line),
+                style,
+            )]));
+        }
+    }
+
+    lines.extend(pager.footer(symbol_row_count(symbols)));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_outline_args() {
+        assert_eq!(parse_outline_args("").unwrap(), OutlineArgs::default());
+        assert_eq!(
+            parse_outline_args("src --ext rs,.py").unwrap(),
+            OutlineArgs {
+                path: Some("src".to_string()),
+                extensions: vec!["rs".to_string(), "py".to_string()],
+            }
+        );
+        assert!(parse_outline_args("src --ext").is_err());
+        assert!(parse_outline_args("a b").is_err());
+    }
+
+    #[test]
+    fn test_outline_files_filters_and_caps() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("node_modules/pkg")).unwrap();
+        fs::write(root.join("src/a.rs"), "").unwrap();
+        fs::write(root.join("src/b.py"), "").unwrap();
+        fs::write(root.join("README.txt"), "").unwrap();
+        fs::write(root.join("node_modules/pkg/index.js"), "").unwrap();
+
+        let (files, capped) = outline_files(root, &[], MAX_OUTLINE_FILES, &SamusIgnore::default()).unwrap();
+        assert_eq!(files, vec![root.join("src/a.rs"), root.join("src/b.py")]);
+        assert!(!capped);
+
+        let (files, _) = outline_files(root, &["py".to_string()], MAX_OUTLINE_FILES, &SamusIgnore::default()).unwrap();
+        assert_eq!(files, vec![root.join("src/b.py")]);
+
+        let (files, capped) = outline_files(root, &[], 1, &SamusIgnore::default()).unwrap();
+        assert_eq!(files.len(), 1);
+        assert!(capped);
+    }
+}
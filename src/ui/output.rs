@@ -1,8 +1,14 @@
+use parking_lot::Mutex;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
 use std::sync::mpsc as std_mpsc;
 use tokio::sync::mpsc;
 use uuid;
 
 use crate::error::TaskError;
+use crate::mcp::client::ChatCompletion;
+use crate::mcp::models::ModelInfo;
+use crate::ui::grep::GrepOutcome;
 
 /// For compatibility with tests
 pub struct Buffer {
@@ -14,16 +20,178 @@ pub struct Line {
     pub content: String,
 }
 
+/// Default number of lines kept per output buffer
+pub const DEFAULT_MAX_BUFFER_LINES: usize = 10_000;
+
+/// Default cap on the bytes held across all output buffers
+pub const DEFAULT_MAX_TOTAL_BYTES: usize = 16 * 1024 * 1024;
+
+/// Bounds on how much output is retained before the oldest lines are evicted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputLimits {
+    /// Lines kept per buffer (and in the main output buffer)
+    pub max_lines_per_buffer: usize,
+    /// Bytes kept across all streaming buffers combined
+    pub max_total_bytes: usize,
+}
+
+impl Default for OutputLimits {
+    fn default() -> Self {
+        Self {
+            max_lines_per_buffer: DEFAULT_MAX_BUFFER_LINES,
+            max_total_bytes: DEFAULT_MAX_TOTAL_BYTES,
+        }
+    }
+}
+
+/// Snapshot of how much output is currently retained
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OutputStats {
+    pub buffers: usize,
+    pub lines: usize,
+    pub bytes: usize,
+    /// Lines dropped so far to stay within the limits
+    pub evicted_lines: u64,
+}
+
+/// Streaming output buffers, shared with the tasks that fill them
+#[derive(Debug, Default)]
+struct BufferStore {
+    limits: OutputLimits,
+    /// Lines per buffer, tagged with a global sequence number so the oldest can be found
+    buffers: HashMap<uuid::Uuid, VecDeque<(u64, String)>>,
+    /// Buffers whose last line was ended by `\r`, so the next line redraws it
+    redrawing: HashSet<uuid::Uuid>,
+    next_seq: u64,
+    total_bytes: usize,
+    evicted_lines: u64,
+    /// Lines dropped from each buffer, counted into `evicted_lines` as well
+    evicted_per_buffer: HashMap<uuid::Uuid, u64>,
+}
+
+impl BufferStore {
+    /// Add a finished line, replacing the last one if that was a progress update
+    fn push(&mut self, id: uuid::Uuid, line: String) {
+        self.write(id, line, false);
+    }
+
+    /// Add a line ended by `\r`, which the next line written replaces, as a progress
+    /// bar redraws itself in a terminal
+    fn push_progress(&mut self, id: uuid::Uuid, line: String) {
+        self.write(id, line, true);
+    }
+
+    fn write(&mut self, id: uuid::Uuid, line: String, progress: bool) {
+        let Some(buffer) = self.buffers.get_mut(&id) else {
+            return;
+        };
+
+        let line = match collapse_carriage_returns(&line) {
+            collapsed if collapsed.len() == line.len() => line,
+            collapsed => collapsed.to_string(),
+        };
+        let redraw = if progress {
+            !self.redrawing.insert(id)
+        } else {
+            self.redrawing.remove(&id)
+        };
+        if let Some((_, last)) = buffer.back_mut().filter(|_| redraw) {
+            self.total_bytes = self.total_bytes - last.len() + line.len();
+            *last = line;
+            while self.total_bytes > self.limits.max_total_bytes && self.evict_oldest() {}
+            return;
+        }
+
+        self.total_bytes += line.len();
+        buffer.push_back((self.next_seq, line));
+        self.next_seq += 1;
+
+        while buffer.len() > self.limits.max_lines_per_buffer {
+            if let Some((_, old)) = buffer.pop_front() {
+                self.total_bytes -= old.len();
+                self.evicted_lines += 1;
+                *self.evicted_per_buffer.entry(id).or_default() += 1;
+            }
+        }
+
+        while self.total_bytes > self.limits.max_total_bytes && self.evict_oldest() {}
+    }
+
+    /// Drop the oldest line across all buffers; returns `false` when nothing is left
+    fn evict_oldest(&mut self) -> bool {
+        let oldest = self
+            .buffers
+            .iter()
+            .filter_map(|(id, lines)| lines.front().map(|(seq, _)| (*seq, *id)))
+            .min();
+
+        let Some((_, id)) = oldest else {
+            return false;
+        };
+        match self.buffers.get_mut(&id).and_then(VecDeque::pop_front) {
+            Some((_, old)) => {
+                self.total_bytes -= old.len();
+                self.evicted_lines += 1;
+                *self.evicted_per_buffer.entry(id).or_default() += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn stats(&self) -> OutputStats {
+        OutputStats {
+            buffers: self.buffers.len(),
+            lines: self.buffers.values().map(VecDeque::len).sum(),
+            bytes: self.total_bytes,
+            evicted_lines: self.evicted_lines,
+        }
+    }
+}
+
+/// What a terminal shows for a line with carriage returns in it: the text after the last
+/// one, or before it when the line ends with `\r`
+pub fn collapse_carriage_returns(line: &str) -> &str {
+    line.split('\r')
+        .rev()
+        .find(|segment| !segment.is_empty())
+        .unwrap_or("")
+}
+
+/// Appends lines to one streaming buffer; cheap to clone into reader tasks
+#[derive(Debug, Clone)]
+pub struct BufferWriter {
+    store: Arc<Mutex<BufferStore>>,
+    id: uuid::Uuid,
+}
+
+impl BufferWriter {
+    pub fn push(&self, line: String) {
+        self.store.lock().push(self.id, line);
+    }
+
+    /// Write a line ended by `\r`; the next line replaces it
+    pub fn push_progress(&self, line: String) {
+        self.store.lock().push_progress(self.id, line);
+    }
+}
+
 /// Manages output rendering and formatting for the terminal UI
 #[derive(Debug)]
 pub struct OutputManager {
     // Add fields for managing output state
     buffer: Vec<String>,
+    // Per-command streaming buffers, bounded by `OutputLimits`
+    buffers: Arc<Mutex<BufferStore>>,
     sender: Option<mpsc::Sender<String>>,
     // For handling LLM responses
-    llm_receiver: Option<std_mpsc::Receiver<Result<String, TaskError>>>,
+    llm_receiver: Option<std_mpsc::Receiver<Result<ChatCompletion, TaskError>>>,
     // For handling shell command results
     shell_receiver: Option<std_mpsc::Receiver<Result<crate::task::TaskResult, TaskError>>>,
+    // For handling /grep results
+    grep_receiver: Option<std_mpsc::Receiver<Result<GrepOutcome, TaskError>>>,
+    // For handling /models results
+    models_receiver: Option<std_mpsc::Receiver<Result<Arc<Vec<ModelInfo>>, TaskError>>>,
 }
 
 impl OutputManager {
@@ -31,24 +199,58 @@ impl OutputManager {
     pub fn new() -> Self {
         Self {
             buffer: Vec::new(),
+            buffers: Arc::default(),
             sender: None,
             llm_receiver: None,
             shell_receiver: None,
+            grep_receiver: None,
+            models_receiver: None,
         }
     }
 
     /// Store the receiver for LLM responses
-    pub fn store_receiver(&mut self, rx: std_mpsc::Receiver<Result<String, TaskError>>) {
+    pub fn store_receiver(&mut self, rx: std_mpsc::Receiver<Result<ChatCompletion, TaskError>>) {
         self.llm_receiver = Some(rx);
     }
     
+    /// Drop the pending LLM response, if any; its result will be ignored
+    pub fn discard_llm_response(&mut self) {
+        self.llm_receiver = None;
+    }
+
+    /// Drop every pending background result; returns how many there were
+    pub fn discard_pending(&mut self) -> usize {
+        [
+            self.llm_receiver.take().is_some(),
+            self.shell_receiver.take().is_some(),
+            self.grep_receiver.take().is_some(),
+            self.models_receiver.take().is_some(),
+        ]
+        .into_iter()
+        .filter(|&pending| pending)
+        .count()
+    }
+
     /// Store the receiver for shell command results
     pub fn store_shell_receiver(&mut self, rx: std_mpsc::Receiver<Result<crate::task::TaskResult, TaskError>>) {
         self.shell_receiver = Some(rx);
     }
 
+    /// Store the receiver for /grep results
+    pub fn store_grep_receiver(&mut self, rx: std_mpsc::Receiver<Result<GrepOutcome, TaskError>>) {
+        self.grep_receiver = Some(rx);
+    }
+
+    /// Store the receiver for /models results
+    pub fn store_models_receiver(
+        &mut self,
+        rx: std_mpsc::Receiver<Result<Arc<Vec<ModelInfo>>, TaskError>>,
+    ) {
+        self.models_receiver = Some(rx);
+    }
+
     /// Check for available LLM responses
-    pub fn check_llm_response(&mut self) -> Option<Result<String, TaskError>> {
+    pub fn check_llm_response(&mut self) -> Option<Result<ChatCompletion, TaskError>> {
         if let Some(rx) = &self.llm_receiver {
             // Try to receive a message without blocking
             match rx.try_recv() {
@@ -99,19 +301,139 @@ impl OutputManager {
         None
     }
 
+    /// Check for a finished /grep
+    pub fn check_grep_result(&mut self) -> Option<Result<GrepOutcome, TaskError>> {
+        if let Some(rx) = &self.grep_receiver {
+            match rx.try_recv() {
+                Ok(result) => {
+                    self.grep_receiver = None;
+                    return Some(result);
+                }
+                Err(std_mpsc::TryRecvError::Empty) => {
+                    return None;
+                }
+                Err(std_mpsc::TryRecvError::Disconnected) => {
+                    self.grep_receiver = None;
+                    return Some(Err(TaskError::ExecutionFailed(
+                        "Search channel disconnected".to_string(),
+                    )));
+                }
+            }
+        }
+        None
+    }
+
+    /// Check for a finished /models
+    pub fn check_models_result(&mut self) -> Option<Result<Arc<Vec<ModelInfo>>, TaskError>> {
+        let rx = self.models_receiver.as_ref()?;
+        match rx.try_recv() {
+            Ok(result) => {
+                self.models_receiver = None;
+                Some(result)
+            }
+            Err(std_mpsc::TryRecvError::Empty) => None,
+            Err(std_mpsc::TryRecvError::Disconnected) => {
+                self.models_receiver = None;
+                Some(Err(TaskError::ExecutionFailed(
+                    "Model list channel disconnected".to_string(),
+                )))
+            }
+        }
+    }
+
     /// Process any pending output - for compatibility with tests
     pub async fn process_output(&self) {
         // This method is maintained for compatibility with existing tests
     }
 
-    /// Create a buffer - for compatibility with tests
+    /// Set the scrollback and memory limits for all buffers
+    pub fn with_limits(self, limits: OutputLimits) -> Self {
+        self.buffers.lock().limits = limits;
+        self
+    }
+
+    /// Current output limits
+    pub fn limits(&self) -> OutputLimits {
+        self.buffers.lock().limits
+    }
+
+    /// Create an empty streaming buffer
     pub fn create_buffer(&self) -> uuid::Uuid {
-        uuid::Uuid::new_v4()
+        let id = uuid::Uuid::new_v4();
+        self.buffers.lock().buffers.insert(id, VecDeque::new());
+        id
+    }
+
+    /// Writer that appends to the given buffer from another task
+    pub fn buffer_writer(&self, id: uuid::Uuid) -> BufferWriter {
+        BufferWriter {
+            store: Arc::clone(&self.buffers),
+            id,
+        }
+    }
+
+    /// Append a line to a streaming buffer, evicting old lines past the limits
+    pub fn append_to_buffer(&self, id: uuid::Uuid, line: String) {
+        self.buffers.lock().push(id, line);
+    }
+
+    /// Append a line ended by `\r`, such as a progress bar update; the next line
+    /// appended replaces it instead of adding another
+    pub fn append_progress_to_buffer(&self, id: uuid::Uuid, line: String) {
+        self.buffers.lock().push_progress(id, line);
+    }
+
+    /// Lines currently held in a buffer, oldest first
+    pub fn buffer_lines(&self, id: uuid::Uuid) -> Vec<String> {
+        self.buffers
+            .lock()
+            .buffers
+            .get(&id)
+            .map(|lines| lines.iter().map(|(_, line)| line.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Lines dropped from a buffer so far to stay within the limits
+    pub fn buffer_evicted_lines(&self, id: uuid::Uuid) -> u64 {
+        self.buffers
+            .lock()
+            .evicted_per_buffer
+            .get(&id)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Drop a buffer and the memory it holds
+    pub fn remove_buffer(&self, id: uuid::Uuid) {
+        let mut store = self.buffers.lock();
+        store.redrawing.remove(&id);
+        store.evicted_per_buffer.remove(&id);
+        if let Some(lines) = store.buffers.remove(&id) {
+            store.total_bytes -= lines.iter().map(|(_, line)| line.len()).sum::<usize>();
+        }
+    }
+
+    /// How much output is retained across all buffers
+    pub fn stats(&self) -> OutputStats {
+        self.buffers.lock().stats()
     }
 
     /// Get buffers - for compatibility with tests
-    pub fn buffers(&self) -> std::collections::HashMap<uuid::Uuid, Buffer> {
-        std::collections::HashMap::new()
+    pub fn buffers(&self) -> HashMap<uuid::Uuid, Buffer> {
+        self.buffers
+            .lock()
+            .buffers
+            .iter()
+            .map(|(id, lines)| {
+                let lines = lines
+                    .iter()
+                    .map(|(_, line)| Line {
+                        content: line.clone(),
+                    })
+                    .collect();
+                (*id, Buffer { lines })
+            })
+            .collect()
     }
 
     // Moved structures to proper place outside impl
@@ -120,15 +442,24 @@ impl OutputManager {
     pub fn with_sender(sender: mpsc::Sender<String>) -> Self {
         Self {
             buffer: Vec::new(),
+            buffers: Arc::default(),
             sender: Some(sender),
             llm_receiver: None,
             shell_receiver: None,
+            grep_receiver: None,
+            models_receiver: None,
         }
     }
 
     /// Add a line to the output buffer
     pub fn add_line(&mut self, line: String) {
         self.buffer.push(line.clone());
+        let max_lines = self.limits().max_lines_per_buffer;
+        if self.buffer.len() > max_lines {
+            let excess = self.buffer.len() - max_lines;
+            self.buffer.drain(..excess);
+            self.buffers.lock().evicted_lines += excess as u64;
+        }
 
         // If a sender is available, try to send the line
         if let Some(sender) = &self.sender {
@@ -157,3 +488,105 @@ impl Default for OutputManager {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffer_keeps_only_newest_lines() {
+        let manager = OutputManager::new().with_limits(OutputLimits {
+            max_lines_per_buffer: 100,
+            max_total_bytes: usize::MAX,
+        });
+        let id = manager.create_buffer();
+
+        for i in 0..100_000 {
+            manager.append_to_buffer(id, format!("line {}", i));
+        }
+
+        let lines = manager.buffer_lines(id);
+        assert_eq!(lines.len(), 100);
+        assert_eq!(lines[0], "line 99900");
+        assert_eq!(lines[99], "line 99999");
+
+        let stats = manager.stats();
+        assert_eq!(stats.lines, 100);
+        assert_eq!(stats.evicted_lines, 99_900);
+        assert_eq!(stats.bytes, lines.iter().map(String::len).sum::<usize>());
+    }
+
+    #[test]
+    fn test_memory_guard_evicts_oldest_across_buffers() {
+        let manager = OutputManager::new().with_limits(OutputLimits {
+            max_lines_per_buffer: 1000,
+            max_total_bytes: 40,
+        });
+        let first = manager.create_buffer();
+        let second = manager.create_buffer();
+
+        // Each line is 10 bytes, so only the four newest fit
+        for i in 0..3 {
+            manager.append_to_buffer(first, format!("first-{:04}", i));
+        }
+        for i in 0..3 {
+            manager.append_to_buffer(second, format!("secnd-{:04}", i));
+        }
+
+        assert_eq!(manager.buffer_lines(first), vec!["first-0002"]);
+        assert_eq!(manager.buffer_lines(second).len(), 3);
+        assert_eq!(manager.stats().bytes, 40);
+        assert_eq!(manager.buffer_evicted_lines(first), 2);
+        assert_eq!(manager.buffer_evicted_lines(second), 0);
+
+        manager.remove_buffer(second);
+        assert_eq!(manager.stats().bytes, 10);
+        assert_eq!(manager.stats().buffers, 1);
+    }
+
+    #[test]
+    fn test_progress_updates_collapse_into_one_line() {
+        let manager = OutputManager::new();
+        let id = manager.create_buffer();
+
+        manager.append_to_buffer(id, "   Compiling samus".to_string());
+        for percent in (0..=100).step_by(5) {
+            manager.append_progress_to_buffer(id, format!("[{:<20}] {}%", "#".repeat(percent / 5), percent));
+        }
+        assert_eq!(manager.buffer_lines(id).len(), 2);
+        manager.append_to_buffer(id, "    Finished".to_string());
+        manager.append_to_buffer(id, "done".to_string());
+
+        // A whole progress run arriving as one line keeps only what's left on screen
+        manager.append_to_buffer(id, "10%\r50%\r100%\r".to_string());
+
+        assert_eq!(
+            manager.buffer_lines(id),
+            vec!["   Compiling samus", "    Finished", "done", "100%"]
+        );
+        let stats = manager.stats();
+        assert_eq!(stats.lines, 4);
+        assert_eq!(stats.bytes, manager.buffer_lines(id).iter().map(String::len).sum::<usize>());
+    }
+
+    #[test]
+    fn test_writes_to_unknown_buffer_are_ignored() {
+        let manager = OutputManager::new();
+        manager.append_to_buffer(uuid::Uuid::new_v4(), "lost".to_string());
+        assert_eq!(manager.stats(), OutputStats::default());
+    }
+
+    #[test]
+    fn test_main_buffer_is_capped() {
+        let mut manager = OutputManager::new().with_limits(OutputLimits {
+            max_lines_per_buffer: 10,
+            ..OutputLimits::default()
+        });
+        for i in 0..25 {
+            manager.add_line(i.to_string());
+        }
+
+        assert_eq!(manager.get_lines().len(), 10);
+        assert_eq!(manager.get_lines()[0], "15");
+    }
+}
@@ -0,0 +1,100 @@
+//! Long result lists in the Search and Code Outline views, shown a page at a time
+//!
+//! Views only build the styled lines for rows within `limit()`, so a search with
+//! thousands of matches costs no more to draw than one with a page of them.
+
+use ratatui::{
+    style::{Color, Style},
+    text::{Line, Span},
+};
+
+use crate::config::DEFAULT_RESULT_PAGE_SIZE;
+
+/// How many rows of a result list are shown, growing a page at a time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResultPager {
+    page_size: usize,
+    shown: usize,
+}
+
+impl Default for ResultPager {
+    fn default() -> Self {
+        Self::new(DEFAULT_RESULT_PAGE_SIZE)
+    }
+}
+
+impl ResultPager {
+    pub fn new(page_size: usize) -> Self {
+        let page_size = page_size.max(1);
+        Self {
+            page_size,
+            shown: page_size,
+        }
+    }
+
+    /// Rows to render
+    pub fn limit(&self) -> usize {
+        self.shown
+    }
+
+    /// Whether some of `total` rows are past the limit
+    pub fn has_more(&self, total: usize) -> bool {
+        total > self.shown
+    }
+
+    /// Show another page of `total` rows; returns whether there was one
+    pub fn load_more(&mut self, total: usize) -> bool {
+        if !self.has_more(total) {
+            return false;
+        }
+        self.shown += self.page_size;
+        true
+    }
+
+    /// Back to the first page, for a new set of results
+    pub fn reset(&mut self) {
+        self.shown = self.page_size;
+    }
+
+    /// The "load more" hint under the rendered rows, if any are left out
+    pub fn footer(&self, total: usize) -> Option<Line<'static>> {
+        if !self.has_more(total) {
+            return None;
+        }
+        let hidden = total - self.shown;
+        Some(Line::from(Span::styled(
+            format!(
+                "… {} more {} (press Space to load {})",
+                hidden,
+                if hidden == 1 { "row" } else { "rows" },
+                hidden.min(self.page_size)
+            ),
+            Style::default().fg(Color::DarkGray),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_more_advances_a_page_at_a_time() {
+        let mut pager = ResultPager::new(20);
+        assert_eq!(pager.limit(), 20);
+        assert!(pager.footer(20).is_none());
+        assert_eq!(
+            pager.footer(45).unwrap().spans[0].content,
+            "… 25 more rows (press Space to load 20)"
+        );
+
+        assert!(pager.load_more(45));
+        assert!(pager.load_more(45));
+        assert_eq!(pager.limit(), 60);
+        assert!(!pager.load_more(45));
+        assert!(pager.footer(45).is_none());
+
+        pager.reset();
+        assert_eq!(pager.limit(), 20);
+    }
+}
@@ -0,0 +1,43 @@
+//! Plain-text export of rendered views
+
+use ratatui::text::Line;
+
+use crate::shell::ansi;
+
+/// Text of `lines` without styling, one line per row, for saving or copying.
+///
+/// Spans are concatenated and any escape sequences in their content are removed,
+/// so the result contains only what was visible on screen.
+pub fn lines_to_plain_text(lines: &[Line]) -> String {
+    let mut text = String::new();
+    for line in lines {
+        for span in &line.spans {
+            text.push_str(&ansi::strip(&span.content));
+        }
+        text.push('\n');
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::style::{Color, Modifier, Style};
+    use ratatui::text::Span;
+
+    #[test]
+    fn test_styled_spans_become_plain_text() {
+        let lines = vec![
+            Line::from(vec![
+                Span::styled("You: ", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+                Span::raw("run "),
+                Span::styled("\x1b[32mcargo test\x1b[0m", Style::default().fg(Color::Cyan)),
+            ]),
+            Line::from(""),
+            Line::styled("done", Style::default().fg(Color::Green)),
+        ];
+
+        assert_eq!(lines_to_plain_text(&lines), "You: run cargo test\n\ndone\n");
+        assert_eq!(lines_to_plain_text(&[]), "");
+    }
+}
@@ -0,0 +1,148 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A named prompt with `{file}`, `{selection}` and `{question}` placeholders,
+/// invoked as `/<name> [file[:start-end]] [question]`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromptTemplate {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub template: String,
+}
+
+/// Values substituted into a template's placeholders
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PromptContext {
+    /// Path of the file the prompt is about
+    pub file: Option<String>,
+    /// The code being asked about: the whole file or the requested line range
+    pub selection: Option<String>,
+    /// Free-form text typed after the file
+    pub question: String,
+}
+
+impl PromptTemplate {
+    pub fn new(name: &str, description: &str, template: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            description: description.to_string(),
+            template: template.to_string(),
+        }
+    }
+
+    /// Fill in the placeholders; missing values become empty
+    pub fn render(&self, context: &PromptContext) -> String {
+        self.template
+            .replace("{file}", context.file.as_deref().unwrap_or(""))
+            .replace("{selection}", context.selection.as_deref().unwrap_or(""))
+            .replace("{question}", &context.question)
+            .trim_end()
+            .to_string()
+    }
+}
+
+/// Templates available as slash commands, keyed by name
+#[derive(Debug, Clone, Default)]
+pub struct PromptTemplateRegistry {
+    templates: BTreeMap<String, PromptTemplate>,
+}
+
+impl PromptTemplateRegistry {
+    /// Registry with the built-in `/explain`, `/refactor` and `/review` templates
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::default();
+        registry.register(PromptTemplate::new(
+            "explain",
+            "Explain what a file or range does",
+            "Explain what the following code from {file} does.\n\n```\n{selection}\n```\n\n{question}",
+        ));
+        registry.register(PromptTemplate::new(
+            "refactor",
+            "Suggest a cleaner version of a file or range",
+            "Refactor the following code from {file} to be clearer and more idiomatic, \
+             keeping its behavior the same. Explain each change.\n\n```\n{selection}\n```\n\n{question}",
+        ));
+        registry.register(PromptTemplate::new(
+            "review",
+            "Review a file or range for bugs and style",
+            "Review the following code from {file}. Point out bugs, edge cases and style \
+             problems, most important first.\n\n```\n{selection}\n```\n\n{question}",
+        ));
+        registry
+    }
+
+    /// Add a template, replacing any existing one with the same name
+    pub fn register(&mut self, template: PromptTemplate) {
+        self.templates.insert(template.name.clone(), template);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&PromptTemplate> {
+        self.templates.get(name)
+    }
+
+    pub fn templates(&self) -> impl Iterator<Item = &PromptTemplate> {
+        self.templates.values()
+    }
+}
+
+/// Split a `file[:start-end]` argument into the path and an optional one-based,
+/// inclusive line range
+pub fn parse_file_arg(arg: &str) -> (&str, Option<(usize, usize)>) {
+    let parsed = arg.rsplit_once(':').and_then(|(path, range)| {
+        let (start, end) = range.split_once('-')?;
+        let (start, end) = (start.parse::<usize>().ok()?, end.parse::<usize>().ok()?);
+        (start >= 1 && start <= end).then_some((path, Some((start, end))))
+    });
+    parsed.unwrap_or((arg, None))
+}
+
+/// The lines of `content` in `range`, or all of it when no range is given
+pub fn select_lines(content: &str, range: Option<(usize, usize)>) -> String {
+    match range {
+        Some((start, end)) => content
+            .lines()
+            .skip(start - 1)
+            .take(end + 1 - start)
+            .collect::<Vec<_>>()
+            .join("\n"),
+        None => content.trim_end().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_fills_placeholders() {
+        let template = PromptTemplate::new("t", "", "{file}: {selection} ({question})");
+        let context = PromptContext {
+            file: Some("a.rs".to_string()),
+            selection: Some("fn a() {}".to_string()),
+            question: "why?".to_string(),
+        };
+        assert_eq!(template.render(&context), "a.rs: fn a() {} (why?)");
+        assert_eq!(template.render(&PromptContext::default()), ":  ()");
+    }
+
+    #[test]
+    fn test_custom_template_overrides_default() {
+        let mut registry = PromptTemplateRegistry::with_defaults();
+        registry.register(PromptTemplate::new("explain", "", "ELI5: {selection}"));
+        registry.register(PromptTemplate::new("test", "", "Write tests for {file}"));
+
+        assert_eq!(registry.get("explain").unwrap().template, "ELI5: {selection}");
+        let names: Vec<&str> = registry.templates().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["explain", "refactor", "review", "test"]);
+    }
+
+    #[test]
+    fn test_file_arg_ranges() {
+        assert_eq!(parse_file_arg("src/main.rs"), ("src/main.rs", None));
+        assert_eq!(parse_file_arg("src/main.rs:2-3"), ("src/main.rs", Some((2, 3))));
+        assert_eq!(parse_file_arg("src/main.rs:5-1"), ("src/main.rs:5-1", None));
+        assert_eq!(select_lines("a\nb\nc\nd\n", Some((2, 3))), "b\nc");
+        assert_eq!(select_lines("a\nb\n", None), "a\nb");
+    }
+}
@@ -0,0 +1,135 @@
+//! Files recently referenced or outlined, most recent first, for switching back with `/recent`
+
+use fuzzy_matcher::FuzzyMatcher;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use std::path::{Path, PathBuf};
+
+use crate::fs::WorkspacePaths;
+use crate::ui::search::SearchOptions;
+
+/// Most-recently-used files, capped at `limit` and saved to `file` as they change
+#[derive(Debug, Clone)]
+pub struct RecentFiles {
+    /// Absolute paths, most recent first
+    files: Vec<PathBuf>,
+    limit: usize,
+    file: Option<PathBuf>,
+}
+
+impl RecentFiles {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            files: Vec::new(),
+            limit: limit.max(1),
+            file: None,
+        }
+    }
+
+    /// Load the list saved at `file`, if any, and keep saving changes there.
+    /// A missing or unreadable file starts empty.
+    pub fn with_file(mut self, file: Option<PathBuf>) -> Self {
+        if let Some(path) = &file {
+            let saved: Vec<PathBuf> = std::fs::read_to_string(path)
+                .ok()
+                .and_then(|content| serde_json::from_str(&content).ok())
+                .unwrap_or_default();
+            self.files = saved;
+            self.files.truncate(self.limit);
+        }
+        self.file = file;
+        self
+    }
+
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    /// Move `path`, which should be absolute, to the front
+    pub fn touch(&mut self, path: &Path) {
+        self.files.retain(|file| file != path);
+        self.files.insert(0, path.to_path_buf());
+        self.files.truncate(self.limit);
+        self.save();
+    }
+
+    /// Forget `path`, e.g. once it's been deleted
+    pub fn remove(&mut self, path: &Path) {
+        let before = self.files.len();
+        self.files.retain(|file| file != path);
+        if self.files.len() != before {
+            self.save();
+        }
+    }
+
+    /// Files whose workspace-relative path fuzzy-matches `query` with at least
+    /// `options.fuzzy_threshold`, best match first and at most `options.fuzzy_max_results`
+    /// of them; equally good matches, and every file for an empty query, stay most recent first
+    pub fn filter(
+        &self,
+        query: &str,
+        paths: &WorkspacePaths,
+        options: &SearchOptions,
+    ) -> Vec<&Path> {
+        let query = query.trim();
+        if query.is_empty() {
+            return self.files.iter().map(PathBuf::as_path).collect();
+        }
+
+        let matcher = SkimMatcherV2::default();
+        let mut matches: Vec<(i64, &Path)> = self
+            .files
+            .iter()
+            .filter_map(|file| {
+                let score = matcher.fuzzy_match(&paths.display(file), query)?;
+                (score >= options.fuzzy_threshold).then_some((score, file.as_path()))
+            })
+            .collect();
+        matches.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+        matches.truncate(options.fuzzy_max_results);
+        matches.into_iter().map(|(_, file)| file).collect()
+    }
+
+    /// Persist the list. This is best effort; failures are ignored.
+    fn save(&self) {
+        let Some(path) = &self.file else {
+            return;
+        };
+        if path
+            .parent()
+            .is_some_and(|parent| std::fs::create_dir_all(parent).is_err())
+        {
+            return;
+        }
+        if let Ok(content) = serde_json::to_string(&self.files) {
+            let _ = std::fs::write(path, content);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_touch_orders_caps_and_persists() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("recent.json");
+        let mut recent = RecentFiles::new(3).with_file(Some(file.clone()));
+
+        for name in ["a.rs", "b.rs", "c.rs", "b.rs", "d.rs"] {
+            recent.touch(&dir.path().join(name));
+        }
+        let names = |recent: &RecentFiles| -> Vec<String> {
+            recent
+                .files()
+                .iter()
+                .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+                .collect()
+        };
+        assert_eq!(names(&recent), ["d.rs", "b.rs", "c.rs"]);
+
+        let reloaded = RecentFiles::new(3).with_file(Some(file));
+        assert_eq!(names(&reloaded), ["d.rs", "b.rs", "c.rs"]);
+    }
+}
@@ -6,6 +6,8 @@ use std::collections::VecDeque;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+use crate::config::{DEFAULT_FUZZY_MAX_RESULTS, DEFAULT_FUZZY_MIN_SCORE, UiConfig};
+
 /// Maximum number of queries to keep in history
 const MAX_QUERY_HISTORY: usize = 50;
 
@@ -67,7 +69,10 @@ pub struct SearchOptions {
     pub case_sensitive: bool,
     pub context_lines: usize,
     pub regex_mode: bool,
+    /// Lowest score a fuzzy match needs
     pub fuzzy_threshold: i64,
+    /// Most fuzzy matches returned, best scoring first
+    pub fuzzy_max_results: usize,
     pub whole_word: bool,    // New: Match whole words only
     pub highlight_all: bool, // New: Highlight all matches in line
 }
@@ -78,13 +83,25 @@ impl Default for SearchOptions {
             case_sensitive: false,
             context_lines: 2,
             regex_mode: false,
-            fuzzy_threshold: 50,
+            fuzzy_threshold: DEFAULT_FUZZY_MIN_SCORE,
+            fuzzy_max_results: DEFAULT_FUZZY_MAX_RESULTS,
             whole_word: false,
             highlight_all: true,
         }
     }
 }
 
+impl SearchOptions {
+    /// Defaults, with the fuzzy-match limits from the `ui` config
+    pub fn from_config(ui: &UiConfig) -> Self {
+        Self {
+            fuzzy_threshold: ui.fuzzy_min_score(),
+            fuzzy_max_results: ui.fuzzy_max_results(),
+            ..Self::default()
+        }
+    }
+}
+
 /// Search engine trait defining the interface for different search implementations
 #[async_trait::async_trait]
 pub trait SearchEngine: Send + Sync {
@@ -224,6 +241,8 @@ impl FuzzySearch {
 
 #[async_trait::async_trait]
 impl SearchEngine for FuzzySearch {
+    /// Lines scoring at least `fuzzy_threshold`, best first and at most
+    /// `fuzzy_max_results` of them; equal scores stay in line order
     async fn search(&self, text: &str, query: &str, options: &SearchOptions) -> Vec<SearchMatch> {
         let mut matches = Vec::new();
         let lines: Vec<&str> = text.lines().collect();
@@ -248,19 +267,24 @@ impl SearchEngine for FuzzySearch {
                     let start_pos = indices.first().copied().unwrap_or(0);
                     let length = indices.last().map(|&i| i - start_pos + 1).unwrap_or(0);
 
-                    matches.push(SearchMatch {
-                        line_number: line_idx + 1,
-                        line_content: line.to_string(),
-                        start_pos,
-                        length,
-                        context_before,
-                        context_after,
-                    });
+                    matches.push((
+                        score,
+                        SearchMatch {
+                            line_number: line_idx + 1,
+                            line_content: line.to_string(),
+                            start_pos,
+                            length,
+                            context_before,
+                            context_after,
+                        },
+                    ));
                 }
             }
         }
 
-        matches
+        matches.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+        matches.truncate(options.fuzzy_max_results);
+        matches.into_iter().map(|(_, found)| found).collect()
     }
 
     async fn update_index(&self, id: String, content: String) {
@@ -393,6 +417,45 @@ mod tests {
         assert!(results.iter().any(|m| m.line_content.contains("Hello")));
     }
 
+    #[tokio::test]
+    async fn test_fuzzy_min_score_and_cap() {
+        let engine = FuzzySearch::new();
+        let text = "load_config\ncfg\nunrelated\ncome find good\nconfig_guard";
+        let score = |line: &str| engine.matcher.fuzzy_match(line, "cfg").unwrap();
+        let lines = |matches: &[SearchMatch]| -> Vec<String> {
+            matches.iter().map(|m| m.line_content.clone()).collect()
+        };
+
+        let everything = SearchOptions {
+            fuzzy_threshold: 0,
+            ..Default::default()
+        };
+        let results = engine.search(text, "cfg", &everything).await;
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0].line_content, "cfg");
+        let scores: Vec<i64> = results.iter().map(|m| score(&m.line_content)).collect();
+        assert!(scores.windows(2).all(|pair| pair[0] >= pair[1]), "{:?}", scores);
+
+        // Raising the threshold past the weakest match prunes it
+        let weakest = results.last().unwrap().line_content.clone();
+        let stricter = SearchOptions {
+            fuzzy_threshold: score(&weakest) + 1,
+            ..Default::default()
+        };
+        let results = engine.search(text, "cfg", &stricter).await;
+        assert!(!lines(&results).contains(&weakest));
+        assert!(results.len() < 4);
+
+        let capped = SearchOptions {
+            fuzzy_threshold: 0,
+            fuzzy_max_results: 2,
+            ..Default::default()
+        };
+        let results = engine.search(text, "cfg", &capped).await;
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].line_content, "cfg");
+    }
+
     #[tokio::test]
     async fn test_search_manager() {
         let manager = SearchManager::new();
@@ -42,6 +42,14 @@ impl SearchView {
         }
     }
 
+    /// A search view matching with `options`, e.g. `SearchOptions::from_config`
+    pub async fn with_options(options: SearchOptions) -> Self {
+        let view = Self::new();
+        view.manager.set_options(options.clone()).await;
+        view.state.lock().await.options = options;
+        view
+    }
+
     pub async fn set_query(&self, query: String) {
         let mut state = self.state.lock().await;
         if !query.is_empty() && (state.history.is_empty() || state.history[0] != query) {
@@ -0,0 +1,113 @@
+use std::time::{Duration, Instant};
+
+use crate::config::SpinnerStyle;
+
+const BRAILLE_FRAMES: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+const ASCII_FRAMES: &[&str] = &["|", "/", "-", "\\"];
+
+/// Busy indicator for the status bar, advanced once per tick while work is running
+#[derive(Debug, Clone)]
+pub struct Spinner {
+    frames: &'static [&'static str],
+    frame: usize,
+    started: Option<Instant>,
+}
+
+impl Spinner {
+    pub fn new(style: SpinnerStyle) -> Self {
+        let frames = match style {
+            SpinnerStyle::Braille => BRAILLE_FRAMES,
+            SpinnerStyle::Ascii => ASCII_FRAMES,
+            SpinnerStyle::Off => &[],
+        };
+        Self {
+            frames,
+            frame: 0,
+            started: None,
+        }
+    }
+
+    /// Start timing from now, unless already running
+    pub fn start(&mut self) {
+        if self.started.is_none() {
+            self.started = Some(Instant::now());
+            self.frame = 0;
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.started = None;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.started.is_some()
+    }
+
+    /// Advance to the next animation frame
+    pub fn tick(&mut self) {
+        if self.is_active() && !self.frames.is_empty() {
+            self.frame = (self.frame + 1) % self.frames.len();
+        }
+    }
+
+    /// Current animation frame; empty when the spinner style is `Off`
+    pub fn frame(&self) -> &'static str {
+        self.frames.get(self.frame).copied().unwrap_or("")
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.map(|start| start.elapsed()).unwrap_or_default()
+    }
+
+    /// Status-bar text such as "⠙ Working… 12s", or `None` when idle
+    pub fn status_text(&self) -> Option<String> {
+        if !self.is_active() {
+            return None;
+        }
+        let secs = self.elapsed().as_secs();
+        Some(if self.frames.is_empty() {
+            format!("Working… {}s", secs)
+        } else {
+            format!("{} Working… {}s", self.frame(), secs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_frames_advance_and_elapsed_grows() {
+        let mut spinner = Spinner::new(SpinnerStyle::Ascii);
+        assert!(spinner.status_text().is_none());
+
+        spinner.start();
+        let first = spinner.frame();
+        let first_elapsed = spinner.elapsed();
+
+        thread::sleep(Duration::from_millis(20));
+        spinner.tick();
+        assert_ne!(spinner.frame(), first);
+        assert!(spinner.elapsed() > first_elapsed);
+
+        // Frames wrap around
+        for _ in 0..ASCII_FRAMES.len() {
+            spinner.tick();
+        }
+        assert_eq!(spinner.frame(), ASCII_FRAMES[1]);
+
+        spinner.stop();
+        assert!(!spinner.is_active());
+        assert_eq!(spinner.elapsed(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_off_style_shows_only_elapsed() {
+        let mut spinner = Spinner::new(SpinnerStyle::Off);
+        spinner.start();
+        spinner.tick();
+        assert_eq!(spinner.status_text().unwrap(), "Working… 0s");
+    }
+}
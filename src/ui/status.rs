@@ -0,0 +1,169 @@
+//! `/status`: whether each subsystem is configured and working, at a glance
+
+use std::path::PathBuf;
+
+use crate::config::McpServerConfig;
+use crate::services::ripgrep::{RipgrepBinaryPreference, RipgrepService};
+use crate::services::tree_sitter::SupportedLanguage;
+
+/// State of one subsystem, with a short detail shown after it
+#[derive(Debug, Clone, PartialEq)]
+pub enum Health {
+    Ok(String),
+    /// Turned off or missing settings; works once configured
+    Unconfigured(String),
+    /// Configured but broken
+    Failed(String),
+}
+
+impl Health {
+    fn marker(&self) -> &'static str {
+        match self {
+            Health::Ok(_) => "ok",
+            Health::Unconfigured(_) => "off",
+            Health::Failed(_) => "error",
+        }
+    }
+
+    fn detail(&self) -> &str {
+        match self {
+            Health::Ok(detail) | Health::Unconfigured(detail) | Health::Failed(detail) => detail,
+        }
+    }
+
+    /// The ripgrep binary `preference` picks, with its version
+    pub fn of_ripgrep(preference: &RipgrepBinaryPreference) -> Self {
+        match RipgrepService::with_preference(preference) {
+            Ok(service) => Health::Ok(service.describe()),
+            Err(e) => Health::Failed(e.to_string()),
+        }
+    }
+
+    /// The tree-sitter grammars that loaded, and any that didn't
+    pub fn of_tree_sitter(initialized: bool) -> Self {
+        if !initialized {
+            return Health::Unconfigured("service not initialized".to_string());
+        }
+        let (available, unavailable): (Vec<_>, Vec<_>) = SupportedLanguage::ALL
+            .iter()
+            .partition(|language| language.is_available());
+        let names = |languages: Vec<&SupportedLanguage>| {
+            languages
+                .iter()
+                .map(|language| format!("{:?}", language))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        match (available.is_empty(), unavailable.is_empty()) {
+            (true, _) => Health::Failed("no grammars loaded".to_string()),
+            (false, true) => Health::Ok(names(available)),
+            (false, false) => Health::Ok(format!(
+                "{} ({} unavailable)",
+                names(available),
+                names(unavailable)
+            )),
+        }
+    }
+
+    /// A configured MCP server: usable only when enabled with an API key
+    pub fn of_mcp_server(server: &McpServerConfig) -> Self {
+        if !server.enabled {
+            Health::Unconfigured("disabled".to_string())
+        } else if server.api_key.is_none() {
+            Health::Unconfigured("enabled, no API key".to_string())
+        } else {
+            Health::Ok(format!("enabled, {}", server.url))
+        }
+    }
+}
+
+/// Everything `/status` shows
+#[derive(Debug, Clone)]
+pub struct StatusReport {
+    pub llm: Health,
+    pub ripgrep: Health,
+    pub tree_sitter: Health,
+    /// Display name and state of each configured server
+    pub mcp_servers: Vec<(String, Health)>,
+    pub workspace: PathBuf,
+}
+
+impl StatusReport {
+    pub fn render(&self) -> String {
+        let line = |name: &str, health: &Health| {
+            format!("  [{}] {}: {}", health.marker(), name, health.detail())
+        };
+
+        let mut lines = vec![
+            "Status".to_string(),
+            line("LLM", &self.llm),
+            line("Ripgrep", &self.ripgrep),
+            line("Tree-sitter", &self.tree_sitter),
+        ];
+        if self.mcp_servers.is_empty() {
+            lines.push("  MCP servers: none configured".to_string());
+        } else {
+            lines.push("  MCP servers:".to_string());
+            for (name, health) in &self.mcp_servers {
+                lines.push(format!("  {}", line(name, health)));
+            }
+        }
+        lines.push(format!("  Workspace: {}", self.workspace.display()));
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secret::Secret;
+
+    fn server(name: &str, enabled: bool, api_key: Option<&str>) -> McpServerConfig {
+        McpServerConfig {
+            id: name.to_lowercase(),
+            name: name.to_string(),
+            url: format!("https://{}.example/v1", name.to_lowercase()),
+            api_key: api_key.map(Secret::new),
+            enabled,
+            headers: McpServerConfig::default_headers(),
+            requests_per_minute: None,
+            context_token_budget: None,
+        }
+    }
+
+    #[test]
+    fn test_mcp_server_health() {
+        assert_eq!(
+            Health::of_mcp_server(&server("Router", true, Some("sk-test"))),
+            Health::Ok("enabled, https://router.example/v1".to_string())
+        );
+        assert!(matches!(
+            Health::of_mcp_server(&server("Keyless", true, None)),
+            Health::Unconfigured(_)
+        ));
+        assert_eq!(
+            Health::of_mcp_server(&server("Off", false, Some("sk-test"))),
+            Health::Unconfigured("disabled".to_string())
+        );
+        assert!(matches!(Health::of_tree_sitter(false), Health::Unconfigured(_)));
+    }
+
+    #[test]
+    fn test_render_marks_each_subsystem() {
+        let report = StatusReport {
+            llm: Health::Unconfigured("use /config <api_key>".to_string()),
+            ripgrep: Health::Failed("ripgrep not found".to_string()),
+            tree_sitter: Health::Ok("Rust, Python".to_string()),
+            mcp_servers: vec![("Router".to_string(), Health::Ok("enabled".to_string()))],
+            workspace: PathBuf::from("/work"),
+        };
+
+        assert_eq!(
+            report.render(),
+            "Status\n  [off] LLM: use /config <api_key>\n  [error] Ripgrep: ripgrep not found\n  \
+             [ok] Tree-sitter: Rust, Python\n  MCP servers:\n    [ok] Router: enabled\n  \
+             Workspace: /work"
+        );
+    }
+}
@@ -3,19 +3,94 @@ use ratatui::{
     Frame,
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Style},
+    text::Line,
     widgets::{Block, Borders, List, ListItem, Paragraph},
 };
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
 use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use uuid::Uuid;
 
+use crate::config::ScrollBehavior;
+use crate::error::TaskError;
 use crate::shell::terminal::{Terminal, TerminalInstance};
 
+/// Output lines kept per terminal view; the oldest are dropped beyond this
+pub const MAX_SCROLLBACK_LINES: usize = 5000;
+
+/// Usage of one command, for ranking suggestions
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct CommandUsage {
+    pub count: u32,
+    /// Value of `CommandFrequency`'s clock when the command was last run
+    pub last_used: u64,
+}
+
+/// How often and how recently each terminal command has been run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CommandFrequency {
+    commands: HashMap<String, CommandUsage>,
+    /// Incremented on every recorded command, so recency survives restarts
+    clock: u64,
+}
+
+impl CommandFrequency {
+    /// Load from `path`; a missing or unreadable file starts empty
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Persist to `path`. This is best effort; failures are ignored.
+    pub fn save(&self, path: &Path) {
+        if path
+            .parent()
+            .is_some_and(|parent| std::fs::create_dir_all(parent).is_err())
+        {
+            return;
+        }
+        if let Ok(content) = serde_json::to_string(self) {
+            let _ = std::fs::write(path, content);
+        }
+    }
+
+    pub fn record(&mut self, command: &str) {
+        self.clock += 1;
+        let usage = self.commands.entry(command.to_string()).or_default();
+        usage.count += 1;
+        usage.last_used = self.clock;
+    }
+
+    pub fn usage(&self, command: &str) -> CommandUsage {
+        self.commands.get(command).copied().unwrap_or_default()
+    }
+
+    /// Order `suggestions` by use count, then by how recently they were run.
+    /// Ties keep their existing order.
+    pub fn rank(&self, suggestions: &mut [String]) {
+        suggestions.sort_by_key(|command| {
+            let usage = self.usage(command);
+            (Reverse(usage.count), Reverse(usage.last_used))
+        });
+    }
+}
+
 /// Represents a terminal view configuration
 #[derive(Clone)]
 pub struct TerminalView {
     pub instance: TerminalInstance,
+    /// Output of commands run in this terminal, oldest first
+    pub output: VecDeque<String>,
+    /// Lines scrolled back from the bottom of `output`
     pub scroll_offset: usize,
+    /// Whether new output keeps the scroll position or jumps to the bottom
+    pub scroll_behavior: ScrollBehavior,
+    /// Output that arrived while scrolled back and hasn't been scrolled into view yet
+    unseen_lines: usize,
     pub command_buffer: String,
     pub cursor_position: usize,
     pub history_index: Option<usize>,
@@ -32,10 +107,14 @@ pub struct TerminalViewManager {
     command_history: Arc<Mutex<HashMap<Uuid, VecDeque<String>>>>,
     max_history: usize,
     terminal: Arc<dyn Terminal>,
+    frequency: CommandFrequency,
+    /// Where `frequency` is persisted; `None` keeps it in memory only
+    frequency_file: Option<PathBuf>,
+    scroll_behavior: ScrollBehavior,
 }
 
 /// Defines how terminal views are arranged
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum TerminalLayout {
     Single,
     HorizontalSplit,
@@ -47,7 +126,10 @@ impl TerminalView {
     pub fn new(instance: TerminalInstance) -> Self {
         Self {
             instance,
+            output: VecDeque::new(),
             scroll_offset: 0,
+            scroll_behavior: ScrollBehavior::default(),
+            unseen_lines: 0,
             command_buffer: String::new(),
             cursor_position: 0,
             history_index: None,
@@ -57,20 +139,69 @@ impl TerminalView {
         }
     }
 
-    fn update_suggestions(&mut self) {
+    /// Append command output. At the bottom the view follows it; when scrolled back it
+    /// stays put or jumps to the bottom depending on `scroll_behavior`.
+    pub fn push_output(&mut self, text: &str) {
+        let mut added = 0;
+        for line in text.lines() {
+            self.output.push_back(line.to_string());
+            added += 1;
+        }
+        while self.output.len() > MAX_SCROLLBACK_LINES {
+            self.output.pop_front();
+        }
+
+        if self.scroll_offset > 0 {
+            match self.scroll_behavior {
+                ScrollBehavior::StayPut => {
+                    self.scroll_offset = (self.scroll_offset + added).min(self.max_scroll());
+                    self.unseen_lines = (self.unseen_lines + added).min(self.scroll_offset);
+                }
+                ScrollBehavior::Follow => self.scroll_offset = 0,
+            }
+        }
+    }
+
+    pub fn scroll_up(&mut self, lines: usize) {
+        self.scroll_offset = (self.scroll_offset + lines).min(self.max_scroll());
+    }
+
+    pub fn scroll_down(&mut self, lines: usize) {
+        self.scroll_offset = self.scroll_offset.saturating_sub(lines);
+        // Anything no longer below the view has been seen
+        self.unseen_lines = self.unseen_lines.min(self.scroll_offset);
+    }
+
+    /// Lines of new output below the visible part of a scrolled-back view
+    pub fn new_lines_below(&self) -> usize {
+        self.unseen_lines
+    }
+
+    fn max_scroll(&self) -> usize {
+        self.output.len().saturating_sub(1)
+    }
+
+    /// The output lines that fit in `height` rows at the current scroll position
+    pub fn visible_output(&self, height: usize) -> impl Iterator<Item = &String> {
+        let end = self.output.len().saturating_sub(self.scroll_offset);
+        let start = end.saturating_sub(height);
+        self.output.range(start..end)
+    }
+
+    fn update_suggestions(&mut self, frequency: &CommandFrequency) {
         if self.command_buffer.is_empty() {
             self.suggestions.clear();
             self.selected_suggestion = None;
             return;
         }
 
-        // Generate suggestions based on command history and current input
-        self.suggestions = self
-            .history
-            .iter()
-            .filter(|cmd| cmd.starts_with(&self.command_buffer))
-            .cloned()
-            .collect();
+        // Generate suggestions based on command history and current input, once per command
+        self.suggestions.clear();
+        for cmd in &self.history {
+            if cmd.starts_with(&self.command_buffer) && !self.suggestions.contains(cmd) {
+                self.suggestions.push(cmd.clone());
+            }
+        }
 
         // Add common command suggestions
         let common_commands = vec!["cd", "ls", "git", "cargo", "vim", "cat", "grep", "find"];
@@ -82,6 +213,8 @@ impl TerminalView {
             }
         }
 
+        frequency.rank(&mut self.suggestions);
+
         self.selected_suggestion = if self.suggestions.is_empty() {
             None
         } else {
@@ -99,11 +232,34 @@ impl TerminalViewManager {
             command_history: Arc::new(Mutex::new(HashMap::new())),
             max_history: 1000,
             terminal,
+            frequency: CommandFrequency::default(),
+            frequency_file: None,
+            scroll_behavior: ScrollBehavior::default(),
         }
     }
 
+    /// How views opened from now on react to new output while scrolled back
+    pub fn with_scroll_behavior(mut self, behavior: ScrollBehavior) -> Self {
+        self.scroll_behavior = behavior;
+        self
+    }
+
+    /// Load command usage counts from `path` and save them there as commands run
+    pub fn with_frequency_file(mut self, path: Option<PathBuf>) -> Self {
+        if let Some(path) = &path {
+            self.frequency = CommandFrequency::load(path);
+        }
+        self.frequency_file = path;
+        self
+    }
+
+    pub fn frequency(&self) -> &CommandFrequency {
+        &self.frequency
+    }
+
     pub fn add_view(&mut self, instance: TerminalInstance) {
-        let view = TerminalView::new(instance.clone());
+        let mut view = TerminalView::new(instance.clone());
+        view.scroll_behavior = self.scroll_behavior;
         self.views.push(view);
 
         // Create history entry for new instance
@@ -112,6 +268,84 @@ impl TerminalViewManager {
         }
     }
 
+    /// Open a new terminal instance and make its view the active one
+    pub fn create_view(&mut self, title: String) -> Result<&TerminalView, TaskError> {
+        let instance = self.terminal.create_instance(title)?;
+        self.terminal.switch_to(&instance)?;
+        self.add_view(instance);
+        self.active_view = self.views.len() - 1;
+        Ok(&self.views[self.active_view])
+    }
+
+    /// Make the next view active, wrapping around
+    pub fn focus_next(&mut self) -> Result<(), TaskError> {
+        if self.views.is_empty() {
+            return Ok(());
+        }
+        let next = (self.active_view + 1) % self.views.len();
+        self.terminal.switch_to(&self.views[next].instance)?;
+        self.active_view = next;
+        Ok(())
+    }
+
+    pub fn views(&self) -> &[TerminalView] {
+        &self.views
+    }
+
+    pub fn active_view(&self) -> Option<&TerminalView> {
+        self.views.get(self.active_view)
+    }
+
+    pub fn active_view_mut(&mut self) -> Option<&mut TerminalView> {
+        self.views.get_mut(self.active_view)
+    }
+
+    pub fn layout(&self) -> TerminalLayout {
+        self.layout
+    }
+
+    /// Change the active terminal's working directory
+    pub fn set_working_directory(&self, dir: std::path::PathBuf) -> Result<(), TaskError> {
+        self.terminal.set_working_directory(dir)
+    }
+
+    /// Working directory of the active terminal, where its commands run
+    pub fn working_directory(&self) -> Option<std::path::PathBuf> {
+        self.active_view()?;
+        self.terminal.get_working_directory().ok()
+    }
+
+    /// Append output to the view of terminal `id`; returns false if it has been closed
+    pub fn append_output(&mut self, id: Uuid, text: &str) -> bool {
+        match self.views.iter_mut().find(|view| view.instance.id() == id) {
+            Some(view) => {
+                view.push_output(text);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Note a command run in the active terminal in its history
+    pub fn record_command(&mut self, command: &str) {
+        self.frequency.record(command);
+        if let Some(path) = &self.frequency_file {
+            self.frequency.save(path);
+        }
+
+        let max_history = self.max_history;
+        let Some(view) = self.views.get_mut(self.active_view) else {
+            return;
+        };
+        view.history.push_front(command.to_string());
+        view.history.truncate(max_history);
+        if let Ok(mut history) = self.command_history.lock() {
+            let entry = history.entry(view.instance.id()).or_default();
+            entry.push_front(command.to_string());
+            entry.truncate(max_history);
+        }
+    }
+
     pub fn cycle_layout(&mut self) {
         self.layout = match self.layout {
             TerminalLayout::Single => TerminalLayout::HorizontalSplit,
@@ -131,7 +365,7 @@ impl TerminalViewManager {
         match (key.modifiers, key.code) {
             // Terminal switching
             (KeyModifiers::CONTROL, KeyCode::Tab) => {
-                self.active_view = (self.active_view + 1) % self.views.len();
+                let _ = self.focus_next();
                 true
             }
 
@@ -189,7 +423,7 @@ impl TerminalViewManager {
                 if let Some(idx) = view.selected_suggestion {
                     view.command_buffer = view.suggestions[idx].clone();
                     view.cursor_position = view.command_buffer.len();
-                    view.update_suggestions();
+                    view.update_suggestions(&self.frequency);
                 }
                 true
             }
@@ -199,7 +433,7 @@ impl TerminalViewManager {
                 view.command_buffer.insert(view.cursor_position, c);
                 view.cursor_position += 1;
                 view.history_index = None;
-                view.update_suggestions();
+                view.update_suggestions(&self.frequency);
                 true
             }
 
@@ -208,7 +442,7 @@ impl TerminalViewManager {
                     view.command_buffer.remove(view.cursor_position - 1);
                     view.cursor_position -= 1;
                     view.history_index = None;
-                    view.update_suggestions();
+                    view.update_suggestions(&self.frequency);
                 }
                 true
             }
@@ -218,7 +452,12 @@ impl TerminalViewManager {
     }
 
     pub fn draw(&self, f: &mut Frame) {
-        let chunks = self.get_layout_chunks(f.size());
+        self.draw_in(f, f.size());
+    }
+
+    /// Draw the views in `area` according to the current layout
+    pub fn draw_in(&self, f: &mut Frame, area: Rect) {
+        let chunks = self.get_layout_chunks(area);
 
         for (i, view) in self.views.iter().enumerate() {
             if i >= chunks.len() {
@@ -296,6 +535,27 @@ impl TerminalViewManager {
             .constraints([Constraint::Min(1), Constraint::Length(1)].as_ref())
             .split(inner_area);
 
+        // Draw output above the input line, keeping the last row for a note about
+        // output that arrived while scrolled back
+        let new_lines = view.new_lines_below();
+        let output_rows = if new_lines > 0 {
+            chunks[0].height.saturating_sub(1)
+        } else {
+            chunks[0].height
+        };
+        let mut output: Vec<Line> = view
+            .visible_output(output_rows as usize)
+            .map(|line| Line::from(line.as_str()))
+            .collect();
+        if new_lines > 0 {
+            output.resize(output_rows as usize, Line::from(""));
+            output.push(Line::styled(
+                format!("-- {} new line{} below --", new_lines, if new_lines == 1 { "" } else { "s" }),
+                Style::default().fg(Color::Yellow),
+            ));
+        }
+        f.render_widget(Paragraph::new(output), chunks[0]);
+
         // Draw suggestions if any
         if !view.suggestions.is_empty() && is_active {
             let suggestion_items: Vec<ListItem> = view
@@ -335,3 +595,132 @@ impl TerminalViewManager {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shell::terminal::TerminalManager;
+
+    fn manager_with_views(titles: &[&str]) -> TerminalViewManager {
+        let mut manager = TerminalViewManager::new(Arc::new(TerminalManager::new()));
+        for title in titles {
+            manager.create_view(title.to_string()).unwrap();
+        }
+        manager
+    }
+
+    #[test]
+    fn test_output_lands_in_its_own_view() {
+        let mut manager = manager_with_views(&["one", "two"]);
+        let first = manager.views()[0].instance.id();
+
+        // "two" is active, but output for "one" still goes to "one"
+        assert!(manager.append_output(first, "hello\nworld\n"));
+        assert_eq!(manager.views()[0].output, vec!["hello", "world"]);
+        assert!(manager.views()[1].output.is_empty());
+        assert!(!manager.append_output(Uuid::new_v4(), "lost"));
+    }
+
+    #[test]
+    fn test_scrollback_is_bounded_and_scrolls() {
+        let mut manager = manager_with_views(&["one"]);
+        let view = manager.active_view_mut().unwrap();
+        for i in 0..MAX_SCROLLBACK_LINES + 10 {
+            view.push_output(&format!("line {}", i));
+        }
+        assert_eq!(view.output.len(), MAX_SCROLLBACK_LINES);
+        assert_eq!(view.output.front().unwrap(), "line 10");
+
+        let bottom: Vec<&String> = view.visible_output(2).collect();
+        assert_eq!(bottom, vec!["line 5008", "line 5009"]);
+
+        view.scroll_up(3);
+        view.push_output("new line");
+        // Scrolled back, so new output doesn't move the visible lines
+        let visible: Vec<&String> = view.visible_output(2).collect();
+        assert_eq!(visible, vec!["line 5005", "line 5006"]);
+
+        view.scroll_down(100);
+        assert_eq!(view.visible_output(1).next().unwrap(), "new line");
+    }
+
+    #[test]
+    fn test_new_output_follows_only_at_bottom_by_default() {
+        let mut manager = manager_with_views(&["one"]);
+        let view = manager.active_view_mut().unwrap();
+        for i in 0..10 {
+            view.push_output(&format!("line {}", i));
+        }
+
+        // At the bottom the view follows new output
+        view.push_output("line 10");
+        assert_eq!(view.scroll_offset, 0);
+        assert_eq!(view.visible_output(1).next().unwrap(), "line 10");
+        assert_eq!(view.new_lines_below(), 0);
+
+        // Scrolled back, it stays put and counts what arrived below
+        view.scroll_up(4);
+        view.push_output("line 11\nline 12");
+        let visible: Vec<&String> = view.visible_output(2).collect();
+        assert_eq!(visible, vec!["line 5", "line 6"]);
+        assert_eq!(view.new_lines_below(), 2);
+
+        view.scroll_down(5);
+        assert_eq!(view.new_lines_below(), 1);
+        view.scroll_down(1);
+        assert_eq!(view.new_lines_below(), 0);
+        assert_eq!(view.visible_output(1).next().unwrap(), "line 12");
+    }
+
+    #[test]
+    fn test_follow_behavior_jumps_to_new_output() {
+        let mut manager = TerminalViewManager::new(Arc::new(TerminalManager::new()))
+            .with_scroll_behavior(ScrollBehavior::Follow);
+        manager.create_view("one".to_string()).unwrap();
+        let view = manager.active_view_mut().unwrap();
+        for i in 0..10 {
+            view.push_output(&format!("line {}", i));
+        }
+
+        view.scroll_up(4);
+        view.push_output("line 10");
+        assert_eq!(view.scroll_offset, 0);
+        assert_eq!(view.visible_output(1).next().unwrap(), "line 10");
+        assert_eq!(view.new_lines_below(), 0);
+    }
+
+    #[test]
+    fn test_frequent_commands_rank_first() {
+        let mut manager = manager_with_views(&["one"]);
+        for _ in 0..3 {
+            manager.record_command("cargo test");
+        }
+        // Most recent, but used less often
+        manager.record_command("cargo build");
+
+        for c in "cargo".chars() {
+            manager.handle_key_event(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        }
+        let view = manager.active_view().unwrap();
+        assert_eq!(view.suggestions, vec!["cargo test", "cargo build", "cargo"]);
+    }
+
+    #[test]
+    fn test_command_frequency_persists() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("samus").join("command_frequency.json");
+
+        let mut manager = TerminalViewManager::new(Arc::new(TerminalManager::new()))
+            .with_frequency_file(Some(path.clone()));
+        manager.create_view("one".to_string()).unwrap();
+        manager.record_command("ls");
+        manager.record_command("ls");
+        manager.record_command("git status");
+
+        let reloaded = TerminalViewManager::new(Arc::new(TerminalManager::new()))
+            .with_frequency_file(Some(path));
+        assert_eq!(reloaded.frequency().usage("ls").count, 2);
+        assert_eq!(reloaded.frequency().usage("git status").last_used, 3);
+        assert_eq!(reloaded.frequency().usage("unknown"), CommandUsage::default());
+    }
+}
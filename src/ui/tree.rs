@@ -0,0 +1,196 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::error::TaskError;
+use crate::fs::SamusIgnore;
+use crate::fs::operations::{DEFAULT_IGNORE_PATTERNS, LocalFileSystem};
+
+/// Depth used by `/tree` when `--depth` isn't given
+pub const DEFAULT_TREE_DEPTH: usize = 3;
+
+const BRANCH: &str = "├── ";
+const LAST_BRANCH: &str = "└── ";
+const PIPE: &str = "│   ";
+const SPACE: &str = "    ";
+
+#[derive(Default)]
+struct TreeNode {
+    is_dir: bool,
+    children: BTreeMap<String, TreeNode>,
+}
+
+/// Parse `/tree` arguments (format: `[path] [--depth N]`)
+pub fn parse_tree_args(args: &str) -> Result<(PathBuf, usize), String> {
+    let mut path = None;
+    let mut depth = DEFAULT_TREE_DEPTH;
+
+    let mut parts = args.split_whitespace();
+    while let Some(part) = parts.next() {
+        if part == "--depth" {
+            depth = parts
+                .next()
+                .and_then(|n| n.parse::<usize>().ok())
+                .filter(|n| *n > 0)
+                .ok_or_else(|| "--depth expects a positive number".to_string())?;
+        } else if path.is_none() {
+            path = Some(PathBuf::from(part));
+        } else {
+            return Err(format!("Unexpected argument: {}", part));
+        }
+    }
+
+    Ok((path.unwrap_or_else(|| PathBuf::from(".")), depth))
+}
+
+/// Build an indented tree of `root`, skipping the default ignore patterns and
+/// anything `samusignore` excludes
+pub fn build_tree(
+    root: &Path,
+    max_depth: usize,
+    samusignore: &SamusIgnore,
+) -> Result<String, TaskError> {
+    if !root.is_dir() {
+        return Err(TaskError::FileSystem(format!(
+            "Not a directory: {}",
+            root.display()
+        )));
+    }
+
+    let ignore: Vec<String> = DEFAULT_IGNORE_PATTERNS
+        .iter()
+        .map(|p| p.to_string())
+        .collect();
+    let entries = LocalFileSystem::new()
+        .with_samusignore(samusignore.clone())
+        .list_files_filtered(root, Some(max_depth), &ignore)?;
+
+    Ok(render_tree(root, &entries))
+}
+
+/// Render `entries` (paths under `root`) like the `tree` utility: directories first,
+/// each level sorted by name, followed by a directory/file count
+pub fn render_tree(root: &Path, entries: &[PathBuf]) -> String {
+    let mut tree = TreeNode {
+        is_dir: true,
+        children: BTreeMap::new(),
+    };
+    let mut dir_count = 0;
+    let mut file_count = 0;
+
+    for entry in entries {
+        let Ok(relative) = entry.strip_prefix(root) else {
+            continue;
+        };
+        let is_dir = entry.is_dir();
+        if is_dir {
+            dir_count += 1;
+        } else {
+            file_count += 1;
+        }
+
+        let mut node = &mut tree;
+        for component in relative.components() {
+            let name = component.as_os_str().to_string_lossy().to_string();
+            node = node.children.entry(name).or_default();
+        }
+        node.is_dir = is_dir;
+    }
+
+    let mut output = format!("{}\n", root.display());
+    render_children(&tree, "", &mut output);
+    output.push_str(&format!(
+        "\n{} {}, {} {}",
+        dir_count,
+        if dir_count == 1 { "directory" } else { "directories" },
+        file_count,
+        if file_count == 1 { "file" } else { "files" }
+    ));
+    output
+}
+
+fn render_children(node: &TreeNode, prefix: &str, output: &mut String) {
+    let mut children: Vec<(&String, &TreeNode)> = node.children.iter().collect();
+    // Directories before files; BTreeMap already keeps names sorted
+    children.sort_by_key(|(_, child)| !child.is_dir);
+
+    let count = children.len();
+    for (i, (name, child)) in children.into_iter().enumerate() {
+        let is_last = i + 1 == count;
+        output.push_str(prefix);
+        output.push_str(if is_last { LAST_BRANCH } else { BRANCH });
+        output.push_str(name);
+        if child.is_dir {
+            output.push('/');
+        }
+        output.push('\n');
+
+        let child_prefix = format!("{}{}", prefix, if is_last { SPACE } else { PIPE });
+        render_children(child, &child_prefix, output);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_tree_args() {
+        assert_eq!(
+            parse_tree_args("").unwrap(),
+            (PathBuf::from("."), DEFAULT_TREE_DEPTH)
+        );
+        assert_eq!(
+            parse_tree_args("src --depth 2").unwrap(),
+            (PathBuf::from("src"), 2)
+        );
+        assert_eq!(
+            parse_tree_args("--depth 1 docs").unwrap(),
+            (PathBuf::from("docs"), 1)
+        );
+        assert!(parse_tree_args("--depth zero").is_err());
+        assert!(parse_tree_args("a b").is_err());
+    }
+
+    #[test]
+    fn test_build_tree_layout() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join("src/ui")).unwrap();
+        fs::create_dir_all(root.join("target/debug")).unwrap();
+        fs::create_dir_all(root.join("docs")).unwrap();
+        fs::write(root.join("Cargo.toml"), "").unwrap();
+        fs::write(root.join("src/main.rs"), "").unwrap();
+        fs::write(root.join("src/ui/app.rs"), "").unwrap();
+        fs::write(root.join("target/debug/samus"), "").unwrap();
+
+        let tree = build_tree(root, DEFAULT_TREE_DEPTH, &SamusIgnore::default()).unwrap();
+        let expected = format!(
+            "{}\n\
+             ├── docs/\n\
+             ├── src/\n\
+             │   ├── ui/\n\
+             │   │   └── app.rs\n\
+             │   └── main.rs\n\
+             └── Cargo.toml\n\
+             \n\
+             3 directories, 3 files",
+            root.display()
+        );
+        assert_eq!(tree, expected);
+
+        // Depth limits how far down the tree goes
+        let shallow = build_tree(root, 1, &SamusIgnore::default()).unwrap();
+        assert!(shallow.contains("├── src/\n"));
+        assert!(!shallow.contains("main.rs"));
+    }
+
+    #[test]
+    fn test_build_tree_rejects_files() {
+        let temp = TempDir::new().unwrap();
+        let file = temp.path().join("file.txt");
+        fs::write(&file, "").unwrap();
+        assert!(build_tree(&file, 2, &SamusIgnore::default()).is_err());
+    }
+}
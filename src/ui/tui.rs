@@ -1,5 +1,6 @@
 use ratatui::{
-    Frame,
+    Frame, Terminal,
+    backend::Backend,
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
@@ -8,6 +9,20 @@ use ratatui::{
 
 use crate::ui::app::{App, MainViewType};
 use crate::ui::input::InputMode;
+use crate::ui::markdown::{MarkdownLineKind, classify_markdown, code_style, fence_style};
+use crate::ui::outline::render_symbols;
+
+/// Draw a frame if the app has changed since the last one, returning whether it did.
+///
+/// The screen isn't cleared first: ratatui diffs each frame against the previous one
+/// and only writes the cells that changed.
+pub fn draw_if_needed<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> std::io::Result<bool> {
+    if !app.take_redraw() {
+        return Ok(false);
+    }
+    terminal.draw(|f| render_ui(f, app))?;
+    Ok(true)
+}
 
 /// Renders the main user interface
 pub fn render_ui(f: &mut Frame, app: &mut App) {
@@ -16,6 +31,20 @@ pub fn render_ui(f: &mut Frame, app: &mut App) {
     
     // If we're displaying a completion, use the full screen for the main view
     if app.displaying_completion {
+        // Keep a status line at the bottom while work is running so the app looks alive
+        if let Some(status) = app.spinner.status_text() {
+            let layout = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(1), Constraint::Length(1)])
+                .split(area);
+            render_main_view(f, app, layout[0]);
+            f.render_widget(
+                Paragraph::new(status).style(Style::default().fg(Color::Yellow)),
+                layout[1],
+            );
+            return;
+        }
+
         // Use the entire terminal area for the main view
         render_main_view(f, app, area);
         return;
@@ -65,6 +94,11 @@ fn render_main_view(f: &mut Frame, app: &mut App, area: Rect) {
             f.render_widget(paragraph, inner_area);
         }
         MainViewType::GitDiff => {
+            if let Some(diff) = &app.active_diff {
+                diff.render(inner_area, f.buffer_mut());
+                return;
+            }
+
             // Placeholder for git diff rendering
             let text = vec![
                 Line::from(vec![Span::styled(
@@ -101,60 +135,8 @@ fn render_main_view(f: &mut Frame, app: &mut App, area: Rect) {
             f.render_widget(paragraph, inner_area);
         }
         MainViewType::ShellOutput => {
-            // Create a combined view of user inputs and responses
-            let mut text: Vec<Line> = Vec::new();
-            
-            // Process all chat messages in order
-            for msg in app.chat_messages.iter() {
-                if msg.is_user {
-                    // User message
-                    text.push(Line::from(vec![
-                        Span::styled(
-                            "You: ",
-                            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
-                        ),
-                        Span::raw(&msg.content),
-                    ]));
-                    text.push(Line::from(""));
-                } else {
-                    // Assistant message
-                    if msg.content == "Thinking..." {
-                        // Skip "Thinking..." messages
-                        continue;
-                    }
-                    
-                    text.push(Line::from(vec![
-                        Span::styled(
-                            "Samus: ",
-                            Style::default().fg(Color::Green).add_modifier(Modifier::BOLD),
-                        ),
-                    ]));
-                    
-                    // Process assistant response, with special handling for different content types
-                    for line in msg.content.lines() {
-                        // Special handling for directory trees
-                        if line.contains("├") || line.contains("└") || line.contains("│") {
-                            text.push(Line::from(Span::styled(line, Style::default().fg(Color::Cyan))));
-                        } else if line.starts_with("$") || line.starts_with("#") {
-                            text.push(Line::from(Span::styled(line, Style::default().fg(Color::Yellow))));
-                        } else if line.starts_with("```") {
-                            // Code block markers
-                            text.push(Line::from(Span::styled(line, Style::default().fg(Color::Cyan))));
-                        } else if line.starts_with("# ") || line.starts_with("## ") {
-                            // Markdown headers
-                            text.push(Line::from(Span::styled(
-                                line,
-                                Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD),
-                            )));
-                        } else {
-                            text.push(Line::from(line));
-                        }
-                    }
-                    
-                    text.push(Line::from("")); // Add a blank line after each message
-                }
-            }
-            
+            let mut text = shell_output_lines(app);
+
             // Show a scroll indicator at the bottom when there's content to scroll
             if text.len() as u16 > inner_area.height {
                 // Add a note at the bottom of the visible content
@@ -177,47 +159,26 @@ fn render_main_view(f: &mut Frame, app: &mut App, area: Rect) {
             f.render_widget(paragraph, inner_area);
         }
         MainViewType::LlmResponse => {
-            // Get the most recent LLM response from chat history
-            let empty_string = String::new();
-            let llm_response = app.chat_messages.iter()
-                .rev()
-                .find(|msg| !msg.is_user && msg.content != "Thinking...")
-                .map(|msg| &msg.content)
-                .unwrap_or(&empty_string);
-                
-            // Convert LLM response to lines
-            let text: Vec<Line> = llm_response
-                .lines()
-                .map(|line| {
-                    // Basic formatting for markdown headers
-                    if line.starts_with("# ") {
-                        Line::from(vec![Span::styled(
-                            line,
-                            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
-                        )])
-                    } else if line.starts_with("## ") {
-                        Line::from(vec![Span::styled(
-                            line,
-                            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
-                        )])
-                    } else if line.starts_with("```") {
-                        Line::from(vec![Span::styled(
-                            line,
-                            Style::default().fg(Color::Cyan),
-                        )])
-                    } else {
-                        Line::from(line)
-                    }
-                })
-                .collect();
-                
+            let text = llm_response_lines(app);
+
             let paragraph = Paragraph::new(text)
                 .wrap(Wrap { trim: true });
                 
             f.render_widget(paragraph, inner_area);
         }
         MainViewType::Search => {
-            // Placeholder for search rendering
+            // Render the latest /grep results if there are any, otherwise placeholder
+            if let Some(outcome) = &app.last_grep {
+                let paragraph = Paragraph::new(crate::ui::grep::render_grouped_results(
+                    outcome,
+                    &app.grep_groups,
+                    &app.workspace,
+                    &app.grep_pager,
+                ));
+                f.render_widget(paragraph, inner_area);
+                return;
+            }
+
             let text = vec![
                 Line::from(vec![
                     Span::styled("Search Results for: ", Style::default().fg(Color::White)),
@@ -246,6 +207,17 @@ fn render_main_view(f: &mut Frame, app: &mut App, area: Rect) {
             let paragraph = Paragraph::new(text);
             f.render_widget(paragraph, inner_area);
         }
+        MainViewType::Terminals => {
+            if app.terminals.views().is_empty() {
+                let paragraph = Paragraph::new(Line::from(Span::styled(
+                    "No terminals open. Use /terminal new [title] to open one.",
+                    Style::default().fg(Color::DarkGray),
+                )));
+                f.render_widget(paragraph, inner_area);
+            } else {
+                app.terminals.draw_in(f, inner_area);
+            }
+        }
         MainViewType::CodeOutline => {
             // Render actual symbols if available, otherwise placeholder
             let text = if !app.current_file_symbols.is_empty() {
@@ -253,28 +225,22 @@ fn render_main_view(f: &mut Frame, app: &mut App, area: Rect) {
                     Line::from(vec![
                         Span::styled("Code Outline for: ", Style::default().fg(Color::White)),
                         Span::styled(
-                            app.current_file_path.as_deref().unwrap_or("Unknown"), 
+                            app.current_file_path
+                                .as_deref()
+                                .map(|path| app.workspace.display(path))
+                                .unwrap_or_else(|| "Unknown".to_string()),
                             Style::default().fg(Color::Yellow)
                         ),
                     ]),
                     Line::from(""),
                 ];
                 
-                // Add each symbol
-                for symbol in &app.current_file_symbols {
-                    let color = match symbol.kind.as_str() {
-                        "Function" | "Method" => Color::Cyan,
-                        "Class" | "Struct" | "Interface" => Color::Green,
-                        "Variable" | "Property" => Color::Blue,
-                        _ => Color::White,
-                    };
-                    
-                    lines.push(Line::from(vec![Span::styled(
-                        format!("{} {} [line {}]", symbol.kind, symbol.name, symbol.line),
-                        Style::default().fg(color),
-                    )]));
-                }
-                
+                lines.extend(render_symbols(
+                    &app.current_file_symbols,
+                    &app.workspace,
+                    &app.outline_pager,
+                    Some(app.outline_selected),
+                ));
                 lines
             } else {
                 // Placeholder data
@@ -314,6 +280,111 @@ fn render_main_view(f: &mut Frame, app: &mut App, area: Rect) {
     }
 }
 
+/// Chat history as shown in the shell output view
+fn shell_output_lines(app: &App) -> Vec<Line<'_>> {
+    // Create a combined view of user inputs and responses
+    let mut text: Vec<Line> = Vec::new();
+
+    // Process all chat messages in order
+    for msg in app.chat_messages.iter() {
+        if msg.is_user {
+            // User message
+            text.push(Line::from(vec![
+                Span::styled(
+                    "You: ",
+                    Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(&msg.content),
+            ]));
+            text.push(Line::from(""));
+        } else {
+            // Assistant message
+            if msg.content == "Thinking..." {
+                // Skip "Thinking..." messages
+                continue;
+            }
+
+            text.push(Line::from(vec![
+                Span::styled(
+                    "Samus: ",
+                    Style::default().fg(Color::Green).add_modifier(Modifier::BOLD),
+                ),
+            ]));
+
+            // Process assistant response, with special handling for different content types.
+            // Fences are tracked across lines so a half-streamed code block stays code.
+            for (kind, line) in classify_markdown(&msg.content) {
+                match kind {
+                    MarkdownLineKind::Fence => {
+                        text.push(Line::from(Span::styled(line, fence_style())));
+                    }
+                    MarkdownLineKind::Code => {
+                        text.push(Line::from(Span::styled(line, code_style())));
+                    }
+                    MarkdownLineKind::Heading => {
+                        // Markdown headers
+                        text.push(Line::from(Span::styled(
+                            line,
+                            Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD),
+                        )));
+                    }
+                    MarkdownLineKind::Text => {
+                        // Grep results get file and line-number styling
+                        if let Some(styled) = crate::ui::grep::style_grep_line(line) {
+                            text.push(styled);
+                        // Special handling for directory trees
+                        } else if line.contains("├") || line.contains("└") || line.contains("│") {
+                            text.push(Line::from(Span::styled(line, Style::default().fg(Color::Cyan))));
+                        } else if line.starts_with("$") || line.starts_with("#") {
+                            text.push(Line::from(Span::styled(line, Style::default().fg(Color::Yellow))));
+                        } else {
+                            text.push(Line::from(line));
+                        }
+                    }
+                }
+            }
+
+            text.push(Line::from("")); // Add a blank line after each message
+        }
+    }
+
+    text
+}
+
+/// The most recent model response as shown in the LLM response view
+fn llm_response_lines(app: &App) -> Vec<Line<'_>> {
+    // Get the most recent LLM response from chat history
+    let llm_response = app.chat_messages.iter()
+        .rev()
+        .find(|msg| !msg.is_user && msg.content != "Thinking...")
+        .map(|msg| msg.content.as_str())
+        .unwrap_or("");
+
+    // Convert LLM response to lines
+    classify_markdown(llm_response)
+        .into_iter()
+        .map(|(kind, line)| match kind {
+            // Basic formatting for markdown headers
+            MarkdownLineKind::Heading => Line::from(vec![Span::styled(
+                line,
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            )]),
+            MarkdownLineKind::Fence => Line::from(vec![Span::styled(line, fence_style())]),
+            MarkdownLineKind::Code => Line::from(vec![Span::styled(line, code_style())]),
+            MarkdownLineKind::Text => Line::from(line),
+        })
+        .collect()
+}
+
+/// Lines of the current main view, for views whose content can be exported as text
+pub fn view_lines(app: &App) -> Option<Vec<Line<'_>>> {
+    match app.current_main_view {
+        MainViewType::ShellOutput => Some(shell_output_lines(app)),
+        MainViewType::LlmResponse => Some(llm_response_lines(app)),
+        _ => None,
+    }
+}
+
 /// This function is no longer used, but kept as a stub for compatibility
 fn render_chat_view(_f: &mut Frame, _app: &mut App, _area: Rect) {
     // No longer used as we've merged the chat view into the main view
@@ -383,7 +454,36 @@ fn render_shortcut_area(f: &mut Frame, app: &mut App, area: Rect) {
         InputMode::Help => "Esc back  ↑↓ navigate  q close",
     };
 
-    let shortcut_text = Paragraph::new(shortcuts)
+    // The shown diff's view mode, which Ctrl+L switches
+    let diff = app
+        .active_diff
+        .as_ref()
+        .filter(|_| app.current_main_view == MainViewType::GitDiff);
+    let shortcuts = match diff {
+        Some(diff) => format!("{}  Ctrl+L view: {}", shortcuts, diff.view_mode().label()),
+        None => shortcuts.to_string(),
+    };
+
+    // Lead with the busy indicator while something is running
+    let shortcuts = match app.spinner.status_text() {
+        Some(status) => format!("{}  {}", status, shortcuts),
+        None => shortcuts,
+    };
+
+    // Size of the prompt being typed, highlighted if it won't fit the model
+    let mut spans = Vec::new();
+    if let Some(preview) = app.prompt_preview().filter(|_| !app.displaying_completion) {
+        let style = if preview.exceeds_context() {
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        spans.push(Span::styled(preview.summary(), style));
+        spans.push(Span::raw("  "));
+    }
+    spans.push(Span::raw(shortcuts));
+
+    let shortcut_text = Paragraph::new(Line::from(spans))
         .style(Style::default().fg(Color::DarkGray))
         .alignment(ratatui::layout::Alignment::Center); // Center align for a cleaner look
 
@@ -405,4 +505,74 @@ fn calculate_input_height(input: &str, width: u16) -> u16 {
 
     // Height is min 1, max 10, plus 2 for borders
     2 + line_count.clamp(1, 10)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use ratatui::backend::TestBackend;
+
+    fn screen_text(terminal: &Terminal<TestBackend>) -> String {
+        terminal.backend().buffer().content().iter().map(|cell| cell.symbol.as_str()).collect()
+    }
+
+    #[test]
+    fn test_frames_are_only_drawn_after_a_state_change() {
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        let mut app = App::with_config(&Config::without_persistence());
+
+        // The first frame is always drawn
+        assert!(draw_if_needed(&mut terminal, &mut app).unwrap());
+        let first = screen_text(&terminal);
+        assert!(!first.trim().is_empty());
+
+        // Idle ticks leave the screen alone, and it isn't blanked in between
+        app.on_tick();
+        assert!(!draw_if_needed(&mut terminal, &mut app).unwrap());
+        assert_eq!(screen_text(&terminal), first);
+
+        // Typing changes what's shown, so the next frame is drawn
+        app.handle_key_event(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE));
+        assert!(draw_if_needed(&mut terminal, &mut app).unwrap());
+        assert!(!draw_if_needed(&mut terminal, &mut app).unwrap());
+
+        // Background work keeps redrawing on every tick
+        app.is_processing = true;
+        app.on_tick();
+        assert!(draw_if_needed(&mut terminal, &mut app).unwrap());
+    }
+
+    #[test]
+    fn test_status_bar_previews_prompt_size() {
+        let mut terminal = Terminal::new(TestBackend::new(100, 24)).unwrap();
+        let mut app = App::with_config(&Config::without_persistence());
+        app.input_text = "How do lifetimes work?".to_string();
+
+        terminal.draw(|f| render_ui(f, &mut app)).unwrap();
+        let preview = app.prompt_preview().unwrap().summary();
+        assert!(screen_text(&terminal).contains(&preview));
+    }
+
+    #[test]
+    fn test_status_bar_shows_the_diff_view_mode() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("old.txt"), "a\nb\n").unwrap();
+        std::fs::write(dir.path().join("new.txt"), "a\nc\n").unwrap();
+        let mut terminal = Terminal::new(TestBackend::new(100, 24)).unwrap();
+        let mut app = App::with_config(&Config::without_persistence());
+        app.workspace = crate::fs::WorkspacePaths::new(dir.path());
+
+        app.input_text = "/diff old.txt new.txt".to_string();
+        app.process_input();
+        // Shortcuts show alongside the input, not in full screen
+        app.displaying_completion = false;
+        terminal.draw(|f| render_ui(f, &mut app)).unwrap();
+        assert!(screen_text(&terminal).contains("Ctrl+L view: inline"));
+
+        app.cycle_diff_view_mode();
+        terminal.draw(|f| render_ui(f, &mut app)).unwrap();
+        assert!(screen_text(&terminal).contains("Ctrl+L view: side by side"));
+    }
+}